@@ -24,6 +24,7 @@ extern crate env_logger;
 extern crate futures;
 extern crate tokio;
 extern crate triehash;
+extern crate substrate_aura as aura;
 extern crate substrate_client as client;
 extern crate substrate_codec as codec;
 extern crate substrate_primitives as primitives;
@@ -49,13 +50,22 @@ pub mod error;
 
 use std::sync::Arc;
 use demo_primitives::Hash;
-use demo_runtime::{Block, BlockId, UncheckedExtrinsic, GenesisConfig,
+use demo_runtime::{Block, BlockId, Call, Extrinsic, Header, UncheckedExtrinsic, GenesisConfig,
 	ConsensusConfig, CouncilConfig, DemocracyConfig, SessionConfig, StakingConfig,
-	TimestampConfig};
+	TimestampCall, TimestampConfig};
 use futures::{Future, Sink, Stream};
 use tokio::runtime::Runtime;
 use demo_executor::NativeExecutor;
 
+/// Duration of an authoring slot in seconds; the block period of the timestamp module.
+const SLOT_DURATION: u64 = 5;
+
+type DemoClient = client::Client<
+	client::in_mem::Backend<Block>,
+	client::LocalCallExecutor<client::in_mem::Backend<Block>, NativeExecutor<demo_executor::Executor>>,
+	Block
+>;
+
 struct DummyPool;
 impl extrinsic_pool::api::ExtrinsicPool<UncheckedExtrinsic, BlockId, Hash> for DummyPool {
 	type Error = extrinsic_pool::txpool::Error;
@@ -131,6 +141,7 @@ pub fn run<I, T>(args: I) -> error::Result<()> where
 		staking: Some(StakingConfig {
 			current_era: 0,
 			intentions: vec![],
+			bonded: vec![],
 			transaction_base_fee: 100,
 			transaction_byte_fee: 1,
 			transfer_fee: 0,
@@ -139,15 +150,23 @@ pub fn run<I, T>(args: I) -> error::Result<()> where
 			existential_deposit: 500,
 			balances: vec![(god_key.clone().into(), 1u64 << 63)].into_iter().collect(),
 			validator_count: 12,
+			minimum_validator_count: 1,
 			sessions_per_era: 24,	// 24 hours per era.
 			bonding_duration: 90,	// 90 days per bond.
 			early_era_slash: 10000,
+			misbehavior_slash: 100000,
+			offline_slash: 10000,
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 			session_reward: 100,
 		}),
 		democracy: Some(DemocracyConfig {
 			launch_period: 120 * 24 * 14,	// 2 weeks per public referendum
 			voting_period: 120 * 24 * 28,	// 4 weeks to discuss & vote on an active referendum
 			minimum_deposit: 1000,	// 1000 as the minimum deposit for a referendum
+			preimage_byte_deposit: 1,
+			enactment_delay: 120 * 24 * 7,	// 1 week to exit before a passed referendum is enacted
+			cooloff_period: 120 * 24 * 28,	// 4 weeks before a cancelled proposal may be proposed again
 		}),
 		council: Some(CouncilConfig {
 			active_council: vec![],
@@ -160,6 +179,7 @@ pub fn run<I, T>(args: I) -> error::Result<()> where
 			term_duration: 180 * 120 * 24,	// 180 day term duration for the council.
 			desired_seats: 0, // start with no council: we'll raise this once the stake has been dispersed a bit.
 			inactive_grace_period: 1,	// one addition vote should go by before an inactive voter can be reaped.
+			on_chain_tally_limit: 10_000,	// count up to 10000 approvals on-chain; beyond that winners must present
 
 			cooloff_period: 90 * 120 * 24, // 90 day cooling off period if council member vetoes a proposal.
 			voting_period: 7 * 120 * 24, // 7 day voting period for council members.
@@ -187,14 +207,26 @@ pub fn run<I, T>(args: I) -> error::Result<()> where
 		)
 	};
 
-	if let Some(_) = matches.subcommand_matches("validator") {
+	if let Some(matches) = matches.subcommand_matches("validator") {
 		info!("Starting validator.");
 		let (exit_send, exit) = futures::sync::mpsc::channel(1);
 		ctrlc::CtrlC::set_handler(move || {
 			exit_send.clone().send(()).wait().expect("Error sending exit notification");
 		});
+		let exit = exit.into_future().map(|_| ()).map_err(|_| ());
 
-		runtime.block_on(exit.into_future()).expect("Error running informant event loop");
+		match matches.value_of("key") {
+			Some(seed) => {
+				let key = Arc::new(ed25519::Pair::from_seed(&seed_from_str(seed)));
+				info!("Authoring blocks with key {}", key.public());
+				let proposer = client.clone();
+				let authoring = aura::start_aura(client.clone(), key, SLOT_DURATION, move |parent, timestamp| {
+					propose(&proposer, parent, timestamp)
+				}, exit);
+				runtime.block_on(authoring).expect("Error running authoring event loop");
+			},
+			None => runtime.block_on(exit).expect("Error running informant event loop"),
+		}
 		return Ok(())
 	}
 
@@ -204,6 +236,28 @@ pub fn run<I, T>(args: I) -> error::Result<()> where
 	Ok(())
 }
 
+// Pad a seed phrase with spaces to a 32-byte seed, as the keystore does.
+fn seed_from_str(seed: &str) -> [u8; 32] {
+	let mut s = [b' '; 32];
+	let len = ::std::cmp::min(32, seed.len());
+	s[..len].copy_from_slice(&seed.as_bytes()[..len]);
+	s
+}
+
+// Build a block holding just the timestamp inherent.
+fn propose(client: &DemoClient, parent: &Header, timestamp: u64) -> aura::Result<Block> {
+	let mut builder = client.new_block_at(&BlockId::hash(parent.hash()))?;
+	builder.push(UncheckedExtrinsic::new(
+		Extrinsic {
+			signed: Default::default(),
+			index: 0,
+			function: Call::Timestamp(TimestampCall::set(timestamp)),
+		},
+		Default::default(),
+	))?;
+	Ok(builder.bake()?)
+}
+
 fn init_logger(pattern: &str) {
 	let mut builder = env_logger::LogBuilder::new();
 	// Disable info logging by default for some modules:
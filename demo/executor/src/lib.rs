@@ -198,7 +198,9 @@ mod tests {
 				current_era: 0,
 				balances: vec![(alice(), 111)],
 				intentions: vec![alice(), bob(), Charlie.to_raw_public().into()],
+				bonded: vec![],
 				validator_count: 3,
+				minimum_validator_count: 0,
 				bonding_duration: 0,
 				transaction_base_fee: 1,
 				transaction_byte_fee: 0,
@@ -207,6 +209,10 @@ mod tests {
 				creation_fee: 0,
 				reclaim_rebate: 0,
 				early_era_slash: 0,
+				misbehavior_slash: 0,
+				offline_slash: 0,
+				offline_slash_grace: 0,
+				report_after_missed: 0,
 				session_reward: 0,
 			}),
 			democracy: Some(Default::default()),
@@ -55,6 +55,7 @@ use version::RuntimeVersion;
 
 #[cfg(any(feature = "std", test))]
 pub use runtime_primitives::BuildStorage;
+pub use timestamp::Call as TimestampCall;
 
 // Workaround for https://github.com/rust-lang/rust/issues/26925 . Remove when sorted.
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -67,7 +68,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: ver_str!("demo"),
 	impl_name: ver_str!("parity-demo"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 2,
 	impl_version: 0,
 };
 
@@ -96,8 +97,11 @@ impl system::Trait for Concrete {
 pub type System = system::Module<Concrete>;
 
 impl consensus::Trait for Concrete {
+	const NOTE_OFFLINE_POSITION: u32 = 1;
 	type PublicAux = <Self as HasPublicAux>::PublicAux;
 	type SessionKey = SessionKey;
+	type OnMisbehavior = Staking;
+	type OnOfflineValidator = Staking;
 }
 
 /// Consensus module for this concrete runtime.
@@ -214,6 +218,7 @@ pub mod api {
 		apply_extrinsic => |extrinsic| super::Executive::apply_extrinsic(extrinsic),
 		execute_block => |block| super::Executive::execute_block(block),
 		finalise_block => |()| super::Executive::finalise_block(),
+		offchain_worker => |number| super::Executive::offchain_worker(number),
 		validator_count => |()| super::Session::validator_count(),
 		validators => |()| super::Session::validators()
 	);
@@ -79,6 +79,10 @@ impl<B: LocalBackend<Block>> PolkadotApi for Client<B, LocalCallExecutor<B, Nati
 		with_runtime!(self, at, ::runtime::Session::validators)
 	}
 
+	fn report_after_missed(&self, at: &BlockId) -> Result<u32> {
+		with_runtime!(self, at, ::runtime::Staking::report_after_missed)
+	}
+
 	fn random_seed(&self, at: &BlockId) -> Result<Hash> {
 		with_runtime!(self, at, ::runtime::System::random_seed)
 	}
@@ -91,6 +95,10 @@ impl<B: LocalBackend<Block>> PolkadotApi for Client<B, LocalCallExecutor<B, Nati
 		with_runtime!(self, at, ::runtime::Timestamp::get)
 	}
 
+	fn block_period(&self, at: &BlockId) -> Result<Timestamp> {
+		with_runtime!(self, at, ::runtime::Timestamp::block_period)
+	}
+
 	fn evaluate_block(&self, at: &BlockId, block: Block) -> Result<bool> {
 		use substrate_executor::error::ErrorKind as ExecErrorKind;
 		use codec::{Decode, Encode};
@@ -132,20 +140,20 @@ impl<B: LocalBackend<Block>> PolkadotApi for Client<B, LocalCallExecutor<B, Nati
 		with_runtime!(self, at, || ::runtime::Parachains::parachain_head(parachain))
 	}
 
-	fn build_block(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>) -> Result<Self::BlockBuilder> {
+	fn build_block(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>, offline_indices: Vec<u32>) -> Result<Self::BlockBuilder> {
 		let mut block_builder = self.new_block_at(at)?;
-		for inherent in self.inherent_extrinsics(at, timestamp, new_heads)? {
+		for inherent in self.inherent_extrinsics(at, timestamp, new_heads, offline_indices)? {
 			block_builder.push(inherent)?;
 		}
 
 		Ok(block_builder)
 	}
 
-	fn inherent_extrinsics(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>) -> Result<Vec<UncheckedExtrinsic>> {
+	fn inherent_extrinsics(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>, offline_indices: Vec<u32>) -> Result<Vec<UncheckedExtrinsic>> {
 		use codec::{Encode, Decode};
 
 		with_runtime!(self, at, || {
-			let extrinsics = ::runtime::inherent_extrinsics(timestamp, new_heads);
+			let extrinsics = ::runtime::inherent_extrinsics(timestamp, new_heads, offline_indices);
 			extrinsics.into_iter()
 				.map(|x| x.encode()) // get encoded representation
 				.map(|x| Decode::decode(&mut &x[..])) // get byte-vec equivalent to extrinsic
@@ -216,7 +224,7 @@ mod tests {
 		let client = client();
 
 		let id = BlockId::number(0);
-		let block_builder = client.build_block(&id, 1_000_000, Vec::new()).unwrap();
+		let block_builder = client.build_block(&id, 1_000_000, Vec::new(), Vec::new()).unwrap();
 		let block = block_builder.bake().unwrap();
 
 		assert_eq!(block.header.number, 1);
@@ -228,7 +236,7 @@ mod tests {
 		let client = client();
 
 		let id = BlockId::number(0);
-		let inherent = client.inherent_extrinsics(&id, 1_000_000, Vec::new()).unwrap();
+		let inherent = client.inherent_extrinsics(&id, 1_000_000, Vec::new(), Vec::new()).unwrap();
 
 		let mut block_builder = client.new_block_at(&id).unwrap();
 		for extrinsic in inherent {
@@ -241,6 +249,12 @@ mod tests {
 		assert!(block.header.extrinsics_root != Default::default());
 	}
 
+	#[test]
+	fn gets_block_period() {
+		let client = client();
+		assert_eq!(client.block_period(&BlockId::number(0)).unwrap(), 5);
+	}
+
 	#[test]
 	fn gets_random_seed_with_genesis() {
 		let client = client();
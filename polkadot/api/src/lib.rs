@@ -99,6 +99,10 @@ pub trait PolkadotApi {
 	/// Get validators at a given block.
 	fn validators(&self, at: &BlockId) -> Result<Vec<AccountId>>;
 
+	/// Get the number of consecutive agreements a validator may miss before it is reported
+	/// offline at a given block. Zero if the chain leaves it to the node.
+	fn report_after_missed(&self, at: &BlockId) -> Result<u32>;
+
 	/// Get the value of the randomness beacon at a given block.
 	fn random_seed(&self, at: &BlockId) -> Result<Hash>;
 
@@ -108,6 +112,9 @@ pub trait PolkadotApi {
 	/// Get the timestamp registered at a block.
 	fn timestamp(&self, at: &BlockId) -> Result<Timestamp>;
 
+	/// Get the minimum period between the timestamps of sequential blocks, at a block.
+	fn block_period(&self, at: &BlockId) -> Result<Timestamp>;
+
 	/// Get the nonce (né index) of an account at a block.
 	fn index(&self, at: &BlockId, account: AccountId) -> Result<Index>;
 
@@ -128,11 +135,11 @@ pub trait PolkadotApi {
 	fn evaluate_block(&self, at: &BlockId, block: Block) -> Result<bool>;
 
 	/// Build a block on top of the given, with inherent extrinsics pre-pushed.
-	fn build_block(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>) -> Result<Self::BlockBuilder>;
+	fn build_block(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>, offline_indices: Vec<u32>) -> Result<Self::BlockBuilder>;
 
 	/// Attempt to produce the (encoded) inherent extrinsics for a block being built upon the given.
 	/// This may vary by runtime and will fail if a runtime doesn't follow the same API.
-	fn inherent_extrinsics(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>) -> Result<Vec<UncheckedExtrinsic>>;
+	fn inherent_extrinsics(&self, at: &BlockId, timestamp: Timestamp, new_heads: Vec<CandidateReceipt>, offline_indices: Vec<u32>) -> Result<Vec<UncheckedExtrinsic>>;
 }
 
 /// Mark for all Polkadot API implementations, that are making use of state data, stored locally.
@@ -80,15 +80,21 @@ use futures::prelude::*;
 use futures::future;
 use collation::CollationFetch;
 use dynamic_inclusion::DynamicInclusion;
+use misbehavior::MisbehaviorReporter;
+use offline_tracker::{OfflineTracker, DEFAULT_REPORT_AFTER_MISSED};
+use parking_lot::RwLock;
 
 pub use self::collation::{validate_collation, Collators};
 pub use self::error::{ErrorKind, Error};
 pub use self::shared_table::{SharedTable, StatementProducer, ProducedStatements, Statement, SignedStatement, GenericStatement};
 pub use service::Service;
+pub use bft::VoteJournal;
 
 mod dynamic_inclusion;
 mod evaluation;
 mod error;
+mod misbehavior;
+mod offline_tracker;
 mod service;
 mod shared_table;
 
@@ -97,6 +103,9 @@ pub mod collation;
 // block size limit.
 const MAX_TRANSACTIONS_SIZE: usize = 4 * 1024 * 1024;
 
+/// An offline tracker shared between the consensus sessions of a node.
+pub type SharedOfflineTracker = Arc<RwLock<OfflineTracker>>;
+
 /// A handle to a statement table router.
 ///
 /// This is expected to be a lightweight, shared type like an `Arc`.
@@ -240,6 +249,8 @@ pub struct ProposerFactory<C, N, P> {
 	pub parachain_empty_duration: Duration,
 	/// Store for extrinsic data.
 	pub extrinsic_store: ExtrinsicStore,
+	/// Offline-tracker.
+	pub offline: SharedOfflineTracker,
 }
 
 impl<C, N, P> bft::Environment<Block> for ProposerFactory<C, N, P>
@@ -279,6 +290,14 @@ impl<C, N, P> bft::Environment<Block> for ProposerFactory<C, N, P>
 			parent_hash, local_duty.validation);
 
 		let active_parachains = self.client.active_parachains(&id)?;
+		let report_after_missed = match self.client.report_after_missed(&id) {
+			Ok(0) => DEFAULT_REPORT_AFTER_MISSED,
+			Ok(n) => n as usize,
+			Err(e) => {
+				debug!(target: "consensus", "Using default offline report threshold: {:?}", e);
+				DEFAULT_REPORT_AFTER_MISSED
+			}
+		};
 
 		debug!(target: "consensus", "Active parachains: {:?}", active_parachains);
 
@@ -316,10 +335,21 @@ impl<C, N, P> bft::Environment<Block> for ProposerFactory<C, N, P>
 			self.extrinsic_store.clone(),
 		);
 
+		let misbehavior = MisbehaviorReporter::new(
+			self.client.clone(),
+			self.transaction_pool.clone(),
+			sign_with,
+			parent_hash,
+			parent_header.number,
+		);
+
 		let proposer = Proposer {
+			authorities: authorities.to_vec(),
 			client: self.client.clone(),
 			dynamic_inclusion,
-			local_key: sign_with,
+			misbehavior,
+			offline: self.offline.clone(),
+			report_after_missed,
 			parent_hash,
 			parent_id: id,
 			parent_number: parent_header.number,
@@ -394,9 +424,12 @@ struct LocalDuty {
 
 /// The Polkadot proposer logic.
 pub struct Proposer<C: PolkadotApi> {
+	authorities: Vec<AuthorityId>,
 	client: Arc<C>,
 	dynamic_inclusion: DynamicInclusion,
-	local_key: Arc<ed25519::Pair>,
+	misbehavior: MisbehaviorReporter<C>,
+	offline: SharedOfflineTracker,
+	report_after_missed: usize,
 	parent_hash: Hash,
 	parent_id: BlockId,
 	parent_number: BlockNumber,
@@ -441,6 +474,7 @@ impl<C> bft::Proposer<Block> for Proposer<C>
 			client: self.client.clone(),
 			transaction_pool: self.transaction_pool.clone(),
 			table: self.table.clone(),
+			offline_indices: self.offline.read().reports(&self.authorities, self.report_after_missed),
 			timing,
 		})
 	}
@@ -473,6 +507,13 @@ impl<C> bft::Proposer<Block> for Proposer<C>
 			}
 		};
 
+		// refuse to slash validators which we don't consider offline ourselves.
+		if !self.offline.read().check_consistency(&self.authorities, proposal.noted_offline(), self.report_after_missed) {
+			debug!(target: "bft", "Proposal noted validators offline which seem online to us: {:?}",
+				proposal.noted_offline());
+			return Box::new(future::ok(false));
+		}
+
 		let vote_delays = {
 			let now = Instant::now();
 
@@ -549,66 +590,19 @@ impl<C> bft::Proposer<Block> for Proposer<C>
 	}
 
 	fn import_misbehavior(&self, misbehavior: Vec<(AuthorityId, bft::Misbehavior<Hash>)>) {
-		use rhododendron::Misbehavior as GenericMisbehavior;
-		use runtime_primitives::bft::{MisbehaviorKind, MisbehaviorReport};
-		use runtime_primitives::MaybeUnsigned;
-		use polkadot_runtime::{Call, Extrinsic, BareExtrinsic, UncheckedExtrinsic, ConsensusCall};
-
-		let local_id = self.local_key.public().0.into();
-		let mut next_index = {
-			let cur_index = self.transaction_pool.cull_and_get_pending(BlockId::hash(self.parent_hash), |pending| pending
-				.filter(|tx| tx.sender().map(|s| s == local_id).unwrap_or(false))
-				.last()
-				.map(|tx| Ok(tx.index()))
-				.unwrap_or_else(|| self.client.index(&self.parent_id, local_id))
-			);
-
-			match cur_index {
-				Ok(Ok(cur_index)) => cur_index + 1,
-				Ok(Err(e)) => {
-					warn!(target: "consensus", "Error computing next transaction index: {}", e);
-					return;
-				}
-				Err(e) => {
-					warn!(target: "consensus", "Error computing next transaction index: {}", e);
-					return;
-				}
-			}
-		};
-
-		for (target, misbehavior) in misbehavior {
-			let report = MisbehaviorReport {
-				parent_hash: self.parent_hash,
-				parent_number: self.parent_number,
-				target,
-				misbehavior: match misbehavior {
-					GenericMisbehavior::ProposeOutOfTurn(_, _, _) => continue,
-					GenericMisbehavior::DoublePropose(_, _, _) => continue,
-					GenericMisbehavior::DoublePrepare(round, (h1, s1), (h2, s2))
-						=> MisbehaviorKind::BftDoublePrepare(round as u32, (h1, s1.signature), (h2, s2.signature)),
-					GenericMisbehavior::DoubleCommit(round, (h1, s1), (h2, s2))
-						=> MisbehaviorKind::BftDoubleCommit(round as u32, (h1, s1.signature), (h2, s2.signature)),
-				}
-			};
-			let extrinsic = BareExtrinsic {
-				signed: local_id,
-				index: next_index,
-				function: Call::Consensus(ConsensusCall::report_misbehavior(report)),
-			};
-
-			next_index += 1;
-
-			let signature = MaybeUnsigned(self.local_key.sign(&extrinsic.encode()).into());
+		self.misbehavior.report_bft(misbehavior);
+	}
 
-			let extrinsic = Extrinsic {
-				signed: extrinsic.signed.into(),
-				index: extrinsic.index,
-				function: extrinsic.function,
-			};
-			let uxt = UncheckedExtrinsic::new(extrinsic, signature);
+	fn on_committed(&self, round_number: usize, signers: &[AuthorityId]) {
+		// the proposers of all earlier rounds failed to get their proposal committed.
+		let failed_proposers = (0..round_number)
+			.map(|round| bft::Proposer::round_proposer(self, round, &self.authorities))
+			.collect::<HashSet<_>>();
 
-			self.transaction_pool.import_unchecked_extrinsic(BlockId::hash(self.parent_hash), uxt)
-				.expect("locally signed extrinsic is valid; qed");
+		let mut offline = self.offline.write();
+		for authority in &self.authorities {
+			let was_online = signers.contains(authority) && !failed_proposers.contains(authority);
+			offline.note_round_end(*authority, was_online);
 		}
 	}
 }
@@ -666,6 +660,7 @@ pub struct CreateProposal<C: PolkadotApi>  {
 	client: Arc<C>,
 	transaction_pool: Arc<TransactionPool<C>>,
 	table: Arc<SharedTable>,
+	offline_indices: Vec<u32>,
 	timing: ProposalTiming,
 }
 
@@ -676,7 +671,12 @@ impl<C> CreateProposal<C> where C: PolkadotApi {
 
 		// TODO: handle case when current timestamp behind that in state.
 		let timestamp = current_timestamp();
-		let mut block_builder = self.client.build_block(&self.parent_id, timestamp, candidates)?;
+		let mut block_builder = self.client.build_block(
+			&self.parent_id,
+			timestamp,
+			candidates,
+			self.offline_indices.clone(),
+		)?;
 
 		{
 			let mut unqueue_invalid = Vec::new();
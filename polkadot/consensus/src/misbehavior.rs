@@ -0,0 +1,243 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Submission of misbehavior witnessed during a consensus session.
+//!
+//! Double votes observed by the BFT agreement are turned into `MisbehaviorReport`
+//! extrinsics signed with the local key and imported into the transaction pool,
+//! so that the offenders get slashed on chain. Misbehavior in the statement table
+//! isn't reported, as the runtime has no way of checking candidate statements yet.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use codec::Encode;
+use ed25519;
+use parking_lot::Mutex;
+use polkadot_api::PolkadotApi;
+use polkadot_primitives::{Hash, BlockId, BlockNumber};
+use primitives::AuthorityId;
+use rhododendron::Misbehavior as GenericMisbehavior;
+use runtime_primitives::bft::{MisbehaviorKind, MisbehaviorReport};
+use transaction_pool::TransactionPool;
+
+use bft;
+
+/// Collects the misbehavior witnessed during the consensus session on top of a
+/// single parent block and submits reports for it.
+pub struct MisbehaviorReporter<C: PolkadotApi> {
+	client: Arc<C>,
+	transaction_pool: Arc<TransactionPool<C>>,
+	local_key: Arc<ed25519::Pair>,
+	parent_hash: Hash,
+	parent_number: BlockNumber,
+	// offenders we have already submitted a report for.
+	reported: Mutex<HashSet<AuthorityId>>,
+}
+
+impl<C: PolkadotApi> MisbehaviorReporter<C> {
+	/// Create a new reporter for the session on top of the given parent.
+	pub fn new(
+		client: Arc<C>,
+		transaction_pool: Arc<TransactionPool<C>>,
+		local_key: Arc<ed25519::Pair>,
+		parent_hash: Hash,
+		parent_number: BlockNumber,
+	) -> Self {
+		MisbehaviorReporter {
+			client,
+			transaction_pool,
+			local_key,
+			parent_hash,
+			parent_number,
+			reported: Mutex::new(HashSet::new()),
+		}
+	}
+
+	/// Report misbehavior witnessed by the BFT agreement. Only double prepares and
+	/// double commits can be checked on chain; other kinds are ignored.
+	pub fn report_bft(&self, misbehavior: Vec<(AuthorityId, bft::Misbehavior<Hash>)>) {
+		let reports = bft_reports(
+			self.parent_hash,
+			self.parent_number,
+			misbehavior,
+			&mut *self.reported.lock(),
+		);
+
+		if !reports.is_empty() {
+			self.submit(reports);
+		}
+	}
+
+	fn submit(&self, reports: Vec<MisbehaviorReport<Hash, BlockNumber>>) {
+		use runtime_primitives::MaybeUnsigned;
+		use polkadot_runtime::{Call, Extrinsic, BareExtrinsic, UncheckedExtrinsic, ConsensusCall};
+
+		let local_id = self.local_key.public().0.into();
+		let mut next_index = {
+			let next_index = self.transaction_pool.cull_and_get_pending(BlockId::hash(self.parent_hash), |pending| pending
+				.filter(|tx| tx.sender().map(|s| s == local_id).unwrap_or(false))
+				.last()
+				.map(|tx| Ok(tx.index() + 1))
+				.unwrap_or_else(|| self.client.index(&BlockId::hash(self.parent_hash), local_id))
+			);
+
+			match next_index {
+				Ok(Ok(next_index)) => next_index,
+				Ok(Err(e)) => {
+					warn!(target: "consensus", "Error computing next transaction index: {}", e);
+					return;
+				}
+				Err(e) => {
+					warn!(target: "consensus", "Error computing next transaction index: {}", e);
+					return;
+				}
+			}
+		};
+
+		for report in reports {
+			info!(target: "consensus", "Reporting misbehavior of {} on top of {}",
+				report.target, self.parent_hash);
+
+			let extrinsic = BareExtrinsic {
+				signed: local_id,
+				index: next_index,
+				function: Call::Consensus(ConsensusCall::report_misbehavior(report)),
+			};
+
+			next_index += 1;
+
+			let signature = MaybeUnsigned(self.local_key.sign(&extrinsic.encode()).into());
+
+			let extrinsic = Extrinsic {
+				signed: extrinsic.signed.into(),
+				index: extrinsic.index,
+				function: extrinsic.function,
+			};
+			let uxt = UncheckedExtrinsic::new(extrinsic, signature);
+
+			if let Err(e) = self.transaction_pool.import_unchecked_extrinsic(BlockId::hash(self.parent_hash), uxt) {
+				warn!(target: "consensus", "Failed to submit misbehavior report: {:?}", e);
+			}
+		}
+	}
+}
+
+/// Convert misbehavior witnessed by the BFT agreement into reports which can be checked
+/// on chain, skipping offenders in `reported` and adding the new ones to it.
+fn bft_reports(
+	parent_hash: Hash,
+	parent_number: BlockNumber,
+	misbehavior: Vec<(AuthorityId, bft::Misbehavior<Hash>)>,
+	reported: &mut HashSet<AuthorityId>,
+) -> Vec<MisbehaviorReport<Hash, BlockNumber>> {
+	misbehavior.into_iter()
+		.filter_map(|(target, misbehavior)| {
+			let misbehavior = match misbehavior {
+				GenericMisbehavior::ProposeOutOfTurn(_, _, _) => return None,
+				GenericMisbehavior::DoublePropose(_, _, _) => return None,
+				GenericMisbehavior::DoublePrepare(round, (h1, s1), (h2, s2))
+					=> MisbehaviorKind::BftDoublePrepare(round as u32, (h1, s1.signature), (h2, s2.signature)),
+				GenericMisbehavior::DoubleCommit(round, (h1, s1), (h2, s2))
+					=> MisbehaviorKind::BftDoubleCommit(round as u32, (h1, s1.signature), (h2, s2.signature)),
+			};
+
+			// the runtime only accepts one report per offender and parent.
+			if !reported.insert(target) {
+				return None;
+			}
+
+			Some(MisbehaviorReport {
+				parent_hash,
+				parent_number,
+				target,
+				misbehavior,
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ed25519::LocalizedSignature;
+	use substrate_keyring::Keyring;
+
+	fn signed(who: Keyring, hash: Hash) -> (Hash, LocalizedSignature) {
+		let signature = LocalizedSignature {
+			signer: who.pair().public(),
+			signature: who.sign(hash.as_ref()),
+		};
+		(hash, signature)
+	}
+
+	#[test]
+	fn converts_double_votes_into_reports() {
+		let parent_hash = [0; 32].into();
+		let offender: AuthorityId = Keyring::Bob.to_raw_public().into();
+		let other: AuthorityId = Keyring::Charlie.to_raw_public().into();
+		let (h1, s1) = signed(Keyring::Bob, [1; 32].into());
+		let (h2, s2) = signed(Keyring::Bob, [2; 32].into());
+
+		let misbehavior = vec![
+			(offender, GenericMisbehavior::DoublePrepare(3, (h1, s1.clone()), (h2, s2.clone()))),
+			(other, GenericMisbehavior::DoubleCommit(4, signed(Keyring::Charlie, h1), signed(Keyring::Charlie, h2))),
+		];
+
+		let reports = bft_reports(parent_hash, 5, misbehavior, &mut HashSet::new());
+		assert_eq!(reports.len(), 2);
+		assert_eq!(reports[0].target, offender);
+		assert_eq!(reports[0].parent_hash, parent_hash);
+		assert_eq!(reports[0].parent_number, 5);
+		assert_eq!(reports[0].misbehavior, MisbehaviorKind::BftDoublePrepare(3, (h1, s1.signature), (h2, s2.signature)));
+		assert_eq!(reports[1].target, other);
+		match reports[1].misbehavior {
+			MisbehaviorKind::BftDoubleCommit(4, _, _) => {},
+			_ => panic!("double commit must be reported as such"),
+		}
+	}
+
+	#[test]
+	fn ignores_proposal_misbehavior() {
+		let offender: AuthorityId = Keyring::Bob.to_raw_public().into();
+		let (h1, s1) = signed(Keyring::Bob, [1; 32].into());
+		let (_, s2) = signed(Keyring::Bob, [2; 32].into());
+
+		let misbehavior = vec![
+			(offender, GenericMisbehavior::ProposeOutOfTurn(1, h1, s1.clone())),
+			(offender, GenericMisbehavior::DoublePropose(1, (h1, s1), ([2; 32].into(), s2))),
+		];
+
+		assert!(bft_reports([0; 32].into(), 5, misbehavior, &mut HashSet::new()).is_empty());
+	}
+
+	#[test]
+	fn reports_each_offender_once() {
+		let offender: AuthorityId = Keyring::Bob.to_raw_public().into();
+		let prepare = || GenericMisbehavior::DoublePrepare(
+			1,
+			signed(Keyring::Bob, [1; 32].into()),
+			signed(Keyring::Bob, [2; 32].into()),
+		);
+
+		let mut reported = HashSet::new();
+		let reports = bft_reports([0; 32].into(), 5, vec![(offender, prepare()), (offender, prepare())], &mut reported);
+		assert_eq!(reports.len(), 1);
+		assert!(reported.contains(&offender));
+
+		assert!(bft_reports([0; 32].into(), 5, vec![(offender, prepare())], &mut reported).is_empty());
+	}
+}
@@ -0,0 +1,103 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracks offline validators.
+//!
+//! A validator is considered offline after it failed to take part in a number of
+//! consecutive agreements, either by not proposing in its round or by not signing
+//! the justification of the committed block.
+
+use std::collections::HashMap;
+
+use primitives::AuthorityId;
+
+/// Number of consecutive agreements a validator may miss before it is reported, unless
+/// the chain sets its own `report_after_missed`.
+pub const DEFAULT_REPORT_AFTER_MISSED: usize = 5;
+
+/// Tracks offline validators and can issue a report for those offline.
+#[derive(Default)]
+pub struct OfflineTracker {
+	// consecutive agreements missed by each validator.
+	missed: HashMap<AuthorityId, usize>,
+}
+
+impl OfflineTracker {
+	/// Create a new tracker.
+	pub fn new() -> Self {
+		OfflineTracker::default()
+	}
+
+	/// Note the end of an agreement and whether the validator took part in it.
+	pub fn note_round_end(&mut self, validator: AuthorityId, was_online: bool) {
+		if was_online {
+			self.missed.remove(&validator);
+		} else {
+			*self.missed.entry(validator).or_insert(0) += 1;
+		}
+	}
+
+	/// The indices of the given validators which missed at least `report_after_missed`
+	/// consecutive agreements.
+	pub fn reports(&self, validators: &[AuthorityId], report_after_missed: usize) -> Vec<u32> {
+		validators.iter()
+			.enumerate()
+			.filter(|&(_, v)| self.is_offline(v, report_after_missed))
+			.map(|(i, _)| i as u32)
+			.collect()
+	}
+
+	/// Whether the validators at the proposed indices are all considered offline
+	/// by this tracker as well.
+	pub fn check_consistency(&self, validators: &[AuthorityId], proposed: &[u32], report_after_missed: usize) -> bool {
+		proposed.iter().all(|&i| validators.get(i as usize)
+			.map_or(false, |v| self.is_offline(v, report_after_missed)))
+	}
+
+	fn is_offline(&self, validator: &AuthorityId, report_after_missed: usize) -> bool {
+		self.missed.get(validator).map_or(false, |&missed| missed >= report_after_missed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validator_offline_after_missing_enough_rounds() {
+		let mut tracker = OfflineTracker::new();
+		let v1: AuthorityId = [0; 32].into();
+		let v2: AuthorityId = [1; 32].into();
+		let validators = vec![v1, v2];
+
+		for _ in 0..2 {
+			tracker.note_round_end(v1, false);
+			tracker.note_round_end(v2, true);
+		}
+		assert!(tracker.reports(&validators, 3).is_empty());
+		assert!(!tracker.check_consistency(&validators, &[0], 3));
+		assert_eq!(tracker.reports(&validators, 2), vec![0]);
+
+		tracker.note_round_end(v1, false);
+		assert_eq!(tracker.reports(&validators, 3), vec![0]);
+		assert!(tracker.check_consistency(&validators, &[0], 3));
+		assert!(!tracker.check_consistency(&validators, &[0, 1], 3));
+		assert!(!tracker.check_consistency(&validators, &[2], 3));
+
+		tracker.note_round_end(v1, true);
+		assert!(tracker.reports(&validators, 3).is_empty());
+	}
+}
@@ -27,14 +27,16 @@ use std::thread;
 use std::time::{Duration, Instant};
 use std::sync::Arc;
 
-use bft::{self, BftService};
+use bft::{self, BftService, VoteJournal};
 use client::{BlockchainEvents, ChainHead, BlockBody};
 use ed25519;
 use futures::prelude::*;
 use polkadot_api::LocalPolkadotApi;
-use polkadot_primitives::{Block, Header};
+use polkadot_primitives::{Block, Hash, Header};
 use transaction_pool::TransactionPool;
 use extrinsic_store::Store as ExtrinsicStore;
+use offline_tracker::OfflineTracker;
+use parking_lot::RwLock;
 
 use tokio::executor::current_thread::TaskExecutor as LocalThreadHandle;
 use tokio::runtime::TaskExecutor as ThreadPoolHandle;
@@ -157,6 +159,7 @@ impl Service {
 		parachain_empty_duration: Duration,
 		key: ed25519::Pair,
 		extrinsic_store: ExtrinsicStore,
+		vote_journal: VoteJournal<Hash>,
 	) -> Service
 		where
 			A: LocalPolkadotApi + Send + Sync + 'static,
@@ -170,6 +173,7 @@ impl Service {
 		let thread = thread::spawn(move || {
 			let mut runtime = LocalRuntime::new().expect("Could not create local runtime");
 			let key = Arc::new(key);
+			let offline = Arc::new(RwLock::new(OfflineTracker::new()));
 
 			let factory = ProposerFactory {
 				client: api.clone(),
@@ -179,8 +183,9 @@ impl Service {
 				parachain_empty_duration,
 				handle: thread_pool.clone(),
 				extrinsic_store: extrinsic_store.clone(),
+				offline: offline.clone(),
 			};
-			let bft_service = Arc::new(BftService::new(client.clone(), key, factory));
+			let bft_service = Arc::new(BftService::new(client.clone(), key, factory, Arc::new(vote_journal)));
 
 			let notifications = {
 				let client = client.clone();
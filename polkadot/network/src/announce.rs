@@ -0,0 +1,143 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of block announcements.
+//!
+//! Blocks authored by aura may fork, so announcements of competing blocks above
+//! the finalized block are legitimate. An announcement which conflicts with a
+//! finalized block, or which doesn't extend its known parent, can only come from
+//! a misbehaving peer.
+
+use polkadot_primitives::{Block, BlockId, BlockNumber, Hash, Header};
+use substrate_network::{ClientHandle, BlockAnnounceValidation, BlockAnnounceValidator};
+
+/// Maximum number of blocks an announced block may be ahead of our best block.
+pub const MAX_BLOCKS_AHEAD: BlockNumber = 1024;
+
+/// Validates announcements of Polkadot blocks against the local chain.
+#[derive(Default)]
+pub struct AnnounceValidator;
+
+impl BlockAnnounceValidator<Block> for AnnounceValidator {
+	fn validate(&self, client: &ClientHandle<Block>, header: &Header) -> BlockAnnounceValidation {
+		let (best_number, finalized_number) = match client.info() {
+			Ok(info) => (info.chain.best_number, info.chain.finalized_number),
+			Err(e) => {
+				debug!(target: "p_net", "Unable to fetch chain info to validate announcement: {:?}", e);
+				return BlockAnnounceValidation::Valid;
+			}
+		};
+		let imported_hash = client.block_hash(header.number).ok().and_then(|h| h);
+		let parent_number = client.header(&BlockId::hash(header.parent_hash)).ok()
+			.and_then(|h| h)
+			.map(|parent| parent.number);
+
+		check_announcement(header, best_number, finalized_number, imported_hash, parent_number)
+	}
+}
+
+/// Check an announced `header` given our best and finalized block numbers, the hash
+/// of the best chain block at the same height, and the number of its parent, if known.
+pub(crate) fn check_announcement(
+	header: &Header,
+	best_number: BlockNumber,
+	finalized_number: BlockNumber,
+	imported_hash: Option<Hash>,
+	parent_number: Option<BlockNumber>,
+) -> BlockAnnounceValidation {
+	if header.number <= finalized_number && imported_hash.map_or(false, |hash| hash != header.hash()) {
+		return BlockAnnounceValidation::Invalid(
+			format!("Block #{} conflicts with a finalized block", header.number)
+		);
+	}
+
+	if let Some(parent_number) = parent_number {
+		if parent_number + 1 != header.number {
+			return BlockAnnounceValidation::Invalid(
+				format!("Block #{} doesn't extend its parent #{}", header.number, parent_number)
+			);
+		}
+	}
+
+	if header.number > best_number.saturating_add(MAX_BLOCKS_AHEAD) {
+		return BlockAnnounceValidation::Invalid(
+			format!("Block #{} is too far ahead of best block #{}", header.number, best_number)
+		);
+	}
+
+	BlockAnnounceValidation::Valid
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn header(number: BlockNumber) -> Header {
+		Header {
+			parent_hash: Default::default(),
+			number,
+			state_root: Default::default(),
+			extrinsics_root: [1; 32].into(),
+			digest: Default::default(),
+		}
+	}
+
+	#[test]
+	fn accepts_block_extending_the_chain() {
+		let header = header(11);
+		assert_eq!(check_announcement(&header, 10, 10, None, Some(10)), BlockAnnounceValidation::Valid);
+		assert_eq!(check_announcement(&header, 10, 10, None, None), BlockAnnounceValidation::Valid);
+		assert_eq!(check_announcement(&header, 11, 11, Some(header.hash()), Some(10)), BlockAnnounceValidation::Valid);
+	}
+
+	#[test]
+	fn rejects_block_conflicting_with_finalized_one() {
+		let header = header(5);
+		match check_announcement(&header, 10, 5, Some([2; 32].into()), Some(4)) {
+			BlockAnnounceValidation::Invalid(_) => {},
+			_ => panic!("conflicting announcement must be invalid"),
+		}
+	}
+
+	#[test]
+	fn accepts_fork_above_finalized_block() {
+		// a competing aura block at the height of our best block.
+		let header = header(5);
+		assert_eq!(check_announcement(&header, 5, 4, Some([2; 32].into()), Some(4)), BlockAnnounceValidation::Valid);
+		assert_eq!(check_announcement(&header, 10, 0, Some([2; 32].into()), Some(4)), BlockAnnounceValidation::Valid);
+	}
+
+	#[test]
+	fn rejects_block_not_extending_its_parent() {
+		match check_announcement(&header(8), 10, 0, None, Some(4)) {
+			BlockAnnounceValidation::Invalid(_) => {},
+			_ => panic!("announcement with a wrong number must be invalid"),
+		}
+	}
+
+	#[test]
+	fn rejects_block_too_far_ahead() {
+		let header = header(10 + MAX_BLOCKS_AHEAD + 1);
+		match check_announcement(&header, 10, 0, None, None) {
+			BlockAnnounceValidation::Invalid(_) => {},
+			_ => panic!("announcement far ahead of the best block must be invalid"),
+		}
+		assert_eq!(
+			check_announcement(&header, 11, 0, None, None),
+			BlockAnnounceValidation::Valid
+		);
+	}
+}
@@ -80,9 +80,12 @@ impl<E> Sink for BftSink<E> {
 			},
 			parent_hash: self.parent_hash,
 		};
-		self.network.with_spec(
-			move |spec, ctx| spec.consensus_gossip.multicast_bft_message(ctx, network_message)
-		);
+		let parent_hash = self.parent_hash;
+		self.network.with_spec(move |spec, ctx| spec.consensus_gossip.multicast(
+			ctx,
+			parent_hash,
+			ConsensusMessage::Bft(network_message),
+		));
 		Ok(::futures::AsyncSink::Ready)
 	}
 
@@ -175,7 +178,7 @@ impl<P: LocalPolkadotApi + Send + Sync + 'static> MessageProcessTask<P> {
 					}
 				}
 			}
-			ConsensusMessage::ChainSpecific(msg, _) => {
+			ConsensusMessage::ChainSpecific(msg) => {
 				debug!(target: "consensus", "Processing consensus statement for live consensus");
 				if let Some(Message::Statement(parent_hash, statement)) = Decode::decode(&mut msg.as_slice()) {
 					if ::polkadot_consensus::check_statement(&statement.statement, &statement.signature, statement.sender, &parent_hash) {
@@ -16,12 +16,13 @@
 
 //! Polkadot-specific network implementation.
 //!
-//! This manages gossip of consensus messages for BFT and for parachain statements,
-//! parachain block and extrinsic data fetching, communication between collators and validators,
+//! This manages gossip of consensus messages for BFT, for parachain statements and for
+//! finality votes, parachain block and extrinsic data fetching, communication between collators and validators,
 //! and more.
 
 extern crate substrate_bft as bft;
 extern crate substrate_codec as codec;
+extern crate substrate_finality as finality;
 extern crate substrate_network;
 extern crate substrate_primitives;
 
@@ -39,6 +40,7 @@ extern crate rhododendron;
 #[macro_use]
 extern crate log;
 
+mod announce;
 mod collator_pool;
 mod local_collations;
 mod router;
@@ -51,20 +53,27 @@ use polkadot_consensus::{Statement, SignedStatement, GenericStatement};
 use polkadot_primitives::{AccountId, Block, SessionKey, Hash, Header};
 use polkadot_primitives::parachain::{Id as ParaId, BlockData, Extrinsic, CandidateReceipt, Collation};
 use substrate_network::{NodeIndex, RequestId, Context, Severity};
-use substrate_network::consensus_gossip::ConsensusGossip;
+use substrate_network::consensus_gossip::{
+	ConsensusGossip, ConsensusMessage, ValidationResult, Validator as GossipValidator,
+};
 use substrate_network::{message, generic_message};
 use substrate_network::specialization::Specialization;
+use substrate_network::{BlockAnnounceValidation, BlockAnnounceValidator};
 use substrate_network::StatusMessage as GenericFullStatus;
+use self::announce::AnnounceValidator;
 use self::collator_pool::{CollatorPool, Role, Action};
 use self::local_collations::LocalCollations;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 
 #[cfg(test)]
 mod tests;
 
+/// Maximum number of concluded consensus parent hashes to remember.
+const MAX_CONCLUDED_TOPICS: usize = 64;
+
 /// Polkadot protocol id.
 pub const DOT_PROTOCOL_ID: ::substrate_network::ProtocolId = *b"dot";
 
@@ -206,6 +215,49 @@ impl CurrentConsensus {
 	}
 }
 
+/// Validates gossiped BFT messages and parachain statements. Both are
+/// gossiped under the relay-chain parent hash as the topic, and expire once
+/// consensus on that parent has concluded.
+#[derive(Default)]
+struct MessageValidator {
+	// parent hashes of concluded consensus rounds, oldest first.
+	concluded: Mutex<VecDeque<Hash>>,
+}
+
+impl MessageValidator {
+	fn note_concluded(&self, parent_hash: Hash) {
+		let mut concluded = self.concluded.lock();
+		if concluded.contains(&parent_hash) { return }
+
+		if concluded.len() >= MAX_CONCLUDED_TOPICS {
+			concluded.pop_front();
+		}
+		concluded.push_back(parent_hash);
+	}
+}
+
+impl GossipValidator<Block> for MessageValidator {
+	fn validate(&self, message: &ConsensusMessage<Block>) -> ValidationResult<Hash> {
+		let topic = match *message {
+			ConsensusMessage::Bft(ref msg) => msg.parent_hash,
+			ConsensusMessage::ChainSpecific(ref raw) => match Message::decode(&mut raw.as_slice()) {
+				Some(Message::Statement(parent_hash, _)) => parent_hash,
+				_ => return ValidationResult::Discard,
+			},
+		};
+
+		if self.concluded.lock().contains(&topic) {
+			ValidationResult::Discard
+		} else {
+			ValidationResult::ProcessAndKeep(topic)
+		}
+	}
+
+	fn message_expired(&self, topic: &Hash, _message: &ConsensusMessage<Block>) -> bool {
+		self.concluded.lock().contains(topic)
+	}
+}
+
 /// Polkadot-specific messages.
 #[derive(Debug)]
 pub enum Message {
@@ -222,6 +274,8 @@ pub enum Message {
 	CollatorRole(Role),
 	/// A collation provided by a peer. Relay parent and collation.
 	Collation(Hash, Collation),
+	/// A finality vote or commit.
+	Finality(Vec<u8>),
 }
 
 impl Encode for Message {
@@ -256,6 +310,10 @@ impl Encode for Message {
 				dest.push(h);
 				dest.push(c);
 			}
+			Message::Finality(ref m) => {
+				dest.push_byte(6);
+				dest.push(m);
+			}
 		}
 	}
 }
@@ -272,6 +330,7 @@ impl Decode for Message {
 			3 => Some(Message::BlockData(Decode::decode(input)?, Decode::decode(input)?)),
 			4 => Some(Message::CollatorRole(Decode::decode(input)?)),
 			5 => Some(Message::Collation(Decode::decode(input)?, Decode::decode(input)?)),
+			6 => Some(Message::Finality(Decode::decode(input)?)),
 			_ => None,
 		}
 	}
@@ -287,7 +346,10 @@ fn send_polkadot_message(ctx: &mut Context<Block>, to: NodeIndex, message: Messa
 pub struct PolkadotProtocol {
 	peers: HashMap<NodeIndex, PeerInfo>,
 	collating_for: Option<(AccountId, ParaId)>,
+	gossip_validator: Arc<MessageValidator>,
 	consensus_gossip: ConsensusGossip<Block>,
+	finality_gossip: Option<(ConsensusGossip<Block>, Arc<finality::communication::GossipValidator>)>,
+	only_sentries: bool,
 	collators: CollatorPool,
 	validators: HashMap<SessionKey, NodeIndex>,
 	local_collations: LocalCollations<Collation>,
@@ -301,9 +363,13 @@ pub struct PolkadotProtocol {
 impl PolkadotProtocol {
 	/// Instantiate a polkadot protocol handler.
 	pub fn new(collating_for: Option<(AccountId, ParaId)>) -> Self {
+		let gossip_validator = Arc::new(MessageValidator::default());
 		PolkadotProtocol {
 			peers: HashMap::new(),
-			consensus_gossip: ConsensusGossip::new(),
+			consensus_gossip: ConsensusGossip::new(gossip_validator.clone()),
+			gossip_validator,
+			finality_gossip: None,
+			only_sentries: false,
 			collators: CollatorPool::new(),
 			collating_for,
 			validators: HashMap::new(),
@@ -316,11 +382,34 @@ impl PolkadotProtocol {
 		}
 	}
 
+	/// Only gossip with sentry nodes. To be used when the local validator is
+	/// hidden behind sentries.
+	pub fn gossip_only_with_sentries(&mut self) {
+		self.only_sentries = true;
+		self.consensus_gossip.gossip_only_with_sentries();
+		if let Some((ref mut gossip, _)) = self.finality_gossip {
+			gossip.gossip_only_with_sentries();
+		}
+	}
+
+	/// Gossip finality votes and commits, of rounds lasting `round_duration` seconds.
+	pub fn enable_finality(&mut self, round_duration: u64) {
+		let validator = Arc::new(finality::communication::GossipValidator::new(
+			round_duration,
+			<Self as finality::communication::FinalityGossip<Block>>::unwrap_message,
+		));
+		let mut gossip = ConsensusGossip::new(validator.clone());
+		if self.only_sentries {
+			gossip.gossip_only_with_sentries();
+		}
+		self.finality_gossip = Some((gossip, validator));
+	}
+
 	/// Gossip a consensus statement.
 	fn gossip_statement(&mut self, ctx: &mut Context<Block>, parent_hash: Hash, statement: SignedStatement) {
 		// TODO: something more targeted than gossip.
 		let raw = Message::Statement(parent_hash, statement).encode();
-		self.consensus_gossip.multicast_chain_specific(ctx, raw, parent_hash);
+		self.consensus_gossip.multicast(ctx, parent_hash, ConsensusMessage::ChainSpecific(raw));
 	}
 
 	/// Fetch block data by candidate receipt.
@@ -355,8 +444,14 @@ impl PolkadotProtocol {
 			}
 		}
 
+		if let Some((old_parent, _)) = old_data {
+			if old_parent != consensus.parent_hash {
+				self.gossip_validator.note_concluded(old_parent);
+			}
+		}
+
 		self.live_consensus = Some(consensus);
-		self.consensus_gossip.collect_garbage(old_data.as_ref().map(|&(ref hash, _)| hash));
+		self.consensus_gossip.collect_garbage();
 	}
 
 	fn dispatch_pending_requests(&mut self, ctx: &mut Context<Block>) {
@@ -412,8 +507,8 @@ impl PolkadotProtocol {
 	fn on_polkadot_message(&mut self, ctx: &mut Context<Block>, who: NodeIndex, raw: Vec<u8>, msg: Message) {
 		trace!(target: "p_net", "Polkadot message from {}: {:?}", who, msg);
 		match msg {
-			Message::Statement(parent_hash, _statement) =>
-				self.consensus_gossip.on_chain_specific(ctx, who, raw, parent_hash),
+			Message::Statement(_, _) =>
+				self.consensus_gossip.on_incoming(ctx, who, ConsensusMessage::ChainSpecific(raw)),
 			Message::SessionKey(key) => self.on_session_key(ctx, who, key),
 			Message::RequestBlockData(req_id, relay_parent, candidate_hash) => {
 				let block_data = self.live_consensus.as_ref()
@@ -427,6 +522,10 @@ impl PolkadotProtocol {
 			Message::BlockData(req_id, data) => self.on_block_data(ctx, who, req_id, data),
 			Message::Collation(relay_parent, collation) => self.on_collation(ctx, who, relay_parent, collation),
 			Message::CollatorRole(role) => self.on_new_role(ctx, who, role),
+			Message::Finality(_) => match self.finality_gossip {
+				Some((ref mut gossip, _)) => gossip.on_incoming(ctx, who, ConsensusMessage::ChainSpecific(raw)),
+				None => trace!(target: "p_net", "Ignoring finality message from {}: finality disabled", who),
+			},
 		}
 	}
 
@@ -557,6 +656,9 @@ impl Specialization<Block> for PolkadotProtocol {
 
 		self.peers.insert(who, peer_info);
 		self.consensus_gossip.new_peer(ctx, who, status.roles);
+		if let Some((ref mut gossip, _)) = self.finality_gossip {
+			gossip.new_peer(ctx, who, status.roles);
+		}
 		self.dispatch_pending_requests(ctx);
 	}
 
@@ -599,6 +701,9 @@ impl Specialization<Block> for PolkadotProtocol {
 				});
 			}
 			self.consensus_gossip.peer_disconnected(ctx, who);
+			if let Some((ref mut gossip, _)) = self.finality_gossip {
+				gossip.peer_disconnected(ctx, who);
+			}
 			self.dispatch_pending_requests(ctx);
 		}
 	}
@@ -608,7 +713,7 @@ impl Specialization<Block> for PolkadotProtocol {
 			generic_message::Message::BftMessage(msg) => {
 				trace!(target: "p_net", "Polkadot BFT message from {}: {:?}", who, msg);
 				// TODO: check signature here? what if relevant block is unknown?
-				self.consensus_gossip.on_bft_message(ctx, who, msg)
+				self.consensus_gossip.on_incoming(ctx, who, ConsensusMessage::Bft(msg))
 			}
 			generic_message::Message::ChainSpecific(raw) => {
 				match Message::decode(&mut raw.as_slice()) {
@@ -625,10 +730,16 @@ impl Specialization<Block> for PolkadotProtocol {
 
 	fn on_abort(&mut self) {
 		self.consensus_gossip.abort();
+		if let Some((ref mut gossip, _)) = self.finality_gossip {
+			gossip.abort();
+		}
 	}
 
 	fn maintain_peers(&mut self, ctx: &mut Context<Block>) {
-		self.consensus_gossip.collect_garbage(None);
+		self.consensus_gossip.collect_garbage();
+		if let Some((ref mut gossip, _)) = self.finality_gossip {
+			gossip.collect_garbage();
+		}
 		self.collators.collect_garbage(None);
 		self.local_collations.collect_garbage(None);
 		self.dispatch_pending_requests(ctx);
@@ -647,12 +758,33 @@ impl Specialization<Block> for PolkadotProtocol {
 		}
 	}
 
+	fn validate_block_announce(&self, ctx: &Context<Block>, _who: NodeIndex, header: &Header) -> BlockAnnounceValidation {
+		AnnounceValidator.validate(ctx.client(), header)
+	}
+
 	fn on_block_imported(&mut self, _ctx: &mut Context<Block>, hash: Hash, header: &Header) {
 		self.collators.collect_garbage(Some(&hash));
 		self.local_collations.collect_garbage(Some(&header.parent_hash));
 	}
 }
 
+impl finality::communication::FinalityGossip<Block> for PolkadotProtocol {
+	fn finality_gossip(&mut self) -> Option<(&mut ConsensusGossip<Block>, &finality::communication::GossipValidator)> {
+		self.finality_gossip.as_mut().map(|&mut (ref mut gossip, ref validator)| (gossip, &**validator))
+	}
+
+	fn wrap_message(message: Vec<u8>) -> Vec<u8> {
+		Message::Finality(message).encode()
+	}
+
+	fn unwrap_message(raw: &[u8]) -> Option<Vec<u8>> {
+		match Message::decode(&mut &raw[..]) {
+			Some(Message::Finality(message)) => Some(message),
+			_ => None,
+		}
+	}
+}
+
 impl PolkadotProtocol {
 	// we received a collation from a peer
 	fn on_collation(&mut self, ctx: &mut Context<Block>, from: NodeIndex, relay_parent: Hash, collation: Collation) {
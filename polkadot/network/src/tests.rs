@@ -16,7 +16,7 @@
 
 //! Tests for polkadot and consensus network.
 
-use super::{PolkadotProtocol, Status, CurrentConsensus, Knowledge, Message, FullStatus};
+use super::{PolkadotProtocol, Status, CurrentConsensus, Knowledge, Message, FullStatus, MessageValidator};
 
 use parking_lot::Mutex;
 use polkadot_consensus::GenericStatement;
@@ -24,6 +24,9 @@ use polkadot_primitives::{Block, Hash, SessionKey};
 use polkadot_primitives::parachain::{CandidateReceipt, HeadData, BlockData};
 use substrate_primitives::H512;
 use codec::Encode;
+use finality::communication::FinalityGossip;
+use substrate_network::consensus_gossip::{ConsensusMessage, ValidationResult, Validator as GossipValidator};
+use substrate_network::generic_message::{BftMessage as GenericBftMessage, SignedConsensusMessage, SignedConsensusVote, ConsensusVote};
 use substrate_network::{Severity, NodeIndex, PeerInfo, ClientHandle, Context, Roles, message::Message as SubstrateMessage, specialization::Specialization, generic_message::Message as GenericMessage};
 
 use std::sync::Arc;
@@ -77,6 +80,7 @@ fn make_status(status: &Status, roles: Roles) -> FullStatus {
 		best_hash: Default::default(),
 		genesis_hash: Default::default(),
 		chain_status: status.encode(),
+		min_supported_version: 1,
 	}
 }
 
@@ -275,3 +279,42 @@ fn remove_bad_collator() {
 		assert!(ctx.disabled.contains(&who));
 	}
 }
+
+#[test]
+fn gossip_of_concluded_consensus_expires() {
+	let validator = MessageValidator::default();
+	let parent_hash: Hash = [1; 32].into();
+	let message = ConsensusMessage::Bft(::substrate_network::LocalizedBftMessage {
+		parent_hash,
+		message: GenericBftMessage::Consensus(SignedConsensusMessage::Vote(SignedConsensusVote {
+			vote: ConsensusVote::AdvanceRound(0),
+			sender: [0; 32].into(),
+			signature: Default::default(),
+		})),
+	});
+
+	assert_eq!(validator.validate(&message), ValidationResult::ProcessAndKeep(parent_hash));
+	assert!(!validator.message_expired(&parent_hash, &message));
+
+	validator.note_concluded(parent_hash);
+	assert_eq!(validator.validate(&message), ValidationResult::Discard);
+	assert!(validator.message_expired(&parent_hash, &message));
+
+	// unknown chain-specific messages are discarded.
+	let session_key = ConsensusMessage::ChainSpecific(Message::SessionKey([2; 32].into()).encode());
+	assert_eq!(validator.validate(&session_key), ValidationResult::Discard);
+}
+
+#[test]
+fn finality_messages_are_wrapped() {
+	let raw = <PolkadotProtocol as FinalityGossip<Block>>::wrap_message(vec![1, 2, 3]);
+	assert_eq!(<PolkadotProtocol as FinalityGossip<Block>>::unwrap_message(&raw), Some(vec![1, 2, 3]));
+
+	let session_key = Message::SessionKey([2; 32].into()).encode();
+	assert_eq!(<PolkadotProtocol as FinalityGossip<Block>>::unwrap_message(&session_key), None);
+
+	let mut protocol = PolkadotProtocol::new(None);
+	assert!(protocol.finality_gossip().is_none());
+	protocol.enable_finality(10);
+	assert!(protocol.finality_gossip().is_some());
+}
@@ -120,3 +120,15 @@ impl Encode for Log {
 		self.0.encode_to(dest)
 	}
 }
+
+impl From<Vec<u8>> for Log {
+	fn from(data: Vec<u8>) -> Self {
+		Log(data)
+	}
+}
+
+impl AsRef<[u8]> for Log {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
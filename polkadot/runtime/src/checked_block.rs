@@ -16,9 +16,10 @@
 
 //! Typesafe block interaction.
 
-use super::{Call, Block, TIMESTAMP_SET_POSITION, PARACHAINS_SET_POSITION};
+use super::{Call, Block, TIMESTAMP_SET_POSITION, PARACHAINS_SET_POSITION, NOTE_OFFLINE_POSITION};
 use timestamp::Call as TimestampCall;
 use parachains::Call as ParachainsCall;
+use consensus::Call as ConsensusCall;
 use primitives::parachain::CandidateReceipt;
 
 /// Provides a type-safe wrapper around a structurally valid block.
@@ -47,6 +48,15 @@ impl CheckedBlock {
 		});
 
 		if !has_heads { return Err(block) }
+
+		let has_offline = block.extrinsics.get(NOTE_OFFLINE_POSITION as usize).map_or(false, |xt| {
+			!xt.is_signed() && match xt.extrinsic.function {
+				Call::Consensus(ConsensusCall::note_offline(_)) => true,
+				_ => false,
+			}
+		});
+
+		if !has_offline { return Err(block) }
 		Ok(CheckedBlock {
 			inner: block,
 			file_line: None,
@@ -88,6 +98,19 @@ impl CheckedBlock {
 		}
 	}
 
+	/// Extract the indices of the validators noted offline from the block.
+	pub fn noted_offline(&self) -> &[u32] {
+		let x = self.inner.extrinsics.get(NOTE_OFFLINE_POSITION as usize).and_then(|xt| match xt.extrinsic.function {
+			Call::Consensus(ConsensusCall::note_offline(ref x)) => Some(&x[..]),
+			_ => None
+		});
+
+		match x {
+			Some(x) => x,
+			None => panic!("Invalid polkadot block asserted at {:?}", self.file_line),
+		}
+	}
+
 	/// Convert into inner block.
 	pub fn into_inner(self) -> Block { self.inner }
 }
@@ -19,7 +19,7 @@
 use rstd::prelude::*;
 use codec::Decode;
 
-use runtime_primitives::traits::{Hash, BlakeTwo256, Executable, RefInto, MaybeEmpty};
+use runtime_primitives::traits::{Hash, BlakeTwo256, Executable, OffchainWorker, RefInto, MaybeEmpty};
 use primitives::parachain::{Id, Chain, DutyRoster, CandidateReceipt};
 use {system, session};
 
@@ -188,6 +188,8 @@ impl<T: Trait> Executable for Module<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Module<T> {}
+
 /// Parachains module genesis configuration.
 #[cfg(any(feature = "std", test))]
 #[derive(Serialize, Deserialize)]
@@ -255,8 +257,11 @@ mod tests {
 		type PublicAux = u64;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = <Self as HasPublicAux>::PublicAux;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl system::Trait for Test {
 		type Index = u64;
@@ -22,9 +22,14 @@ use runtime_primitives::traits::{Checkable, AuxLookup};
 use primitives::parachain::CandidateReceipt;
 use timestamp::Call as TimestampCall;
 use parachains::Call as ParachainsCall;
+use consensus::Call as ConsensusCall;
 
 /// Produces the list of inherent extrinsics.
-pub fn inherent_extrinsics(timestamp: ::primitives::Timestamp, parachain_heads: Vec<CandidateReceipt>) -> Vec<UncheckedExtrinsic> {
+pub fn inherent_extrinsics(
+	timestamp: ::primitives::Timestamp,
+	parachain_heads: Vec<CandidateReceipt>,
+	offline_indices: Vec<u32>,
+) -> Vec<UncheckedExtrinsic> {
 	vec![
 		UncheckedExtrinsic::new(
 			Extrinsic {
@@ -41,6 +46,14 @@ pub fn inherent_extrinsics(timestamp: ::primitives::Timestamp, parachain_heads:
 				index: 0,
 			},
 			Default::default()
+		),
+		UncheckedExtrinsic::new(
+			Extrinsic {
+				signed: Default::default(),
+				function: Call::Consensus(ConsensusCall::note_offline(offline_indices)),
+				index: 0,
+			},
+			Default::default()
 		)
 	]
 }
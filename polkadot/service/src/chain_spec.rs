@@ -23,6 +23,8 @@ use polkadot_runtime::{GenesisConfig, ConsensusConfig, CouncilConfig, DemocracyC
 use service::ChainSpec;
 
 const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
+const STAGING_AUTHORITY_ENDOWMENT: u128 = 1 << 50;
+const STAGING_AUTHORITY_BOND: u128 = 1 << 48;
 
 pub fn poc_1_testnet_config() -> Result<ChainSpec<GenesisConfig>, String> {
 	ChainSpec::from_embedded(include_bytes!("../res/krummelanke.json"))
@@ -52,6 +54,7 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 		staking: Some(StakingConfig {
 			current_era: 0,
 			intentions: initial_authorities.iter().cloned().map(Into::into).collect(),
+			bonded: initial_authorities.iter().cloned().map(|k| (k.into(), k.into(), STAGING_AUTHORITY_BOND)).collect(),
 			transaction_base_fee: 100,
 			transaction_byte_fee: 1,
 			existential_deposit: 500,
@@ -59,9 +62,16 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 			creation_fee: 0,
 			reclaim_rebate: 0,
 			early_era_slash: 10000,
+			misbehavior_slash: 100000,
+			offline_slash: 10000,
+			offline_slash_grace: 0,
+			report_after_missed: 5,
 			session_reward: 100,
-			balances: endowed_accounts.iter().map(|&k|(k, 1u128 << 60)).collect(),
+			balances: endowed_accounts.iter().map(|&k|(k, 1u128 << 60))
+				.chain(initial_authorities.iter().map(|&k| (k.into(), STAGING_AUTHORITY_ENDOWMENT)))
+				.collect(),
 			validator_count: 12,
+			minimum_validator_count: 4,
 			sessions_per_era: 12,	// 1 hour per era
 			bonding_duration: 24,	// 1 day per bond.
 		}),
@@ -69,6 +79,9 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 			launch_period: 12 * 60 * 24,	// 1 day per public referendum
 			voting_period: 12 * 60 * 24 * 3,	// 3 days to discuss & vote on an active referendum
 			minimum_deposit: 5000,	// 12000 as the minimum deposit for a referendum
+			preimage_byte_deposit: 1,
+			enactment_delay: 12 * 60 * 24 * 2,	// 2 days to exit before a passed referendum is enacted
+			cooloff_period: 12 * 60 * 24 * 7,	// 1 week before a cancelled proposal may be proposed again
 		}),
 		council: Some(CouncilConfig {
 			active_council: vec![],
@@ -81,6 +94,7 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 			term_duration: 12 * 60 * 24 * 24,	// 24 day term duration for the council.
 			desired_seats: 0, // start with no council: we'll raise this once the stake has been dispersed a bit.
 			inactive_grace_period: 1,	// one addition vote should go by before an inactive voter can be reaped.
+			on_chain_tally_limit: 10_000,	// count up to 10000 approvals on-chain; beyond that winners must present
 
 			cooloff_period: 12 * 60 * 24 * 4, // 4 day cooling off period if council member vetoes a proposal.
 			voting_period: 12 * 60 * 24, // 1 day voting period for council members.
@@ -104,7 +118,7 @@ pub fn staging_testnet_config() -> ChainSpec<GenesisConfig> {
 	)
 }
 
-fn testnet_genesis(initial_authorities: Vec<AuthorityId>) -> GenesisConfig {
+fn testnet_genesis(initial_authorities: Vec<AuthorityId>, block_period: u64) -> GenesisConfig {
 	let endowed_accounts = vec![
 		ed25519::Pair::from_seed(b"Alice                           ").public().0.into(),
 		ed25519::Pair::from_seed(b"Bob                             ").public().0.into(),
@@ -127,6 +141,7 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>) -> GenesisConfig {
 		staking: Some(StakingConfig {
 			current_era: 0,
 			intentions: initial_authorities.iter().cloned().map(Into::into).collect(),
+			bonded: initial_authorities.iter().cloned().map(|k| (k.into(), k.into(), 1u128 << 50)).collect(),
 			transaction_base_fee: 1,
 			transaction_byte_fee: 0,
 			existential_deposit: 500,
@@ -135,15 +150,23 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>) -> GenesisConfig {
 			reclaim_rebate: 0,
 			balances: endowed_accounts.iter().map(|&k|(k, (1u128 << 60))).collect(),
 			validator_count: 2,
+			minimum_validator_count: 1,
 			sessions_per_era: 5,
 			bonding_duration: 2,
 			early_era_slash: 0,
+			misbehavior_slash: 0,
+			offline_slash: 0,
+			offline_slash_grace: 0,
+			report_after_missed: 5,
 			session_reward: 0,
 		}),
 		democracy: Some(DemocracyConfig {
 			launch_period: 9,
 			voting_period: 18,
 			minimum_deposit: 10,
+			preimage_byte_deposit: 0,
+			enactment_delay: 0,
+			cooloff_period: 0,
 		}),
 		council: Some(CouncilConfig {
 			active_council: endowed_accounts.iter().filter(|a| initial_authorities.iter().find(|&b| a.0 == b.0).is_none()).map(|a| (a.clone(), 1000000)).collect(),
@@ -156,21 +179,23 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>) -> GenesisConfig {
 			term_duration: 1000000,
 			desired_seats: (endowed_accounts.len() - initial_authorities.len()) as u32,
 			inactive_grace_period: 1,
+			on_chain_tally_limit: 1_000,
 
 			cooloff_period: 75,
 			voting_period: 20,
 		}),
 		parachains: Some(Default::default()),
 		timestamp: Some(TimestampConfig {
-			period: 5,					// 5 second block time.
+			period: block_period,
 		}),
 	}
 }
 
 fn development_config_genesis() -> GenesisConfig {
+	// no minimum block period, so that dev-seal can author blocks as soon as they are due.
 	testnet_genesis(vec![
 		ed25519::Pair::from_seed(b"Alice                           ").public().into(),
-	])
+	], 0)
 }
 
 /// Development config (single validator Alice)
@@ -182,7 +207,7 @@ fn local_testnet_genesis() -> GenesisConfig {
 	testnet_genesis(vec![
 		ed25519::Pair::from_seed(b"Alice                           ").public().into(),
 		ed25519::Pair::from_seed(b"Bob                             ").public().into(),
-	])
+	], 5)	// 5 second block time.
 }
 
 /// Local testnet config (multivalidator Alice + Bob)
@@ -27,6 +27,8 @@ extern crate polkadot_api;
 extern crate polkadot_consensus as consensus;
 extern crate polkadot_transaction_pool as transaction_pool;
 extern crate polkadot_network;
+extern crate substrate_aura as aura;
+extern crate substrate_finality as finality;
 extern crate substrate_primitives as primitives;
 extern crate substrate_network as network;
 extern crate substrate_codec as codec;
@@ -46,15 +48,15 @@ use std::collections::HashMap;
 
 use codec::{Encode, Decode};
 use transaction_pool::TransactionPool;
-use polkadot_api::{PolkadotApi, light::RemotePolkadotApiWrapper};
-use polkadot_primitives::{parachain, AccountId, Block, BlockId, Hash};
+use polkadot_api::{BlockBuilder, PolkadotApi, light::RemotePolkadotApiWrapper};
+use polkadot_primitives::{parachain, AccountId, Block, BlockId, Hash, Header, Timestamp, UncheckedExtrinsic};
 use polkadot_runtime::GenesisConfig;
 use client::Client;
 use polkadot_network::{PolkadotProtocol, consensus::ConsensusNetwork};
 use tokio::runtime::TaskExecutor;
 use service::FactoryFullConfiguration;
 
-pub use service::{Roles, PruningMode, ExtrinsicPoolOptions,
+pub use service::{Roles, PruningMode, ExtrinsicPoolOptions, DevSeal,
 	ErrorKind, Error, ComponentBlock, LightComponents, FullComponents};
 pub use client::ExecutionStrategy;
 
@@ -150,10 +152,59 @@ impl service::ServiceFactory for Factory {
 		if let Some((_, ref para_id)) = config.custom.collating_for {
 			info!("Starting network in Collator mode for parachain {:?}", para_id);
 		}
-		Ok(PolkadotProtocol::new(config.custom.collating_for))
+		let mut protocol = PolkadotProtocol::new(config.custom.collating_for);
+		if let Some(round_duration) = config.finality_round_duration {
+			protocol.enable_finality(round_duration);
+		}
+		if config.network.sentry_mode.is_validator() {
+			info!("Running behind sentry nodes");
+			protocol.gossip_only_with_sentries();
+		}
+		Ok(protocol)
+	}
+
+	fn build_full_block_import(config: &Configuration, client: Arc<service::FullClient<Self>>)
+		-> Result<Arc<client::BlockImport<Block>>, Error>
+	{
+		match config.aura_slot_duration {
+			Some(slot_duration) => Ok(Arc::new(aura::AuraBlockImport::new(client, slot_duration))),
+			None => Ok(client),
+		}
+	}
+
+	fn build_light_block_import(config: &Configuration, client: Arc<service::LightClient<Self>>)
+		-> Result<Arc<client::BlockImport<Block>>, Error>
+	{
+		match config.aura_slot_duration {
+			Some(slot_duration) => Ok(Arc::new(aura::AuraBlockImport::new(client, slot_duration))),
+			None => Ok(client),
+		}
+	}
+
+	fn build_dev_seal_inherents(client: &service::FullClient<Self>, parent: &Header)
+		-> Result<Option<Vec<UncheckedExtrinsic>>, Error>
+	{
+		let api_err = |e: polkadot_api::Error| Error::from(format!("Unable to build inherents: {}", e));
+		let parent_id = BlockId::hash(parent.hash());
+		// the timestamp module rejects blocks less than a block period apart. Waiting for the
+		// period to pass keeps block timestamps from running ahead of the clock.
+		let timestamp = current_timestamp();
+		let block_period = client.block_period(&parent_id).map_err(api_err)?;
+		if timestamp < client.timestamp(&parent_id).map_err(api_err)? + block_period {
+			return Ok(None);
+		}
+		client.inherent_extrinsics(&parent_id, timestamp, Vec::new(), Vec::new()).map_err(api_err).map(Some)
 	}
 }
 
+fn current_timestamp() -> Timestamp {
+	use std::time;
+
+	time::SystemTime::now().duration_since(time::UNIX_EPOCH)
+		.expect("now always later than unix epoch; qed")
+		.as_secs()
+}
+
 /// Polkadot service.
 pub struct Service<C: Components> {
 	inner: service::Service<C>,
@@ -209,11 +260,36 @@ pub fn new_full(config: Configuration, executor: TaskExecutor)
 		})?
 	};
 
-	let is_validator = (config.roles & Roles::AUTHORITY) == Roles::AUTHORITY;
+	let is_authority = (config.roles & Roles::AUTHORITY) == Roles::AUTHORITY && config.dev_seal.is_none();
+	let aura_slot_duration = config.aura_slot_duration;
+	let finality_round_duration = config.finality_round_duration;
+
+	// dev-seal and aura author blocks without BFT consensus.
+	let is_validator = is_authority && aura_slot_duration.is_none();
+
+	// messages signed in consensus, kept across restarts to avoid equivocation.
+	let vote_journal = if is_validator {
+		let mut path = ::std::path::PathBuf::from(config.database_path.clone());
+		path.push("bft_votes");
+		Some(consensus::VoteJournal::open(path)?)
+	} else {
+		None
+	};
 	let service = service::Service::<FullComponents<Factory>>::new(config, executor.clone())?;
 
+	if let Some(slot_duration) = aura_slot_duration {
+		// the timestamp module rejects blocks of consecutive slots otherwise.
+		let client = service.client();
+		let best = BlockId::hash(client.info()?.chain.best_hash);
+		let block_period = client.block_period(&best)
+			.map_err(|e| Error::from(format!("Unable to read the block period: {}", e)))?;
+		if slot_duration < block_period {
+			return Err(format!("Aura slot duration must be at least the block period of {} seconds", block_period).into());
+		}
+	}
+
 	// Spin consensus service if configured
-	let consensus = if is_validator {
+	let consensus = if let Some(vote_journal) = vote_journal {
 		// Load the first available key
 		let key = service.keystore().load(&service.keystore().contents()?[0], "")?;
 		info!("Using authority key {}", key.public());
@@ -226,15 +302,42 @@ pub fn new_full(config: Configuration, executor: TaskExecutor)
 			client.clone(),
 			consensus_net,
 			service.extrinsic_pool(),
-			executor,
+			executor.clone(),
 			::std::time::Duration::from_secs(4), // TODO: dynamic
 			key,
 			av_store.clone(),
+			vote_journal,
 		))
 	} else {
 		None
 	};
 
+	match aura_slot_duration {
+		Some(slot_duration) if is_authority => {
+			let key = Arc::new(service.keystore().load(&service.keystore().contents()?[0], "")?);
+			info!("Authoring blocks in slots of {}s with key {}", slot_duration, key.public());
+
+			let client = service.client();
+			let pool = service.extrinsic_pool();
+			let propose = {
+				let client = client.clone();
+				move |parent: &Header, timestamp| author_block(&*client, &*pool, parent, timestamp)
+			};
+			executor.spawn(aura::start_aura(client, key, slot_duration, propose, service.on_exit()));
+		},
+		_ => {},
+	}
+
+	if let Some(round_duration) = finality_round_duration {
+		let key = if is_authority {
+			Some(Arc::new(service.keystore().load(&service.keystore().contents()?[0], "")?))
+		} else {
+			None
+		};
+		info!("Finalizing blocks in rounds of {}s{}", round_duration, if key.is_some() { " as a voter" } else { "" });
+		executor.spawn(finality::start_finality(service.client(), service.network(), key, round_duration, service.on_exit()));
+	}
+
 	service.network().with_spec(|spec, _| spec.register_availability_store(av_store));
 
 	Ok(Service {
@@ -246,6 +349,23 @@ pub fn new_full(config: Configuration, executor: TaskExecutor)
 	})
 }
 
+// Build a block with the pending transactions on top of `parent`, to be sealed by aura.
+fn author_block<A: PolkadotApi>(client: &A, pool: &TransactionPool<A>, parent: &Header, timestamp: Timestamp)
+	-> aura::Result<Block>
+{
+	let api_err = |e: polkadot_api::Error| aura::Error::from(format!("Unable to build block: {}", e));
+	let parent_id = BlockId::hash(parent.hash());
+	let mut block_builder = client.build_block(&parent_id, timestamp, Vec::new(), Vec::new()).map_err(api_err)?;
+	pool.cull_and_get_pending(parent_id, |pending| {
+		for tx in pending {
+			if let Err(e) = block_builder.push_extrinsic(tx.primitive_extrinsic()) {
+				debug!("Skipping transaction {:?}: {}", tx.hash(), e);
+			}
+		}
+	}).map_err(|e| aura::Error::from(format!("Unable to get pending transactions: {}", e)))?;
+	block_builder.bake().map_err(api_err)
+}
+
 /// Creates bare client without any networking.
 pub fn new_client(config: Configuration)
 -> Result<Arc<service::ComponentClient<FullComponents<Factory>>>, Error>
@@ -487,15 +487,17 @@ mod tests {
 
 		fn session_keys(&self, _at: &BlockId) -> Result<Vec<SessionKey>> { unimplemented!() }
 		fn validators(&self, _at: &BlockId) -> Result<Vec<AccountId>> { unimplemented!() }
+		fn report_after_missed(&self, _at: &BlockId) -> Result<u32> { unimplemented!() }
 		fn random_seed(&self, _at: &BlockId) -> Result<Hash> { unimplemented!() }
 		fn duty_roster(&self, _at: &BlockId) -> Result<DutyRoster> { unimplemented!() }
 		fn timestamp(&self, _at: &BlockId) -> Result<u64> { unimplemented!() }
+		fn block_period(&self, _at: &BlockId) -> Result<u64> { unimplemented!() }
 		fn evaluate_block(&self, _at: &BlockId, _block: Block) -> Result<bool> { unimplemented!() }
 		fn active_parachains(&self, _at: &BlockId) -> Result<Vec<ParaId>> { unimplemented!() }
 		fn parachain_code(&self, _at: &BlockId, _parachain: ParaId) -> Result<Option<Vec<u8>>> { unimplemented!() }
 		fn parachain_head(&self, _at: &BlockId, _parachain: ParaId) -> Result<Option<Vec<u8>>> { unimplemented!() }
-		fn build_block(&self, _at: &BlockId, _timestamp: Timestamp, _new_heads: Vec<CandidateReceipt>) -> Result<Self::BlockBuilder> { unimplemented!() }
-		fn inherent_extrinsics(&self, _at: &BlockId, _timestamp: Timestamp, _new_heads: Vec<CandidateReceipt>) -> Result<Vec<Vec<u8>>> { unimplemented!() }
+		fn build_block(&self, _at: &BlockId, _timestamp: Timestamp, _new_heads: Vec<CandidateReceipt>, _offline_indices: Vec<u32>) -> Result<Self::BlockBuilder> { unimplemented!() }
+		fn inherent_extrinsics(&self, _at: &BlockId, _timestamp: Timestamp, _new_heads: Vec<CandidateReceipt>, _offline_indices: Vec<u32>) -> Result<Vec<Vec<u8>>> { unimplemented!() }
 
 		fn index(&self, _at: &BlockId, _account: AccountId) -> Result<Index> {
 			Ok((_account[0] as u32) + number_of(_at))
@@ -0,0 +1,70 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Error types in slot-based authoring.
+
+use SlotNumber;
+
+error_chain! {
+	links {
+		Client(::client::error::Error, ::client::error::ErrorKind);
+		Bft(::bft::Error, ::bft::ErrorKind);
+	}
+
+	errors {
+		/// Header carries no seal.
+		HeaderUnsealed(h: String) {
+			description("Header is unsealed"),
+			display("Header {} is unsealed", h),
+		}
+
+		/// Last digest item of the header is not a seal.
+		HeaderBadSeal(h: String) {
+			description("Header has a bad seal"),
+			display("Header {} has a bad seal", h),
+		}
+
+		/// Seal is not signed by the author of the slot.
+		BadSignature(h: String) {
+			description("Header is not signed by the slot author"),
+			display("Header {} is not signed by the author of its slot", h),
+		}
+
+		/// No authorities to author the slot.
+		NoAuthorities {
+			description("No authorities"),
+			display("No authorities to author the slot."),
+		}
+
+		/// Slot has not started yet according to the local clock.
+		SlotInFuture(slot: SlotNumber, now: SlotNumber) {
+			description("Slot is in the future"),
+			display("Slot {} is in the future; current slot is {}", slot, now),
+		}
+
+		/// Slot is not after the slot of the parent.
+		SlotNotIncreasing(slot: SlotNumber, parent: SlotNumber) {
+			description("Slot does not increase"),
+			display("Slot {} is not after the parent's slot {}", slot, parent),
+		}
+
+		/// Local clock is before the UNIX epoch.
+		ClockBeforeEpoch {
+			description("Clock is before the UNIX epoch"),
+			display("Local clock is before the UNIX epoch."),
+		}
+	}
+}
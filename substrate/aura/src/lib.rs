@@ -0,0 +1,388 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slot-based round-robin block authoring.
+//!
+//! Time is split into slots of a fixed number of seconds since the UNIX epoch. The
+//! authorities at the parent block take turns: slot `s` belongs to
+//! `authorities[s % authorities.len()]`. The author seals its block by appending a digest
+//! item holding the slot and its signature over the unsealed header.
+//!
+//! Blocks carry no justification: forks resolve by the longest chain, and finality can be
+//! provided separately by `substrate-finality`.
+//!
+//! To use it, return an `AuraBlockImport` from the service factory's block import
+//! constructors and run `start_aura` on authoring nodes.
+
+pub mod error;
+
+extern crate substrate_bft as bft;
+extern crate substrate_client as client;
+extern crate substrate_codec as codec;
+extern crate substrate_primitives as primitives;
+extern crate substrate_runtime_primitives as runtime_primitives;
+extern crate ed25519;
+extern crate futures;
+extern crate tokio;
+
+#[cfg(test)]
+extern crate substrate_keyring as keyring;
+#[cfg(test)]
+extern crate substrate_test_client as test_client;
+
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+extern crate error_chain;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bft::Authorities;
+use client::{BlockImport, BlockOrigin, CallExecutor, Client, ImportResult};
+use codec::{Decode, Encode};
+use futures::{Future, Stream};
+use primitives::AuthorityId;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, Digest};
+use tokio::timer::Interval;
+
+pub use error::{Error, ErrorKind, Result};
+
+/// Index of a time slot.
+pub type SlotNumber = u64;
+
+/// How many slots ahead of the local clock a header may be, to tolerate clock drift.
+pub const MAX_SLOT_DRIFT: SlotNumber = 1;
+
+/// The slot a UNIX timestamp, in seconds, falls into.
+pub fn slot_at(timestamp: u64, slot_duration: u64) -> SlotNumber {
+	timestamp / slot_duration
+}
+
+/// The current slot according to the local clock.
+pub fn slot_now(slot_duration: u64) -> Result<SlotNumber> {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH)
+		.map_err(|_| ErrorKind::ClockBeforeEpoch)?;
+	Ok(slot_at(now.as_secs(), slot_duration))
+}
+
+/// The authority expected to author a block in the given slot.
+pub fn slot_author(slot: SlotNumber, authorities: &[AuthorityId]) -> Option<&AuthorityId> {
+	if authorities.is_empty() {
+		return None;
+	}
+	let index = slot % authorities.len() as u64;
+	authorities.get(index as usize)
+}
+
+/// A digest item which can hold the seal of a block.
+pub trait CompatibleDigestItem: Sized {
+	/// Construct a seal for a block authored in `slot`.
+	fn aura_seal(slot: SlotNumber, signature: ed25519::Signature) -> Self;
+
+	/// The slot and signature, if this item is a seal.
+	fn as_aura_seal(&self) -> Option<(SlotNumber, ed25519::Signature)>;
+}
+
+impl<T: From<Vec<u8>> + AsRef<[u8]>> CompatibleDigestItem for T {
+	fn aura_seal(slot: SlotNumber, signature: ed25519::Signature) -> Self {
+		(slot, signature).encode().into()
+	}
+
+	fn as_aura_seal(&self) -> Option<(SlotNumber, ed25519::Signature)> {
+		Decode::decode(&mut self.as_ref())
+	}
+}
+
+// The message signed by the author: the slot and the hash of the unsealed header.
+fn seal_payload<H: Encode>(slot: SlotNumber, pre_hash: &H) -> Vec<u8> {
+	let mut payload = slot.encode();
+	pre_hash.encode_to(&mut payload);
+	payload
+}
+
+/// The slot a header was sealed in. `None` for unsealed headers, like genesis.
+pub fn header_slot<H>(header: &H) -> Option<SlotNumber> where
+	H: HeaderT,
+	H::Digest: Digest,
+	<H::Digest as Digest>::Item: CompatibleDigestItem,
+{
+	header.digest().logs().last().and_then(|item| item.as_aura_seal()).map(|(slot, _)| slot)
+}
+
+/// Seal a header authored in `slot` with the given key.
+pub fn seal_header<H>(mut header: H, slot: SlotNumber, key: &ed25519::Pair) -> H where
+	H: HeaderT,
+	H::Digest: Digest,
+	<H::Digest as Digest>::Item: CompatibleDigestItem,
+{
+	let signature = key.sign(&seal_payload(slot, &header.hash()));
+	let mut digest = header.digest().clone();
+	digest.push(CompatibleDigestItem::aura_seal(slot, signature));
+	header.set_digest(digest);
+	header
+}
+
+/// Check the seal of a header against the authorities at its parent.
+///
+/// Returns the unsealed header, which is what the runtime executes, and the slot.
+pub fn check_header<H>(header: &H, slot_now: SlotNumber, authorities: &[AuthorityId]) -> Result<(H, SlotNumber)> where
+	H: HeaderT,
+	H::Digest: Digest,
+	<H::Digest as Digest>::Item: CompatibleDigestItem,
+{
+	let hash = header.hash();
+	let mut digest = header.digest().clone();
+	let seal = digest.pop().ok_or_else(|| ErrorKind::HeaderUnsealed(format!("{}", hash)))?;
+	let (slot, signature) = seal.as_aura_seal().ok_or_else(|| ErrorKind::HeaderBadSeal(format!("{}", hash)))?;
+	if slot > slot_now + MAX_SLOT_DRIFT {
+		bail!(ErrorKind::SlotInFuture(slot, slot_now));
+	}
+
+	let author = slot_author(slot, authorities).ok_or(ErrorKind::NoAuthorities)?;
+	let mut pre_header = header.clone();
+	pre_header.set_digest(digest);
+	if !ed25519::verify_strong(&signature, &seal_payload(slot, &pre_header.hash()), ed25519::Public::from(*author)) {
+		bail!(ErrorKind::BadSignature(format!("{}", hash)));
+	}
+
+	Ok((pre_header, slot))
+}
+
+/// Block import which checks the seal and slot of blocks before importing them.
+pub struct AuraBlockImport<B, E, Block: BlockT> {
+	client: Arc<Client<B, E, Block>>,
+	slot_duration: u64,
+}
+
+impl<B, E, Block: BlockT> AuraBlockImport<B, E, Block> {
+	/// Create a new block import for slots of `slot_duration` seconds.
+	pub fn new(client: Arc<Client<B, E, Block>>, slot_duration: u64) -> Self {
+		AuraBlockImport { client, slot_duration }
+	}
+}
+
+impl<B, E, Block> BlockImport<Block> for AuraBlockImport<B, E, Block> where
+	B: client::backend::Backend<Block> + Send + Sync,
+	E: CallExecutor<Block> + Send + Sync,
+	Block: BlockT,
+	<Block::Header as HeaderT>::Digest: Digest,
+	<<Block::Header as HeaderT>::Digest as Digest>::Item: CompatibleDigestItem,
+{
+	fn import(
+		&self,
+		origin: BlockOrigin,
+		header: Block::Header,
+		_justification: Option<runtime_primitives::bft::Justification<Block::Hash>>,
+		body: Option<Vec<Block::Extrinsic>>,
+	) -> client::error::Result<ImportResult> {
+		let hash = header.hash();
+		let parent_id = BlockId::Hash(*header.parent_hash());
+		let parent = match self.client.header(&parent_id)? {
+			Some(parent) => parent,
+			None => return Ok(ImportResult::UnknownParent),
+		};
+		let authorities = self.client.authorities_at(&parent_id)?;
+		let now = slot_now(self.slot_duration).map_err(|e| format!("{}", e))?;
+
+		let checked = check_header(&header, now, &authorities).and_then(|(pre_header, slot)| {
+			match header_slot(&parent) {
+				Some(parent_slot) if parent_slot >= slot => bail!(ErrorKind::SlotNotIncreasing(slot, parent_slot)),
+				_ => Ok(pre_header),
+			}
+		});
+
+		match checked {
+			Ok(pre_header) => self.client.import_sealed_block(origin, header, pre_header, body),
+			Err(e) => {
+				debug!(target: "aura", "Rejecting block {}: {}", hash, e);
+				match *e.kind() {
+					// may become valid once our clock catches up.
+					ErrorKind::SlotInFuture(..) => Err(format!("{}", e).into()),
+					_ => Ok(ImportResult::KnownBad),
+				}
+			},
+		}
+	}
+}
+
+/// Start authoring blocks in the slots of the given key. `propose` builds the unsealed block on
+/// top of a parent header, given the timestamp of the slot.
+///
+/// The returned future resolves once `exit` does.
+pub fn start_aura<B, E, Block, P, X>(
+	client: Arc<Client<B, E, Block>>,
+	key: Arc<ed25519::Pair>,
+	slot_duration: u64,
+	propose: P,
+	exit: X,
+) -> impl Future<Item=(), Error=()> where
+	B: client::backend::Backend<Block> + Send + Sync + 'static,
+	E: CallExecutor<Block> + Send + Sync + 'static,
+	Block: BlockT,
+	<Block::Header as HeaderT>::Digest: Digest,
+	<<Block::Header as HeaderT>::Digest as Digest>::Item: CompatibleDigestItem,
+	P: Fn(&Block::Header, u64) -> Result<Block>,
+	X: Future<Item=(), Error=()>,
+{
+	let mut last_slot = 0;
+	// tick more often than slots change, so no slot is missed.
+	Interval::new(Instant::now(), Duration::from_secs(1))
+		.map_err(|e| warn!(target: "aura", "Faulty timer: {}", e))
+		.for_each(move |_| {
+			match slot_now(slot_duration) {
+				Ok(slot) if slot > last_slot => {
+					last_slot = slot;
+					if let Err(e) = author_slot(&*client, &*key, slot, slot_duration, &propose) {
+						warn!(target: "aura", "Unable to author block in slot {}: {}", slot, e);
+					}
+				},
+				Ok(_) => {},
+				Err(e) => warn!(target: "aura", "{}", e),
+			}
+			Ok(())
+		})
+		.select(exit)
+		.then(|_| Ok(()))
+}
+
+// Author and import a block in `slot`, if it belongs to `key`.
+fn author_slot<B, E, Block, P>(
+	client: &Client<B, E, Block>,
+	key: &ed25519::Pair,
+	slot: SlotNumber,
+	slot_duration: u64,
+	propose: &P,
+) -> Result<()> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+	<Block::Header as HeaderT>::Digest: Digest,
+	<<Block::Header as HeaderT>::Digest as Digest>::Item: CompatibleDigestItem,
+	P: Fn(&Block::Header, u64) -> Result<Block>,
+{
+	let parent = client.best_block_header()?;
+	let authorities = client.authorities(&BlockId::Hash(parent.hash()))?;
+	let local_id: AuthorityId = key.public().into();
+	if slot_author(slot, &authorities) != Some(&local_id) {
+		return Ok(());
+	}
+	if header_slot(&parent).map_or(false, |parent_slot| parent_slot >= slot) {
+		return Ok(());
+	}
+
+	let (pre_header, body) = propose(&parent, slot * slot_duration)?.deconstruct();
+	let header = seal_header(pre_header.clone(), slot, key);
+	info!(target: "aura", "Authored block #{} ({}) in slot {}", header.number(), header.hash(), slot);
+	client.import_sealed_block(BlockOrigin::Own, header, pre_header, Some(body))?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use keyring::Keyring;
+	use runtime_primitives::generic;
+	use runtime_primitives::traits::BlakeTwo256;
+
+	type Header = generic::Header<u64, BlakeTwo256, Vec<u8>>;
+
+	fn header() -> Header {
+		Header::new(1, Default::default(), Default::default(), [69; 32].into(), Default::default())
+	}
+
+	fn authorities() -> Vec<AuthorityId> {
+		vec![Keyring::Alice.to_raw_public().into(), Keyring::Bob.to_raw_public().into()]
+	}
+
+	#[test]
+	fn slots_should_rotate_through_authorities() {
+		let authorities = authorities();
+		assert_eq!(slot_author(4, &authorities), Some(&authorities[0]));
+		assert_eq!(slot_author(5, &authorities), Some(&authorities[1]));
+		assert_eq!(slot_author(5, &[]), None);
+		assert_eq!(slot_at(11, 5), 2);
+	}
+
+	#[test]
+	fn sealed_header_should_check() {
+		let sealed = seal_header(header(), 5, &Keyring::Bob.pair());
+		assert_eq!(header_slot(&sealed), Some(5));
+		let (pre_header, slot) = check_header(&sealed, 6, &authorities()).unwrap();
+		assert_eq!(pre_header, header());
+		assert_eq!(slot, 5);
+	}
+
+	#[test]
+	fn header_sealed_out_of_turn_should_fail() {
+		let sealed = seal_header(header(), 4, &Keyring::Bob.pair());
+		match *check_header(&sealed, 6, &authorities()).unwrap_err().kind() {
+			ErrorKind::BadSignature(_) => {},
+			_ => panic!("header sealed by the wrong authority must be rejected"),
+		}
+	}
+
+	#[test]
+	fn header_from_future_slot_should_fail() {
+		let sealed = seal_header(header(), 8, &Keyring::Alice.pair());
+		match *check_header(&sealed, 6, &authorities()).unwrap_err().kind() {
+			ErrorKind::SlotInFuture(8, 6) => {},
+			_ => panic!("header from a future slot must be rejected"),
+		}
+		assert!(check_header(&header(), 6, &authorities()).is_err());
+	}
+
+	#[test]
+	fn header_within_slot_drift_should_check() {
+		let sealed = seal_header(header(), 7, &Keyring::Bob.pair());
+		assert_eq!(check_header(&sealed, 6, &authorities()).unwrap().1, 7);
+	}
+
+	// Seal a block built on the best block with the key of the authority owning `slot`.
+	fn sealed_block(client: &test_client::client::Client<test_client::Backend, test_client::Executor, test_client::runtime::Block>, slot: SlotNumber)
+		-> (test_client::runtime::Header, Vec<test_client::runtime::Extrinsic>)
+	{
+		let keys = [Keyring::Alice, Keyring::Bob, Keyring::Charlie];
+		let (pre_header, body) = client.new_block().unwrap().bake().unwrap().deconstruct();
+		(seal_header(pre_header, slot, &keys[(slot % 3) as usize].pair()), body)
+	}
+
+	#[test]
+	fn import_with_unknown_parent_should_not_be_checked() {
+		let client = Arc::new(test_client::new());
+		let import = AuraBlockImport::new(client.clone(), 5);
+		let (mut header, body) = sealed_block(&client, slot_now(5).unwrap() - 1);
+		header.parent_hash = [1; 32].into();
+		assert_eq!(import.import(BlockOrigin::File, header, None, Some(body)).unwrap(), ImportResult::UnknownParent);
+	}
+
+	#[test]
+	fn import_with_non_increasing_slot_should_fail() {
+		let client = Arc::new(test_client::new());
+		let import = AuraBlockImport::new(client.clone(), 5);
+		let slot = slot_now(5).unwrap() - 1;
+
+		let (header, body) = sealed_block(&client, slot);
+		assert_eq!(import.import(BlockOrigin::File, header, None, Some(body)).unwrap(), ImportResult::Queued);
+		assert_eq!(client.info().unwrap().chain.best_number, 1);
+
+		let (header, body) = sealed_block(&client, slot);
+		assert_eq!(import.import(BlockOrigin::File, header, None, Some(body)).unwrap(), ImportResult::KnownBad);
+		assert_eq!(client.info().unwrap().chain.best_number, 1);
+	}
+}
@@ -0,0 +1,252 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Write-ahead journal of the messages signed by the local authority.
+//!
+//! Every proposal, prepare and commit is recorded, and flushed to disk if the journal is
+//! backed by a file, before it is signed. After a restart the journal is consulted so the
+//! local authority never signs two different digests for the same parent, round and kind
+//! of message.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use codec::{Encode, Decode};
+use parking_lot::Mutex;
+
+/// Number of most recent parent hashes for which signed messages are remembered.
+const KEEP_PARENTS: usize = 16;
+
+/// Kinds of message which may be signed at most once per parent and round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoteKind {
+	/// Proposal of a candidate.
+	Propose,
+	/// Prepare vote.
+	Prepare,
+	/// Commit vote.
+	Commit,
+}
+
+impl VoteKind {
+	fn to_byte(self) -> u8 {
+		match self {
+			VoteKind::Propose => 0,
+			VoteKind::Prepare => 1,
+			VoteKind::Commit => 2,
+		}
+	}
+
+	fn from_byte(b: u8) -> Option<Self> {
+		match b {
+			0 => Some(VoteKind::Propose),
+			1 => Some(VoteKind::Prepare),
+			2 => Some(VoteKind::Commit),
+			_ => None,
+		}
+	}
+}
+
+struct Records<H> {
+	signed: HashMap<(H, u32, VoteKind), H>,
+	parents: VecDeque<H>,
+}
+
+impl<H: Clone + Eq + Hash> Records<H> {
+	// Insert a record, returning `true` if it belongs to a parent not seen before.
+	fn insert(&mut self, parent: H, round: u32, kind: VoteKind, digest: H) -> bool {
+		let new_parent = !self.parents.contains(&parent);
+		if new_parent {
+			self.parents.push_back(parent.clone());
+		}
+		self.signed.insert((parent, round, kind), digest);
+		new_parent
+	}
+
+	// Forget the oldest parents, returning `true` if any were dropped.
+	fn prune(&mut self) -> bool {
+		let mut pruned = false;
+		while self.parents.len() > KEEP_PARENTS {
+			let old = self.parents.pop_front().expect("more than KEEP_PARENTS entries; qed");
+			self.signed.retain(|&(ref parent, _, _), _| parent != &old);
+			pruned = true;
+		}
+		pruned
+	}
+}
+
+/// Journal of signed messages, optionally persisted to a file.
+pub struct VoteJournal<H> {
+	path: Option<PathBuf>,
+	records: Mutex<Records<H>>,
+}
+
+impl<H: Encode + Decode + Clone + Eq + Hash> VoteJournal<H> {
+	/// Create a journal which is not persisted. Only suitable for tests.
+	pub fn in_memory() -> Self {
+		VoteJournal {
+			path: None,
+			records: Mutex::new(Records { signed: HashMap::new(), parents: VecDeque::new() }),
+		}
+	}
+
+	/// Open the journal at the given path, loading any previously signed messages.
+	pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+		let path = path.into();
+		let mut records = Records { signed: HashMap::new(), parents: VecDeque::new() };
+
+		match File::open(&path) {
+			Ok(mut file) => {
+				let mut data = Vec::new();
+				file.read_to_end(&mut data)?;
+
+				// a torn entry at the end was never signed, so stop at the first undecodable one.
+				let mut input = &data[..];
+				while !input.is_empty() {
+					match <(H, u32, u8, H)>::decode(&mut input) {
+						Some((parent, round, kind, digest)) => match VoteKind::from_byte(kind) {
+							Some(kind) => { records.insert(parent, round, kind, digest); },
+							None => break,
+						},
+						None => break,
+					}
+				}
+				records.prune();
+			}
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => return Err(e),
+		}
+
+		Ok(VoteJournal {
+			path: Some(path),
+			records: Mutex::new(records),
+		})
+	}
+
+	/// Record that a message of the given kind for `digest` is about to be signed.
+	///
+	/// If a message of the same kind was already signed for a different digest at this
+	/// parent and round, nothing is recorded and that digest is returned instead.
+	pub fn note_signing(&self, parent: &H, round: u32, kind: VoteKind, digest: &H) -> io::Result<Option<H>> {
+		let mut records = self.records.lock();
+		match records.signed.get(&(parent.clone(), round, kind)) {
+			Some(signed) if signed == digest => return Ok(None),
+			Some(signed) => return Ok(Some(signed.clone())),
+			None => {},
+		}
+
+		let new_parent = records.insert(parent.clone(), round, kind, digest.clone());
+		let path = match self.path {
+			Some(ref path) => path,
+			None => {
+				records.prune();
+				return Ok(None);
+			}
+		};
+
+		let written = if new_parent && records.prune() {
+			rewrite(path, &records)
+		} else {
+			append(path, &(parent.clone(), round, kind.to_byte(), digest.clone()).encode())
+		};
+
+		if let Err(e) = written {
+			// the message will not be signed, so it must not be remembered either.
+			records.signed.remove(&(parent.clone(), round, kind));
+			return Err(e);
+		}
+
+		Ok(None)
+	}
+}
+
+fn append(path: &PathBuf, entry: &[u8]) -> io::Result<()> {
+	let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+	file.write_all(entry)?;
+	file.sync_data()
+}
+
+// Atomically replace the journal file with the retained records.
+fn rewrite<H: Encode + Eq>(path: &PathBuf, records: &Records<H>) -> io::Result<()> {
+	// entries are written in parent order, so the oldest parents are still pruned first on reload.
+	let mut data = Vec::new();
+	for parent in &records.parents {
+		for (&(ref p, round, kind), digest) in &records.signed {
+			if p == parent {
+				(parent, round, kind.to_byte(), digest).encode_to(&mut data);
+			}
+		}
+	}
+
+	let tmp = path.with_extension("tmp");
+	{
+		let mut file = File::create(&tmp)?;
+		file.write_all(&data)?;
+		file.sync_data()?;
+	}
+	fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use primitives::H256;
+
+	fn temp_path(name: &str) -> PathBuf {
+		let mut path = ::std::env::temp_dir();
+		path.push(format!("substrate-bft-journal-{}-{}", name, ::std::process::id()));
+		let _ = fs::remove_file(&path);
+		path
+	}
+
+	#[test]
+	fn conflicting_digest_is_refused() {
+		let journal = VoteJournal::<H256>::in_memory();
+		let parent = [1; 32].into();
+
+		assert_eq!(journal.note_signing(&parent, 0, VoteKind::Prepare, &[2; 32].into()).unwrap(), None);
+		assert_eq!(journal.note_signing(&parent, 0, VoteKind::Prepare, &[2; 32].into()).unwrap(), None);
+		assert_eq!(journal.note_signing(&parent, 0, VoteKind::Prepare, &[3; 32].into()).unwrap(), Some([2; 32].into()));
+
+		// other kinds, rounds and parents are independent.
+		assert_eq!(journal.note_signing(&parent, 0, VoteKind::Commit, &[3; 32].into()).unwrap(), None);
+		assert_eq!(journal.note_signing(&parent, 1, VoteKind::Prepare, &[3; 32].into()).unwrap(), None);
+		assert_eq!(journal.note_signing(&[4; 32].into(), 0, VoteKind::Prepare, &[3; 32].into()).unwrap(), None);
+	}
+
+	#[test]
+	fn signed_messages_survive_reopen() {
+		let path = temp_path("reopen");
+		let parent: H256 = [1; 32].into();
+
+		{
+			let journal = VoteJournal::<H256>::open(path.clone()).unwrap();
+			for i in 0..(KEEP_PARENTS as u8 + 2) {
+				journal.note_signing(&[100 + i; 32].into(), 0, VoteKind::Propose, &[i; 32].into()).unwrap();
+			}
+			journal.note_signing(&parent, 3, VoteKind::Commit, &[2; 32].into()).unwrap();
+		}
+
+		let journal = VoteJournal::<H256>::open(path.clone()).unwrap();
+		assert_eq!(journal.note_signing(&parent, 3, VoteKind::Commit, &[5; 32].into()).unwrap(), Some([2; 32].into()));
+		assert_eq!(journal.records.lock().parents.len(), KEEP_PARENTS);
+
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -33,6 +33,7 @@
 #![recursion_limit="128"]
 
 pub mod error;
+mod journal;
 
 extern crate substrate_codec as codec;
 extern crate substrate_primitives as primitives;
@@ -64,13 +65,14 @@ use runtime_primitives::traits::{Block, Header};
 use runtime_primitives::bft::{Message as PrimitiveMessage, Action as PrimitiveAction, Justification as PrimitiveJustification};
 use primitives::AuthorityId;
 
-use futures::{task, Async, Stream, Sink, Future, IntoFuture};
+use futures::{task, Async, AsyncSink, Poll, Stream, Sink, StartSend, Future, IntoFuture};
 use futures::sync::oneshot;
 use tokio::timer::Delay;
 use parking_lot::Mutex;
 
 pub use rhododendron::InputStreamConcluded;
 pub use error::{Error, ErrorKind};
+pub use journal::{VoteJournal, VoteKind};
 
 /// Messages over the proposal.
 /// Each message carries an associated round number.
@@ -178,9 +180,14 @@ pub trait Proposer<B: Block> {
 	/// Evaluate proposal. True means valid.
 	fn evaluate(&self, proposal: &B) -> Self::Evaluate;
 
-	/// Import witnessed misbehavior.
+	/// Import witnessed misbehavior. Called as soon as misbehavior is witnessed
+	/// and once more when the agreement is dropped.
 	fn import_misbehavior(&self, misbehavior: Vec<(AuthorityId, Misbehavior<B::Hash>)>);
 
+	/// Note that agreement was reached in the given round, justified by the signatures
+	/// of `signers`. Authorities missing from either may be considered offline.
+	fn on_committed(&self, round_number: usize, signers: &[AuthorityId]);
+
 	/// Determine the proposer for a given round. This should be a deterministic function
 	/// with consistent results across all authorities.
 	fn round_proposer(&self, round_number: usize, authorities: &[AuthorityId]) -> AuthorityId;
@@ -204,9 +211,64 @@ struct BftInstance<B: Block, P> {
 	authorities: Vec<AuthorityId>,
 	parent_hash: B::Hash,
 	round_timeout_multiplier: u64,
+	journal: Arc<VoteJournal<B::Hash>>,
+	// set once a message was refused, aborting the agreement.
+	aborted: Arc<AtomicBool>,
 	proposer: P,
 }
 
+impl<B: Block, P> BftInstance<B, P> {
+	// Record the message in the vote journal before it is signed. A message conflicting with one
+	// signed earlier for the same parent and round, possibly before a restart, or which cannot
+	// be recorded, must not be signed and is returned as an error.
+	fn journaled_message(&self, message: Message<B>) -> Result<Message<B>, Message<B>> {
+		let noted = match message {
+			::rhododendron::Message::Propose(r, ref proposal) => Some((r, VoteKind::Propose, proposal.hash())),
+			::rhododendron::Message::Vote(::rhododendron::Vote::Prepare(r, ref h)) => Some((r, VoteKind::Prepare, h.clone())),
+			::rhododendron::Message::Vote(::rhododendron::Vote::Commit(r, ref h)) => Some((r, VoteKind::Commit, h.clone())),
+			::rhododendron::Message::Vote(::rhododendron::Vote::AdvanceRound(_)) => None,
+		};
+		let (round, kind, digest) = match noted {
+			Some(noted) => noted,
+			None => return Ok(message),
+		};
+
+		match self.journal.note_signing(&self.parent_hash, round as u32, kind, &digest) {
+			Ok(None) => Ok(message),
+			Ok(Some(signed)) => {
+				warn!(target: "bft", "Refusing to sign {:?} of {:?} in round {}: already signed {:?}", kind, digest, round, signed);
+				Err(message)
+			}
+			Err(e) => {
+				warn!(target: "bft", "Refusing to sign {:?} in round {}: unable to record it to the vote journal: {}", kind, round, e);
+				Err(message)
+			}
+		}
+	}
+}
+
+// Output of an agreement, dropping every message once the agreement is aborted.
+struct AbortableSink<S> {
+	inner: S,
+	aborted: Arc<AtomicBool>,
+}
+
+impl<S: Sink> Sink for AbortableSink<S> {
+	type SinkItem = S::SinkItem;
+	type SinkError = S::SinkError;
+
+	fn start_send(&mut self, item: S::SinkItem) -> StartSend<S::SinkItem, S::SinkError> {
+		if self.aborted.load(Ordering::Acquire) {
+			return Ok(AsyncSink::Ready);
+		}
+		self.inner.start_send(item)
+	}
+
+	fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+		self.inner.poll_complete()
+	}
+}
+
 impl<B: Block, P: Proposer<B>> rhododendron::Context for BftInstance<B, P>
 	where
 		B: Clone + Eq,
@@ -234,7 +296,15 @@ impl<B: Block, P: Proposer<B>> rhododendron::Context for BftInstance<B, P>
 	}
 
 	fn sign_local(&self, message: Message<B>) -> LocalizedMessage<B> {
-		sign_message(message, &*self.key, self.parent_hash.clone())
+		match self.journaled_message(message) {
+			Ok(message) => sign_message(message, &*self.key, self.parent_hash.clone()),
+			Err(message) => {
+				// the agreement stalls: the unsigned message is never sent, and the agreement
+				// future resolves before anything else is.
+				self.aborted.store(true, Ordering::Release);
+				localize_message(message, self.key.public(), self.parent_hash.clone(), |_| Default::default())
+			}
+		}
 	}
 
 	fn round_proposer(&self, round: usize) -> AuthorityId {
@@ -271,8 +341,9 @@ pub struct BftFuture<B, P, I, InStream, OutSink> where
 	InStream: Stream<Item=Communication<B>, Error=P::Error>,
 	OutSink: Sink<SinkItem=Communication<B>, SinkError=P::Error>,
 {
-	inner: rhododendron::Agreement<BftInstance<B, P>, InStream, OutSink>,
+	inner: rhododendron::Agreement<BftInstance<B, P>, InStream, AbortableSink<OutSink>>,
 	cancel: Arc<AtomicBool>,
+	aborted: Arc<AtomicBool>,
 	send_task: Option<oneshot::Sender<task::Task>>,
 	import: Arc<I>,
 }
@@ -301,7 +372,26 @@ impl<B, P, I, InStream, OutSink> Future for BftFuture<B, P, I, InStream, OutSink
 		}
 
 		// TODO: handle and log this error in a way which isn't noisy on exit.
-		let committed = try_ready!(self.inner.poll().map_err(|_| ()));
+		let result = self.inner.poll();
+
+		// report misbehavior as soon as it is witnessed rather than at the end of the round.
+		let misbehavior = self.inner.drain_misbehavior().collect::<Vec<_>>();
+		if !misbehavior.is_empty() {
+			self.inner.context().proposer.import_misbehavior(misbehavior);
+		}
+
+		// a message conflicting with the vote journal was about to be signed.
+		if self.aborted.load(Ordering::Acquire) {
+			warn!(target: "bft", "Aborting agreement on top of {}", self.inner.context().parent_hash);
+			return Ok(Async::Ready(()))
+		}
+
+		let committed = try_ready!(result.map_err(|_| ()));
+
+		let signers = committed.justification.signatures.iter()
+			.map(|s| s.signer.clone().into())
+			.collect::<Vec<AuthorityId>>();
+		self.inner.context().proposer.on_committed(committed.justification.round_number, &signers);
 
 		// If we didn't see the proposal (very unlikely),
 		// we will get the block from the network later.
@@ -325,7 +415,6 @@ impl<B, P, I, InStream, OutSink> Drop for BftFuture<B, P, I, InStream, OutSink>
 	OutSink: Sink<SinkItem=Communication<B>, SinkError=P::Error>,
 {
 	fn drop(&mut self) {
-		// TODO: have a trait member to pass misbehavior reports into.
 		let misbehavior = self.inner.drain_misbehavior().collect::<Vec<_>>();
 		self.inner.context().proposer.import_misbehavior(misbehavior);
 	}
@@ -360,6 +449,7 @@ pub struct BftService<B: Block, P, I> {
 	live_agreement: Mutex<Option<(B::Hash, AgreementHandle)>>,
 	round_timeout_multiplier: u64,
 	key: Arc<ed25519::Pair>, // TODO: key changing over time.
+	journal: Arc<VoteJournal<B::Hash>>,
 	factory: P,
 }
 
@@ -371,13 +461,15 @@ impl<B, P, I> BftService<B, P, I>
 		I: BlockImport<B> + Authorities<B>,
 {
 
-	/// Create a new service instance.
-	pub fn new(client: Arc<I>, key: Arc<ed25519::Pair>, factory: P) -> BftService<B, P, I> {
+	/// Create a new service instance. Signed messages are recorded in `journal`, which should
+	/// be persistent so that no conflicting messages are signed after a restart.
+	pub fn new(client: Arc<I>, key: Arc<ed25519::Pair>, factory: P, journal: Arc<VoteJournal<B::Hash>>) -> BftService<B, P, I> {
 		BftService {
 			client: client,
 			live_agreement: Mutex::new(None),
 			round_timeout_multiplier: 4,
 			key: key, // TODO: key changing over time.
+			journal: journal,
 			factory: factory,
 		}
 	}
@@ -425,11 +517,14 @@ impl<B, P, I> BftService<B, P, I>
 
 		let (proposer, input, output) = self.factory.init(header, &authorities, self.key.clone())?;
 
+		let aborted = Arc::new(AtomicBool::new(false));
 		let bft_instance = BftInstance {
 			proposer,
 			parent_hash: hash.clone(),
 			round_timeout_multiplier: self.round_timeout_multiplier,
 			key: self.key.clone(),
+			journal: self.journal.clone(),
+			aborted: aborted.clone(),
 			authorities: authorities,
 		};
 
@@ -438,7 +533,7 @@ impl<B, P, I> BftService<B, P, I>
 			n,
 			max_faulty,
 			input,
-			output,
+			AbortableSink { inner: output, aborted: aborted.clone() },
 		);
 
 		let cancel = Arc::new(AtomicBool::new(false));
@@ -456,6 +551,7 @@ impl<B, P, I> BftService<B, P, I>
 		Ok(Some(BftFuture {
 			inner: agreement,
 			cancel: cancel,
+			aborted,
 			send_task: Some(tx),
 			import: self.client.clone(),
 		}))
@@ -585,8 +681,14 @@ fn check_action<B: Block>(action: PrimitiveAction<B, B::Hash>, parent_hash: &B::
 
 /// Sign a BFT message with the given key.
 pub fn sign_message<B: Block + Clone>(message: Message<B>, key: &ed25519::Pair, parent_hash: B::Hash) -> LocalizedMessage<B> {
-	let signer = key.public();
+	localize_message(message, key.public(), parent_hash, |to_sign| key.sign(to_sign))
+}
 
+// Localize a BFT message from `signer`, with signatures produced by `sign`.
+fn localize_message<B, F>(message: Message<B>, signer: ed25519::Public, parent_hash: B::Hash, sign: F) -> LocalizedMessage<B> where
+	B: Block + Clone,
+	F: Fn(&[u8]) -> ed25519::Signature,
+{
 	let sign_action = |action: PrimitiveAction<B, B::Hash>| {
 		let primitive = PrimitiveMessage {
 			parent: parent_hash.clone(),
@@ -596,7 +698,7 @@ pub fn sign_message<B: Block + Clone>(message: Message<B>, key: &ed25519::Pair,
 		let to_sign = Encode::encode(&primitive);
 		LocalizedSignature {
 			signer: signer.clone(),
-			signature: key.sign(&to_sign),
+			signature: sign(&to_sign),
 		}
 	};
 
@@ -713,6 +815,8 @@ mod tests {
 
 		fn import_misbehavior(&self, _misbehavior: Vec<(AuthorityId, Misbehavior<H256>)>) {}
 
+		fn on_committed(&self, _round_number: usize, _signers: &[AuthorityId]) {}
+
 		fn round_proposer(&self, round_number: usize, authorities: &[AuthorityId]) -> AuthorityId {
 			authorities[round_number % authorities.len()].clone()
 		}
@@ -726,6 +830,7 @@ mod tests {
 			live_agreement: Mutex::new(None),
 			round_timeout_multiplier: 4,
 			key: Arc::new(Keyring::One.into()),
+			journal: Arc::new(VoteJournal::in_memory()),
 			factory: DummyFactory
 		}
 	}
@@ -921,4 +1026,97 @@ mod tests {
 			assert!(false);
 		}
 	}
+
+	fn instance_with_journal(journal: VoteJournal<H256>) -> BftInstance<TestBlock, DummyProposer> {
+		BftInstance {
+			key: Arc::new(Keyring::Alice.pair()),
+			authorities: vec![Keyring::Alice.to_raw_public().into()],
+			parent_hash: [0xaa; 32].into(),
+			round_timeout_multiplier: 4,
+			journal: Arc::new(journal),
+			aborted: Arc::new(AtomicBool::new(false)),
+			proposer: DummyProposer(1),
+		}
+	}
+
+	fn is_signed(message: &LocalizedMessage<TestBlock>, parent_hash: H256) -> bool {
+		match *message {
+			::rhododendron::LocalizedMessage::Vote(ref vote) =>
+				sign_vote(vote.vote.clone(), &Keyring::Alice.pair(), parent_hash) == vote.signature,
+			::rhododendron::LocalizedMessage::Propose(ref proposal) =>
+				proposal.full_signature.signature != Default::default(),
+		}
+	}
+
+	#[test]
+	fn sign_local_does_not_equivocate() {
+		use rhododendron::Context;
+
+		let instance = instance_with_journal(VoteJournal::in_memory());
+		let parent_hash = instance.parent_hash;
+		let first: H256 = [1; 32].into();
+		let second: H256 = [2; 32].into();
+
+		let signed = instance.sign_local(::rhododendron::Vote::Prepare(1, first).into());
+		assert!(is_signed(&signed, parent_hash));
+		let signed = instance.sign_local(::rhododendron::Vote::Prepare(1, first).into());
+		assert!(is_signed(&signed, parent_hash));
+		let signed = instance.sign_local(::rhododendron::Vote::Prepare(2, second).into());
+		assert!(is_signed(&signed, parent_hash));
+		assert!(!instance.aborted.load(Ordering::Acquire));
+
+		let refused = instance.sign_local(::rhododendron::Vote::Prepare(1, second).into());
+		assert!(!is_signed(&refused, parent_hash));
+		assert!(instance.aborted.load(Ordering::Acquire));
+
+		let instance = instance_with_journal(VoteJournal::in_memory());
+		let block = |number| TestBlock { header: from_block_number(number), extrinsics: Default::default() };
+		assert!(is_signed(&instance.sign_local(::rhododendron::Message::Propose(1, block(1))), parent_hash));
+		assert!(!is_signed(&instance.sign_local(::rhododendron::Message::Propose(1, block(2))), parent_hash));
+		assert!(instance.aborted.load(Ordering::Acquire));
+	}
+
+	#[test]
+	fn sign_local_refuses_unrecorded_messages() {
+		use rhododendron::Context;
+
+		let mut path = ::std::env::temp_dir();
+		path.push(format!("substrate-bft-missing-{}", ::std::process::id()));
+		path.push("journal");
+		let instance = instance_with_journal(VoteJournal::open(path).unwrap());
+		let parent_hash = instance.parent_hash;
+
+		let refused = instance.sign_local(::rhododendron::Vote::Commit(1, [1; 32].into()).into());
+		assert!(!is_signed(&refused, parent_hash));
+		assert!(instance.aborted.load(Ordering::Acquire));
+	}
+
+	#[test]
+	fn aborted_agreement_sends_nothing() {
+		use std::sync::atomic::AtomicUsize;
+
+		struct Counter(Arc<AtomicUsize>);
+
+		impl Sink for Counter {
+			type SinkItem = u32;
+			type SinkError = ();
+
+			fn start_send(&mut self, _item: u32) -> ::futures::StartSend<u32, ()> {
+				self.0.fetch_add(1, Ordering::SeqCst);
+				Ok(::futures::AsyncSink::Ready)
+			}
+
+			fn poll_complete(&mut self) -> ::futures::Poll<(), ()> {
+				Ok(Async::Ready(()))
+			}
+		}
+
+		let sent = Arc::new(AtomicUsize::new(0));
+		let aborted = Arc::new(AtomicBool::new(false));
+		let mut sink = AbortableSink { inner: Counter(sent.clone()), aborted: aborted.clone() };
+		sink.start_send(1).unwrap();
+		aborted.store(true, Ordering::Release);
+		sink.start_send(2).unwrap();
+		assert_eq!(sent.load(Ordering::SeqCst), 1);
+	}
 }
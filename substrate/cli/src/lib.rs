@@ -250,6 +250,9 @@ where
 		} else if matches.is_present("validator") || matches.is_present("dev") {
 			config.execution_strategy = service::ExecutionStrategy::Both;
 			service::Roles::AUTHORITY
+		} else if matches.is_present("sentry") {
+			config.execution_strategy = service::ExecutionStrategy::NativeWhenPossible;
+			service::Roles::FULL | service::Roles::SENTRY
 		} else {
 			config.execution_strategy = service::ExecutionStrategy::NativeWhenPossible;
 			service::Roles::FULL
@@ -259,6 +262,19 @@ where
 		config.max_heap_pages = v.parse().map_err(|_| "Invalid --max-heap-pages argument")?;
 	}
 
+	if let Some(v) = matches.value_of("wasm-fuel-limit") {
+		config.wasm_fuel_limit = Some(v.parse().map_err(|_| "Invalid --wasm-fuel-limit argument")?);
+	}
+
+	config.profile_runtime = matches.is_present("profile-runtime");
+
+	if let Some(v) = matches.value_of("rpc-fuel-limit") {
+		config.rpc_fuel_limit = match v.parse().map_err(|_| "Invalid --rpc-fuel-limit argument")? {
+			0 => None,
+			limit => Some(limit),
+		};
+	}
+
 	if let Some(s) = matches.value_of("execution") {
 		config.execution_strategy = match s {
 			"both" => service::ExecutionStrategy::Both,
@@ -268,11 +284,44 @@ where
 		};
 	}
 
+	if let Some(s) = matches.value_of("dev-seal") {
+		config.dev_seal = Some(s.parse().map_err(error::ErrorKind::Input)?);
+	}
+
+	if let Some(v) = matches.value_of("aura-slot-duration") {
+		config.aura_slot_duration = match v.parse().map_err(|_| "Invalid --aura-slot-duration argument")? {
+			0 => return Err(error::ErrorKind::Input("Aura slot duration must be non-zero".to_owned()).into()),
+			duration => Some(duration),
+		};
+	}
+
+	if let Some(v) = matches.value_of("finality-round-duration") {
+		config.finality_round_duration = match v.parse().map_err(|_| "Invalid --finality-round-duration argument")? {
+			0 => return Err(error::ErrorKind::Input("Finality round duration must be non-zero".to_owned()).into()),
+			duration => Some(duration),
+		};
+	}
+
+	config.offchain_worker = matches.is_present("offchain-worker");
+
 	config.roles = role;
 	{
+		// Development chains are not shipped with bootnodes, so we rely on
+		// mDNS for nodes of a local cluster to find each other.
+		config.network.enable_mdns = !matches.is_present("no-mdns")
+			&& config.chain_spec.boot_nodes().is_empty();
 		config.network.boot_nodes.extend(matches
 			.values_of("bootnodes")
 			.map_or(Default::default(), |v| v.map(|n| n.to_owned()).collect::<Vec<_>>()));
+		config.network.sentry_mode = if let Some(sentries) = matches.values_of("sentry-nodes") {
+			// Validators behind sentries must stay off discovery entirely.
+			config.network.enable_mdns = false;
+			network::SentryMode::Validator(sentries.map(|n| n.to_owned()).collect())
+		} else if let Some(validators) = matches.values_of("sentry") {
+			network::SentryMode::Sentry(validators.map(|n| n.to_owned()).collect())
+		} else {
+			network::SentryMode::Disabled
+		};
 		config.network.config_path = Some(network_path(&base_path, config.chain_spec.id()).to_string_lossy().into());
 		config.network.net_config_path = config.network.config_path.clone();
 
@@ -356,6 +405,12 @@ fn import_blocks<F, E>(matches: &clap::ArgMatches, spec: ChainSpec<FactoryGenesi
 		config.max_heap_pages = v.parse().map_err(|_| "Invalid --max-heap-pages argument")?;
 	}
 
+	if let Some(v) = matches.value_of("wasm-fuel-limit") {
+		config.wasm_fuel_limit = Some(v.parse().map_err(|_| "Invalid --wasm-fuel-limit argument")?);
+	}
+
+	config.profile_runtime = matches.is_present("profile-runtime");
+
 	if let Some(s) = matches.value_of("execution") {
 		config.execution_strategy = match s {
 			"both" => service::ExecutionStrategy::Both,
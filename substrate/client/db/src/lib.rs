@@ -51,12 +51,12 @@ use parking_lot::RwLock;
 use primitives::{H256, AuthorityId};
 use runtime_primitives::generic::BlockId;
 use runtime_primitives::bft::Justification;
-use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, As, Hash, HashFor, NumberFor, Zero};
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, As, Hash, HashFor, NumberFor, Zero, One};
 use runtime_primitives::BuildStorage;
 use state_machine::backend::Backend as StateBackend;
 use executor::RuntimeInfo;
 use state_machine::{CodeExecutor, TrieH256, DBValue, ExecutionStrategy};
-use utils::{Meta, db_err, meta_keys, number_to_db_key, open_database, read_db, read_id, read_meta};
+use utils::{Meta, db_err, meta_keys, number_to_db_key, open_database, read_meta};
 use state_db::StateDb;
 pub use state_db::PruningMode;
 
@@ -100,6 +100,34 @@ mod columns {
 	pub const HEADER: Option<u32> = Some(4);
 	pub const BODY: Option<u32> = Some(5);
 	pub const JUSTIFICATION: Option<u32> = Some(6);
+	pub const FINALITY_PROOF: Option<u32> = Some(7);
+	pub const OFFCHAIN: Option<u32> = Some(8);
+	pub const AUX: Option<u32> = Some(9);
+}
+
+// Key under which the data of a block is stored: its number followed by its hash, so that blocks
+// of different forks at the same height are kept apart. `BLOCK_INDEX` maps both the hash of a
+// block and, for the best chain, its number to this key.
+fn block_lookup_key<N: As<u64>, H: AsRef<[u8]>>(number: N, hash: &H) -> Vec<u8> {
+	let mut key = number_to_db_key(number).to_vec();
+	key.extend_from_slice(hash.as_ref());
+	key
+}
+
+// Read the lookup key of a block. Numbers refer to blocks of the best chain.
+fn read_lookup_key<Block: BlockT>(db: &KeyValueDB, id: BlockId<Block>) -> Result<Option<DBValue>, client::error::Error> {
+	match id {
+		BlockId::Hash(h) => db.get(columns::BLOCK_INDEX, h.as_ref()),
+		BlockId::Number(n) => db.get(columns::BLOCK_INDEX, &number_to_db_key(n)),
+	}.map_err(db_err)
+}
+
+// Read a column entry of a block.
+fn read_block_data<Block: BlockT>(db: &KeyValueDB, col: Option<u32>, id: BlockId<Block>) -> Result<Option<DBValue>, client::error::Error> {
+	match read_lookup_key(db, id)? {
+		Some(key) => db.get(col, &key).map_err(db_err),
+		None => Ok(None),
+	}
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -128,7 +156,8 @@ pub struct BlockchainDb<Block: BlockT> {
 
 impl<Block: BlockT> BlockchainDb<Block> {
 	fn new(db: Arc<KeyValueDB>) -> Result<Self, client::error::Error> {
-		let meta = read_meta::<Block>(&*db, columns::HEADER)?;
+		let genesis_key = read_lookup_key::<Block>(&*db, BlockId::Number(Zero::zero()))?;
+		let meta = read_meta::<Block>(&*db, columns::HEADER, genesis_key.as_ref().map(|key| &key[..]))?;
 		Ok(BlockchainDb {
 			db,
 			meta: RwLock::new(meta)
@@ -140,16 +169,23 @@ impl<Block: BlockT> BlockchainDb<Block> {
 			let mut meta = self.meta.write();
 			if number == Zero::zero() {
 				meta.genesis_hash = hash;
+				meta.finalized_hash = hash;
 			}
 			meta.best_number = number;
 			meta.best_hash = hash;
 		}
 	}
+
+	fn update_finalized(&self, hash: Block::Hash, number: <Block::Header as HeaderT>::Number) {
+		let mut meta = self.meta.write();
+		meta.finalized_hash = hash;
+		meta.finalized_number = number;
+	}
 }
 
 impl<Block: BlockT> client::blockchain::HeaderBackend<Block> for BlockchainDb<Block> {
 	fn header(&self, id: BlockId<Block>) -> Result<Option<Block::Header>, client::error::Error> {
-		match read_db(&*self.db, columns::BLOCK_INDEX, columns::HEADER, id)? {
+		match read_block_data(&*self.db, columns::HEADER, id)? {
 			Some(header) => match Block::Header::decode(&mut &header[..]) {
 				Some(header) => Ok(Some(header)),
 				None => return Err(client::error::ErrorKind::Backend("Error decoding header".into()).into()),
@@ -164,12 +200,14 @@ impl<Block: BlockT> client::blockchain::HeaderBackend<Block> for BlockchainDb<Bl
 			best_hash: meta.best_hash,
 			best_number: meta.best_number,
 			genesis_hash: meta.genesis_hash,
+			finalized_hash: meta.finalized_hash,
+			finalized_number: meta.finalized_number,
 		})
 	}
 
 	fn status(&self, id: BlockId<Block>) -> Result<client::blockchain::BlockStatus, client::error::Error> {
 		let exists = match id {
-			BlockId::Hash(_) => read_id(&*self.db, columns::BLOCK_INDEX, id)?.is_some(),
+			BlockId::Hash(_) => read_lookup_key(&*self.db, id)?.is_some(),
 			BlockId::Number(n) => n <= self.meta.read().best_number,
 		};
 		match exists {
@@ -179,7 +217,7 @@ impl<Block: BlockT> client::blockchain::HeaderBackend<Block> for BlockchainDb<Bl
 	}
 
 	fn hash(&self, number: <Block::Header as HeaderT>::Number) -> Result<Option<Block::Hash>, client::error::Error> {
-		read_db::<Block>(&*self.db, columns::BLOCK_INDEX, columns::HEADER, BlockId::Number(number)).map(|x|
+		read_block_data::<Block>(&*self.db, columns::HEADER, BlockId::Number(number)).map(|x|
 			x.map(|raw| HashFor::<Block>::hash(&raw[..])).map(Into::into)
 		)
 	}
@@ -187,7 +225,7 @@ impl<Block: BlockT> client::blockchain::HeaderBackend<Block> for BlockchainDb<Bl
 
 impl<Block: BlockT> client::blockchain::Backend<Block> for BlockchainDb<Block> {
 	fn body(&self, id: BlockId<Block>) -> Result<Option<Vec<Block::Extrinsic>>, client::error::Error> {
-		match read_db(&*self.db, columns::BLOCK_INDEX, columns::BODY, id)? {
+		match read_block_data(&*self.db, columns::BODY, id)? {
 			Some(body) => match Decode::decode(&mut &body[..]) {
 				Some(body) => Ok(Some(body)),
 				None => return Err(client::error::ErrorKind::Backend("Error decoding body".into()).into()),
@@ -197,7 +235,7 @@ impl<Block: BlockT> client::blockchain::Backend<Block> for BlockchainDb<Block> {
 	}
 
 	fn justification(&self, id: BlockId<Block>) -> Result<Option<Justification<Block::Hash>>, client::error::Error> {
-		match read_db(&*self.db, columns::BLOCK_INDEX, columns::JUSTIFICATION, id)? {
+		match read_block_data(&*self.db, columns::JUSTIFICATION, id)? {
 			Some(justification) => match Decode::decode(&mut &justification[..]) {
 				Some(justification) => Ok(Some(justification)),
 				None => return Err(client::error::ErrorKind::Backend("Error decoding justification".into()).into()),
@@ -206,6 +244,10 @@ impl<Block: BlockT> client::blockchain::Backend<Block> for BlockchainDb<Block> {
 		}
 	}
 
+	fn finality_proof(&self, id: BlockId<Block>) -> Result<Option<Vec<u8>>, client::error::Error> {
+		read_block_data(&*self.db, columns::FINALITY_PROOF, id).map(|proof| proof.map(|p| p.to_vec()))
+	}
+
 	fn cache(&self) -> Option<&client::blockchain::Cache<Block>> {
 		None
 	}
@@ -315,6 +357,24 @@ impl<Block: BlockT> Backend<Block> {
 			finalization_window,
 		})
 	}
+
+	// Whether the block `hash` at `number` joins the best chain no lower than the canonicalized block at `finalized`.
+	fn descends_from_canonical(&self, mut hash: Block::Hash, mut number: u64, finalized: u64) -> Result<bool, client::error::Error> {
+		use client::blockchain::HeaderBackend;
+		loop {
+			if self.blockchain.hash(As::sa(number))? == Some(hash) {
+				return Ok(true);
+			}
+			if number <= finalized {
+				return Ok(false);
+			}
+			hash = match self.blockchain.header(BlockId::Hash(hash))? {
+				Some(header) => *header.parent_hash(),
+				None => return Ok(false),
+			};
+			number -= 1;
+		}
+	}
 }
 
 fn apply_state_commit(transaction: &mut DBTransaction, commit: state_db::CommitSet<H256>) {
@@ -351,8 +411,18 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 		let mut transaction = DBTransaction::new();
 		if let Some(pending_block) = operation.pending_block {
 			let hash = pending_block.header.hash();
+			let parent_hash = *pending_block.header.parent_hash();
 			let number = pending_block.header.number().clone();
-			let key = number_to_db_key(number.clone());
+			let number_u64: u64 = number.as_();
+
+			// state of blocks that do not descend from the last canonicalized block has been discarded.
+			let finalized = self.storage.state_db.best_finalized();
+			if number_u64 > 0 && (number_u64 <= finalized || !self.descends_from_canonical(parent_hash, number_u64 - 1, finalized)?) {
+				return Err(client::error::ErrorKind::Backend(
+					format!("Block {:?} ({}) does not build on the canonicalized state", hash, number)).into());
+			}
+
+			let key = block_lookup_key(number, &hash);
 			transaction.put(columns::HEADER, &key, &pending_block.header.encode());
 			if let Some(body) = pending_block.body {
 				transaction.put(columns::BODY, &key, &body.encode());
@@ -361,9 +431,34 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 				transaction.put(columns::JUSTIFICATION, &key, &justification.encode());
 			}
 			transaction.put(columns::BLOCK_INDEX, hash.as_ref(), &key);
+
+			// blocks that become canonical with this one, when it is the new best block.
+			let mut enacted: Vec<(u64, Block::Hash)> = Vec::new();
 			if pending_block.is_best {
 				transaction.put(columns::META, meta_keys::BEST_BLOCK, &key);
+				let (mut canonical_number, mut canonical_hash) = (number, hash);
+				let mut canonical_parent = parent_hash;
+				while self.blockchain.hash(canonical_number)? != Some(canonical_hash) {
+					transaction.put(columns::BLOCK_INDEX, &number_to_db_key(canonical_number), &block_lookup_key(canonical_number, &canonical_hash));
+					enacted.push((canonical_number.as_(), canonical_hash));
+					if canonical_number.is_zero() {
+						break;
+					}
+					canonical_number = canonical_number - One::one();
+					canonical_hash = canonical_parent;
+					canonical_parent = match self.blockchain.header(BlockId::Hash(canonical_hash))? {
+						Some(header) => *header.parent_hash(),
+						None => break,
+					};
+				}
+				let best_number = self.blockchain.meta.read().best_number;
+				let mut retracted = number + One::one();
+				while retracted <= best_number {
+					transaction.delete(columns::BLOCK_INDEX, &number_to_db_key(retracted));
+					retracted = retracted + One::one();
+				}
 			}
+
 			let mut changeset: state_db::ChangeSet<H256> = state_db::ChangeSet::default();
 			for (key, (val, rc)) in operation.updates.drain() {
 				if rc > 0 {
@@ -372,18 +467,20 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 					changeset.deleted.push(key.0.into());
 				}
 			}
-			let number_u64 = number.as_().into();
-			let commit = self.storage.state_db.insert_block(&hash, number_u64, &pending_block.header.parent_hash(), changeset);
+			let commit = self.storage.state_db.insert_block(&hash, number_u64, &parent_hash, changeset);
 			apply_state_commit(&mut transaction, commit);
 
-			//finalize an older block
+			//finalize an older block of the best chain
 			if number_u64 > self.finalization_window {
 				let finalizing_hash = if self.finalization_window == 0 {
-					Some(hash)
+					if pending_block.is_best { Some(hash) } else { None }
 				} else {
 					let finalizing = number_u64 - self.finalization_window;
 					if finalizing > self.storage.state_db.best_finalized() {
-						self.blockchain.hash(As::sa(finalizing))?
+						match enacted.iter().find(|&&(n, _)| n == finalizing) {
+							Some(&(_, h)) => Some(h),
+							None => self.blockchain.hash(As::sa(finalizing))?,
+						}
 					} else {
 						None
 					}
@@ -402,6 +499,26 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 		Ok(())
 	}
 
+	fn finalize_block(&self, block: BlockId<Block>, proof: Option<Vec<u8>>) -> Result<(), client::error::Error> {
+		use client::blockchain::HeaderBackend;
+		let header = self.blockchain.header(block)?
+			.ok_or_else(|| client::error::ErrorKind::UnknownBlock(format!("{}", block)))?;
+		let hash = header.hash();
+		let number = *header.number();
+		let key = block_lookup_key(number, &hash);
+
+		let mut transaction = DBTransaction::new();
+		if let Some(proof) = proof {
+			transaction.put(columns::FINALITY_PROOF, &key, &proof);
+		}
+		transaction.put(columns::META, meta_keys::FINALIZED_BLOCK, &key);
+
+		debug!("DB Finalize {:?} ({})", hash, number);
+		self.storage.db.write(transaction).map_err(db_err)?;
+		self.blockchain.update_finalized(hash, number);
+		Ok(())
+	}
+
 	fn revert(&self, n: NumberFor<Block>) -> Result<NumberFor<Block>, client::error::Error> {
 		use client::blockchain::HeaderBackend;
 		let mut best = self.blockchain.info()?.best_number;
@@ -416,12 +533,12 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 					let removed = self.blockchain.hash(best)?.ok_or_else(
 						|| client::error::ErrorKind::UnknownBlock(
 							format!("Error reverting to {}. Block hash not found.", best)))?;
+					transaction.delete(columns::BLOCK_INDEX, &number_to_db_key(best));
 					best -= As::sa(1);
-					let key = number_to_db_key(best.clone());
 					let hash = self.blockchain.hash(best)?.ok_or_else(
 						|| client::error::ErrorKind::UnknownBlock(
 							format!("Error reverting to {}. Block hash not found.", best)))?;
-					transaction.put(columns::META, meta_keys::BEST_BLOCK, &key);
+					transaction.put(columns::META, meta_keys::BEST_BLOCK, &block_lookup_key(best, &hash));
 					transaction.delete(columns::BLOCK_INDEX, removed.as_ref());
 					self.storage.db.write(transaction).map_err(db_err)?;
 					self.blockchain.update_meta(hash, best, true);
@@ -432,6 +549,26 @@ impl<Block: BlockT> client::backend::Backend<Block> for Backend<Block> {
 		Ok(n)
 	}
 
+	fn offchain_storage(&self, key: &[u8]) -> Result<Option<Vec<u8>>, client::error::Error> {
+		Ok(self.storage.db.get(columns::OFFCHAIN, key).map_err(db_err)?.map(|v| v.to_vec()))
+	}
+
+	fn set_offchain_storage(&self, key: &[u8], value: &[u8]) -> Result<(), client::error::Error> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::OFFCHAIN, key, value);
+		self.storage.db.write(transaction).map_err(db_err)
+	}
+
+	fn aux_storage(&self, key: &[u8]) -> Result<Option<Vec<u8>>, client::error::Error> {
+		Ok(self.storage.db.get(columns::AUX, key).map_err(db_err)?.map(|v| v.to_vec()))
+	}
+
+	fn set_aux_storage(&self, key: &[u8], value: &[u8]) -> Result<(), client::error::Error> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::AUX, key, value);
+		self.storage.db.write(transaction).map_err(db_err)
+	}
+
 	fn blockchain(&self) -> &BlockchainDb<Block> {
 		&self.blockchain
 	}
@@ -509,7 +646,7 @@ mod tests {
 	#[test]
 	fn set_state_data() {
 		let db = Backend::<Block>::new_test();
-		{
+		let hash = {
 			let mut op = db.begin_operation(BlockId::Hash(Default::default())).unwrap();
 			let mut header = Header {
 				number: 0,
@@ -530,6 +667,8 @@ mod tests {
 				.map(|(x, y)| (x, Some(y)))
 			).0.into();
 
+			let hash = header.hash();
+
 			op.reset_storage(storage.iter().cloned()).unwrap();
 			op.set_block_data(
 				header,
@@ -546,13 +685,14 @@ mod tests {
 			assert_eq!(state.storage(&[1, 2, 3]).unwrap(), Some(vec![9, 9, 9]));
 			assert_eq!(state.storage(&[5, 5, 5]).unwrap(), None);
 
-		}
+			hash
+		};
 
 		{
 			let mut op = db.begin_operation(BlockId::Number(0)).unwrap();
 			let mut header = Header {
 				number: 1,
-				parent_hash: Default::default(),
+				parent_hash: hash,
 				state_root: Default::default(),
 				digest: Default::default(),
 				extrinsics_root: Default::default(),
@@ -689,4 +829,108 @@ mod tests {
 			assert!(backend.storage.db.get(::columns::STATE, &key.0[..]).unwrap().is_none());
 		}
 	}
+
+	#[test]
+	fn finalized_block_survives_reopen() {
+		use client::blockchain::Backend as BlockchainBackend;
+		use utils::NUM_COLUMNS;
+
+		let kvdb = Arc::new(::kvdb_memorydb::create(NUM_COLUMNS));
+		let db = Backend::<Block>::from_kvdb(kvdb.clone() as Arc<_>, PruningMode::keep_blocks(0), 0).unwrap();
+		let mut parent_hash = Default::default();
+		for i in 0..3 {
+			let id = if i == 0 { BlockId::Hash(Default::default()) } else { BlockId::Number(i - 1) };
+			let mut op = db.begin_operation(id).unwrap();
+			let header = Header {
+				number: i,
+				parent_hash,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			parent_hash = header.hash();
+			op.set_block_data(header, Some(vec![]), None, true).unwrap();
+			db.commit_operation(op).unwrap();
+		}
+
+		assert_eq!(db.blockchain().info().unwrap().finalized_number, 0);
+
+		db.finalize_block(BlockId::Number(2), Some(vec![1, 2, 3])).unwrap();
+		assert_eq!(db.blockchain().info().unwrap().finalized_hash, parent_hash);
+
+		let db = Backend::<Block>::from_kvdb(kvdb as Arc<_>, PruningMode::keep_blocks(0), 0).unwrap();
+		let info = db.blockchain().info().unwrap();
+		assert_eq!(info.finalized_number, 2);
+		assert_eq!(info.finalized_hash, parent_hash);
+		assert_eq!(db.blockchain().finality_proof(BlockId::Number(2)).unwrap(), Some(vec![1, 2, 3]));
+		assert_eq!(db.blockchain().justification(BlockId::Number(2)).unwrap(), None);
+	}
+
+	#[test]
+	fn fork_blocks_are_kept_apart_and_reorganised() {
+		let db = Backend::<Block>::from_kvdb(
+			Arc::new(::kvdb_memorydb::create(::utils::NUM_COLUMNS)) as Arc<_>,
+			PruningMode::keep_blocks(0),
+			2,
+		).unwrap();
+		let insert = |number: u64, parent_hash: H256, fork: u8, is_best: bool| {
+			let id = if number == 0 { BlockId::Hash(Default::default()) } else { BlockId::Hash(parent_hash) };
+			let mut op = db.begin_operation(id).unwrap();
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: [fork; 32].into(),
+			};
+			let hash = header.hash();
+			op.set_block_data(header, Some(vec![]), None, is_best).unwrap();
+			db.commit_operation(op).map(|_| hash)
+		};
+
+		let genesis = insert(0, Default::default(), 0, true).unwrap();
+		let a1 = insert(1, genesis, 0, true).unwrap();
+		let a2 = insert(2, a1, 0, true).unwrap();
+
+		// a competing block at the same height does not replace the best one.
+		let b2 = insert(2, a1, 1, false).unwrap();
+		assert_eq!(db.blockchain().hash(2).unwrap(), Some(a2));
+		assert_eq!(db.blockchain().header(BlockId::Number(2)).unwrap().unwrap().hash(), a2);
+		assert_eq!(db.blockchain().header(BlockId::Hash(b2)).unwrap().unwrap().hash(), b2);
+		assert_eq!(db.blockchain().info().unwrap().best_hash, a2);
+
+		// the fork becomes the best chain.
+		let b3 = insert(3, b2, 1, true).unwrap();
+		assert_eq!(db.blockchain().hash(1).unwrap(), Some(a1));
+		assert_eq!(db.blockchain().hash(2).unwrap(), Some(b2));
+		assert_eq!(db.blockchain().hash(3).unwrap(), Some(b3));
+		assert_eq!(db.blockchain().header(BlockId::Hash(a2)).unwrap().unwrap().hash(), a2);
+		let info = db.blockchain().info().unwrap();
+		assert_eq!((info.best_number, info.best_hash), (3, b3));
+
+		// blocks below the canonicalized state can't be imported.
+		assert!(insert(1, genesis, 2, false).is_err());
+	}
+
+	#[test]
+	fn offchain_storage_survives_reopen() {
+		use utils::NUM_COLUMNS;
+
+		let kvdb = Arc::new(::kvdb_memorydb::create(NUM_COLUMNS));
+		let db = Backend::<Block>::from_kvdb(kvdb.clone() as Arc<_>, PruningMode::keep_blocks(0), 0).unwrap();
+		assert_eq!(db.offchain_storage(b"key").unwrap(), None);
+		db.set_offchain_storage(b"key", b"value").unwrap();
+
+		let db = Backend::<Block>::from_kvdb(kvdb as Arc<_>, PruningMode::keep_blocks(0), 0).unwrap();
+		assert_eq!(db.offchain_storage(b"key").unwrap(), Some(b"value".to_vec()));
+	}
+
+	#[test]
+	fn aux_storage_is_kept_apart_from_offchain_storage() {
+		let db = Backend::<Block>::new_test();
+		db.set_aux_storage(b"key", b"aux").unwrap();
+		db.set_offchain_storage(b"key", b"offchain").unwrap();
+		assert_eq!(db.aux_storage(b"key").unwrap(), Some(b"aux".to_vec()));
+		assert_eq!(db.offchain_storage(b"key").unwrap(), Some(b"offchain".to_vec()));
+	}
 }
@@ -80,7 +80,8 @@ impl<Block> LightStorage<Block>
 
 	fn from_kvdb(db: Arc<KeyValueDB>) -> ClientResult<Self> {
 		let cache = DbCache::new(db.clone(), columns::BLOCK_INDEX, columns::AUTHORITIES)?;
-		let meta = RwLock::new(read_meta::<Block>(&*db, columns::HEADER)?);
+		let genesis_key = number_to_db_key(<<Block as BlockT>::Header as HeaderT>::Number::zero());
+		let meta = RwLock::new(read_meta::<Block>(&*db, columns::HEADER, Some(&genesis_key[..]))?);
 
 		Ok(LightStorage {
 			db,
@@ -104,6 +105,7 @@ impl<Block> LightStorage<Block>
 			let mut meta = self.meta.write();
 			if number == <<Block as BlockT>::Header as HeaderT>::Number::zero() {
 				meta.genesis_hash = hash;
+				meta.finalized_hash = hash;
 			}
 
 			meta.best_number = number;
@@ -132,6 +134,8 @@ impl<Block> BlockchainHeaderBackend<Block> for LightStorage<Block>
 			best_hash: meta.best_hash,
 			best_number: meta.best_number,
 			genesis_hash: meta.genesis_hash,
+			finalized_hash: meta.finalized_hash,
+			finalized_number: meta.finalized_number,
 		})
 	}
 
@@ -31,7 +31,7 @@ use DatabaseSettings;
 
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
-pub const NUM_COLUMNS: u32 = 7;
+pub const NUM_COLUMNS: u32 = 10;
 /// Meta column. Thes set of keys in the column is shared by full && light storages.
 pub const COLUMN_META: Option<u32> = Some(0);
 
@@ -41,6 +41,8 @@ pub mod meta_keys {
 	pub const TYPE: &[u8; 4] = b"type";
 	/// Best block key.
 	pub const BEST_BLOCK: &[u8; 4] = b"best";
+	/// Last finalized block key.
+	pub const FINALIZED_BLOCK: &[u8; 5] = b"final";
 	/// Best authorities block key.
 	pub const BEST_AUTHORITIES: &[u8; 4] = b"auth";
 }
@@ -53,6 +55,10 @@ pub struct Meta<N, H> {
 	pub best_number: N,
 	/// Hash of the genesis block.
 	pub genesis_hash: H,
+	/// Hash of the last finalized block.
+	pub finalized_hash: H,
+	/// Number of the last finalized block.
+	pub finalized_number: N,
 }
 
 /// Type of block key in the database (LE block number).
@@ -145,34 +151,47 @@ pub fn read_db<Block>(db: &KeyValueDB, col_index: Option<u32>, col: Option<u32>,
 	})
 }
 
-/// Read meta from the database.
-pub fn read_meta<Block>(db: &KeyValueDB, col_header: Option<u32>) -> Result<Meta<<<Block as BlockT>::Header as HeaderT>::Number, Block::Hash>, client::error::Error>
+/// Read meta from the database. The genesis header is stored in `col_header` under `genesis_key`,
+/// if it has been imported.
+pub fn read_meta<Block>(db: &KeyValueDB, col_header: Option<u32>, genesis_key: Option<&[u8]>) -> Result<Meta<<<Block as BlockT>::Header as HeaderT>::Number, Block::Hash>, client::error::Error>
 	where
 		Block: BlockT,
 {
 	let genesis_number = <<Block as BlockT>::Header as HeaderT>::Number::zero();
-	let (best_hash, best_number) = if let Some(Some(header)) = db.get(COLUMN_META, meta_keys::BEST_BLOCK).and_then(|id|
-		match id {
-			Some(id) => db.get(col_header, &id).map(|h| h.map(|b| Block::Header::decode(&mut &b[..]))),
-			None => Ok(None),
-		}).map_err(db_err)?
-	{
-		let hash = header.hash();
-		debug!("DB Opened blockchain db, best {:?} ({})", hash, header.number());
-		(hash, *header.number())
-	} else {
-		(Default::default(), genesis_number)
+	let load_meta_block = |desc: &str, key: &[u8]| -> Result<_, client::error::Error> {
+		if let Some(Some(header)) = db.get(COLUMN_META, key).and_then(|id|
+			match id {
+				Some(id) => db.get(col_header, &id).map(|h| h.map(|b| Block::Header::decode(&mut &b[..]))),
+				None => Ok(None),
+			}).map_err(db_err)?
+		{
+			let hash = header.hash();
+			debug!("DB Opened blockchain db, {} {:?} ({})", desc, hash, header.number());
+			Ok(Some((hash, *header.number())))
+		} else {
+			Ok(None)
+		}
 	};
 
-	let genesis_hash = db.get(col_header, &number_to_db_key(genesis_number))
-		.map_err(db_err)?
-		.map(|raw| HashFor::<Block>::hash(&raw[..]))
-		.unwrap_or_default()
-		.into();
+	let genesis_hash: Block::Hash = match genesis_key {
+		Some(key) => db.get(col_header, key)
+			.map_err(db_err)?
+			.map(|raw| HashFor::<Block>::hash(&raw[..]))
+			.unwrap_or_default()
+			.into(),
+		None => Default::default(),
+	};
+
+	let (best_hash, best_number) = load_meta_block("best", &meta_keys::BEST_BLOCK[..])?
+		.unwrap_or_else(|| (Default::default(), genesis_number));
+	let (finalized_hash, finalized_number) = load_meta_block("final", &meta_keys::FINALIZED_BLOCK[..])?
+		.unwrap_or_else(|| (genesis_hash, genesis_number));
 
 	Ok(Meta {
 		best_hash,
 		best_number,
 		genesis_hash,
+		finalized_hash,
+		finalized_number,
 	})
 }
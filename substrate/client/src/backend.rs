@@ -73,9 +73,20 @@ pub trait Backend<Block: BlockT>: Send + Sync {
 	fn blockchain(&self) -> &Self::Blockchain;
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, block: BlockId<Block>) -> error::Result<Self::State>;
+	/// Mark a block of the best chain and all of its ancestors as final, optionally storing
+	/// the proof of its finality.
+	fn finalize_block(&self, block: BlockId<Block>, proof: Option<Vec<u8>>) -> error::Result<()>;
 	/// Attempts to revert the chain by `n` blocks. Returns the number of blocks that were
 	/// successfully reverted.
 	fn revert(&self, n: NumberFor<Block>) -> error::Result<NumberFor<Block>>;
+	/// Get a value from the node-local storage of offchain workers.
+	fn offchain_storage(&self, key: &[u8]) -> error::Result<Option<Vec<u8>>>;
+	/// Set a value in the node-local storage of offchain workers.
+	fn set_offchain_storage(&self, key: &[u8], value: &[u8]) -> error::Result<()>;
+	/// Get a value from the node-local auxiliary storage, where client components keep their data.
+	fn aux_storage(&self, key: &[u8]) -> error::Result<Option<Vec<u8>>>;
+	/// Set a value in the node-local auxiliary storage.
+	fn set_aux_storage(&self, key: &[u8], value: &[u8]) -> error::Result<()>;
 }
 
 /// Mark for all Backend implementations, that are making use of state data, stored locally.
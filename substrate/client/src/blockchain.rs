@@ -41,6 +41,8 @@ pub trait Backend<Block: BlockT>: HeaderBackend<Block> {
 	fn body(&self, id: BlockId<Block>) -> Result<Option<Vec<<Block as BlockT>::Extrinsic>>>;
 	/// Get block justification. Returns `None` if justification does not exist.
 	fn justification(&self, id: BlockId<Block>) -> Result<Option<Justification<Block::Hash>>>;
+	/// Get the proof of finality stored for a finalized block.
+	fn finality_proof(&self, id: BlockId<Block>) -> Result<Option<Vec<u8>>>;
 
 	/// Returns data cache reference, if it is enabled on this backend.
 	fn cache(&self) -> Option<&Cache<Block>>;
@@ -73,6 +75,10 @@ pub struct Info<Block: BlockT> {
 	pub best_number: <<Block as BlockT>::Header as HeaderT>::Number,
 	/// Genesis block hash.
 	pub genesis_hash: <<Block as BlockT>::Header as HeaderT>::Hash,
+	/// Last finalized block hash.
+	pub finalized_hash: <<Block as BlockT>::Header as HeaderT>::Hash,
+	/// Last finalized block number.
+	pub finalized_number: <<Block as BlockT>::Header as HeaderT>::Number,
 }
 
 /// Block status.
@@ -30,7 +30,7 @@ use state_machine::{Ext, OverlayedChanges, Backend as StateBackend, CodeExecutor
 use backend::{self, BlockImportOperation};
 use blockchain::{self, Info as ChainInfo, Backend as ChainBackend, HeaderBackend as ChainHeaderBackend};
 use call_executor::{CallExecutor, LocalCallExecutor};
-use executor::{RuntimeVersion, RuntimeInfo};
+use executor::{profiling, RuntimeVersion, RuntimeInfo};
 use notifications::{StorageNotifications, StorageEventStream};
 use {error, in_mem, block_builder, runtime_io, bft, genesis};
 
@@ -84,7 +84,7 @@ pub struct ClientInfo<Block: BlockT> {
 }
 
 /// Block import result.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ImportResult {
 	/// Added to the import queue.
 	Queued,
@@ -96,6 +96,8 @@ pub enum ImportResult {
 	KnownBad,
 	/// Block parent is not in the chain.
 	UnknownParent,
+	/// Block lacks the justification required to import it.
+	MissingJustification,
 }
 
 /// Block status.
@@ -156,6 +158,19 @@ impl<Block: BlockT> JustifiedHeader<Block> {
 	}
 }
 
+/// Import of blocks received from the network. Implemented by the consensus engine in use,
+/// which decides what makes a block valid.
+pub trait BlockImport<Block: BlockT>: Send + Sync {
+	/// Verify and import a block. Parent is supposed to be existing in the blockchain.
+	fn import(
+		&self,
+		origin: BlockOrigin,
+		header: Block::Header,
+		justification: Option<Justification<Block::Hash>>,
+		body: Option<Vec<Block::Extrinsic>>,
+	) -> error::Result<ImportResult>;
+}
+
 /// Create an instance of in-memory client.
 pub fn new_in_mem<E, Block, S>(
 	executor: E,
@@ -311,6 +326,39 @@ impl<B, E, Block> Client<B, E, Block> where
 		body: Option<Vec<<Block as BlockT>::Extrinsic>>,
 	) -> error::Result<ImportResult> {
 		let (header, justification, authorities) = header.into_inner();
+		let pre_header = header.clone();
+		self.import_checked_block(origin, header, pre_header, Some(justification), body, authorities)
+	}
+
+	/// Queue a block which carries no BFT justification for import. Its validity must have been
+	/// established by the caller, e.g. by checking a seal in the digest.
+	///
+	/// The block is executed with `pre_header`, the header as it was before sealing, and stored
+	/// with the sealed `header`.
+	pub fn import_sealed_block(
+		&self,
+		origin: BlockOrigin,
+		header: <Block as BlockT>::Header,
+		pre_header: <Block as BlockT>::Header,
+		body: Option<Vec<<Block as BlockT>::Extrinsic>>,
+	) -> error::Result<ImportResult> {
+		let parent_hash = header.parent_hash().clone();
+		let authorities = match self.backend.blockchain().status(BlockId::Hash(parent_hash))? {
+			blockchain::BlockStatus::InChain => self.authorities_at(&BlockId::Hash(parent_hash))?,
+			blockchain::BlockStatus::Unknown => return Ok(ImportResult::UnknownParent),
+		};
+		self.import_checked_block(origin, header, pre_header, None, body, authorities)
+	}
+
+	fn import_checked_block(
+		&self,
+		origin: BlockOrigin,
+		header: Block::Header,
+		pre_header: Block::Header,
+		justification: Option<bft::Justification<Block::Hash>>,
+		body: Option<Vec<Block::Extrinsic>>,
+		authorities: Vec<AuthorityId>,
+	) -> error::Result<ImportResult> {
 		let parent_hash = header.parent_hash().clone();
 		match self.backend.blockchain().status(BlockId::Hash(parent_hash))? {
 			blockchain::BlockStatus::InChain => {},
@@ -320,13 +368,30 @@ impl<B, E, Block> Client<B, E, Block> where
 		let _import_lock = self.import_lock.lock();
 		let height: u64 = header.number().as_();
 		*self.importing_block.write() = Some(hash);
-		let result = self.execute_and_import_block(origin, hash, header, justification, body, authorities);
+		let profile_runtime = profiling::is_enabled();
+		if profile_runtime {
+			// discard anything executed on this thread before the import.
+			profiling::take();
+		}
+		let result = self.execute_and_import_block(origin, hash, header, pre_header, justification, body, authorities);
 		*self.importing_block.write() = None;
 		telemetry!("block.import";
 			"height" => height,
 			"best" => ?hash,
 			"origin" => ?origin
 		);
+		if profile_runtime {
+			let profile = profiling::take();
+			if !profile.is_empty() {
+				info!(target: "runtime-profile", "Runtime profile of block #{} ({}):\n{}", height, hash, profile);
+				telemetry!("block.profile";
+					"height" => height,
+					"best" => ?hash,
+					"methods" => ?profile.methods,
+					"host_functions" => ?profile.host_functions
+				);
+			}
+		}
 		result
 	}
 
@@ -335,7 +400,8 @@ impl<B, E, Block> Client<B, E, Block> where
 		origin: BlockOrigin,
 		hash: Block::Hash,
 		header: Block::Header,
-		justification: bft::Justification<Block::Hash>,
+		pre_header: Block::Header,
+		justification: Option<bft::Justification<Block::Hash>>,
 		body: Option<Vec<Block::Extrinsic>>,
 		authorities: Vec<AuthorityId>,
 	) -> error::Result<ImportResult> {
@@ -344,6 +410,10 @@ impl<B, E, Block> Client<B, E, Block> where
 			blockchain::BlockStatus::InChain => return Ok(ImportResult::AlreadyInChain),
 			blockchain::BlockStatus::Unknown => {},
 		}
+		if !self.descends_from_finalized(&header)? {
+			debug!("Rejecting block {} which conflicts with the last finalized block", hash);
+			return Ok(ImportResult::KnownBad);
+		}
 
 		let mut transaction = self.backend.begin_operation(BlockId::Hash(parent_hash))?;
 		let (storage_update, storage_changes) = match transaction.state()? {
@@ -353,7 +423,7 @@ impl<B, E, Block> Client<B, E, Block> where
 					transaction_state,
 					&mut overlay,
 					"execute_block",
-					&<Block as BlockT>::new(header.clone(), body.clone().unwrap_or_default()).encode(),
+					&<Block as BlockT>::new(pre_header, body.clone().unwrap_or_default()).encode(),
 					match (origin, self.execution_strategy) {
 						(BlockOrigin::NetworkInitialSync, _) | (_, ExecutionStrategy::NativeWhenPossible) =>
 							ExecutionManager::NativeWhenPossible,
@@ -381,8 +451,11 @@ impl<B, E, Block> Client<B, E, Block> where
 
 		let is_new_best = header.number() == &(self.backend.blockchain().info()?.best_number + One::one());
 		trace!("Imported {}, (#{}), best={}, origin={:?}", hash, header.number(), is_new_best, origin);
-		let unchecked: bft::UncheckedJustification<_> = justification.uncheck().into();
-		transaction.set_block_data(header.clone(), body, Some(unchecked.into()), is_new_best)?;
+		let justification = justification.map(|j| {
+			let unchecked: bft::UncheckedJustification<_> = j.uncheck().into();
+			unchecked.into()
+		});
+		transaction.set_block_data(header.clone(), body, justification, is_new_best)?;
 		transaction.update_authorities(authorities);
 		if let Some(storage_update) = storage_update {
 			transaction.update_storage(storage_update)?;
@@ -415,6 +488,55 @@ impl<B, E, Block> Client<B, E, Block> where
 		Ok(self.backend.revert(n)?)
 	}
 
+	// Whether a block builds on the last finalized block. Blocks of other forks must never
+	// become best.
+	fn descends_from_finalized(&self, header: &Block::Header) -> error::Result<bool> {
+		let blockchain = self.backend.blockchain();
+		let finalized_number = blockchain.info()?.finalized_number;
+		if *header.number() <= finalized_number {
+			return Ok(false);
+		}
+
+		let mut parent_hash = *header.parent_hash();
+		let mut parent_number = *header.number() - One::one();
+		// the finalized block is part of the best chain, so only forks need walking back.
+		while blockchain.hash(parent_number)? != Some(parent_hash) {
+			if parent_number <= finalized_number {
+				return Ok(false);
+			}
+			let parent = blockchain.header(BlockId::Hash(parent_hash))?
+				.ok_or_else(|| error::ErrorKind::UnknownBlock(format!("{}", parent_hash)))?;
+			parent_hash = *parent.parent_hash();
+			parent_number = parent_number - One::one();
+		}
+		Ok(true)
+	}
+
+	/// Finalize a block of the best chain together with all of its ancestors, storing the
+	/// given proof of finality for it. Blocks at or below the last finalized one are ignored.
+	pub fn finalize_block(&self, id: BlockId<Block>, proof: Option<Vec<u8>>) -> error::Result<()> {
+		let _import_lock = self.import_lock.lock();
+		let header = self.header(&id)?.ok_or_else(|| error::ErrorKind::UnknownBlock(format!("{}", id)))?;
+		let hash = header.hash();
+		let number = *header.number();
+
+		let info = self.backend.blockchain().info()?;
+		if number <= info.finalized_number {
+			return Ok(());
+		}
+		if self.backend.blockchain().hash(number)? != Some(hash) {
+			return Err(error::ErrorKind::NotInBestChain(format!("{}", hash)).into());
+		}
+
+		trace!("Finalizing {}, (#{})", hash, number);
+		self.backend.finalize_block(BlockId::Hash(hash), proof)?;
+		telemetry!("block.finalize";
+			"height" => ?number,
+			"best" => ?hash
+		);
+		Ok(())
+	}
+
 	/// Get blockchain info.
 	pub fn info(&self) -> error::Result<ClientInfo<Block>> {
 		let info = self.backend.blockchain().info().map_err(|e| error::Error::from_blockchain(Box::new(e)))?;
@@ -475,6 +597,11 @@ impl<B, E, Block> Client<B, E, Block> where
 		self.backend.blockchain().justification(*id)
 	}
 
+	/// Get the proof of finality stored for a block by id.
+	pub fn finality_proof(&self, id: &BlockId<Block>) -> error::Result<Option<Vec<u8>>> {
+		self.backend.blockchain().finality_proof(*id)
+	}
+
 	/// Get full block by id.
 	pub fn block(&self, id: &BlockId<Block>) -> error::Result<Option<SignedBlock<Block::Header, Block::Extrinsic, Block::Hash>>> {
 		Ok(match (self.header(id)?, self.body(id)?, self.justification(id)?) {
@@ -514,6 +641,30 @@ impl<B, E, Block> bft::BlockImport<Block> for Client<B, E, Block>
 	}
 }
 
+impl<B, E, Block> BlockImport<Block> for Client<B, E, Block>
+	where
+		B: backend::Backend<Block> + Send + Sync,
+		E: CallExecutor<Block> + Send + Sync,
+		Block: BlockT,
+{
+	/// Import a block finalised by BFT: a justification is required.
+	fn import(
+		&self,
+		origin: BlockOrigin,
+		header: Block::Header,
+		justification: Option<Justification<Block::Hash>>,
+		body: Option<Vec<Block::Extrinsic>>,
+	) -> error::Result<ImportResult> {
+		let justification = match justification {
+			Some(justification) => justification,
+			None => return Ok(ImportResult::MissingJustification),
+		};
+		// TODO: defer justification check.
+		let justified_header = self.check_justification(header, justification.into())?;
+		self.import_block(origin, justified_header, body)
+	}
+}
+
 impl<B, E, Block> bft::Authorities<Block> for Client<B, E, Block>
 	where
 		B: backend::Backend<Block>,
@@ -678,4 +829,44 @@ mod tests {
 		assert!(client.state_at(&BlockId::Number(1)).unwrap() != client.state_at(&BlockId::Number(0)).unwrap());
 		assert_eq!(client.body(&BlockId::Number(1)).unwrap().unwrap().len(), 1)
 	}
+
+	#[test]
+	fn finalizing_block_finalizes_ancestors_only_forwards() {
+		let client = test_client::new();
+		for _ in 0..3 {
+			let builder = client.new_block().unwrap();
+			client.justify_and_import(BlockOrigin::Own, builder.bake().unwrap()).unwrap();
+		}
+
+		let genesis_hash = client.block_hash(0).unwrap().unwrap();
+		assert_eq!(client.info().unwrap().chain.finalized_hash, genesis_hash);
+
+		client.finalize_block(BlockId::Number(2), None).unwrap();
+		assert_eq!(client.info().unwrap().chain.finalized_number, 2);
+
+		client.finalize_block(BlockId::Number(1), None).unwrap();
+		assert_eq!(client.info().unwrap().chain.finalized_number, 2);
+		assert_eq!(client.info().unwrap().chain.finalized_hash, client.block_hash(2).unwrap().unwrap());
+	}
+
+	#[test]
+	fn block_conflicting_with_finalized_block_is_rejected() {
+		let client = test_client::new();
+		client.justify_and_import(BlockOrigin::Own, client.new_block().unwrap().bake().unwrap()).unwrap();
+		client.finalize_block(BlockId::Number(1), Some(vec![42])).unwrap();
+		assert_eq!(client.finality_proof(&BlockId::Number(1)).unwrap(), Some(vec![42]));
+
+		let mut builder = client.new_block_at(&BlockId::Number(0)).unwrap();
+		builder.push_transfer(Transfer {
+			from: Keyring::Alice.to_raw_public().into(),
+			to: Keyring::Ferdie.to_raw_public().into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let fork = builder.bake().unwrap();
+
+		let result = client.import_sealed_block(BlockOrigin::Own, fork.header.clone(), fork.header, Some(fork.extrinsics));
+		assert_eq!(result.unwrap(), ImportResult::KnownBad);
+		assert_eq!(client.info().unwrap().chain.best_hash, client.block_hash(1).unwrap().unwrap());
+	}
 }
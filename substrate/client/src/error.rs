@@ -95,6 +95,12 @@ error_chain! {
 			display("bad justification for header: {}", &*h),
 		}
 
+		/// Block is not part of the best chain.
+		NotInBestChain(h: String) {
+			description("block not in best chain"),
+			display("Block {} is not part of the best chain", &*h),
+		}
+
 		/// Not available on light client.
 		NotAvailableOnLightClient {
 			description("not available on light client"),
@@ -153,6 +153,57 @@ mod tests {
 		).unwrap();
 	}
 
+	#[cfg(feature = "bench")]
+	#[bench]
+	fn bench_execute_block_with_wasm(b: &mut ::test::Bencher) {
+		let mut storage = GenesisConfig::new_simple(
+			vec![Keyring::One.to_raw_public().into(), Keyring::Two.to_raw_public().into()], 1000
+		).genesis_map();
+		let block = construct_genesis_block::<Block>(&storage);
+		let genesis_hash = block.header.hash();
+		storage.extend(additional_storage_with_genesis(&block).into_iter());
+
+		let backend = InMemory::from(storage);
+		let (b1data, _b1hash) = block1(genesis_hash, &backend);
+		let executor = executor();
+
+		b.iter(|| {
+			let mut overlay = OverlayedChanges::default();
+			execute(
+				&backend,
+				&mut overlay,
+				&executor,
+				"execute_block",
+				&b1data,
+				ExecutionStrategy::AlwaysWasm,
+			).unwrap()
+		});
+	}
+
+	#[cfg(feature = "bench")]
+	#[bench]
+	fn bench_execute_block_with_uncached_wasm(b: &mut ::test::Bencher) {
+		let mut storage = GenesisConfig::new_simple(
+			vec![Keyring::One.to_raw_public().into(), Keyring::Two.to_raw_public().into()], 1000
+		).genesis_map();
+		let block = construct_genesis_block::<Block>(&storage);
+		let genesis_hash = block.header.hash();
+		storage.extend(additional_storage_with_genesis(&block).into_iter());
+
+		let backend = InMemory::from(storage);
+		let (b1data, _b1hash) = block1(genesis_hash, &backend);
+		let code = include_bytes!("../../test-runtime/wasm/target/wasm32-unknown-unknown/release/substrate_test_runtime.compact.wasm");
+		let executor = ::executor::WasmExecutor::new(8);
+
+		// the baseline without the module cache: the runtime is parsed again for every block.
+		b.iter(|| {
+			let mut overlay = OverlayedChanges::default();
+			let mut ext = ::state_machine::Ext::new(&mut overlay, &backend);
+			let module = ::executor::PreparedModule::new(&code[..], false).unwrap();
+			executor.call_in_prepared_module(&mut ext, &module, "execute_block", &b1data).unwrap()
+		});
+	}
+
 	#[test]
 	fn construct_genesis_should_work_with_wasm() {
 		let mut storage = GenesisConfig::new_simple(
@@ -24,7 +24,7 @@ use backend;
 use light;
 use primitives::AuthorityId;
 use runtime_primitives::generic::BlockId;
-use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, Zero, NumberFor, As};
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, Zero, One, NumberFor, As};
 use runtime_primitives::bft::Justification;
 use blockchain::{self, BlockStatus};
 use state_machine::backend::{Backend as StateBackend, InMemory};
@@ -86,6 +86,9 @@ struct BlockchainStorage<Block: BlockT> {
 	best_hash: Block::Hash,
 	best_number: <<Block as BlockT>::Header as HeaderT>::Number,
 	genesis_hash: Block::Hash,
+	finalized_hash: Block::Hash,
+	finalized_number: <<Block as BlockT>::Header as HeaderT>::Number,
+	finality_proofs: HashMap<Block::Hash, Vec<u8>>,
 }
 
 /// In-memory blockchain. Supports concurrent reads.
@@ -130,6 +133,9 @@ impl<Block: BlockT> Blockchain<Block> {
 				best_hash: Default::default(),
 				best_number: Zero::zero(),
 				genesis_hash: Default::default(),
+				finalized_hash: Default::default(),
+				finalized_number: Zero::zero(),
+				finality_proofs: HashMap::new(),
 			}));
 		Blockchain {
 			storage: storage.clone(),
@@ -152,14 +158,47 @@ impl<Block: BlockT> Blockchain<Block> {
 		let number = header.number().clone();
 		let mut storage = self.storage.write();
 		storage.blocks.insert(hash.clone(), StoredBlock::new(header, body, justification));
-		storage.hashes.insert(number, hash.clone());
 		if is_new_best {
+			// the new best block and its ancestors replace the blocks of a retracted fork.
+			let best_number = storage.best_number;
+			let mut canonical = (number, hash.clone());
+			while storage.hashes.get(&canonical.0) != Some(&canonical.1) {
+				storage.hashes.insert(canonical.0, canonical.1);
+				let parent_hash = match storage.blocks.get(&canonical.1) {
+					Some(block) if !canonical.0.is_zero() => *block.header().parent_hash(),
+					_ => break,
+				};
+				canonical = (canonical.0 - One::one(), parent_hash);
+			}
+			let mut retracted = number + One::one();
+			while retracted <= best_number {
+				storage.hashes.remove(&retracted);
+				retracted = retracted + One::one();
+			}
+
 			storage.best_hash = hash.clone();
 			storage.best_number = number.clone();
 		}
 		if number == Zero::zero() {
 			storage.genesis_hash = hash;
+			storage.finalized_hash = hash;
+		}
+	}
+
+	/// Mark a block as finalized, storing the proof of its finality if one is given.
+	pub fn finalize(&self, id: BlockId<Block>, proof: Option<Vec<u8>>) -> error::Result<()> {
+		let hash = self.id(id).ok_or_else(|| error::ErrorKind::UnknownBlock(format!("{}", id)))?;
+		let mut storage = self.storage.write();
+		let number = *storage.blocks.get(&hash)
+			.ok_or_else(|| error::ErrorKind::UnknownBlock(format!("{}", id)))?
+			.header().number();
+		if let Some(proof) = proof {
+			storage.finality_proofs.insert(hash, proof);
 		}
+
+		storage.finalized_hash = hash;
+		storage.finalized_number = number;
+		Ok(())
 	}
 
 	/// Compare this blockchain with another in-mem blockchain
@@ -175,6 +214,7 @@ impl<Block: BlockT> Blockchain<Block> {
 			&& this.best_hash == other.best_hash
 			&& this.best_number == other.best_number
 			&& this.genesis_hash == other.genesis_hash
+			&& this.finalized_hash == other.finalized_hash
 	}
 }
 
@@ -191,6 +231,8 @@ impl<Block: BlockT> blockchain::HeaderBackend<Block> for Blockchain<Block> {
 			best_hash: storage.best_hash,
 			best_number: storage.best_number,
 			genesis_hash: storage.genesis_hash,
+			finalized_hash: storage.finalized_hash,
+			finalized_number: storage.finalized_number,
 		})
 	}
 
@@ -221,6 +263,10 @@ impl<Block: BlockT> blockchain::Backend<Block> for Blockchain<Block> {
 		))
 	}
 
+	fn finality_proof(&self, id: BlockId<Block>) -> error::Result<Option<Vec<u8>>> {
+		Ok(self.id(id).and_then(|hash| self.storage.read().finality_proofs.get(&hash).cloned()))
+	}
+
 	fn cache(&self) -> Option<&blockchain::Cache<Block>> {
 		Some(&self.cache)
 	}
@@ -298,6 +344,8 @@ pub struct Backend<Block> where
 {
 	states: RwLock<HashMap<Block::Hash, InMemory>>,
 	blockchain: Blockchain<Block>,
+	offchain_storage: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+	aux_storage: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
 }
 
 impl<Block> Backend<Block> where
@@ -308,6 +356,8 @@ impl<Block> Backend<Block> where
 		Backend {
 			states: RwLock::new(HashMap::new()),
 			blockchain: Blockchain::new(),
+			offchain_storage: RwLock::new(HashMap::new()),
+			aux_storage: RwLock::new(HashMap::new()),
 		}
 	}
 }
@@ -361,9 +411,31 @@ impl<Block> backend::Backend<Block> for Backend<Block> where
 		}
 	}
 
+	fn finalize_block(&self, block: BlockId<Block>, proof: Option<Vec<u8>>) -> error::Result<()> {
+		self.blockchain.finalize(block, proof)
+	}
+
 	fn revert(&self, _n: NumberFor<Block>) -> error::Result<NumberFor<Block>> {
 		Ok(As::sa(0))
 	}
+
+	fn offchain_storage(&self, key: &[u8]) -> error::Result<Option<Vec<u8>>> {
+		Ok(self.offchain_storage.read().get(key).cloned())
+	}
+
+	fn set_offchain_storage(&self, key: &[u8], value: &[u8]) -> error::Result<()> {
+		self.offchain_storage.write().insert(key.to_vec(), value.to_vec());
+		Ok(())
+	}
+
+	fn aux_storage(&self, key: &[u8]) -> error::Result<Option<Vec<u8>>> {
+		Ok(self.aux_storage.read().get(key).cloned())
+	}
+
+	fn set_aux_storage(&self, key: &[u8], value: &[u8]) -> error::Result<()> {
+		self.aux_storage.write().insert(key.to_vec(), value.to_vec());
+		Ok(())
+	}
 }
 
 impl<Block: BlockT> backend::LocalBackend<Block> for Backend<Block> {}
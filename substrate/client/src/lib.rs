@@ -18,6 +18,7 @@
 
 #![warn(missing_docs)]
 #![recursion_limit="128"]
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
 
 extern crate substrate_bft as bft;
 extern crate substrate_codec as codec;
@@ -41,6 +42,7 @@ extern crate triehash;
 #[macro_use] extern crate log;
 #[cfg_attr(test, macro_use)] extern crate substrate_executor as executor;
 #[cfg(test)] #[macro_use] extern crate hex_literal;
+#[cfg(all(test, feature = "bench"))] extern crate test;
 
 pub mod error;
 pub mod blockchain;
@@ -58,7 +60,7 @@ pub use call_executor::{CallResult, CallExecutor, LocalCallExecutor};
 pub use client::{
 	new_in_mem,
 	BlockBody, BlockStatus, BlockOrigin, BlockchainEventStream, BlockchainEvents,
-	Client, ClientInfo, ChainHead,
+	BlockImport, BlockImportNotification, Client, ClientInfo, ChainHead,
 	ImportResult, JustifiedHeader,
 };
 pub use notifications::{StorageEventStream, StorageChangeSet};
@@ -101,9 +101,29 @@ impl<S, F, Block> ClientBackend<Block> for Backend<S, F> where
 		})
 	}
 
+	fn finalize_block(&self, _block: BlockId<Block>, _proof: Option<Vec<u8>>) -> ClientResult<()> {
+		Err(ClientErrorKind::NotAvailableOnLightClient.into())
+	}
+
 	fn revert(&self, _n: NumberFor<Block>) -> ClientResult<NumberFor<Block>> {
 		unimplemented!()
 	}
+
+	fn offchain_storage(&self, _key: &[u8]) -> ClientResult<Option<Vec<u8>>> {
+		Err(ClientErrorKind::NotAvailableOnLightClient.into())
+	}
+
+	fn set_offchain_storage(&self, _key: &[u8], _value: &[u8]) -> ClientResult<()> {
+		Err(ClientErrorKind::NotAvailableOnLightClient.into())
+	}
+
+	fn aux_storage(&self, _key: &[u8]) -> ClientResult<Option<Vec<u8>>> {
+		Err(ClientErrorKind::NotAvailableOnLightClient.into())
+	}
+
+	fn set_aux_storage(&self, _key: &[u8], _value: &[u8]) -> ClientResult<()> {
+		Err(ClientErrorKind::NotAvailableOnLightClient.into())
+	}
 }
 
 impl<S, F, Block> RemoteBackend<Block> for Backend<S, F> where Block: BlockT, S: BlockchainStorage<Block>, F: Fetcher<Block> {}
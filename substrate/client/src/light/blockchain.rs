@@ -102,6 +102,10 @@ impl<S, F, Block> BlockchainBackend<Block> for Blockchain<S, F> where Block: Blo
 		Ok(None)
 	}
 
+	fn finality_proof(&self, _id: BlockId<Block>) -> ClientResult<Option<Vec<u8>>> {
+		Ok(None)
+	}
+
 	fn cache(&self) -> Option<&BlockchainCache<Block>> {
 		self.storage.cache()
 	}
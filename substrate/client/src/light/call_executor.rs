@@ -46,6 +46,15 @@ impl<B, F> RemoteCallExecutor<B, F> {
 	}
 }
 
+impl<B, F> Clone for RemoteCallExecutor<B, F> {
+	fn clone(&self) -> Self {
+		RemoteCallExecutor {
+			blockchain: self.blockchain.clone(),
+			fetcher: self.fetcher.clone(),
+		}
+	}
+}
+
 impl<B, F, Block> CallExecutor<Block> for RemoteCallExecutor<B, F>
 	where
 		Block: BlockT,
@@ -70,6 +70,18 @@ error_chain! {
 			display("Runtime error"),
 		}
 
+		/// Execution used up its fuel budget.
+		OutOfFuel(limit: u64) {
+			description("out of fuel"),
+			display("Execution exceeded its fuel limit of {}", limit),
+		}
+
+		/// Code could not be instrumented for fuel metering.
+		Instrumentation {
+			description("instrumentation failure"),
+			display("Unable to instrument code for fuel metering"),
+		}
+
 		/// Runtime failed.
 		InvalidMemoryReference {
 			description("invalid memory reference"),
@@ -27,6 +27,7 @@
 
 #![warn(missing_docs)]
 #![recursion_limit="128"]
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
 
 extern crate substrate_codec as codec;
 extern crate substrate_runtime_io as runtime_io;
@@ -38,10 +39,13 @@ extern crate ed25519;
 
 extern crate serde;
 extern crate wasmi;
+extern crate parity_wasm;
+extern crate pwasm_utils;
 extern crate byteorder;
 extern crate triehash;
 extern crate parking_lot;
 extern crate twox_hash;
+extern crate linked_hash_map;
 #[macro_use] extern crate log;
 
 #[macro_use]
@@ -50,12 +54,21 @@ extern crate lazy_static;
 #[macro_use]
 extern crate error_chain;
 
+#[cfg(all(test, feature = "bench"))]
+extern crate test;
+
 #[cfg(test)]
 extern crate assert_matches;
 
 #[cfg(test)]
 extern crate wabt;
 
+#[cfg(test)]
+extern crate schnorrkel;
+
+#[cfg(test)]
+extern crate secp256k1;
+
 #[cfg(test)]
 #[macro_use]
 extern crate hex_literal;
@@ -68,7 +81,8 @@ mod native_executor;
 mod sandbox;
 
 pub mod error;
-pub use wasm_executor::WasmExecutor;
+pub mod profiling;
+pub use wasm_executor::{WasmExecutor, PreparedModule, prepared_module, with_restricted_fuel};
 pub use native_executor::{with_native_environment, NativeExecutor, NativeExecutionDispatch};
 pub use state_machine::Externalities;
 pub use runtime_version::RuntimeVersion;
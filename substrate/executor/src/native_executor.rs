@@ -16,69 +16,54 @@
 
 use error::{Error, ErrorKind, Result};
 use state_machine::{CodeExecutor, Externalities};
-use wasm_executor::WasmExecutor;
-use wasmi::Module as WasmModule;
+use wasm_executor::{WasmExecutor, gen_cache_key};
 use runtime_version::RuntimeVersion;
 use std::collections::HashMap;
 use codec::Decode;
-use twox_hash::XxHash;
-use std::hash::Hasher;
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::Mutex;
 use RuntimeInfo;
 
 // For the internal Runtime Cache:
 // Is it compatible enough to run this natively or do we need to fall back on the WasmModule
 
+#[derive(Clone)]
 enum Compatibility {
-	InvalidVersion(WasmModule),
-	IsCompatible(RuntimeVersion, WasmModule),
-	NotCompatible(RuntimeVersion, WasmModule)
+	InvalidVersion,
+	IsCompatible(RuntimeVersion),
+	NotCompatible(RuntimeVersion)
 }
 
-unsafe impl Send for Compatibility {}
-
 type CacheType = HashMap<u64, Compatibility>;
 
 lazy_static! {
 	static ref RUNTIMES_CACHE: Mutex<CacheType> = Mutex::new(HashMap::new());
 }
 
-// helper function to generate low-over-head caching_keys
-// it is asserted that part of the audit process that any potential on-chain code change
-// will have done is to ensure that the two-x hash is different to that of any other
-// :code value from the same chain
-fn gen_cache_key(code: &[u8]) -> u64 {
-	let mut h = XxHash::with_seed(0);
-	h.write(code);
-	h.finish()
-}
-
 /// fetch a runtime version from the cache or if there is no cached version yet, create
 /// the runtime version entry for `code`, determines whether `Compatibility::IsCompatible`
 /// can be used by by comparing returned RuntimeVersion to `ref_version`
-fn fetch_cached_runtime_version<'a, E: Externalities>(
+fn fetch_cached_runtime_version<E: Externalities>(
 	wasm_executor: &WasmExecutor,
-	cache: &'a mut MutexGuard<CacheType>,
 	ext: &mut E,
 	code: &[u8],
 	ref_version: RuntimeVersion
-) -> &'a Compatibility {
-	cache.entry(gen_cache_key(code))
+) -> Compatibility {
+	RUNTIMES_CACHE.lock().entry(gen_cache_key(code))
 		.or_insert_with(|| {
-			let module = WasmModule::from_buffer(code).expect("all modules compiled with rustc are valid wasm code; qed");
-			let version = wasm_executor.call_in_wasm_module(ext, &module, "version", &[]).ok()
+			let version = wasm_executor.call(ext, code, "version", &[]).ok()
 				.and_then(|v| RuntimeVersion::decode(&mut v.as_slice()));
 
 			if let Some(v) = version {
 				if ref_version.can_call_with(&v) {
-					Compatibility::IsCompatible(v, module)
+					Compatibility::IsCompatible(v)
 				} else {
-					Compatibility::NotCompatible(v, module)
+					Compatibility::NotCompatible(v)
 				}
 			} else {
-				Compatibility::InvalidVersion(module)
+				Compatibility::InvalidVersion
 			}
-	})
+		})
+		.clone()
 }
 
 fn safe_call<F, U>(f: F) -> Result<U>
@@ -136,6 +121,12 @@ impl<D: NativeExecutionDispatch> NativeExecutor<D> {
 			fallback: WasmExecutor::new(max_heap_pages),
 		}
 	}
+
+	/// Limit the fuel available to each call of the wasm fallback. Native calls are not metered.
+	pub fn with_fuel_limit(mut self, fuel_limit: Option<u64>) -> Self {
+		self.fallback.fuel_limit = fuel_limit;
+		self
+	}
 }
 
 impl<D: NativeExecutionDispatch> Clone for NativeExecutor<D> {
@@ -155,10 +146,9 @@ impl<D: NativeExecutionDispatch> RuntimeInfo for NativeExecutor<D> {
 		ext: &mut E,
 		code: &[u8],
 	) -> Option<RuntimeVersion> {
-		let mut c = RUNTIMES_CACHE.lock();
-		match fetch_cached_runtime_version(&self.fallback, &mut c, ext, code, D::VERSION) {
-			Compatibility::IsCompatible(v, _) | Compatibility::NotCompatible(v, _) => Some(v.clone()),
-			Compatibility::InvalidVersion(_m) => None
+		match fetch_cached_runtime_version(&self.fallback, ext, code, D::VERSION) {
+			Compatibility::IsCompatible(v) | Compatibility::NotCompatible(v) => Some(v),
+			Compatibility::InvalidVersion => None
 		}
 	}
 }
@@ -174,11 +164,9 @@ impl<D: NativeExecutionDispatch> CodeExecutor for NativeExecutor<D> {
 		data: &[u8],
 		use_native: bool,
 	) -> (Result<Vec<u8>>, bool) {
-		let mut c = RUNTIMES_CACHE.lock();
-		match (use_native, fetch_cached_runtime_version(&self.fallback, &mut c, ext, code, D::VERSION)) {
-			(_, Compatibility::NotCompatible(_, m)) | (_, Compatibility::InvalidVersion(m)) | (false, Compatibility::IsCompatible(_, m)) =>
-				(self.fallback.call_in_wasm_module(ext, m, method, data), false),
-			_ => (D::dispatch(ext, method, data), true),
+		match (use_native, fetch_cached_runtime_version(&self.fallback, ext, code, D::VERSION)) {
+			(true, Compatibility::IsCompatible(_)) => (D::dispatch(ext, method, data), true),
+			_ => (self.fallback.call(ext, code, method, data), false),
 		}
 	}
 }
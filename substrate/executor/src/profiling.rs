@@ -0,0 +1,159 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Optional profiling of Wasm runtime execution.
+//!
+//! When enabled, the number of calls, the bytes processed and the time spent are recorded
+//! for every host function and every exported runtime method invoked through the
+//! `WasmExecutor`. Statistics are gathered per thread, so the profile of a single block
+//! import can be taken on the importing thread once the block has been executed. Times
+//! are inclusive: a method's time contains the time of the host functions it called.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = ATOMIC_BOOL_INIT;
+
+thread_local! {
+	static PROFILE: RefCell<RuntimeProfile> = RefCell::new(RuntimeProfile::default());
+	// bytes processed by the innermost call being timed.
+	static CURRENT_BYTES: Cell<u64> = Cell::new(0);
+}
+
+/// Enable or disable profiling for all threads.
+pub fn set_enabled(enabled: bool) {
+	ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether profiling is enabled.
+pub fn is_enabled() -> bool {
+	ENABLED.load(Ordering::Relaxed)
+}
+
+/// Take the profile gathered on the current thread so far, leaving an empty one in its place.
+pub fn take() -> RuntimeProfile {
+	PROFILE.with(|p| ::std::mem::replace(&mut *p.borrow_mut(), RuntimeProfile::default()))
+}
+
+/// Attribute the given number of bytes to the call currently being timed, if any.
+pub fn note_bytes(bytes: usize) {
+	if is_enabled() {
+		CURRENT_BYTES.with(|b| b.set(b.get() + bytes as u64));
+	}
+}
+
+/// Statistics of a single host function or runtime method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallStats {
+	/// Number of calls.
+	pub calls: u64,
+	/// Total bytes processed.
+	pub bytes: u64,
+	/// Total time spent.
+	pub time: Duration,
+}
+
+/// Statistics of runtime execution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeProfile {
+	/// Statistics per host function.
+	pub host_functions: BTreeMap<&'static str, CallStats>,
+	/// Statistics per exported runtime method.
+	pub methods: BTreeMap<String, CallStats>,
+}
+
+impl RuntimeProfile {
+	/// Whether nothing was recorded.
+	pub fn is_empty(&self) -> bool {
+		self.host_functions.is_empty() && self.methods.is_empty()
+	}
+}
+
+fn fmt_section<K: fmt::Display>(f: &mut fmt::Formatter, title: &str, stats: Vec<(K, &CallStats)>) -> fmt::Result {
+	writeln!(f, "{}:", title)?;
+	for (name, stats) in stats {
+		let micros = stats.time.as_secs() * 1_000_000 + stats.time.subsec_nanos() as u64 / 1_000;
+		writeln!(f, "  {:<32} {:>8} calls {:>12} bytes {:>12} us", name, stats.calls, stats.bytes, micros)?;
+	}
+	Ok(())
+}
+
+impl fmt::Display for RuntimeProfile {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// most expensive entries first.
+		let mut methods: Vec<_> = self.methods.iter().collect();
+		methods.sort_by(|a, b| b.1.time.cmp(&a.1.time));
+		let mut host_functions: Vec<_> = self.host_functions.iter().collect();
+		host_functions.sort_by(|a, b| b.1.time.cmp(&a.1.time));
+
+		fmt_section(f, "Runtime methods", methods)?;
+		fmt_section(f, "Host functions", host_functions)
+	}
+}
+
+enum Subject {
+	HostFunction(&'static str),
+	Method(String),
+}
+
+/// Times a call until dropped. Does nothing if profiling is disabled.
+pub struct Timer {
+	active: Option<(Subject, Instant, u64)>,
+}
+
+impl Timer {
+	/// Start timing a host function.
+	pub fn host_function(name: &'static str) -> Self {
+		Self::start(|| Subject::HostFunction(name))
+	}
+
+	/// Start timing an exported runtime method.
+	pub fn method(name: &str) -> Self {
+		Self::start(|| Subject::Method(name.to_owned()))
+	}
+
+	fn start<F: FnOnce() -> Subject>(subject: F) -> Self {
+		if !is_enabled() {
+			return Timer { active: None };
+		}
+
+		// bytes of an enclosing call are restored once this one is done.
+		let outer_bytes = CURRENT_BYTES.with(|b| b.replace(0));
+		Timer { active: Some((subject(), Instant::now(), outer_bytes)) }
+	}
+}
+
+impl Drop for Timer {
+	fn drop(&mut self) {
+		if let Some((subject, started, outer_bytes)) = self.active.take() {
+			let time = started.elapsed();
+			let bytes = CURRENT_BYTES.with(|b| b.replace(outer_bytes));
+			PROFILE.with(|p| {
+				let mut profile = p.borrow_mut();
+				let stats = match subject {
+					Subject::HostFunction(name) => profile.host_functions.entry(name).or_insert_with(Default::default),
+					Subject::Method(name) => profile.methods.entry(name).or_insert_with(Default::default),
+				};
+				stats.calls += 1;
+				stats.bytes += bytes;
+				stats.time += time;
+			});
+		}
+	}
+}
@@ -16,20 +16,30 @@
 
 //! Rust implementation of Substrate contracts.
 
-use std::cmp::Ordering;
+use std::cell::Cell;
+use std::cmp::{self, Ordering};
 use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
 use wasmi::{
-	Module, ModuleInstance, MemoryInstance, MemoryRef, TableRef, ImportsBuilder
+	Module, ModuleInstance, ModuleRef, MemoryInstance, MemoryRef, TableRef, ImportsBuilder
 };
 use wasmi::RuntimeValue::{I32, I64};
 use wasmi::memory_units::{Pages, Bytes};
+use parity_wasm::elements;
+use pwasm_utils::{self, rules};
 use state_machine::Externalities;
 use error::{Error, ErrorKind, Result};
 use wasm_utils::UserError;
-use primitives::{blake2_256, twox_128, twox_256};
+use profiling;
+use runtime_io::ParallelVerifier;
+use primitives::{blake2_256, twox_128, twox_256, keccak_256};
 use primitives::hexdisplay::HexDisplay;
 use primitives::sandbox as sandbox_primitives;
 use triehash::ordered_trie_root;
+use twox_hash::XxHash;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
 use sandbox;
 
 struct Heap {
@@ -78,10 +88,13 @@ struct FunctionExecutor<'e, E: Externalities + 'e> {
 	table: Option<TableRef>,
 	ext: &'e mut E,
 	hash_lookup: HashMap<Vec<u8>, Vec<u8>>,
+	fuel: Option<u64>,
+	out_of_fuel: bool,
+	signature_queue: Option<ParallelVerifier>,
 }
 
 impl<'e, E: Externalities> FunctionExecutor<'e, E> {
-	fn new(m: MemoryRef, heap_pages: usize, t: Option<TableRef>, e: &'e mut E) -> Result<Self> {
+	fn new(m: MemoryRef, heap_pages: usize, t: Option<TableRef>, e: &'e mut E, fuel: Option<u64>) -> Result<Self> {
 		Ok(FunctionExecutor {
 			sandbox_store: sandbox::Store::new(),
 			heap: Heap::new(&m, heap_pages)?,
@@ -89,8 +102,26 @@ impl<'e, E: Externalities> FunctionExecutor<'e, E> {
 			table: t,
 			ext: e,
 			hash_lookup: HashMap::new(),
+			fuel,
+			out_of_fuel: false,
+			signature_queue: None,
 		})
 	}
+
+	// Charge fuel for executing instructions, returning `false` once the budget is exhausted.
+	fn charge_fuel(&mut self, amount: u32) -> bool {
+		match self.fuel {
+			Some(fuel) if fuel < amount as u64 => {
+				self.out_of_fuel = true;
+				false
+			}
+			Some(ref mut fuel) => {
+				*fuel -= amount as u64;
+				true
+			}
+			None => true,
+		}
+	}
 }
 
 impl<'e, E: Externalities> sandbox::SandboxCapabilities for FunctionExecutor<'e, E> {
@@ -139,6 +170,12 @@ impl ReadPrimitive<u32> for MemoryInstance {
 }
 
 impl_function_executor!(this: FunctionExecutor<'e, E>,
+	gas(amount: u32) => {
+		if !this.charge_fuel(amount) {
+			Err(UserError("Wasm execution ran out of fuel"))?;
+		}
+		Ok(())
+	},
 	ext_print_utf8(utf8_data: *const u8, utf8_len: u32) => {
 		if let Ok(utf8) = this.memory.get(utf8_data, utf8_len as usize) {
 			if let Ok(message) = String::from_utf8(utf8) {
@@ -158,6 +195,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32 => {
+		profiling::note_bytes(n);
 		let sl1 = this.memory.get(s1, n as usize).map_err(|_| UserError("Invalid attempt to read from memory in first arg of ext_memcmp"))?;
 		let sl2 = this.memory.get(s2, n as usize).map_err(|_| UserError("Invalid attempt to read from memory in second arg of ext_memcmp"))?;
 		Ok(match sl1.cmp(&sl2) {
@@ -167,18 +205,21 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		})
 	},
 	ext_memcpy(dest: *mut u8, src: *const u8, count: usize) -> *mut u8 => {
+		profiling::note_bytes(count);
 		this.memory.copy_nonoverlapping(src as usize, dest as usize, count as usize)
 			.map_err(|_| UserError("Invalid attempt to copy_nonoverlapping in ext_memcpy"))?;
 		debug_trace!(target: "runtime-io", "memcpy {} from {}, {} bytes", dest, src, count);
 		Ok(dest)
 	},
 	ext_memmove(dest: *mut u8, src: *const u8, count: usize) -> *mut u8 => {
+		profiling::note_bytes(count);
 		this.memory.copy(src as usize, dest as usize, count as usize)
 			.map_err(|_| UserError("Invalid attempt to copy in ext_memmove"))?;
 		debug_trace!(target: "runtime-io", "memmove {} from {}, {} bytes", dest, src, count);
 		Ok(dest)
 	},
 	ext_memset(dest: *mut u8, val: u32, count: usize) -> *mut u8 => {
+		profiling::note_bytes(count);
 		debug_trace!(target: "runtime-io", "memset {} with {}, {} bytes", dest, val, count);
 		this.memory.clear(dest as usize, val as u8, count as usize)
 			.map_err(|_| UserError("Invalid attempt to clear in ext_memset"))?;
@@ -195,6 +236,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_set_storage(key_data: *const u8, key_len: u32, value_data: *const u8, value_len: u32) => {
+		profiling::note_bytes(key_len as usize + value_len as usize);
 		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_set_storage"))?;
 		let value = this.memory.get(value_data, value_len as usize).map_err(|_| UserError("Invalid attempt to determine value in ext_set_storage"))?;
 		if let Some(_preimage) = this.hash_lookup.get(&key) {
@@ -206,6 +248,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_clear_storage(key_data: *const u8, key_len: u32) => {
+		profiling::note_bytes(key_len as usize);
 		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_clear_storage"))?;
 		debug_trace!(target: "wasm-trace", "*** Clearing storage: {}   [k={}]",
 			if let Some(_preimage) = this.hash_lookup.get(&key) {
@@ -217,10 +260,12 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_exists_storage(key_data: *const u8, key_len: u32) -> u32 => {
+		profiling::note_bytes(key_len as usize);
 		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_exists_storage"))?;
 		Ok(if this.ext.exists_storage(&key) { 1 } else { 0 })
 	},
 	ext_clear_prefix(prefix_data: *const u8, prefix_len: u32) => {
+		profiling::note_bytes(prefix_len as usize);
 		let prefix = this.memory.get(prefix_data, prefix_len as usize).map_err(|_| UserError("Invalid attempt to determine prefix in ext_clear_prefix"))?;
 		this.ext.clear_prefix(&prefix);
 		Ok(())
@@ -229,6 +274,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 	ext_get_allocated_storage(key_data: *const u8, key_len: u32, written_out: *mut u32) -> *mut u8 => {
 		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_get_allocated_storage"))?;
 		let maybe_value = this.ext.storage(&key);
+		profiling::note_bytes(key.len() + maybe_value.as_ref().map_or(0, |v| v.len()));
 
 		debug_trace!(target: "wasm-trace", "*** Getting storage: {} == {}   [k={}]",
 			if let Some(_preimage) = this.hash_lookup.get(&key) {
@@ -260,6 +306,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 	ext_get_storage_into(key_data: *const u8, key_len: u32, value_data: *mut u8, value_len: u32, value_offset: u32) -> u32 => {
 		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to get key in ext_get_storage_into"))?;
 		let maybe_value = this.ext.storage(&key);
+		profiling::note_bytes(key.len() + maybe_value.as_ref().map_or(0, |v| v.len()));
 		debug_trace!(target: "wasm-trace", "*** Getting storage: {} == {}   [k={}]",
 			if let Some(_preimage) = this.hash_lookup.get(&key) {
 				format!("%{}", ::primitives::hexdisplay::ascii_format(&_preimage))
@@ -283,6 +330,47 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 			Ok(u32::max_value())
 		}
 	},
+	ext_submit_extrinsic(data: *const u8, len: u32) -> u32 => {
+		profiling::note_bytes(len as usize);
+		let extrinsic = this.memory.get(data, len as usize).map_err(|_| UserError("Invalid attempt to get extrinsic in ext_submit_extrinsic"))?;
+		Ok(if ::runtime_io::submit_extrinsic(&extrinsic) { 0 } else { 1 })
+	},
+	// return 0 and place u32::max_value() into written_out if no value exists for the key.
+	ext_local_storage_get(key_data: *const u8, key_len: u32, written_out: *mut u32) -> *mut u8 => {
+		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_local_storage_get"))?;
+		let maybe_value = ::runtime_io::local_storage_get(&key);
+		profiling::note_bytes(key.len() + maybe_value.as_ref().map_or(0, |v| v.len()));
+
+		if let Some(value) = maybe_value {
+			let offset = this.heap.allocate(value.len() as u32) as u32;
+			this.memory.set(offset, &value).map_err(|_| UserError("Invalid attempt to set memory in ext_local_storage_get"))?;
+			this.memory.write_primitive(written_out, value.len() as u32)
+				.map_err(|_| UserError("Invalid attempt to write written_out in ext_local_storage_get"))?;
+			Ok(offset)
+		} else {
+			this.memory.write_primitive(written_out, u32::max_value())
+				.map_err(|_| UserError("Invalid attempt to write failed written_out in ext_local_storage_get"))?;
+			Ok(0)
+		}
+	},
+	ext_local_storage_set(key_data: *const u8, key_len: u32, value_data: *const u8, value_len: u32) => {
+		profiling::note_bytes(key_len as usize + value_len as usize);
+		let key = this.memory.get(key_data, key_len as usize).map_err(|_| UserError("Invalid attempt to determine key in ext_local_storage_set"))?;
+		let value = this.memory.get(value_data, value_len as usize).map_err(|_| UserError("Invalid attempt to determine value in ext_local_storage_set"))?;
+		::runtime_io::local_storage_set(&key, &value);
+		Ok(())
+	},
+	ext_local_sign(msg_data: *const u8, msg_len: u32, pubkey_data: *mut u8, sig_data: *mut u8) -> u32 => {
+		let msg = this.memory.get(msg_data, msg_len as usize).map_err(|_| UserError("Invalid attempt to get message in ext_local_sign"))?;
+		match ::runtime_io::local_sign(&msg) {
+			Some((pubkey, sig)) => {
+				this.memory.set(pubkey_data, &pubkey[..]).map_err(|_| UserError("Invalid attempt to set pubkey in ext_local_sign"))?;
+				this.memory.set(sig_data, &sig[..]).map_err(|_| UserError("Invalid attempt to set signature in ext_local_sign"))?;
+				Ok(0)
+			}
+			None => Ok(1),
+		}
+	},
 	ext_storage_root(result: *mut u8) => {
 		let r = this.ext.storage_root();
 		this.memory.set(result, &r[..]).map_err(|_| UserError("Invalid attempt to set memory in ext_storage_root"))?;
@@ -299,6 +387,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 					.map_err(|_| UserError("Invalid attempt to get memory in ext_enumerated_trie_root"))
 			)
 			.collect::<::std::result::Result<Vec<_>, UserError>>()?;
+		profiling::note_bytes(values.iter().map(|v| v.len()).sum());
 		let r = ordered_trie_root(values.into_iter());
 		this.memory.set(result, &r[..]).map_err(|_| UserError("Invalid attempt to set memory in ext_enumerated_trie_root"))?;
 		Ok(())
@@ -307,6 +396,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(this.ext.chain_id())
 	},
 	ext_twox_128(data: *const u8, len: u32, out: *mut u8) => {
+		profiling::note_bytes(len as usize);
 		let result = if len == 0 {
 			let hashed = twox_128(&[0u8; 0]);
 			debug_trace!(target: "xxhash", "XXhash: '' -> {}", HexDisplay::from(&hashed));
@@ -331,6 +421,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_twox_256(data: *const u8, len: u32, out: *mut u8) => {
+		profiling::note_bytes(len as usize);
 		let result = if len == 0 {
 			twox_256(&[0u8; 0])
 		} else {
@@ -340,6 +431,7 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		Ok(())
 	},
 	ext_blake2_256(data: *const u8, len: u32, out: *mut u8) => {
+		profiling::note_bytes(len as usize);
 		let result = if len == 0 {
 			blake2_256(&[0u8; 0])
 		} else {
@@ -348,7 +440,18 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 		this.memory.set(out, &result).map_err(|_| UserError("Invalid attempt to set result in ext_blake2_256"))?;
 		Ok(())
 	},
+	ext_keccak_256(data: *const u8, len: u32, out: *mut u8) => {
+		profiling::note_bytes(len as usize);
+		let result = if len == 0 {
+			keccak_256(&[0u8; 0])
+		} else {
+			keccak_256(&this.memory.get(data, len as usize).map_err(|_| UserError("Invalid attempt to get data in ext_keccak_256"))?)
+		};
+		this.memory.set(out, &result).map_err(|_| UserError("Invalid attempt to set result in ext_keccak_256"))?;
+		Ok(())
+	},
 	ext_ed25519_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32 => {
+		profiling::note_bytes(msg_len as usize);
 		let mut sig = [0u8; 64];
 		this.memory.get_into(sig_data, &mut sig[..]).map_err(|_| UserError("Invalid attempt to get signature in ext_ed25519_verify"))?;
 		let mut pubkey = [0u8; 32];
@@ -361,6 +464,63 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 			5
 		})
 	},
+	ext_start_batch_verify() => {
+		if this.signature_queue.is_none() {
+			this.signature_queue = Some(ParallelVerifier::default());
+		}
+		Ok(())
+	},
+	ext_ed25519_batch_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32 => {
+		profiling::note_bytes(msg_len as usize);
+		let mut sig = [0u8; 64];
+		this.memory.get_into(sig_data, &mut sig[..]).map_err(|_| UserError("Invalid attempt to get signature in ext_ed25519_batch_verify"))?;
+		let pubkey = this.memory.get(pubkey_data, 32).map_err(|_| UserError("Invalid attempt to get pubkey in ext_ed25519_batch_verify"))?;
+		let msg = this.memory.get(msg_data, msg_len as usize).map_err(|_| UserError("Invalid attempt to get message in ext_ed25519_batch_verify"))?;
+
+		Ok(match this.signature_queue {
+			Some(ref mut batch) => {
+				batch.push_ed25519(sig, msg, pubkey);
+				0
+			}
+			None if ::ed25519::verify(&sig, &msg, &pubkey) => 0,
+			None => 5,
+		})
+	},
+	ext_finish_batch_verify() -> u32 => {
+		Ok(if this.signature_queue.take().map_or(true, ParallelVerifier::verify) {
+			0
+		} else {
+			5
+		})
+	},
+	ext_sr25519_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32 => {
+		profiling::note_bytes(msg_len as usize);
+		let mut sig = [0u8; 64];
+		this.memory.get_into(sig_data, &mut sig[..]).map_err(|_| UserError("Invalid attempt to get signature in ext_sr25519_verify"))?;
+		let mut pubkey = [0u8; 32];
+		this.memory.get_into(pubkey_data, &mut pubkey[..]).map_err(|_| UserError("Invalid attempt to get pubkey in ext_sr25519_verify"))?;
+		let msg = this.memory.get(msg_data, msg_len as usize).map_err(|_| UserError("Invalid attempt to get message in ext_sr25519_verify"))?;
+
+		Ok(if ::runtime_io::sr25519_verify(&sig, &msg, &pubkey) {
+			0
+		} else {
+			5
+		})
+	},
+	ext_secp256k1_ecdsa_recover(msg_data: *const u8, sig_data: *const u8, pubkey_data: *mut u8) -> u32 => {
+		let mut sig = [0u8; 65];
+		this.memory.get_into(sig_data, &mut sig[..]).map_err(|_| UserError("Invalid attempt to get signature in ext_secp256k1_ecdsa_recover"))?;
+		let mut msg = [0u8; 32];
+		this.memory.get_into(msg_data, &mut msg[..]).map_err(|_| UserError("Invalid attempt to get message in ext_secp256k1_ecdsa_recover"))?;
+
+		match ::runtime_io::secp256k1_ecdsa_recover(&sig, &msg) {
+			Some(pubkey) => {
+				this.memory.set(pubkey_data, &pubkey[..]).map_err(|_| UserError("Invalid attempt to set pubkey in ext_secp256k1_ecdsa_recover"))?;
+				Ok(0)
+			}
+			None => Ok(5),
+		}
+	},
 	ext_sandbox_instantiate(dispatch_thunk_idx: usize, wasm_ptr: *const u8, wasm_len: usize, imports_ptr: *const u8, imports_len: usize, state: usize) -> u32 => {
 		let wasm = this.memory.get(wasm_ptr, wasm_len as usize).map_err(|_| UserError("Sandbox error"))?;
 		let raw_env_def = this.memory.get(imports_ptr, imports_len as usize).map_err(|_| UserError("Sandbox error"))?;
@@ -479,6 +639,107 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 	=> <'e, E: Externalities + 'e>
 );
 
+/// Number of prepared modules kept in the cache.
+const MODULE_CACHE_SIZE: usize = 8;
+
+lazy_static! {
+	// prepared modules by code hash and whether they are instrumented for fuel metering.
+	static ref PREPARED_MODULES: Mutex<LinkedHashMap<(u64, bool), Arc<PreparedModule>>> = Mutex::new(LinkedHashMap::new());
+}
+
+// helper function to generate low-over-head caching_keys
+// it is asserted that part of the audit process that any potential on-chain code change
+// will have done is to ensure that the two-x hash is different to that of any other
+// :code value from the same chain
+pub fn gen_cache_key(code: &[u8]) -> u64 {
+	let mut h = XxHash::with_seed(0);
+	h.write(code);
+	h.finish()
+}
+
+/// A parsed and validated wasm module, instrumented for fuel metering if required. Every call
+/// runs in a fresh instance of it: instances are not reused, since a call may leave the memory,
+/// globals and heap of an instance in any state, and no state may leak from one call to the next.
+pub struct PreparedModule {
+	module: Module,
+	metered: bool,
+}
+
+impl PreparedModule {
+	/// Parse the given code, instrumenting it for fuel metering if `metered`.
+	pub fn new(code: &[u8], metered: bool) -> Result<Self> {
+		let module = elements::deserialize_buffer(code)
+			.map_err(|_| Error::from(ErrorKind::Instrumentation))?;
+		let module = if metered {
+			pwasm_utils::inject_gas_counter(module, &rules::Set::default())
+				.map_err(|_| Error::from(ErrorKind::Instrumentation))?
+		} else {
+			module
+		};
+
+		Ok(PreparedModule {
+			module: Module::from_parity_wasm_module(module)?,
+			metered,
+		})
+	}
+
+	/// Whether the module is instrumented for fuel metering.
+	pub fn is_metered(&self) -> bool {
+		self.metered
+	}
+}
+
+// A started module instance.
+struct Instance {
+	instance: ModuleRef,
+	memory: MemoryRef,
+	table: Option<TableRef>,
+}
+
+/// The prepared module for `code`, parsing it if it is not cached yet. The cache is only locked
+/// while looking the module up, not while it runs.
+pub fn prepared_module(code: &[u8], metered: bool) -> Result<Arc<PreparedModule>> {
+	let key = (gen_cache_key(code), metered);
+	if let Some(module) = PREPARED_MODULES.lock().get_refresh(&key) {
+		return Ok(module.clone());
+	}
+
+	// parse without holding the lock; a module prepared concurrently is simply replaced.
+	let module = Arc::new(PreparedModule::new(code, metered)?);
+	let mut cache = PREPARED_MODULES.lock();
+	cache.insert(key, module.clone());
+	while cache.len() > MODULE_CACHE_SIZE {
+		cache.pop_front();
+	}
+	Ok(module)
+}
+
+thread_local! {
+	// additional fuel limit for calls made on this thread, see `with_restricted_fuel`.
+	static THREAD_FUEL_LIMIT: Cell<Option<u64>> = Cell::new(None);
+}
+
+/// Execute `f`, limiting every wasm call it makes on the current thread to at most `limit` fuel
+/// on top of the limit of the executor used.
+pub fn with_restricted_fuel<R, F: FnOnce() -> R>(limit: u64, f: F) -> R {
+	struct Restore(Option<u64>);
+
+	impl Drop for Restore {
+		fn drop(&mut self) {
+			let previous = self.0;
+			THREAD_FUEL_LIMIT.with(|l| l.set(previous));
+		}
+	}
+
+	let previous = THREAD_FUEL_LIMIT.with(|l| {
+		let previous = l.get();
+		l.set(Some(previous.map_or(limit, |p| cmp::min(p, limit))));
+		previous
+	});
+	let _restore = Restore(previous);
+	f()
+}
+
 /// Wasm rust executor for contracts.
 ///
 /// Executes the provided code in a sandboxed wasm runtime.
@@ -486,12 +747,16 @@ impl_function_executor!(this: FunctionExecutor<'e, E>,
 pub struct WasmExecutor {
 	/// The max number of pages to allocate for the heap.
 	pub max_heap_pages: usize,
+	/// The max amount of fuel, roughly the number of instructions, a call may use.
+	/// `None` if unlimited.
+	pub fuel_limit: Option<u64>,
 }
 
 impl Clone for WasmExecutor {
 	fn clone(&self) -> Self {
 		WasmExecutor {
 			max_heap_pages: self.max_heap_pages,
+			fuel_limit: self.fuel_limit,
 		}
 	}
 }
@@ -502,12 +767,20 @@ impl WasmExecutor {
 	pub fn new(max_heap_pages: usize) -> Self {
 		WasmExecutor {
 			max_heap_pages,
+			fuel_limit: None,
 		}
 	}
 
+	// The fuel limit for a call made now on this thread.
+	fn current_fuel_limit(&self) -> Option<u64> {
+		match (self.fuel_limit, THREAD_FUEL_LIMIT.with(|l| l.get())) {
+			(Some(a), Some(b)) => Some(cmp::min(a, b)),
+			(a, b) => a.or(b),
+		}
+	}
 
-	/// Call a given method in the given code.
-	/// This should be used for tests only.
+
+	/// Call a given method in the given code. The prepared module is cached by code hash.
 	pub fn call<E: Externalities>(
 		&self,
 		ext: &mut E,
@@ -515,11 +788,26 @@ impl WasmExecutor {
 		method: &str,
 		data: &[u8],
 		) -> Result<Vec<u8>> {
-		let module = ::wasmi::Module::from_buffer(code).expect("all modules compiled with rustc are valid wasm code; qed");
-		self.call_in_wasm_module(ext, &module, method, data)
+		let module = prepared_module(code, self.current_fuel_limit().is_some())?;
+		self.call_in_prepared_module(ext, &module, method, data)
+	}
+
+	/// Call a given method in a fresh instance of the given prepared module.
+	///
+	/// Fuel is only metered if the module is, see `PreparedModule::new`.
+	pub fn call_in_prepared_module<E: Externalities>(
+		&self,
+		ext: &mut E,
+		module: &PreparedModule,
+		method: &str,
+		data: &[u8],
+	) -> Result<Vec<u8>> {
+		self.call_in_wasm_module(ext, &module.module, method, data)
 	}
 
 	/// Call a given method in the given wasm-module runtime.
+	///
+	/// Fuel is only metered if `module` was instrumented, as done by `PreparedModule`.
 	pub fn call_in_wasm_module<E: Externalities>(
 		&self,
 		ext: &mut E,
@@ -527,6 +815,11 @@ impl WasmExecutor {
 		method: &str,
 		data: &[u8],
 	) -> Result<Vec<u8>> {
+		let instance = self.instantiate(ext, module)?;
+		self.invoke(ext, &instance, method, data)
+	}
+
+	fn instantiate<E: Externalities>(&self, ext: &mut E, module: &Module) -> Result<Instance> {
 		// start module instantiation. Don't run 'start' function yet.
 		let intermediate_instance = ModuleInstance::new(
 			module,
@@ -550,16 +843,43 @@ impl WasmExecutor {
 			.export_by_name("__indirect_function_table")
 			.and_then(|e| e.as_table().cloned());
 
-		let mut fec = FunctionExecutor::new(memory.clone(), self.max_heap_pages, table, ext)?;
+		let instance = {
+			let fuel_limit = self.current_fuel_limit();
+			let mut fec = FunctionExecutor::new(memory.clone(), self.max_heap_pages, table.clone(), ext, fuel_limit)?;
 
-		// finish instantiation by running 'start' function (if any).
-		let instance = intermediate_instance.run_start(&mut fec)?;
+			// finish instantiation by running 'start' function (if any).
+			match intermediate_instance.run_start(&mut fec) {
+				Ok(instance) => instance,
+				Err(_) if fec.out_of_fuel => return Err(ErrorKind::OutOfFuel(fuel_limit.unwrap_or(0)).into()),
+				Err(e) => return Err(e.into()),
+			}
+		};
+
+		Ok(Instance {
+			instance,
+			memory,
+			table,
+		})
+	}
 
+	fn invoke<E: Externalities>(
+		&self,
+		ext: &mut E,
+		instance: &Instance,
+		method: &str,
+		data: &[u8],
+	) -> Result<Vec<u8>> {
+		let memory = instance.memory.clone();
+		let fuel_limit = self.current_fuel_limit();
+		let mut fec = FunctionExecutor::new(memory.clone(), self.max_heap_pages, instance.table.clone(), ext, fuel_limit)?;
+
+		let _timer = profiling::Timer::method(method);
+		profiling::note_bytes(data.len());
 		let size = data.len() as u32;
 		let offset = fec.heap.allocate(size);
 		memory.set(offset, &data)?;
 
-		let result = instance.invoke_export(
+		let result = instance.instance.invoke_export(
 			method,
 			&[
 				I32(offset as i32),
@@ -570,6 +890,10 @@ impl WasmExecutor {
 
 		let returned = match result {
 			Ok(x) => x,
+			Err(_) if fec.out_of_fuel => {
+				trace!(target: "wasm-executor", "Call to {} ran out of fuel", method);
+				return Err(ErrorKind::OutOfFuel(fuel_limit.unwrap_or(0)).into())
+			},
 			Err(e) => {
 				trace!(target: "wasm-executor", "Failed to execute code with {} pages", self.max_heap_pages);
 				return Err(e.into())
@@ -579,6 +903,7 @@ impl WasmExecutor {
 		if let Some(I64(r)) = returned {
 			let offset = r as u32;
 			let length = (r >> 32) as u32 as usize;
+			profiling::note_bytes(length);
 			memory.get(offset, length)
 				.map_err(|_| ErrorKind::Runtime.into())
 		} else {
@@ -593,6 +918,8 @@ mod tests {
 	use super::*;
 	use codec::Encode;
 	use state_machine::TestExternalities;
+	#[cfg(feature = "bench")]
+	use test::Bencher;
 
 	// TODO: move into own crate.
 	macro_rules! map {
@@ -705,6 +1032,20 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn keccak_256_should_work() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		assert_eq!(
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_keccak_256", &[]).unwrap(),
+			hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+		);
+		assert_eq!(
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_keccak_256", b"Hello world!").unwrap(),
+			keccak_256(&b"Hello world!"[..]).encode()
+		);
+	}
+
 	#[test]
 	fn ed25519_verify_should_work() {
 		let mut ext = TestExternalities::default();
@@ -731,6 +1072,125 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn ed25519_batch_verify_should_work() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let key = ::ed25519::Pair::from_seed(&blake2_256(b"test"));
+
+		for (msg, expected) in vec![(&b"all ok!"[..], vec![1, 1]), (&b"all is not ok!"[..], vec![1, 0])] {
+			let mut calldata = vec![];
+			calldata.extend_from_slice(key.public().as_ref());
+			calldata.extend_from_slice(key.sign(msg).as_ref());
+
+			assert_eq!(
+				WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_ed25519_batch_verify", &calldata).unwrap(),
+				expected
+			);
+		}
+	}
+
+	#[test]
+	fn sr25519_verify_should_work() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let key = ::schnorrkel::MiniSecretKey::from_bytes(&blake2_256(b"test")).unwrap().expand_to_keypair();
+		let context = ::schnorrkel::signing_context(b"substrate");
+
+		for (msg, expected) in vec![(&b"all ok!"[..], vec![1]), (&b"all is not ok!"[..], vec![0])] {
+			let mut calldata = vec![];
+			calldata.extend_from_slice(&key.public.to_bytes());
+			calldata.extend_from_slice(&key.sign(context.bytes(msg)).to_bytes());
+
+			assert_eq!(
+				WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_sr25519_verify", &calldata).unwrap(),
+				expected
+			);
+		}
+	}
+
+	#[test]
+	fn secp256k1_ecdsa_recover_should_work() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let secret = ::secp256k1::SecretKey::parse(&blake2_256(b"test")).unwrap();
+		let public = ::secp256k1::PublicKey::from_secret_key(&secret);
+		let msg = keccak_256(b"all ok!");
+		let (rs, v) = ::secp256k1::sign(&::secp256k1::Message::parse(&msg), &secret).unwrap();
+
+		let mut calldata = vec![];
+		calldata.extend_from_slice(&rs.serialize());
+		calldata.push(v.serialize() + 27);
+		calldata.extend_from_slice(&msg);
+
+		assert_eq!(
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_secp256k1_ecdsa_recover", &calldata).unwrap(),
+			public.serialize()[1..65].to_vec()
+		);
+
+		// an invalid recovery id recovers nothing.
+		calldata[64] = 42;
+		assert_eq!(
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_secp256k1_ecdsa_recover", &calldata).unwrap(),
+			Vec::<u8>::new()
+		);
+	}
+
+	#[test]
+	fn offchain_functions_should_work() {
+		#[derive(Default)]
+		struct TestOffchain {
+			submitted: Vec<Vec<u8>>,
+			storage: HashMap<Vec<u8>, Vec<u8>>,
+			key: Option<::ed25519::Pair>,
+		}
+
+		impl ::runtime_io::OffchainExt for TestOffchain {
+			fn submit_extrinsic(&mut self, extrinsic: Vec<u8>) -> bool {
+				self.submitted.push(extrinsic);
+				true
+			}
+			fn local_storage_get(&self, key: &[u8]) -> Option<Vec<u8>> {
+				self.storage.get(key).cloned()
+			}
+			fn local_storage_set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+				self.storage.insert(key, value);
+			}
+			fn sign(&self, msg: &[u8]) -> Option<([u8; 32], [u8; 64])> {
+				self.key.as_ref().map(|key| (key.public().0, key.sign(msg).0))
+			}
+		}
+
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+
+		// without an offchain context nothing is submitted nor stored.
+		assert_eq!(
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_offchain", b"xt").unwrap(),
+			vec![0]
+		);
+
+		let mut offchain = TestOffchain::default();
+		let output = ::runtime_io::with_offchain(&mut offchain, || {
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_offchain", b"xt").unwrap()
+		});
+		assert_eq!(output, b"\x01xt".to_vec());
+		assert_eq!(offchain.submitted, vec![b"xt".to_vec()]);
+		assert_eq!(offchain.storage.get(&b"foo"[..]), Some(&b"xt".to_vec()));
+
+		// with a local key the input is signed.
+		let key = ::ed25519::Pair::from_seed(&blake2_256(b"test"));
+		let public = key.public();
+		let mut offchain = TestOffchain { key: Some(key), ..Default::default() };
+		let output = ::runtime_io::with_offchain(&mut offchain, || {
+			WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_offchain", b"xt").unwrap()
+		});
+		assert_eq!(&output[..3], b"\x01xt");
+		assert_eq!(&output[3..35], &public.0[..]);
+		let sig = ::ed25519::Signature::from_slice(&output[35..99]);
+		assert!(::ed25519::verify_strong(&sig, b"xt", &public));
+	}
+
 	#[test]
 	fn enumerated_trie_root_should_work() {
 		let mut ext = TestExternalities::default();
@@ -742,4 +1202,99 @@ mod tests {
 	}
 
 
+	#[test]
+	fn calls_should_not_share_instances() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let executor = WasmExecutor::new(8);
+		let module = PreparedModule::new(&test_code[..], false).unwrap();
+
+		assert!(executor.call_in_prepared_module(&mut ext, &module, "test_conditional_panic", &[2]).is_err());
+		for _ in 0..2 {
+			assert_eq!(
+				executor.call_in_prepared_module(&mut ext, &module, "test_twox_128", b"Hello world!").unwrap(),
+				hex!("b27dfd7f223f177f2a13647b533599af")
+			);
+		}
+	}
+
+	#[test]
+	fn modules_should_only_be_metered_with_a_fuel_limit() {
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		assert!(!prepared_module(&test_code[..], false).unwrap().is_metered());
+		assert!(prepared_module(&test_code[..], true).unwrap().is_metered());
+
+		let mut ext = TestExternalities::default();
+		let mut executor = WasmExecutor::new(8);
+		executor.fuel_limit = Some(1);
+		assert!(executor.call_in_prepared_module(&mut ext, &PreparedModule::new(&test_code[..], false).unwrap(), "test_twox_128", b"Hello world!").is_ok());
+	}
+
+	#[test]
+	fn running_out_of_fuel_should_fail() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let mut executor = WasmExecutor::new(8);
+
+		executor.fuel_limit = Some(1);
+		assert_matches!(
+			executor.call(&mut ext, &test_code[..], "test_twox_128", b"Hello world!").map_err(|e| e.0),
+			Err(ErrorKind::OutOfFuel(1))
+		);
+
+		executor.fuel_limit = Some(100_000_000);
+		assert!(executor.call(&mut ext, &test_code[..], "test_twox_128", b"Hello world!").is_ok());
+		assert_matches!(
+			with_restricted_fuel(1, || executor.call(&mut ext, &test_code[..], "test_twox_128", b"Hello world!")).map_err(|e| e.0),
+			Err(ErrorKind::OutOfFuel(1))
+		);
+	}
+
+	#[test]
+	fn profiling_should_record_host_functions_and_methods() {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+
+		profiling::set_enabled(true);
+		let _ = profiling::take();
+		WasmExecutor::new(8).call(&mut ext, &test_code[..], "test_blake2_256", b"Hello world!").unwrap();
+		let profile = profiling::take();
+		profiling::set_enabled(false);
+
+		assert_eq!(profile.methods["test_blake2_256"].calls, 1);
+		assert_eq!(profile.host_functions["ext_blake2_256"].calls, 1);
+		assert_eq!(profile.host_functions["ext_blake2_256"].bytes, 12);
+		assert!(profiling::take().is_empty());
+	}
+
+	#[cfg(feature = "bench")]
+	#[bench]
+	fn bench_call_uncached(b: &mut Bencher) {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let executor = WasmExecutor::new(1024);
+		b.iter(|| {
+			let module = Module::from_buffer(&test_code[..]).unwrap();
+			executor.call_in_wasm_module(&mut ext, &module, "test_twox_128", b"Hello world!").unwrap()
+		});
+	}
+
+	#[cfg(feature = "bench")]
+	#[bench]
+	fn bench_call_cached(b: &mut Bencher) {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let executor = WasmExecutor::new(1024);
+		b.iter(|| executor.call(&mut ext, &test_code[..], "test_twox_128", b"Hello world!").unwrap());
+	}
+
+	#[cfg(feature = "bench")]
+	#[bench]
+	fn bench_call_cached_metered(b: &mut Bencher) {
+		let mut ext = TestExternalities::default();
+		let test_code = include_bytes!("../wasm/target/wasm32-unknown-unknown/release/runtime_test.compact.wasm");
+		let mut executor = WasmExecutor::new(1024);
+		executor.fuel_limit = Some(u64::max_value());
+		b.iter(|| executor.call(&mut ext, &test_code[..], "test_twox_128", b"Hello world!").unwrap());
+	}
 }
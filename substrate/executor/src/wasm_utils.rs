@@ -152,6 +152,7 @@ macro_rules! dispatch_fn {
 
 	( @iter $index:expr, $index_ident:ident, $objectname:ident, $args_iter:ident, $name:ident ( $( $names:ident : $params:ty ),* ) $( -> $returns:ty )* => $body:tt $($tail:tt)*) => (
 		if $index_ident == $index {
+			let _timer = $crate::profiling::Timer::host_function(stringify!($name));
 			{ marshall!($args_iter, $objectname, ( $( $names : $params ),* ) $( -> $returns )* => $body) }
 		}
 		dispatch_fn!( @iter $index + 1, $index_ident, $objectname, $args_iter $($tail)*)
@@ -11,8 +11,10 @@ extern crate substrate_runtime_io as runtime_io;
 extern crate substrate_runtime_sandbox as sandbox;
 
 use runtime_io::{
-	set_storage, storage, clear_prefix, print, blake2_256,
-	twox_128, twox_256, ed25519_verify, enumerated_trie_root
+	set_storage, storage, clear_prefix, print, blake2_256, keccak_256,
+	twox_128, twox_256, ed25519_verify, sr25519_verify, secp256k1_ecdsa_recover, enumerated_trie_root,
+	start_batch_verify, ed25519_batch_verify, finish_batch_verify,
+	submit_extrinsic, local_storage_get, local_storage_set, local_sign
 };
 
 impl_stubs!(
@@ -44,6 +46,7 @@ impl_stubs!(
 	test_blake2_256 NO_DECODE => |input| blake2_256(input).to_vec(),
 	test_twox_256 NO_DECODE => |input| twox_256(input).to_vec(),
 	test_twox_128 NO_DECODE => |input| twox_128(input).to_vec(),
+	test_keccak_256 NO_DECODE => |input| keccak_256(input).to_vec(),
 	test_ed25519_verify NO_DECODE => |input: &[u8]| {
 		let mut pubkey = [0; 32];
 		let mut sig = [0; 64];
@@ -54,6 +57,47 @@ impl_stubs!(
 		let msg = b"all ok!";
 		[ed25519_verify(&sig, &msg[..], &pubkey) as u8].to_vec()
 	},
+	test_sr25519_verify NO_DECODE => |input: &[u8]| {
+		let mut pubkey = [0; 32];
+		let mut sig = [0; 64];
+
+		pubkey.copy_from_slice(&input[0..32]);
+		sig.copy_from_slice(&input[32..96]);
+
+		let msg = b"all ok!";
+		[sr25519_verify(&sig, &msg[..], &pubkey) as u8].to_vec()
+	},
+	test_secp256k1_ecdsa_recover NO_DECODE => |input: &[u8]| {
+		let mut sig = [0; 65];
+		let mut msg = [0; 32];
+
+		sig.copy_from_slice(&input[0..65]);
+		msg.copy_from_slice(&input[65..97]);
+
+		secp256k1_ecdsa_recover(&sig, &msg).map_or_else(Vec::new, |pubkey| pubkey.to_vec())
+	},
+	test_ed25519_batch_verify NO_DECODE => |input: &[u8]| {
+		let mut pubkey = [0; 32];
+		let mut sig = [0; 64];
+
+		pubkey.copy_from_slice(&input[0..32]);
+		sig.copy_from_slice(&input[32..96]);
+
+		start_batch_verify();
+		let queued = ed25519_batch_verify(&sig, &b"all ok!"[..], &pubkey);
+		[queued as u8, finish_batch_verify() as u8].to_vec()
+	},
+	test_offchain NO_DECODE => |input: &[u8]| {
+		local_storage_set(b"foo", input);
+		let mut output = Vec::new();
+		output.push(submit_extrinsic(input) as u8);
+		output.extend(local_storage_get(b"foo").unwrap_or_default());
+		if let Some((pubkey, sig)) = local_sign(input) {
+			output.extend_from_slice(&pubkey[..]);
+			output.extend_from_slice(&sig[..]);
+		}
+		output
+	},
 	test_enumerated_trie_root NO_DECODE => |_| {
 		enumerated_trie_root(&[&b"zero"[..], &b"one"[..], &b"two"[..]]).to_vec()
 	},
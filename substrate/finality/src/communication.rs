@@ -0,0 +1,260 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Gossip of votes and commits through the network's `ConsensusGossip`.
+
+use std::sync::Arc;
+
+use codec::Decode;
+use futures::Stream;
+use futures::stream::FilterMap;
+use futures::sync::mpsc;
+use network::Service as NetworkService;
+use network::consensus_gossip::{ConsensusGossip, ConsensusMessage, ValidationResult, Validator};
+use network::specialization::Specialization;
+use parking_lot::RwLock;
+use primitives::AuthorityId;
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT};
+
+use {check_commit, commit_topic, round_now, round_topic, GossipMessage, RoundNumber, SetId};
+
+/// Number of past rounds whose votes are still gossiped.
+const LIVE_ROUNDS: RoundNumber = 2;
+
+/// Gossip of votes and commits between voters.
+pub trait Network<Block: BlockT> {
+	/// Stream of raw messages.
+	type In: Stream<Item=Vec<u8>, Error=()>;
+
+	/// Messages gossiped under `topic`, including those received before the call.
+	fn messages_for(&self, topic: Block::Hash) -> Self::In;
+
+	/// Gossip a message under `topic`.
+	fn send_message(&self, topic: Block::Hash, message: Vec<u8>);
+
+	/// Note the current authority set, whose messages alone are gossiped on.
+	fn note_voters(&self, set_id: SetId, voters: Vec<AuthorityId>);
+}
+
+/// A network specialization which gossips finality messages as chain-specific consensus
+/// messages, wrapped in its own message type and checked by a `GossipValidator`.
+pub trait FinalityGossip<Block: BlockT>: Specialization<Block> {
+	/// The consensus gossip to send and receive finality messages through, along with its
+	/// validator. `None` while finality is disabled.
+	fn finality_gossip(&mut self) -> Option<(&mut ConsensusGossip<Block>, &GossipValidator)>;
+
+	/// Wrap a finality message into a chain-specific message.
+	fn wrap_message(message: Vec<u8>) -> Vec<u8>;
+
+	/// Extract a finality message from a chain-specific message.
+	fn unwrap_message(raw: &[u8]) -> Option<Vec<u8>>;
+}
+
+fn finality_message<Block: BlockT, S: FinalityGossip<Block>>(message: ConsensusMessage<Block>) -> Option<Vec<u8>> {
+	match message {
+		ConsensusMessage::ChainSpecific(raw) => S::unwrap_message(&raw),
+		ConsensusMessage::Bft(_) => None,
+	}
+}
+
+impl<Block, S> Network<Block> for Arc<NetworkService<Block, S>> where
+	Block: BlockT + 'static,
+	Block::Header: HeaderT<Number=u64>,
+	S: FinalityGossip<Block>,
+{
+	type In = FilterMap<mpsc::UnboundedReceiver<ConsensusMessage<Block>>, fn(ConsensusMessage<Block>) -> Option<Vec<u8>>>;
+
+	fn messages_for(&self, topic: Block::Hash) -> Self::In {
+		let incoming = self.with_spec(|spec, _| spec.finality_gossip().map(|(gossip, _)| gossip.messages_for(topic)))
+			.and_then(|incoming| incoming)
+			.unwrap_or_else(|| {
+				warn!(target: "finality", "Cannot receive votes: network is down or finality disabled");
+				mpsc::unbounded().1
+			});
+		incoming.filter_map(finality_message::<Block, S> as fn(_) -> _)
+	}
+
+	fn send_message(&self, topic: Block::Hash, message: Vec<u8>) {
+		let message = ConsensusMessage::ChainSpecific(S::wrap_message(message));
+		self.with_spec(move |spec, ctx| if let Some((gossip, _)) = spec.finality_gossip() {
+			gossip.multicast(ctx, topic, message);
+		});
+	}
+
+	fn note_voters(&self, set_id: SetId, voters: Vec<AuthorityId>) {
+		self.with_spec(move |spec, _| if let Some((_, validator)) = spec.finality_gossip() {
+			validator.note_voters(set_id, voters);
+		});
+	}
+}
+
+/// Validates gossiped finality messages. Only messages of the current authority set are
+/// accepted: votes of its members while their round is recent, and commits it signed.
+pub struct GossipValidator {
+	round_duration: u64,
+	unwrap: fn(&[u8]) -> Option<Vec<u8>>,
+	voters: RwLock<Option<(SetId, Vec<AuthorityId>)>>,
+}
+
+impl GossipValidator {
+	/// Create a validator for rounds of `round_duration` seconds, extracting finality messages
+	/// from chain-specific ones with `unwrap`.
+	pub fn new(round_duration: u64, unwrap: fn(&[u8]) -> Option<Vec<u8>>) -> Self {
+		GossipValidator {
+			round_duration,
+			unwrap,
+			voters: RwLock::new(None),
+		}
+	}
+
+	/// Note the current authority set. Until the first call, every message is discarded.
+	pub fn note_voters(&self, set_id: SetId, voters: Vec<AuthorityId>) {
+		*self.voters.write() = Some((set_id, voters));
+	}
+
+	fn decode_message<Block: BlockT>(&self, message: &ConsensusMessage<Block>) -> Option<GossipMessage<Block::Hash>> {
+		match *message {
+			ConsensusMessage::ChainSpecific(ref raw) => (self.unwrap)(raw)
+				.and_then(|inner| GossipMessage::decode(&mut &inner[..])),
+			ConsensusMessage::Bft(_) => None,
+		}
+	}
+
+	fn is_live(&self, round: RoundNumber) -> bool {
+		match round_now(self.round_duration) {
+			Ok(now) => round + LIVE_ROUNDS >= now && round <= now + 1,
+			Err(_) => false,
+		}
+	}
+
+	fn is_current_set(&self, set_id: SetId) -> bool {
+		self.voters.read().as_ref().map_or(false, |&(id, _)| id == set_id)
+	}
+}
+
+impl<Block: BlockT> Validator<Block> for GossipValidator {
+	fn validate(&self, message: &ConsensusMessage<Block>) -> ValidationResult<Block::Hash> {
+		let voters = self.voters.read();
+		let (set_id, voters) = match *voters {
+			Some((set_id, ref voters)) => (set_id, voters),
+			None => return ValidationResult::Discard,
+		};
+
+		match self.decode_message(message) {
+			Some(GossipMessage::Vote(vote)) => {
+				let valid = vote.set_id == set_id
+					&& voters.contains(&vote.voter)
+					&& self.is_live(vote.round)
+					&& vote.check();
+				if valid {
+					ValidationResult::ProcessAndKeep(round_topic::<Block>(vote.set_id, vote.round))
+				} else {
+					ValidationResult::Discard
+				}
+			}
+			Some(GossipMessage::Commit(commit)) => {
+				if commit.set_id == set_id && check_commit(&commit, voters).is_ok() {
+					ValidationResult::ProcessAndKeep(commit_topic::<Block>(commit.set_id))
+				} else {
+					ValidationResult::Discard
+				}
+			}
+			None => ValidationResult::Discard,
+		}
+	}
+
+	fn message_expired(&self, _topic: &Block::Hash, message: &ConsensusMessage<Block>) -> bool {
+		match self.decode_message(message) {
+			Some(GossipMessage::Vote(vote)) => !self.is_current_set(vote.set_id) || !self.is_live(vote.round),
+			Some(GossipMessage::Commit(commit)) => !self.is_current_set(commit.set_id),
+			None => true,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use keyring::Keyring;
+	use test_client::runtime::{Block, Hash};
+	use {Commit, SignedVote, Stage};
+
+	const ROUND_DURATION: u64 = 10;
+
+	fn validator() -> GossipValidator {
+		let validator = GossipValidator::new(ROUND_DURATION, |raw| Some(raw.to_vec()));
+		validator.note_voters(3, vec![
+			Keyring::Alice.to_raw_public().into(),
+			Keyring::Bob.to_raw_public().into(),
+			Keyring::Charlie.to_raw_public().into(),
+		]);
+		validator
+	}
+
+	fn message(message: GossipMessage<Hash>) -> ConsensusMessage<Block> {
+		ConsensusMessage::ChainSpecific(message.encode())
+	}
+
+	fn vote(who: Keyring, set_id: SetId) -> ConsensusMessage<Block> {
+		let round = round_now(ROUND_DURATION).unwrap();
+		message(GossipMessage::Vote(SignedVote::sign(&who.pair(), set_id, round, Stage::Prevote, Hash::from([1; 32]))))
+	}
+
+	fn is_kept(result: ValidationResult<Hash>) -> bool {
+		match result {
+			ValidationResult::ProcessAndKeep(_) => true,
+			_ => false,
+		}
+	}
+
+	#[test]
+	fn only_votes_of_current_voters_are_kept() {
+		let validator = validator();
+		assert!(is_kept(Validator::<Block>::validate(&validator, &vote(Keyring::Alice, 3))));
+		assert!(!is_kept(Validator::<Block>::validate(&validator, &vote(Keyring::Eve, 3))));
+		assert!(!is_kept(Validator::<Block>::validate(&validator, &vote(Keyring::Alice, 4))));
+
+		let unknown_set = GossipValidator::new(ROUND_DURATION, |raw| Some(raw.to_vec()));
+		assert!(!is_kept(Validator::<Block>::validate(&unknown_set, &vote(Keyring::Alice, 3))));
+	}
+
+	#[test]
+	fn only_commits_of_current_voters_are_kept() {
+		let validator = validator();
+		let hash = Hash::from([1; 32]);
+		let precommit = |who: Keyring| {
+			let vote = SignedVote::sign(&who.pair(), 3, 7, Stage::Precommit, hash);
+			(hash, vote.voter, vote.signature)
+		};
+		let mut commit = Commit {
+			set_id: 3,
+			round: 7,
+			hash,
+			precommits: vec![precommit(Keyring::Alice), precommit(Keyring::Eve)],
+		};
+		assert!(!is_kept(Validator::<Block>::validate(&validator, &message(GossipMessage::Commit(commit.clone())))));
+
+		commit.precommits.push(precommit(Keyring::Bob));
+		commit.precommits.push(precommit(Keyring::Charlie));
+		let commit = message(GossipMessage::Commit(commit));
+		assert!(is_kept(Validator::<Block>::validate(&validator, &commit)));
+		assert!(!Validator::<Block>::message_expired(&validator, &hash, &commit));
+
+		validator.note_voters(4, Vec::new());
+		assert!(Validator::<Block>::message_expired(&validator, &hash, &commit));
+	}
+}
@@ -0,0 +1,43 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Error types of the finality gadget.
+
+error_chain! {
+	links {
+		Client(::client::error::Error, ::client::error::ErrorKind);
+	}
+
+	errors {
+		/// Commit does not justify the block it claims to finalize.
+		BadCommit(h: String) {
+			description("Bad commit"),
+			display("Commit for block {} is not justified by the voters", h),
+		}
+
+		/// Stored authority set identifier could not be decoded.
+		BadSetId {
+			description("Bad authority set identifier"),
+			display("Stored authority set identifier is invalid."),
+		}
+
+		/// Local clock is before the UNIX epoch.
+		ClockBeforeEpoch {
+			description("Clock is before the UNIX epoch"),
+			display("Local clock is before the UNIX epoch."),
+		}
+	}
+}
@@ -0,0 +1,904 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Finality gadget which finalizes chains of blocks by authority vote, independently of how
+//! blocks are authored.
+//!
+//! Time is split into rounds of a fixed number of seconds since the UNIX epoch. At the start
+//! of a round each authority prevotes for the head of its best chain. Halfway through, it
+//! precommits to the prevote-GHOST: the deepest block which more than two thirds of the
+//! authorities prevoted for or for a descendant of. The deepest block which more than two
+//! thirds precommit to or to a descendant of is finalized along with all of its ancestors, and
+//! the precommits are stored as the proof of its finality.
+//!
+//! A voter which precommitted to a block is locked on it: it only prevotes for the block or its
+//! descendants until the block is finalized, or until the prevote-GHOST of a later round doesn't
+//! contain it. The lock is kept in the auxiliary storage of the client, so that it survives a
+//! restart. Blocks conflicting with the last finalized block are never imported.
+//!
+//! Votes are gossiped through `ConsensusGossip` under a topic per authority set and round, and
+//! commits under a topic per authority set, so that nodes which missed a round can still check
+//! and apply its outcome.
+//!
+//! The voters are the authorities at the last finalized block. When the `consensus` runtime
+//! module changes the authorities, votes stop at the block which made the change; once that
+//! block is final, the new authorities take over as a new set, identified by its number.
+
+pub mod communication;
+pub mod error;
+pub mod round;
+
+extern crate substrate_client as client;
+extern crate substrate_codec as codec;
+extern crate substrate_network as network;
+extern crate substrate_primitives as primitives;
+extern crate substrate_runtime_consensus as runtime_consensus;
+extern crate substrate_runtime_primitives as runtime_primitives;
+extern crate ed25519;
+extern crate futures;
+extern crate parking_lot;
+extern crate tokio;
+
+#[cfg(test)]
+extern crate substrate_keyring as keyring;
+#[cfg(test)]
+extern crate substrate_test_client as test_client;
+
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+extern crate error_chain;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use client::{CallExecutor, Client};
+use codec::{Decode, Encode, Input, Output};
+use futures::{Async, Future, Stream};
+use primitives::AuthorityId;
+use primitives::storage::StorageKey;
+use runtime_consensus::AUTHORITY_SET_CHANGED;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{As, Block as BlockT, Hash as HashT, HashFor, Header as HeaderT, NumberFor};
+use tokio::timer::Interval;
+
+use communication::Network;
+use round::{Chain, Round};
+
+pub use error::{Error, ErrorKind, Result};
+
+/// Identifier of an authority set: the number of the block which enacted it.
+pub type SetId = u64;
+
+/// Index of a voting round.
+pub type RoundNumber = u32;
+
+// Key of the lock of the voter in the auxiliary storage of the client.
+const LOCK_KEY: &[u8] = b"finality_lock";
+
+/// The stage of a round a vote is cast in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+	/// Vote for the head of the best chain.
+	Prevote,
+	/// Vote to finalize the prevote-GHOST.
+	Precommit,
+}
+
+impl Encode for Stage {
+	fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+		f(&[match *self {
+			Stage::Prevote => 0u8,
+			Stage::Precommit => 1u8,
+		}])
+	}
+}
+
+impl Decode for Stage {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		match input.read_byte()? {
+			0 => Some(Stage::Prevote),
+			1 => Some(Stage::Precommit),
+			_ => None,
+		}
+	}
+}
+
+/// A signed vote, as gossiped between voters.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SignedVote<H> {
+	/// The authority set the voter belongs to.
+	pub set_id: SetId,
+	/// The round the vote is cast in.
+	pub round: RoundNumber,
+	/// The stage of the round.
+	pub stage: Stage,
+	/// The block voted for.
+	pub hash: H,
+	/// The voter.
+	pub voter: AuthorityId,
+	/// Signature of the voter over the rest of the vote.
+	pub signature: ed25519::Signature,
+}
+
+impl<H: Encode> Encode for SignedVote<H> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.set_id);
+		dest.push(&self.round);
+		dest.push(&self.stage);
+		dest.push(&self.hash);
+		dest.push(&self.voter);
+		dest.push(&self.signature);
+	}
+}
+
+impl<H: Decode> Decode for SignedVote<H> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(SignedVote {
+			set_id: Decode::decode(input)?,
+			round: Decode::decode(input)?,
+			stage: Decode::decode(input)?,
+			hash: Decode::decode(input)?,
+			voter: Decode::decode(input)?,
+			signature: Decode::decode(input)?,
+		})
+	}
+}
+
+/// Precommits of more than two thirds of an authority set for a block or its descendants,
+/// finalizing it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Commit<H> {
+	/// The authority set which voted.
+	pub set_id: SetId,
+	/// The round the precommits were cast in.
+	pub round: RoundNumber,
+	/// The finalized block.
+	pub hash: H,
+	/// The blocks precommitted to, the voters and their precommit signatures.
+	pub precommits: Vec<(H, AuthorityId, ed25519::Signature)>,
+}
+
+impl<H: Encode> Encode for Commit<H> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.set_id);
+		dest.push(&self.round);
+		dest.push(&self.hash);
+		dest.push(&self.precommits);
+	}
+}
+
+impl<H: Decode> Decode for Commit<H> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(Commit {
+			set_id: Decode::decode(input)?,
+			round: Decode::decode(input)?,
+			hash: Decode::decode(input)?,
+			precommits: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A message gossiped between voters.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GossipMessage<H> {
+	/// A vote in a round.
+	Vote(SignedVote<H>),
+	/// The commit a round ended with.
+	Commit(Commit<H>),
+}
+
+impl<H: Encode> Encode for GossipMessage<H> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		match *self {
+			GossipMessage::Vote(ref vote) => {
+				dest.push_byte(0);
+				dest.push(vote);
+			}
+			GossipMessage::Commit(ref commit) => {
+				dest.push_byte(1);
+				dest.push(commit);
+			}
+		}
+	}
+}
+
+impl<H: Decode> Decode for GossipMessage<H> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		match input.read_byte()? {
+			0 => Some(GossipMessage::Vote(Decode::decode(input)?)),
+			1 => Some(GossipMessage::Commit(Decode::decode(input)?)),
+			_ => None,
+		}
+	}
+}
+
+// The message signed by a voter.
+fn vote_payload<H: Encode>(set_id: SetId, round: RoundNumber, stage: Stage, hash: &H) -> Vec<u8> {
+	let mut payload = b"fin".to_vec();
+	set_id.encode_to(&mut payload);
+	round.encode_to(&mut payload);
+	stage.encode_to(&mut payload);
+	hash.encode_to(&mut payload);
+	payload
+}
+
+impl<H: Encode> SignedVote<H> {
+	/// Sign a vote with the given key.
+	pub fn sign(key: &ed25519::Pair, set_id: SetId, round: RoundNumber, stage: Stage, hash: H) -> Self {
+		let signature = key.sign(&vote_payload(set_id, round, stage, &hash));
+		SignedVote {
+			set_id,
+			round,
+			stage,
+			hash,
+			voter: key.public().into(),
+			signature,
+		}
+	}
+
+	/// Whether the signature matches the voter.
+	pub fn check(&self) -> bool {
+		let payload = vote_payload(self.set_id, self.round, self.stage, &self.hash);
+		ed25519::verify_strong(&self.signature, &payload, ed25519::Public::from(self.voter))
+	}
+}
+
+/// The round a UNIX timestamp, in seconds, falls into.
+pub fn round_at(timestamp: u64, round_duration: u64) -> RoundNumber {
+	(timestamp / round_duration) as RoundNumber
+}
+
+/// The current round according to the local clock.
+pub fn round_now(round_duration: u64) -> Result<RoundNumber> {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH)
+		.map_err(|_| ErrorKind::ClockBeforeEpoch)?;
+	Ok(round_at(now.as_secs(), round_duration))
+}
+
+/// The gossip topic of the votes of a set in a round.
+pub fn round_topic<Block: BlockT>(set_id: SetId, round: RoundNumber) -> Block::Hash {
+	let mut topic = b"fin".to_vec();
+	set_id.encode_to(&mut topic);
+	round.encode_to(&mut topic);
+	HashFor::<Block>::hash(&topic)
+}
+
+/// The gossip topic of the commits of a set.
+pub fn commit_topic<Block: BlockT>(set_id: SetId) -> Block::Hash {
+	let mut topic = b"fin-commits".to_vec();
+	set_id.encode_to(&mut topic);
+	HashFor::<Block>::hash(&topic)
+}
+
+// The precommits of a commit which are correctly signed by the given voters, in a round on `base`.
+fn commit_round<H: Encode + Copy + Eq + ::std::hash::Hash>(commit: &Commit<H>, voters: &[AuthorityId], base: H) -> Round<H> {
+	let mut round = Round::new(voters.to_vec(), base);
+	for &(ref hash, ref voter, ref signature) in &commit.precommits {
+		let payload = vote_payload(commit.set_id, commit.round, Stage::Precommit, hash);
+		if ed25519::verify_strong(signature, &payload, ed25519::Public::from(*voter)) {
+			round.import_precommit(*voter, *hash, *signature);
+		}
+	}
+	round
+}
+
+/// Check that a commit is signed by more than two thirds of the given voters, the members of
+/// the set it claims to be from. Whether the precommits are for the committed block or its
+/// descendants is checked by `check_commit_on_chain`.
+pub fn check_commit<H: Encode + Copy + Eq + ::std::hash::Hash + ::std::fmt::Display>(
+	commit: &Commit<H>,
+	voters: &[AuthorityId],
+) -> Result<()> {
+	let round = commit_round(commit, voters, commit.hash);
+	if voters.is_empty() || round.precommit_count() < round::threshold(voters.len()) {
+		bail!(ErrorKind::BadCommit(format!("{}", commit.hash)));
+	}
+	Ok(())
+}
+
+/// Check that more than two thirds of the given voters precommitted to the block of a commit or
+/// to its descendants. Fails while any of those blocks is unknown.
+pub fn check_commit_on_chain<H, C>(commit: &Commit<H>, voters: &[AuthorityId], chain: &C) -> Result<()> where
+	H: Encode + Copy + Eq + ::std::hash::Hash + ::std::fmt::Display,
+	C: Chain<H>,
+{
+	let round = commit_round(commit, voters, commit.hash);
+	if voters.is_empty() || round.precommits_for(chain, &commit.hash).len() < round::threshold(voters.len()) {
+		bail!(ErrorKind::BadCommit(format!("{}", commit.hash)));
+	}
+	Ok(())
+}
+
+impl<B, E, Block> Chain<Block::Hash> for Client<B, E, Block> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	fn ancestry(&self, base: Block::Hash, block: Block::Hash) -> Option<Vec<Block::Hash>> {
+		let base_number = *self.header(&BlockId::Hash(base)).ok()??.number();
+		let mut ancestry = Vec::new();
+		let mut current = block;
+		while current != base {
+			let header = self.header(&BlockId::Hash(current)).ok()??;
+			if *header.number() <= base_number {
+				return None;
+			}
+			ancestry.push(current);
+			current = *header.parent_hash();
+		}
+		Some(ancestry)
+	}
+}
+
+/// The identifier of the authority set in charge of finalizing the children of a block.
+pub fn authority_set_id<B, E, Block>(client: &Client<B, E, Block>, id: &BlockId<Block>) -> Result<SetId> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	match client.storage(id, &StorageKey(AUTHORITY_SET_CHANGED.to_vec())) {
+		Ok(data) => NumberFor::<Block>::decode(&mut &data.0[..])
+			.map(|number| number.as_())
+			.ok_or_else(|| ErrorKind::BadSetId.into()),
+		// the genesis authorities never changed.
+		Err(e) => match *e.kind() {
+			client::error::ErrorKind::NoValueForKey(_) => Ok(0),
+			_ => Err(e.into()),
+		},
+	}
+}
+
+// The block to prevote for: the best block, or the locked block if the best chain doesn't
+// contain it. Unless a block between it and `base` changes the authority set: the first such
+// block must then be finalized by the current set first.
+fn vote_target<B, E, Block>(
+	client: &Client<B, E, Block>,
+	base: Block::Hash,
+	set_id: SetId,
+	lock: Option<Block::Hash>,
+) -> Result<Block::Hash> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	let best = client.best_block_header()?.hash();
+	let head = match lock {
+		Some(lock) if lock != best && client.ancestry(lock, best).is_none() => lock,
+		_ => best,
+	};
+	let ancestry = match client.ancestry(base, head) {
+		Some(ancestry) => ancestry,
+		None => return Ok(base),
+	};
+
+	for hash in ancestry.into_iter().rev() {
+		if authority_set_id(client, &BlockId::Hash(hash))? != set_id {
+			return Ok(hash);
+		}
+	}
+	Ok(head)
+}
+
+// Whether a block is unknown or above the last finalized block.
+fn is_unfinalized<B, E, Block>(client: &Client<B, E, Block>, hash: Block::Hash) -> Result<bool> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	let finalized_number = client.info()?.chain.finalized_number;
+	Ok(client.header(&BlockId::Hash(hash))?.map_or(true, |header| *header.number() > finalized_number))
+}
+
+// The lock stored by a previous run of the voter.
+fn stored_lock<B, E, Block>(client: &Client<B, E, Block>) -> Option<(RoundNumber, Block::Hash)> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	match client.backend().aux_storage(LOCK_KEY) {
+		Ok(raw) => raw.and_then(|raw| Option::<(RoundNumber, Block::Hash)>::decode(&mut &raw[..])).and_then(|lock| lock),
+		Err(e) => {
+			warn!(target: "finality", "Unable to read the lock: {}", e);
+			None
+		},
+	}
+}
+
+// Store the lock of the voter, so that it survives a restart.
+fn store_lock<B, E, Block>(client: &Client<B, E, Block>, lock: Option<(RoundNumber, Block::Hash)>) where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	if let Err(e) = client.backend().set_aux_storage(LOCK_KEY, &lock.encode()) {
+		warn!(target: "finality", "Unable to store the lock: {}", e);
+	}
+}
+
+// Finalize the block of a checked commit, storing the commit as the proof. Fails while the
+// block is not imported yet.
+fn apply_commit<B, E, Block>(client: &Client<B, E, Block>, commit: &Commit<Block::Hash>) -> bool where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	match client.finalize_block(BlockId::Hash(commit.hash), Some(commit.encode())) {
+		Ok(()) => {
+			info!(target: "finality", "Finalized block {} in round {}", commit.hash, commit.round);
+			true
+		},
+		Err(e) => {
+			debug!(target: "finality", "Unable to finalize block {}: {}", commit.hash, e);
+			false
+		},
+	}
+}
+
+struct ActiveRound<H, S> {
+	number: RoundNumber,
+	set_id: SetId,
+	votes: Round<H>,
+	incoming: S,
+	prevoted: bool,
+	precommitted: bool,
+	committed: bool,
+}
+
+struct Voter<B, E, Block: BlockT, N: Network<Block>> {
+	client: Arc<Client<B, E, Block>>,
+	network: N,
+	key: Option<Arc<ed25519::Pair>>,
+	round_duration: u64,
+	round: Option<ActiveRound<Block::Hash, N::In>>,
+	// the commits gossiped for the current set.
+	commits: Option<(SetId, N::In)>,
+	// commits signed by the current set whose blocks are not all imported yet.
+	pending_commits: Vec<Commit<Block::Hash>>,
+	// the round of the last precommit and the block it was for, until the lock is released.
+	lock: Option<(RoundNumber, Block::Hash)>,
+}
+
+impl<B, E, Block, N> Voter<B, E, Block, N> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+	N: Network<Block>,
+{
+	fn new(client: Arc<Client<B, E, Block>>, network: N, key: Option<Arc<ed25519::Pair>>, round_duration: u64) -> Self {
+		let lock = stored_lock(&*client);
+		Voter {
+			client,
+			network,
+			key,
+			round_duration,
+			round: None,
+			commits: None,
+			pending_commits: Vec::new(),
+			lock,
+		}
+	}
+
+	fn tick(&mut self) -> Result<()> {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH)
+			.map_err(|_| ErrorKind::ClockBeforeEpoch)?
+			.as_secs();
+		self.tick_at(now)
+	}
+
+	fn tick_at(&mut self, now: u64) -> Result<()> {
+		let number = round_at(now, self.round_duration);
+		if self.round.as_ref().map_or(true, |round| round.number != number) {
+			self.start_round(number)?;
+		}
+
+		let round = self.round.as_mut().expect("round started above; qed");
+		while let Ok(Async::Ready(Some(raw))) = round.incoming.poll() {
+			if let Some(GossipMessage::Vote(vote)) = GossipMessage::decode(&mut &raw[..]) {
+				import_vote(round, vote);
+			}
+		}
+
+		if let Some((_, ref mut commits)) = self.commits {
+			while let Ok(Async::Ready(Some(raw))) = commits.poll() {
+				let commit = match GossipMessage::decode(&mut &raw[..]) {
+					Some(GossipMessage::Commit(commit)) => commit,
+					_ => continue,
+				};
+				if commit.set_id != round.set_id || !is_unfinalized(&*self.client, commit.hash)? {
+					continue;
+				}
+				match check_commit(&commit, round.votes.voters()) {
+					Ok(()) => if !self.pending_commits.contains(&commit) {
+						self.pending_commits.push(commit);
+					},
+					Err(e) => debug!(target: "finality", "Ignoring commit for {}: {}", commit.hash, e),
+				}
+			}
+		}
+
+		// a lock is released once the prevote-GHOST of a later round doesn't contain its block.
+		let client = &*self.client;
+		let lock_released = match self.lock {
+			Some((lock_round, lock)) if lock_round < round.number => round.votes.prevote_ghost(client)
+				.map_or(false, |ghost| client.ancestry(lock, ghost).is_none()),
+			_ => false,
+		};
+		if lock_released {
+			self.lock = None;
+			store_lock(&*self.client, None);
+		}
+
+		let key = match self.key {
+			Some(ref key) if round.votes.is_voter(&key.public().into()) => Some(key),
+			_ => None,
+		};
+		if let Some(key) = key {
+			if !round.prevoted {
+				let lock = self.lock.map(|(_, lock)| lock);
+				let target = vote_target(&*self.client, *round.votes.base(), round.set_id, lock)?;
+				let vote = SignedVote::sign(key, round.set_id, round.number, Stage::Prevote, target);
+				round.prevoted = true;
+				cast_vote(&self.network, round, vote);
+			}
+
+			// precommit halfway through the round, once prevotes had time to spread.
+			let precommit_due = (now % self.round_duration) * 2 >= self.round_duration;
+			if !round.precommitted && precommit_due {
+				if let Some(ghost) = round.votes.prevote_ghost(&*self.client) {
+					let vote = SignedVote::sign(key, round.set_id, round.number, Stage::Precommit, ghost);
+					round.precommitted = true;
+					self.lock = Some((round.number, ghost));
+					store_lock(&*self.client, self.lock);
+					cast_vote(&self.network, round, vote);
+				}
+			}
+		}
+
+		// the block may not have been imported yet; retry on the next tick then.
+		if !round.committed {
+			if let Some((hash, precommits)) = round.votes.commit(&*self.client) {
+				let commit = Commit { set_id: round.set_id, round: round.number, hash, precommits };
+				if apply_commit(&*self.client, &commit) {
+					round.committed = true;
+					// let nodes which missed the round's votes finalize too.
+					let message = GossipMessage::Commit(commit).encode();
+					self.network.send_message(commit_topic::<Block>(round.set_id), message);
+				}
+			}
+		}
+
+		for commit in ::std::mem::replace(&mut self.pending_commits, Vec::new()) {
+			if !is_unfinalized(&*self.client, commit.hash)? {
+				continue;
+			}
+			let applied = check_commit_on_chain(&commit, round.votes.voters(), &*self.client).is_ok()
+				&& apply_commit(&*self.client, &commit);
+			if !applied {
+				self.pending_commits.push(commit);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn start_round(&mut self, number: RoundNumber) -> Result<()> {
+		let base = self.client.info()?.chain.finalized_hash;
+		let set_id = authority_set_id(&*self.client, &BlockId::Hash(base))?;
+		let voters = self.client.authorities_at(&BlockId::Hash(base))?;
+		trace!(target: "finality", "Starting round {} of set {} on {}", number, set_id, base);
+
+		// a lock is released once its block, or a conflicting one, is final.
+		let lock_released = match self.lock {
+			Some((_, lock)) => self.client.ancestry(base, lock).map_or(true, |ancestry| ancestry.is_empty()),
+			None => false,
+		};
+		if lock_released {
+			self.lock = None;
+			store_lock(&*self.client, None);
+		}
+
+		if self.commits.as_ref().map_or(true, |&(id, _)| id != set_id) {
+			self.commits = Some((set_id, self.network.messages_for(commit_topic::<Block>(set_id))));
+			self.pending_commits.clear();
+		}
+		self.network.note_voters(set_id, voters.clone());
+
+		self.round = Some(ActiveRound {
+			number,
+			set_id,
+			votes: Round::new(voters, base),
+			incoming: self.network.messages_for(round_topic::<Block>(set_id, number)),
+			prevoted: false,
+			precommitted: false,
+			committed: false,
+		});
+		Ok(())
+	}
+}
+
+fn import_vote<H, S>(round: &mut ActiveRound<H, S>, vote: SignedVote<H>) where
+	H: Encode + ::std::hash::Hash + Eq + Clone,
+{
+	if vote.set_id != round.set_id || vote.round != round.number || !vote.check() {
+		return;
+	}
+	let counted = match vote.stage {
+		Stage::Prevote => round.votes.import_prevote(vote.voter, vote.hash),
+		Stage::Precommit => round.votes.import_precommit(vote.voter, vote.hash, vote.signature),
+	};
+	if !counted {
+		trace!(target: "finality", "Ignoring vote of {:?} in round {}", vote.voter, round.number);
+	}
+}
+
+fn cast_vote<Block, N>(network: &N, round: &mut ActiveRound<Block::Hash, N::In>, vote: SignedVote<Block::Hash>) where
+	Block: BlockT,
+	N: Network<Block>,
+{
+	debug!(target: "finality", "Casting {:?} for {} in round {}", vote.stage, vote.hash, vote.round);
+	let message = GossipMessage::Vote(vote.clone()).encode();
+	network.send_message(round_topic::<Block>(vote.set_id, vote.round), message);
+	import_vote(round, vote);
+}
+
+/// Start taking part in finality rounds of `round_duration` seconds. With a key, votes are
+/// cast whenever it belongs to the current authority set; without one, blocks are only
+/// finalized as commits are observed.
+///
+/// The returned future resolves once `exit` does.
+pub fn start_finality<B, E, Block, N, X>(
+	client: Arc<Client<B, E, Block>>,
+	network: N,
+	key: Option<Arc<ed25519::Pair>>,
+	round_duration: u64,
+	exit: X,
+) -> impl Future<Item=(), Error=()> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+	N: Network<Block>,
+	X: Future<Item=(), Error=()>,
+{
+	let mut voter = Voter::new(client, network, key, round_duration);
+
+	// tick often enough for votes to be handled promptly.
+	Interval::new(Instant::now(), Duration::from_millis(500))
+		.map_err(|e| warn!(target: "finality", "Faulty timer: {}", e))
+		.for_each(move |_| {
+			if let Err(e) = voter.tick() {
+				warn!(target: "finality", "Error in finality round: {}", e);
+			}
+			Ok(())
+		})
+		.select(exit)
+		.then(|_| Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use futures::future;
+	use futures::sync::mpsc;
+	use keyring::Keyring;
+	use parking_lot::Mutex;
+	use primitives::H256;
+	use test_client::{self, BlockBuilderExt, TestClient};
+	use test_client::client::BlockOrigin;
+	use test_client::runtime::{Block, Hash, Transfer};
+
+	fn voters() -> Vec<AuthorityId> {
+		vec![
+			Keyring::Alice.to_raw_public().into(),
+			Keyring::Bob.to_raw_public().into(),
+			Keyring::Charlie.to_raw_public().into(),
+			Keyring::Dave.to_raw_public().into(),
+		]
+	}
+
+	#[derive(Default)]
+	struct Gossip {
+		history: Vec<(Hash, Vec<u8>)>,
+		sinks: Vec<(Hash, mpsc::UnboundedSender<Vec<u8>>)>,
+	}
+
+	// Gossip delivering every message to every voter, including those subscribing later.
+	#[derive(Clone, Default)]
+	struct TestNetwork(Arc<Mutex<Gossip>>);
+
+	impl Network<Block> for TestNetwork {
+		type In = mpsc::UnboundedReceiver<Vec<u8>>;
+
+		fn messages_for(&self, topic: Hash) -> Self::In {
+			let mut gossip = self.0.lock();
+			let (sink, stream) = mpsc::unbounded();
+			for &(_, ref message) in gossip.history.iter().filter(|&&(t, _)| t == topic) {
+				let _ = sink.unbounded_send(message.clone());
+			}
+			gossip.sinks.push((topic, sink));
+			stream
+		}
+
+		fn send_message(&self, topic: Hash, message: Vec<u8>) {
+			let mut gossip = self.0.lock();
+			for &(_, ref sink) in gossip.sinks.iter().filter(|&&(t, _)| t == topic) {
+				let _ = sink.unbounded_send(message.clone());
+			}
+			gossip.history.push((topic, message));
+		}
+
+		fn note_voters(&self, _set_id: SetId, _voters: Vec<AuthorityId>) {}
+	}
+
+	type TestVoter = Voter<test_client::Backend, test_client::Executor, Block, TestNetwork>;
+
+	fn client_with_blocks(blocks: &[test_client::runtime::Block]) -> Arc<test_client::client::Client<test_client::Backend, test_client::Executor, Block>> {
+		let client = test_client::new();
+		for block in blocks {
+			client.justify_and_import(BlockOrigin::File, block.clone()).unwrap();
+		}
+		Arc::new(client)
+	}
+
+	fn tick_all(voters: &mut [TestVoter], now: u64) {
+		future::lazy(|| {
+			for voter in voters.iter_mut() {
+				voter.tick_at(now).unwrap();
+			}
+			Ok::<(), ()>(())
+		}).wait().unwrap();
+	}
+
+	#[test]
+	fn signed_vote_should_check_and_roundtrip() {
+		let vote = SignedVote::sign(&Keyring::Alice.pair(), 3, 7, Stage::Precommit, H256::from([1; 32]));
+		assert!(vote.check());
+		assert_eq!(SignedVote::decode(&mut &vote.encode()[..]), Some(vote.clone()));
+
+		let mut other_round = vote;
+		other_round.round = 8;
+		assert!(!other_round.check());
+	}
+
+	#[test]
+	fn commit_of_supermajority_should_check() {
+		let hash = H256::from([1; 32]);
+		let precommit = |who: Keyring| {
+			let vote = SignedVote::sign(&who.pair(), 3, 7, Stage::Precommit, hash);
+			(hash, vote.voter, vote.signature)
+		};
+		let mut commit = Commit {
+			set_id: 3,
+			round: 7,
+			hash,
+			precommits: vec![precommit(Keyring::Alice), precommit(Keyring::Bob)],
+		};
+		assert!(check_commit(&commit, &voters()).is_err());
+
+		commit.precommits.push(precommit(Keyring::Eve));
+		assert!(check_commit(&commit, &voters()).is_err());
+
+		commit.precommits.push(precommit(Keyring::Charlie));
+		assert!(check_commit(&commit, &voters()).is_ok());
+		assert_eq!(Commit::decode(&mut &commit.encode()[..]), Some(commit.clone()));
+
+		commit.set_id = 4;
+		assert!(check_commit(&commit, &voters()).is_err());
+	}
+
+	#[test]
+	fn locked_voter_should_prevote_for_lock() {
+		let client = test_client::new();
+		let genesis = client.genesis_hash();
+		let best = client.new_block().unwrap().bake().unwrap();
+		client.justify_and_import(BlockOrigin::File, best.clone()).unwrap();
+
+		let mut fork = client.new_block_at(&BlockId::Hash(genesis)).unwrap();
+		fork.push_transfer(Transfer {
+			from: Keyring::Alice.to_raw_public().into(),
+			to: Keyring::Bob.to_raw_public().into(),
+			amount: 1,
+			nonce: 0,
+		}).unwrap();
+		let fork = fork.bake().unwrap();
+		client.justify_and_import(BlockOrigin::File, fork.clone()).unwrap();
+
+		assert_eq!(client.best_block_header().unwrap().hash(), best.header.hash());
+		assert_eq!(vote_target(&client, genesis, 0, None).unwrap(), best.header.hash());
+		assert_eq!(vote_target(&client, genesis, 0, Some(fork.header.hash())).unwrap(), fork.header.hash());
+		assert_eq!(vote_target(&client, genesis, 0, Some(genesis)).unwrap(), best.header.hash());
+	}
+
+	#[test]
+	fn lock_should_survive_restart_until_released_by_later_ghost() {
+		let builder = test_client::new();
+		let genesis = builder.genesis_hash();
+		let best = builder.new_block().unwrap().bake().unwrap();
+		builder.justify_and_import(BlockOrigin::File, best.clone()).unwrap();
+		let mut fork = builder.new_block_at(&BlockId::Hash(genesis)).unwrap();
+		fork.push_transfer(Transfer {
+			from: Keyring::Alice.to_raw_public().into(),
+			to: Keyring::Bob.to_raw_public().into(),
+			amount: 1,
+			nonce: 0,
+		}).unwrap();
+		let fork = fork.bake().unwrap();
+		let blocks = [best, fork.clone()];
+
+		let network = TestNetwork::default();
+		let mut nodes: Vec<TestVoter> = [Keyring::Alice, Keyring::Bob, Keyring::Charlie].iter()
+			.map(|who| {
+				let client = client_with_blocks(&blocks);
+				if *who == Keyring::Alice {
+					store_lock(&*client, Some((3, fork.header.hash())));
+				}
+				Voter::new(client, network.clone(), Some(Arc::new(who.pair())), 10)
+			})
+			.collect();
+		assert_eq!(nodes[0].lock, Some((3, fork.header.hash())));
+
+		// Alice prevotes for her lock, the others for the best block: the GHOST is the genesis.
+		tick_all(&mut nodes, 100);
+		assert_eq!(nodes[0].lock, Some((3, fork.header.hash())));
+		tick_all(&mut nodes, 100);
+		assert_eq!(nodes[0].round.as_ref().unwrap().votes.prevote_ghost(&*nodes[0].client), Some(genesis));
+		assert_eq!(nodes[0].lock, None);
+		assert_eq!(stored_lock(&*nodes[0].client), None);
+	}
+
+	#[test]
+	fn voters_should_finalize_best_block() {
+		let builder = test_client::new();
+		let mut blocks = Vec::new();
+		for _ in 0..3 {
+			let block = builder.new_block().unwrap().bake().unwrap();
+			builder.justify_and_import(BlockOrigin::File, block.clone()).unwrap();
+			blocks.push(block);
+		}
+		let best = blocks[2].header.hash();
+
+		let network = TestNetwork::default();
+		let mut nodes: Vec<TestVoter> = [Keyring::Alice, Keyring::Bob, Keyring::Charlie].iter()
+			.map(|who| Voter::new(client_with_blocks(&blocks), network.clone(), Some(Arc::new(who.pair())), 10))
+			.collect();
+
+		// prevote at the start of round 10, precommit halfway through.
+		tick_all(&mut nodes, 100);
+		tick_all(&mut nodes, 105);
+		tick_all(&mut nodes, 105);
+
+		let mut rounds = HashMap::new();
+		for voter in &nodes {
+			let info = voter.client.info().unwrap().chain;
+			assert_eq!(info.finalized_number, 3);
+			assert_eq!(info.finalized_hash, best);
+
+			let proof = voter.client.finality_proof(&BlockId::Number(3)).unwrap().unwrap();
+			let commit = Commit::<Hash>::decode(&mut &proof[..]).unwrap();
+			assert!(check_commit(&commit, &voters()[..3]).is_ok());
+			rounds.insert(commit.round, commit.hash);
+		}
+		assert_eq!(rounds.into_iter().collect::<Vec<_>>(), vec![(10, best)]);
+
+		// a node without a key which missed the round finalizes from the gossiped commit.
+		let mut observer = vec![Voter::new(client_with_blocks(&blocks), network.clone(), None, 10)];
+		tick_all(&mut observer, 115);
+		assert_eq!(observer[0].client.info().unwrap().chain.finalized_hash, best);
+		assert!(observer[0].client.finality_proof(&BlockId::Number(3)).unwrap().is_some());
+	}
+}
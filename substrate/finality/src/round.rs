@@ -0,0 +1,271 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tallying of the votes cast in a single round.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use ed25519::Signature;
+use primitives::AuthorityId;
+
+/// Ancestry lookups, so that a vote for a block counts for its ancestors too.
+pub trait Chain<H> {
+	/// The blocks from `block` back to, but excluding, `base`, starting with `block` itself.
+	/// `None` if `block` is unknown or does not descend from `base`.
+	fn ancestry(&self, base: H, block: H) -> Option<Vec<H>>;
+}
+
+/// The number of votes out of `n` which make up more than two thirds.
+pub fn threshold(n: usize) -> usize {
+	n - n.saturating_sub(1) / 3
+}
+
+/// Votes cast in a round by a fixed set of voters, on blocks descending from the last
+/// finalized block. Only the first prevote and precommit of each voter count.
+pub struct Round<H> {
+	voters: Vec<AuthorityId>,
+	base: H,
+	prevotes: HashMap<AuthorityId, H>,
+	precommits: HashMap<AuthorityId, (H, Signature)>,
+}
+
+impl<H: Hash + Eq + Clone> Round<H> {
+	/// Create a round of the given voters, voting on descendants of `base`.
+	pub fn new(voters: Vec<AuthorityId>, base: H) -> Self {
+		Round {
+			voters,
+			base,
+			prevotes: HashMap::new(),
+			precommits: HashMap::new(),
+		}
+	}
+
+	/// The block finalized when the round started.
+	pub fn base(&self) -> &H {
+		&self.base
+	}
+
+	/// The authorities voting in this round.
+	pub fn voters(&self) -> &[AuthorityId] {
+		&self.voters
+	}
+
+	/// Whether the given authority may vote in this round.
+	pub fn is_voter(&self, who: &AuthorityId) -> bool {
+		self.voters.contains(who)
+	}
+
+	/// Import a prevote. Returns whether it was counted.
+	pub fn import_prevote(&mut self, voter: AuthorityId, hash: H) -> bool {
+		if !self.is_voter(&voter) || self.prevotes.contains_key(&voter) {
+			return false;
+		}
+		self.prevotes.insert(voter, hash);
+		true
+	}
+
+	/// Import a signed precommit. Returns whether it was counted.
+	pub fn import_precommit(&mut self, voter: AuthorityId, hash: H, signature: Signature) -> bool {
+		if !self.is_voter(&voter) || self.precommits.contains_key(&voter) {
+			return false;
+		}
+		self.precommits.insert(voter, (hash, signature));
+		true
+	}
+
+	/// The number of voters which precommitted.
+	pub fn precommit_count(&self) -> usize {
+		self.precommits.len()
+	}
+
+	/// The prevote-GHOST: the deepest block which more than two thirds of the voters prevoted
+	/// for or for a descendant of. The base if no block beyond it has that much support; `None`
+	/// until more than two thirds of the prevotes are on known descendants of the base.
+	pub fn prevote_ghost<C: Chain<H>>(&self, chain: &C) -> Option<H> {
+		let (counted, ghost) = self.deepest_supported(chain, self.prevotes.values())?;
+		if counted < threshold(self.voters.len()) {
+			return None;
+		}
+		Some(ghost.unwrap_or_else(|| self.base.clone()))
+	}
+
+	/// The block this round commits to, with the precommits justifying it: the deepest block
+	/// which more than two thirds of the voters precommitted to or to a descendant of. `None`
+	/// if there is no such block beyond the base yet.
+	pub fn commit<C: Chain<H>>(&self, chain: &C) -> Option<(H, Vec<(H, AuthorityId, Signature)>)> {
+		let (_, committed) = self.deepest_supported(chain, self.precommits.values().map(|&(ref hash, _)| hash))?;
+		let committed = committed?;
+		let precommits = self.precommits_for(chain, &committed);
+		Some((committed, precommits))
+	}
+
+	/// The precommits for `block` or for its descendants.
+	pub fn precommits_for<C: Chain<H>>(&self, chain: &C, block: &H) -> Vec<(H, AuthorityId, Signature)> {
+		self.precommits.iter()
+			.filter(|&(_, &(ref hash, _))| chain.ancestry(block.clone(), hash.clone()).is_some())
+			.map(|(voter, &(ref hash, ref signature))| (hash.clone(), *voter, *signature))
+			.collect()
+	}
+
+	// The number of votes on known descendants of the base, and the deepest block beyond the
+	// base which more than two thirds of the voters voted for or for a descendant of.
+	fn deepest_supported<'a, C, I>(&self, chain: &C, votes: I) -> Option<(usize, Option<H>)> where
+		C: Chain<H>,
+		I: Iterator<Item=&'a H>,
+		H: 'a,
+	{
+		if self.voters.is_empty() {
+			return None;
+		}
+		let threshold = threshold(self.voters.len());
+
+		let mut counted = 0;
+		// weight and depth of every block voted for, directly or through a descendant.
+		let mut support: HashMap<H, (usize, usize)> = HashMap::new();
+		for hash in votes {
+			let ancestry = match chain.ancestry(self.base.clone(), hash.clone()) {
+				Some(ancestry) => ancestry,
+				None => continue,
+			};
+			counted += 1;
+
+			let depth = ancestry.len();
+			for (i, block) in ancestry.into_iter().enumerate() {
+				support.entry(block).or_insert((0, depth - i)).0 += 1;
+			}
+		}
+
+		// two blocks on different forks can't both have a supermajority, so this is unique.
+		let deepest = support.into_iter()
+			.filter(|&(_, (weight, _))| weight >= threshold)
+			.max_by_key(|&(_, (_, depth))| depth)
+			.map(|(hash, _)| hash);
+		Some((counted, deepest))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// blocks are numbered; the map points each block to its parent.
+	struct TestChain(HashMap<u32, u32>);
+
+	impl TestChain {
+		// 0 <- 1 <- 2 <- 3
+		//             \- 4
+		fn new() -> Self {
+			TestChain(vec![(1, 0), (2, 1), (3, 2), (4, 2)].into_iter().collect())
+		}
+	}
+
+	impl Chain<u32> for TestChain {
+		fn ancestry(&self, base: u32, mut block: u32) -> Option<Vec<u32>> {
+			let mut ancestry = Vec::new();
+			while block != base {
+				ancestry.push(block);
+				block = *self.0.get(&block)?;
+			}
+			Some(ancestry)
+		}
+	}
+
+	fn voters(n: u8) -> Vec<AuthorityId> {
+		(0..n).map(|i| [i; 32].into()).collect()
+	}
+
+	#[test]
+	fn threshold_is_more_than_two_thirds() {
+		assert_eq!(threshold(1), 1);
+		assert_eq!(threshold(3), 3);
+		assert_eq!(threshold(4), 3);
+		assert_eq!(threshold(7), 5);
+		assert_eq!(threshold(10), 7);
+	}
+
+	#[test]
+	fn ghost_counts_votes_for_descendants() {
+		let voters = voters(4);
+		let mut round = Round::new(voters.clone(), 0);
+
+		round.import_prevote(voters[0], 3);
+		round.import_prevote(voters[1], 3);
+		assert_eq!(round.prevote_ghost(&TestChain::new()), None);
+
+		round.import_prevote(voters[2], 4);
+		assert_eq!(round.prevote_ghost(&TestChain::new()), Some(2));
+
+		round.import_prevote(voters[3], 3);
+		assert_eq!(round.prevote_ghost(&TestChain::new()), Some(3));
+	}
+
+	#[test]
+	fn ghost_falls_back_to_base() {
+		let voters = voters(3);
+		let mut round = Round::new(voters.clone(), 1);
+
+		round.import_prevote(voters[0], 3);
+		round.import_prevote(voters[1], 4);
+		round.import_prevote(voters[2], 1);
+		assert_eq!(round.prevote_ghost(&TestChain::new()), Some(1));
+	}
+
+	#[test]
+	fn only_first_vote_of_voters_counts() {
+		let voters = voters(4);
+		let mut round = Round::new(voters.clone(), 0);
+
+		assert!(round.import_prevote(voters[0], 3));
+		assert!(!round.import_prevote(voters[0], 4));
+		assert!(!round.import_prevote([9; 32].into(), 4));
+		assert!(round.import_prevote(voters[1], 4));
+		assert!(round.import_prevote(voters[2], 4));
+		assert_eq!(round.prevote_ghost(&TestChain::new()), Some(2));
+	}
+
+	#[test]
+	fn commit_needs_supermajority() {
+		let voters = voters(4);
+		let mut round = Round::new(voters.clone(), 0);
+
+		round.import_precommit(voters[0], 3, Default::default());
+		round.import_precommit(voters[1], 4, Default::default());
+		assert!(round.commit(&TestChain::new()).is_none());
+
+		round.import_precommit(voters[2], 0, Default::default());
+		assert!(round.commit(&TestChain::new()).is_none());
+	}
+
+	#[test]
+	fn commit_counts_precommits_for_descendants() {
+		let voters = voters(4);
+		let mut round = Round::new(voters.clone(), 0);
+
+		round.import_precommit(voters[0], 1, Default::default());
+		round.import_precommit(voters[1], 2, Default::default());
+		round.import_precommit(voters[2], 3, Default::default());
+		let (hash, precommits) = round.commit(&TestChain::new()).unwrap();
+		assert_eq!(hash, 1);
+		assert_eq!(precommits.len(), 3);
+
+		round.import_precommit(voters[3], 4, Default::default());
+		let (hash, mut precommits) = round.commit(&TestChain::new()).unwrap();
+		assert_eq!(hash, 2);
+		precommits.sort_by_key(|&(hash, _, _)| hash);
+		assert_eq!(precommits.into_iter().map(|(hash, _, _)| hash).collect::<Vec<_>>(), vec![2, 3, 4]);
+	}
+}
@@ -19,6 +19,7 @@
 
 extern crate parking_lot;
 extern crate fnv;
+extern crate get_if_addrs;
 extern crate futures;
 extern crate tokio;
 extern crate tokio_io;
@@ -25,7 +25,7 @@ use libp2p::peerstore::json_peerstore::JsonPeerstore;
 use libp2p::peerstore::memory_peerstore::MemoryPeerstore;
 use libp2p::ping::Pinger;
 use libp2p::secio;
-use {Error, ErrorKind, NetworkConfiguration, NonReservedPeerMode};
+use {Error, ErrorKind, NetworkConfiguration, NonReservedPeerMode, SentryMode};
 use {NodeIndex, ProtocolId, SessionInfo};
 use parking_lot::{Mutex, RwLock};
 use rand::{self, Rng};
@@ -60,6 +60,9 @@ pub struct NetworkState {
 	reserved_only: atomic::AtomicBool,
 	/// List of the IDs of the reserved peers.
 	reserved_peers: RwLock<FnvHashSet<PeerId>>,
+	/// List of the IDs of the validators we are a sentry for. Their addresses
+	/// must never be revealed to other nodes.
+	hidden_peers: FnvHashSet<PeerId>,
 
 	/// Each node we discover gets assigned a new unique ID. This ID increases linearly.
 	next_node_index: atomic::AtomicUsize,
@@ -219,6 +222,28 @@ impl NetworkState {
 			RwLock::new(reserved_peers)
 		};
 
+		// Sentries and the validators behind them are always reserved peers
+		// of each other.
+		let mut hidden_peers = FnvHashSet::default();
+		match config.sentry_mode {
+			SentryMode::Disabled => {},
+			SentryMode::Validator(ref sentries) =>
+				for sentry in sentries.iter() {
+					let id = parse_and_add_to_node_store(sentry, &node_store)?;
+					reserved_peers.write().insert(id);
+				},
+			SentryMode::Sentry(ref validators) =>
+				for validator in validators.iter() {
+					let id = parse_and_add_to_node_store(validator, &node_store)?;
+					reserved_peers.write().insert(id.clone());
+					hidden_peers.insert(id);
+				},
+		}
+
+		// A validator behind sentries doesn't accept any other peer.
+		let reserved_only = config.non_reserved_mode == NonReservedPeerMode::Deny ||
+			config.sentry_mode.is_validator();
+
 		let expected_max_peers = config.max_peers as usize + config.reserved_nodes.len();
 
 		Ok(NetworkState {
@@ -229,8 +254,9 @@ impl NetworkState {
 				peer_by_nodeid: FnvHashMap::with_capacity_and_hasher(expected_max_peers, Default::default()),
 				info_by_peer: FnvHashMap::with_capacity_and_hasher(expected_max_peers, Default::default()),
 			}),
-			reserved_only: atomic::AtomicBool::new(false),
+			reserved_only: atomic::AtomicBool::new(reserved_only),
 			reserved_peers,
+			hidden_peers,
 			next_node_index: atomic::AtomicUsize::new(0),
 			disabled_nodes: Mutex::new(Default::default()),
 			local_private_key,
@@ -281,6 +307,22 @@ impl NetworkState {
 		}
 	}
 
+	/// Returns the list of reserved peers we are not connected to.
+	pub fn disconnected_reserved_peers(&self) -> Vec<PeerId> {
+		let connections = self.connections.read();
+		self.reserved_peers.read()
+			.iter()
+			.filter(|id| !connections.peer_by_nodeid.contains_key(id))
+			.cloned()
+			.collect()
+	}
+
+	/// Returns true if the given peer is a validator we are a sentry for.
+	/// Such peers must never be advertised to other nodes.
+	pub fn is_hidden_peer(&self, node_id: &PeerId) -> bool {
+		self.hidden_peers.contains(node_id)
+	}
+
 	/// Returns true if we are connected to any peer at all.
 	pub fn has_connected_peer(&self) -> bool {
 		!self.connections.read().peer_by_nodeid.is_empty()
@@ -25,12 +25,14 @@ use libp2p::kad::{KadIncomingRequest, KadConnecController, KadPeer};
 use libp2p::kad::{KadConnectionType, KadQueryEvent};
 use libp2p::identify::{IdentifyInfo, IdentifyOutput, IdentifyTransportOutcome};
 use libp2p::identify::{IdentifyProtocolConfig, PeerIdTransport};
+use libp2p::mdns::{MdnsService, MdnsPacket};
 use libp2p::core::{upgrade, Transport, MuxedTransport, ConnectionUpgrade};
 use libp2p::core::{Endpoint, PeerId as PeerstorePeerId, PublicKey};
 use libp2p::core::{SwarmController, UniqueConnecState};
 use libp2p::ping;
 use libp2p::transport_timeout::TransportTimeout;
 use {PacketId, SessionInfo, ConnectionFilter, TimerToken};
+use get_if_addrs;
 use rand;
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use std::iter;
@@ -39,7 +41,7 @@ use std::sync::Arc;
 use std::sync::mpsc as sync_mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
-use futures::{future, Future, Stream, IntoFuture};
+use futures::{future, Async, Future, Stream, IntoFuture};
 use futures::sync::{mpsc, oneshot};
 use tokio::runtime::current_thread;
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -123,7 +125,13 @@ impl NetworkService {
 			local_peer_id: local_peer_id.clone(),
 			kbuckets_timeout: Duration::from_secs(600),
 			request_timeout: Duration::from_secs(10),
-			known_initial_peers: network_state.known_peers(),
+			known_initial_peers: {
+				// Never hand the validators we are a sentry for to Kademlia.
+				let known_peers = network_state.known_peers()
+					.filter(|who| !network_state.is_hidden_peer(who))
+					.collect::<Vec<_>>();
+				known_peers.into_iter()
+			},
 		});
 
 		// Channel we use to signal success or failure of the bg thread
@@ -461,7 +469,13 @@ fn init_thread(
 		}
 	}
 	// Explicitely connect to _all_ the boostrap nodes as a temporary measure.
-	for bootnode in shared.config.boot_nodes.iter() {
+	// A validator behind sentries only connects to its sentries.
+	let boot_nodes = if shared.config.sentry_mode.is_validator() {
+		&[][..]
+	} else {
+		&shared.config.boot_nodes[..]
+	};
+	for bootnode in boot_nodes.iter() {
 		match shared.network_state.add_peer(bootnode) {
 			Ok(who) => {
 				trace!(target: "sub-libp2p", "Dialing bootnode {:?}", who);
@@ -531,8 +545,23 @@ fn init_thread(
 		});
 
 	// Start the process of periodically discovering nodes to connect to.
-	let discovery = start_kademlia_discovery(shared.clone(),
-		transport.clone(), swarm_controller.clone());
+	// A validator behind sentries only ever talks to its sentries and doesn't
+	// take part in discovery.
+	let discovery = if !shared.config.sentry_mode.is_validator() {
+		future::Either::A(start_kademlia_discovery(shared.clone(),
+			transport.clone(), swarm_controller.clone()))
+	} else {
+		debug!(target: "sub-libp2p", "Running behind sentry nodes ; discovery disabled");
+		future::Either::B(future::empty())
+	};
+
+	// Start the process of discovering nodes on the local network, if enabled.
+	let mdns = if shared.config.enable_mdns && !shared.config.sentry_mode.is_validator() {
+		future::Either::A(start_mdns_discovery(shared.clone(),
+			transport.clone(), swarm_controller.clone()))
+	} else {
+		future::Either::B(future::empty())
+	};
 
 	// Start the process of pinging the active nodes on the network.
 	let pinger = start_pinger(shared.clone(), transport, swarm_controller);
@@ -540,6 +569,7 @@ fn init_thread(
 	// Merge all the futures into one!
 	Ok(swarm_future
 		.select(discovery).map_err(|(err, _)| err).and_then(|(_, rest)| rest)
+		.select(mdns).map_err(|(err, _)| err).and_then(|(_, rest)| rest)
 		.select(pinger).map_err(|(err, _)| err).and_then(|(_, rest)| rest)
 		.select(timeouts).map_err(|(err, _)| err).and_then(|(_, rest)| rest)
 		.select(close_rx.then(|_| Ok(()))).map(|_| ()).map_err(|(err, _)| err)
@@ -604,7 +634,7 @@ fn listener_handle<'a, C>(
 						env!("CARGO_PKG_VERSION")).to_owned(),		// TODO: ?
 					agent_version: concat!("substrate/",
 						env!("CARGO_PKG_VERSION")).to_owned(),
-					listen_addrs: shared.listened_addrs.read().clone(),
+					listen_addrs: advertised_addrs(&shared),
 					protocols: Vec::new(),		// TODO: protocols_to_report,
 				},
 				&original_addr
@@ -696,9 +726,17 @@ fn build_kademlia_response(
 			if who == *shared.kad_system.local_peer_id() {
 				KadPeer {
 					node_id: who.clone(),
-					multiaddrs: shared.listened_addrs.read().clone(),
+					multiaddrs: advertised_addrs(shared),
 					connection_ty: KadConnectionType::Connected,
 				}
+			} else if shared.network_state.is_hidden_peer(&who) {
+				// Pretend we don't know anything about the validators we
+				// are a sentry for ; they are filtered out below.
+				KadPeer {
+					node_id: who.clone(),
+					multiaddrs: Vec::new(),
+					connection_ty: KadConnectionType::NotConnected,
+				}
 			} else {
 				let addrs = shared.network_state.addrs_of_peer(&who);
 				let connec_ty = if shared.network_state.has_connection(&who) {
@@ -726,6 +764,16 @@ fn build_kademlia_response(
 		.collect::<Vec<_>>()
 }
 
+/// Returns the addresses we advertise to other nodes through the identify
+/// and Kademlia protocols. Empty if we are a validator behind sentries.
+fn advertised_addrs(shared: &Shared) -> Vec<Multiaddr> {
+	if shared.config.sentry_mode.is_validator() {
+		Vec::new()
+	} else {
+		shared.listened_addrs.read().clone()
+	}
+}
+
 /// Handles a newly-opened connection to a remote with a custom protocol
 /// (eg. `/substrate/dot/0`).
 /// Returns a future that corresponds to when the handling is finished.
@@ -923,6 +971,125 @@ fn start_kademlia_discovery<T, To, St, C>(shared: Arc<Shared>, transport: T,
 	Box::new(final_future) as Box<Future<Item = _, Error = _>>
 }
 
+/// Discovers peers on the local network through mDNS.
+/// We answer the queries of other nodes with the addresses we listen on, and
+/// each response we receive adds the addresses of the remote to the peer store
+/// and triggers a new round of outgoing connections.
+/// Failing to start mDNS (eg. because multicast is unavailable) is not fatal;
+/// discovery then relies on Kademlia alone.
+fn start_mdns_discovery<T, To, St, C>(
+	shared: Arc<Shared>,
+	transport: T,
+	swarm_controller: SwarmController<St>
+) -> impl Future<Item = (), Error = IoError>
+	where T: MuxedTransport<Output =  TransportOutput<To>> + Clone + 'static,
+		T::MultiaddrFuture: 'static,
+		To: AsyncRead + AsyncWrite + 'static,
+		St: MuxedTransport<Output = FinalUpgrade<C>> + Clone + 'static,
+		C: 'static {
+	let mut service = match MdnsService::new() {
+		Ok(service) => service,
+		Err(err) => {
+			warn!(target: "sub-libp2p", "Failed to start mDNS discovery: {:?}", err);
+			return Box::new(future::empty()) as Box<Future<Item = (), Error = IoError>>
+		},
+	};
+	debug!(target: "sub-libp2p", "Started mDNS discovery");
+
+	// TTL of the records we send back in response to a query.
+	let ttl = Duration::from_secs(5 * 60);
+
+	let future = future::poll_fn(move || {
+		loop {
+			match service.poll() {
+				Async::Ready(MdnsPacket::Query(query)) => {
+					let local_peer_id = shared.kad_system.local_peer_id().clone();
+					let addrs = mdns_advertised_addrs(&shared);
+					trace!(target: "sub-libp2p", "Responding to mDNS query with {:?}", addrs);
+					if let Err(err) = query.respond(local_peer_id, addrs, ttl) {
+						debug!(target: "sub-libp2p", "Error while responding to mDNS query: {:?}", err);
+					}
+				},
+				Async::Ready(MdnsPacket::Response(response)) => {
+					let mut discovered_any = false;
+					for peer in response.discovered_peers() {
+						if peer.id() == shared.kad_system.local_peer_id() {
+							continue
+						}
+
+						for addr in peer.addresses() {
+							trace!(target: "sub-libp2p", "mDNS discovered {:?} at {}", peer.id(), addr);
+							shared.network_state.add_kad_discovered_addr(peer.id(), addr);
+							discovered_any = true;
+						}
+					}
+
+					if discovered_any {
+						connect_to_nodes(shared.clone(), transport.clone(), &swarm_controller);
+					}
+				},
+				Async::Ready(MdnsPacket::ServiceDiscovery(discovery)) => {
+					discovery.respond(ttl);
+				},
+				Async::NotReady => return Ok(Async::NotReady),
+			}
+		}
+	});
+
+	// Note that we use a Box in order to speed compilation time.
+	Box::new(future) as Box<Future<Item = _, Error = _>>
+}
+
+/// Returns the addresses to advertise to other nodes on the local network.
+/// If we listen on an unspecified address (eg. `0.0.0.0`), it is replaced
+/// with the address of each of our network interfaces.
+fn mdns_advertised_addrs(shared: &Shared) -> Vec<Multiaddr> {
+	let mut addrs = shared.listened_addrs.read().clone();
+
+	let original_addr = match *shared.original_listened_addr.read() {
+		Some(ref addr) => addr.clone(),
+		None => return addrs,
+	};
+
+	let mut components = original_addr.iter();
+	let (ip, rest) = match components.next() {
+		Some(AddrComponent::IP4(ip)) => (IpAddr::V4(ip), components.collect::<Vec<_>>()),
+		Some(AddrComponent::IP6(ip)) => (IpAddr::V6(ip), components.collect::<Vec<_>>()),
+		_ => {
+			addrs.push(original_addr.clone());
+			return addrs
+		},
+	};
+
+	if !ip.is_unspecified() {
+		addrs.push(original_addr);
+		return addrs
+	}
+
+	let interfaces = match get_if_addrs::get_if_addrs() {
+		Ok(interfaces) => interfaces,
+		Err(err) => {
+			debug!(target: "sub-libp2p", "Failed to list network interfaces: {:?}", err);
+			return addrs
+		},
+	};
+
+	for interface in interfaces {
+		let component = match (interface.ip(), ip) {
+			(IpAddr::V4(if_ip), IpAddr::V4(_)) => AddrComponent::IP4(if_ip),
+			(IpAddr::V6(if_ip), IpAddr::V6(_)) => AddrComponent::IP6(if_ip),
+			_ => continue,
+		};
+
+		let addr: Multiaddr = iter::once(component).chain(rest.iter().cloned()).collect();
+		if !addrs.contains(&addr) {
+			addrs.push(addr);
+		}
+	}
+
+	addrs
+}
+
 /// Performs a kademlia request to a random node.
 /// Note that we don't actually care about the results, so the future
 /// produces `()`.
@@ -985,6 +1152,21 @@ fn connect_to_nodes<T, To, St, C>(
 		To: AsyncRead + AsyncWrite + 'static,
 		St: MuxedTransport<Output = FinalUpgrade<C>> + Clone + 'static,
 		C: 'static {
+	// Reserved peers (which include sentries and the validators behind them)
+	// are always dialed, even in reserved-only mode.
+	for peer in shared.network_state.disconnected_reserved_peers() {
+		trace!(target: "sub-libp2p", "Dialing reserved peer {:?}", peer);
+		for proto in shared.protocols.0.clone().into_iter() {
+			open_peer_custom_proto(
+				shared.clone(),
+				base_transport.clone(),
+				proto,
+				peer.clone(),
+				swarm_controller
+			)
+		}
+	}
+
 	let num_slots = shared.network_state.should_open_outgoing_custom_connections();
 	debug!(target: "sub-libp2p",
 		"Outgoing connections cycle ; opening up to {} outgoing connections",
@@ -147,6 +147,8 @@ pub struct NetworkConfiguration {
 	pub nat_enabled: bool,
 	/// Enable discovery
 	pub discovery_enabled: bool,
+	/// Enable discovery of nodes on the local network through mDNS
+	pub enable_mdns: bool,
 	/// List of initial node addresses
 	pub boot_nodes: Vec<String>,
 	/// Use provided node key instead of default
@@ -163,6 +165,8 @@ pub struct NetworkConfiguration {
 	pub reserved_nodes: Vec<String>,
 	/// The non-reserved peer mode.
 	pub non_reserved_mode: NonReservedPeerMode,
+	/// Position of this node in a sentry topology.
+	pub sentry_mode: SentryMode,
 	/// IP filter
 	pub ip_filter: IpFilter,
 	/// Client identifier
@@ -186,6 +190,7 @@ impl NetworkConfiguration {
 			udp_port: None,
 			nat_enabled: true,
 			discovery_enabled: true,
+			enable_mdns: false,
 			boot_nodes: Vec::new(),
 			use_secret: None,
 			min_peers: 25,
@@ -195,6 +200,7 @@ impl NetworkConfiguration {
 			ip_filter: IpFilter::default(),
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
+			sentry_mode: SentryMode::Disabled,
 			client_version: "Parity-network".into(),
 		}
 	}
@@ -349,6 +355,45 @@ impl NonReservedPeerMode {
 	}
 }
 
+/// Position of the local node in a sentry topology.
+///
+/// A validator running behind sentries only ever connects to its sentry nodes
+/// and never takes part in discovery. The sentries connect to the rest of the
+/// network on its behalf and never reveal its address to other nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SentryMode {
+	/// Regular node. This is the default.
+	Disabled,
+	/// Validator reachable only through the given sentry node addresses.
+	Validator(Vec<String>),
+	/// Sentry node relaying for the given validator node addresses.
+	Sentry(Vec<String>),
+}
+
+impl SentryMode {
+	/// Returns true if we are a validator hidden behind sentry nodes.
+	pub fn is_validator(&self) -> bool {
+		match *self {
+			SentryMode::Validator(_) => true,
+			_ => false,
+		}
+	}
+
+	/// Returns true if we are a sentry relaying for validators.
+	pub fn is_sentry(&self) -> bool {
+		match *self {
+			SentryMode::Sentry(_) => true,
+			_ => false,
+		}
+	}
+}
+
+impl Default for SentryMode {
+	fn default() -> Self {
+		SentryMode::Disabled
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IpFilter {
 	pub predefined: AllowIP,
@@ -16,7 +16,7 @@
 
 //! Blockchain access trait
 
-use client::{self, Client as SubstrateClient, ImportResult, ClientInfo, BlockStatus, BlockOrigin, CallExecutor};
+use client::{self, Client as SubstrateClient, ClientInfo, BlockStatus, CallExecutor};
 use client::error::Error;
 use runtime_primitives::traits::{Block as BlockT, Header as HeaderT};
 use runtime_primitives::generic::BlockId;
@@ -24,9 +24,6 @@ use runtime_primitives::bft::Justification;
 
 /// Local client abstraction for the network.
 pub trait Client<Block: BlockT>: Send + Sync {
-	/// Import a new block. Parent is supposed to be existing in the blockchain.
-	fn import(&self, origin: BlockOrigin, header: Block::Header, justification: Justification<Block::Hash>, body: Option<Vec<Block::Extrinsic>>) -> Result<ImportResult, Error>;
-
 	/// Get blockchain info.
 	fn info(&self) -> Result<ClientInfo<Block>, Error>;
 
@@ -54,12 +51,6 @@ impl<B, E, Block> Client<Block> for SubstrateClient<B, E, Block> where
 	E: CallExecutor<Block> + Send + Sync + 'static,
 	Block: BlockT,
 {
-	fn import(&self, origin: BlockOrigin, header: Block::Header, justification: Justification<Block::Hash>, body: Option<Vec<Block::Extrinsic>>) -> Result<ImportResult, Error> {
-		// TODO: defer justification check.
-		let justified_header = self.check_justification(header, justification.into())?;
-		(self as &SubstrateClient<B, E, Block>).import_block(origin, justified_header, body)
-	}
-
 	fn info(&self) -> Result<ClientInfo<Block>, Error> {
 		(self as &SubstrateClient<B, E, Block>).info()
 	}
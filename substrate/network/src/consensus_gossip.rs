@@ -18,6 +18,7 @@
 //! Handles chain-specific and standard BFT messages.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use futures::sync::mpsc;
 use std::time::{Instant, Duration};
 use network_libp2p::NodeIndex;
@@ -40,54 +41,99 @@ pub enum ConsensusMessage<B: BlockT> {
 	/// A message concerning BFT agreement
 	Bft(message::LocalizedBftMessage<B>),
 	/// A message concerning some chain-specific aspect of consensus
-	ChainSpecific(Vec<u8>, B::Hash),
+	ChainSpecific(Vec<u8>),
+}
+
+impl<B: BlockT> ConsensusMessage<B> {
+	fn to_generic(&self) -> message::Message<B> {
+		match *self {
+			ConsensusMessage::Bft(ref bft) => GenericMessage::BftMessage(bft.clone()),
+			ConsensusMessage::ChainSpecific(ref msg) => GenericMessage::ChainSpecific(msg.clone()),
+		}
+	}
+}
+
+/// Outcome of the validation of a consensus message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationResult<H> {
+	/// The message is valid for the given topic and should be propagated to other peers.
+	ProcessAndKeep(H),
+	/// The message is valid for the given topic but should not be propagated.
+	ProcessAndDiscard(H),
+	/// The message is invalid or irrelevant and should be ignored.
+	Discard,
+}
+
+/// Chain-specific validation of gossiped consensus messages.
+pub trait Validator<B: BlockT>: Send + Sync {
+	/// Validate a message and determine the topic it belongs to.
+	fn validate(&self, message: &ConsensusMessage<B>) -> ValidationResult<B::Hash>;
+
+	/// Whether a message with the given topic has expired and should be discarded.
+	fn message_expired(&self, topic: &B::Hash, message: &ConsensusMessage<B>) -> bool;
 }
 
 struct MessageEntry<B: BlockT> {
-	hash: B::Hash,
+	message_hash: B::Hash,
+	topic: B::Hash,
 	message: ConsensusMessage<B>,
 	instant: Instant,
+	propagate: bool,
 }
 
 /// Consensus network protocol handler. Manages statements and candidate requests.
 pub struct ConsensusGossip<B: BlockT> {
 	peers: HashMap<NodeIndex, PeerConsensus<B::Hash>>,
-	message_sink: Option<(mpsc::UnboundedSender<ConsensusMessage<B>>, B::Hash)>,
+	live_message_sinks: HashMap<B::Hash, Vec<mpsc::UnboundedSender<ConsensusMessage<B>>>>,
 	messages: Vec<MessageEntry<B>>,
-	message_hashes: HashSet<B::Hash>,
+	known_messages: HashSet<B::Hash>,
+	validator: Arc<Validator<B>>,
+	sentries_only: bool,
 }
 
 impl<B: BlockT> ConsensusGossip<B> where B::Header: HeaderT<Number=u64> {
-	/// Create a new instance.
-	pub fn new() -> Self {
+	/// Create a new instance using the given message validator.
+	pub fn new(validator: Arc<Validator<B>>) -> Self {
 		ConsensusGossip {
 			peers: HashMap::new(),
-			message_sink: None,
+			live_message_sinks: HashMap::new(),
 			messages: Default::default(),
-			message_hashes: Default::default(),
+			known_messages: Default::default(),
+			validator,
+			sentries_only: false,
 		}
 	}
 
+	/// Only exchange messages with peers having the sentry role. To be used
+	/// by authorities which are only reachable through their sentry nodes.
+	pub fn gossip_only_with_sentries(&mut self) {
+		self.sentries_only = true;
+	}
+
 	/// Closes all notification streams.
 	pub fn abort(&mut self) {
-		self.message_sink = None;
+		self.live_message_sinks.clear();
 	}
 
 	/// Handle new connected peer.
 	pub fn new_peer(&mut self, protocol: &mut Context<B>, who: NodeIndex, roles: Roles) {
-		if roles.intersects(Roles::AUTHORITY | Roles::FULL) {
+		if self.sentries_only && !roles.contains(Roles::SENTRY) {
+			trace!(target:"gossip", "Ignoring non-sentry peer {:?} {}", roles, who);
+			return;
+		}
+
+		if roles.intersects(Roles::AUTHORITY | Roles::FULL | Roles::SENTRY) {
 			trace!(target:"gossip", "Registering {:?} {}", roles, who);
-			// Send out all known messages.
+			// Send out all known messages which are still relevant.
 			// TODO: limit by size
 			let mut known_messages = HashSet::new();
 			for entry in self.messages.iter() {
-				known_messages.insert(entry.hash);
-				let message = match entry.message {
-					ConsensusMessage::Bft(ref bft) => GenericMessage::BftMessage(bft.clone()),
-					ConsensusMessage::ChainSpecific(ref msg, _) => GenericMessage::ChainSpecific(msg.clone()),
-				};
+				if !entry.propagate || self.validator.message_expired(&entry.topic, &entry.message) {
+					continue;
+				}
 
-				protocol.send_message(who, message);
+				known_messages.insert(entry.message_hash);
+				protocol.send_message(who, entry.message.to_generic());
 			}
 			self.peers.insert(who, PeerConsensus {
 				known_messages,
@@ -95,73 +141,112 @@ impl<B: BlockT> ConsensusGossip<B> where B::Header: HeaderT<Number=u64> {
 		}
 	}
 
-	fn propagate(&mut self, protocol: &mut Context<B>, message: message::Message<B>, hash: B::Hash) {
+	fn propagate(&mut self, protocol: &mut Context<B>, message: message::Message<B>, message_hash: B::Hash) {
 		for (id, ref mut peer) in self.peers.iter_mut() {
-			if peer.known_messages.insert(hash.clone()) {
+			if peer.known_messages.insert(message_hash.clone()) {
 				trace!(target:"gossip", "Propagating to {}: {:?}", id, message);
 				protocol.send_message(*id, message.clone());
 			}
 		}
 	}
 
-	fn register_message(&mut self, hash: B::Hash, message: ConsensusMessage<B>) {
-		if self.message_hashes.insert(hash) {
+	fn register_message(&mut self, message_hash: B::Hash, topic: B::Hash, message: ConsensusMessage<B>, propagate: bool) {
+		if self.known_messages.insert(message_hash) {
 			self.messages.push(MessageEntry {
-				hash,
+				message_hash,
+				topic,
 				instant: Instant::now(),
 				message,
+				propagate,
 			});
 		}
 	}
 
-	/// Handles incoming BFT message, passing to stream and repropagating.
-	pub fn on_bft_message(&mut self, protocol: &mut Context<B>, who: NodeIndex, message: message::LocalizedBftMessage<B>) {
-		if let Some((hash, message)) = self.handle_incoming(protocol, who, ConsensusMessage::Bft(message)) {
-			// propagate to other peers.
-			self.multicast(protocol, message, Some(hash));
+	/// Handles an incoming consensus message: validates it, passes it to the
+	/// streams interested in its topic and repropagates it if the validator allows.
+	pub fn on_incoming(&mut self, protocol: &mut Context<B>, who: NodeIndex, message: ConsensusMessage<B>) {
+		let message_hash = ::protocol::hash_message(&message.to_generic());
+
+		if self.known_messages.contains(&message_hash) {
+			trace!(target:"gossip", "Ignored already known message from {}", who);
+			if let Some(ref mut peer) = self.peers.get_mut(&who) {
+				peer.known_messages.insert(message_hash);
+			}
+			return;
+		}
+
+		let (topic, keep) = match self.validator.validate(&message) {
+			ValidationResult::ProcessAndKeep(topic) => (topic, true),
+			ValidationResult::ProcessAndDiscard(topic) => (topic, false),
+			ValidationResult::Discard => {
+				trace!(target:"gossip", "Discarded invalid message from {}", who);
+				return;
+			},
+		};
+
+		match (protocol.client().info(), protocol.client().header(&BlockId::Hash(topic))) {
+			(_, Err(e)) | (Err(e), _) => {
+				debug!(target:"gossip", "Error reading blockchain: {:?}", e);
+				return;
+			},
+			(Ok(info), Ok(Some(header))) => {
+				if header.number() < &info.chain.best_number {
+					trace!(target:"gossip", "Ignored ancient message from {}, topic={}", who, topic);
+					return;
+				}
+			},
+			(Ok(_), Ok(None)) => {},
 		}
-	}
 
-	/// Handles incoming chain-specific message and repropagates
-	pub fn on_chain_specific(&mut self, protocol: &mut Context<B>, who: NodeIndex, message: Vec<u8>, parent_hash: B::Hash) {
-		debug!(target: "gossip", "received chain-specific gossip message");
-		if let Some((hash, message)) = self.handle_incoming(protocol, who, ConsensusMessage::ChainSpecific(message, parent_hash)) {
-			debug!(target: "gossip", "handled incoming chain-specific message");
+		match self.peers.get_mut(&who) {
+			Some(ref mut peer) => {
+				peer.known_messages.insert(message_hash);
+			},
+			None => {
+				trace!(target:"gossip", "Ignored statement from unregistered peer {}", who);
+				return;
+			},
+		}
+
+		if let Some(sinks) = self.live_message_sinks.get_mut(&topic) {
+			debug!(target: "gossip", "Pushing consensus message to {} sinks for topic {}.", sinks.len(), topic);
+			sinks.retain(|sink| {
+				if let Err(e) = sink.unbounded_send(message.clone()) {
+					trace!(target:"gossip", "Error broadcasting message notification: {:?}", e);
+					false
+				} else {
+					true
+				}
+			});
+		}
+
+		self.register_message(message_hash, topic, message.clone(), keep);
+		if keep {
 			// propagate to other peers.
-			self.multicast(protocol, message, Some(hash));
+			self.propagate(protocol, message.to_generic(), message_hash);
 		}
 	}
 
-	/// Get a stream of messages relevant to consensus on top of a given parent hash.
-	pub fn messages_for(&mut self, parent_hash: B::Hash) -> mpsc::UnboundedReceiver<ConsensusMessage<B>> {
+	/// Get a stream of messages relevant to consensus on the given topic.
+	/// Messages which were received before the stream was created are included.
+	pub fn messages_for(&mut self, topic: B::Hash) -> mpsc::UnboundedReceiver<ConsensusMessage<B>> {
 		let (sink, stream) = mpsc::unbounded();
 
-		for entry in self.messages.iter() {
-			let message_matches = match entry.message {
-				ConsensusMessage::Bft(ref msg) => msg.parent_hash == parent_hash,
-				ConsensusMessage::ChainSpecific(_, ref h) => h == &parent_hash,
-			};
-
-			if message_matches {
-				sink.unbounded_send(entry.message.clone()).expect("receiving end known to be open; qed");
-			}
+		for entry in self.messages.iter().filter(|e| e.topic == topic) {
+			sink.unbounded_send(entry.message.clone()).expect("receiving end known to be open; qed");
 		}
 
-		self.message_sink = Some((sink, parent_hash));
+		self.live_message_sinks.entry(topic).or_insert_with(Vec::new).push(sink);
 		stream
 	}
 
-	/// Multicast a chain-specific message to other authorities.
-	pub fn multicast_chain_specific(&mut self, protocol: &mut Context<B>, message: Vec<u8>, parent_hash: B::Hash) {
-		trace!(target:"gossip", "sending chain-specific message");
-		self.multicast(protocol, ConsensusMessage::ChainSpecific(message, parent_hash), None);
-	}
-
-	/// Multicast a BFT message to other authorities
-	pub fn multicast_bft_message(&mut self, protocol: &mut Context<B>, message: message::LocalizedBftMessage<B>) {
-		// Broadcast message to all authorities.
-		trace!(target:"gossip", "Broadcasting BFT message {:?}", message);
-		self.multicast(protocol, ConsensusMessage::Bft(message), None);
+	/// Multicast a locally-produced consensus message on the given topic to other authorities.
+	pub fn multicast(&mut self, protocol: &mut Context<B>, topic: B::Hash, message: ConsensusMessage<B>) {
+		trace!(target:"gossip", "Broadcasting consensus message {:?} on topic {}", message, topic);
+		let generic = message.to_generic();
+		let message_hash = ::protocol::hash_message(&generic);
+		self.register_message(message_hash, topic, message, true);
+		self.propagate(protocol, generic, message_hash);
 	}
 
 	/// Call when a peer has been disconnected to stop tracking gossip status.
@@ -169,193 +254,159 @@ impl<B: BlockT> ConsensusGossip<B> where B::Header: HeaderT<Number=u64> {
 		self.peers.remove(&who);
 	}
 
-	/// Prune old or no longer relevant consensus messages.
-	/// Supply an optional block hash where consensus is known to have concluded.
-	pub fn collect_garbage(&mut self, best_hash: Option<&B::Hash>) {
-		let hashes = &mut self.message_hashes;
+	/// Prune messages which are too old or which the validator considers expired.
+	pub fn collect_garbage(&mut self) {
+		let known_messages = &mut self.known_messages;
+		let validator = &self.validator;
+		let mut expired_topics = HashSet::new();
 		let before = self.messages.len();
 		let now = Instant::now();
 		self.messages.retain(|entry| {
-			if entry.instant + MESSAGE_LIFETIME >= now &&
-				best_hash.map_or(true, |parent_hash|
-					match entry.message {
-						ConsensusMessage::Bft(ref msg) => &msg.parent_hash != parent_hash,
-						ConsensusMessage::ChainSpecific(_, ref h) => h != parent_hash,
-					})
-			{
-				true
-			} else {
-				hashes.remove(&entry.hash);
+			if validator.message_expired(&entry.topic, &entry.message) {
+				expired_topics.insert(entry.topic);
+				known_messages.remove(&entry.message_hash);
+				false
+			} else if entry.instant + MESSAGE_LIFETIME < now {
+				known_messages.remove(&entry.message_hash);
 				false
+			} else {
+				true
 			}
 		});
 		if self.messages.len() != before {
 			trace!(target:"gossip", "Cleaned up {} stale messages", before - self.messages.len());
 		}
+
+		// close the streams of expired topics.
+		for topic in expired_topics {
+			self.live_message_sinks.remove(&topic);
+		}
+
 		for (_, ref mut peer) in self.peers.iter_mut() {
-			peer.known_messages.retain(|h| hashes.contains(h));
+			peer.known_messages.retain(|h| known_messages.contains(h));
 		}
 	}
+}
 
-	fn handle_incoming(&mut self, protocol: &mut Context<B>, who: NodeIndex, message: ConsensusMessage<B>) -> Option<(B::Hash, ConsensusMessage<B>)> {
-		let (hash, parent, message) = match message {
-			ConsensusMessage::Bft(msg) => {
-				let parent = msg.parent_hash;
-				let generic = GenericMessage::BftMessage(msg);
-				(
-					::protocol::hash_message(&generic),
-					parent,
-					match generic {
-						GenericMessage::BftMessage(msg) => ConsensusMessage::Bft(msg),
-						_ => panic!("`generic` is known to be the `BftMessage` variant; qed"),
-					}
-				)
-			}
-			ConsensusMessage::ChainSpecific(msg, parent) => {
-				let generic = GenericMessage::ChainSpecific(msg);
-				(
-					::protocol::hash_message::<B>(&generic),
-					parent,
-					match generic {
-						GenericMessage::ChainSpecific(msg) => ConsensusMessage::ChainSpecific(msg, parent),
-						_ => panic!("`generic` is known to be the `ChainSpecific` variant; qed"),
-					}
-				)
-			}
-		};
+#[cfg(test)]
+mod tests {
+	use runtime_primitives::bft::Justification;
+	use runtime_primitives::testing::{H256, Block as RawBlock};
+	use std::time::Instant;
+	use parking_lot::Mutex;
+	use message::{self, generic};
+	use super::*;
 
-		if self.message_hashes.contains(&hash) {
-			trace!(target:"gossip", "Ignored already known message from {}", who);
-			return None;
+	type Block = RawBlock<u64>;
+
+	/// Takes the parent hash of BFT messages as the topic and considers
+	/// every other message invalid. Topics can be manually expired.
+	#[derive(Default)]
+	struct TestValidator {
+		expired: Mutex<HashSet<H256>>,
+	}
+
+	impl Validator<Block> for TestValidator {
+		fn validate(&self, message: &ConsensusMessage<Block>) -> ValidationResult<H256> {
+			match *message {
+				ConsensusMessage::Bft(ref msg) => ValidationResult::ProcessAndKeep(msg.parent_hash),
+				ConsensusMessage::ChainSpecific(_) => ValidationResult::Discard,
+			}
 		}
 
-		match (protocol.client().info(), protocol.client().header(&BlockId::Hash(parent))) {
-			(_, Err(e)) | (Err(e), _) => {
-				debug!(target:"gossip", "Error reading blockchain: {:?}", e);
-				return None;
-			},
-			(Ok(info), Ok(Some(header))) => {
-				if header.number() < &info.chain.best_number {
-					trace!(target:"gossip", "Ignored ancient message from {}, hash={}", who, parent);
-					return None;
-				}
-			},
-			(Ok(_), Ok(None)) => {},
+		fn message_expired(&self, topic: &H256, _message: &ConsensusMessage<Block>) -> bool {
+			self.expired.lock().contains(topic)
 		}
+	}
 
-		if let Some(ref mut peer) = self.peers.get_mut(&who) {
-			peer.known_messages.insert(hash);
-			if let Some((sink, parent_hash)) = self.message_sink.take() {
-				if parent == parent_hash {
-					debug!(target: "gossip", "Pushing relevant consensus message to sink.");
-					if let Err(e) = sink.unbounded_send(message.clone()) {
-						trace!(target:"gossip", "Error broadcasting message notification: {:?}", e);
-					}
-				}
+	#[derive(Default)]
+	struct TestContext {
+		sent: Vec<NodeIndex>,
+	}
 
-				self.message_sink = Some((sink, parent_hash));
-			}
-		} else {
-			trace!(target:"gossip", "Ignored statement from unregistered peer {}", who);
-			return None;
+	impl Context<Block> for TestContext {
+		fn client(&self) -> &::chain::Client<Block> {
+			unimplemented!()
 		}
 
-		Some((hash, message))
-	}
+		fn report_peer(&mut self, _who: NodeIndex, _reason: ::network_libp2p::Severity) {
+			unimplemented!()
+		}
 
-	fn multicast(&mut self, protocol: &mut Context<B>, message: ConsensusMessage<B>, hash: Option<B::Hash>) {
-		let generic = match message {
-			ConsensusMessage::Bft(ref message) => GenericMessage::BftMessage(message.clone()),
-			ConsensusMessage::ChainSpecific(ref message, _) => GenericMessage::ChainSpecific(message.clone()),
-		};
+		fn peer_info(&self, _peer: NodeIndex) -> Option<::protocol::PeerInfo<Block>> {
+			unimplemented!()
+		}
 
-		let hash = hash.unwrap_or_else(|| ::protocol::hash_message(&generic));
-		self.register_message(hash, message);
-		self.propagate(protocol, generic, hash);
+		fn send_message(&mut self, who: NodeIndex, _data: message::Message<Block>) {
+			self.sent.push(who)
+		}
 	}
-}
-
-#[cfg(test)]
-mod tests {
-	use runtime_primitives::bft::Justification;
-	use runtime_primitives::testing::{H256, Header, Block as RawBlock};
-	use std::time::Instant;
-	use message::{self, generic};
-	use super::*;
 
-	type Block = RawBlock<u64>;
+	fn bft_message(parent_hash: H256) -> ConsensusMessage<Block> {
+		ConsensusMessage::Bft(message::LocalizedBftMessage {
+			parent_hash,
+			message: message::generic::BftMessage::Auxiliary(Justification {
+				round_number: 0,
+				hash: Default::default(),
+				signatures: Default::default(),
+			}),
+		})
+	}
 
 	#[test]
 	fn collects_garbage() {
 		let prev_hash = H256::random();
 		let best_hash = H256::random();
-		let mut consensus = ConsensusGossip::<Block>::new();
+		let validator = Arc::new(TestValidator::default());
+		let mut consensus = ConsensusGossip::<Block>::new(validator.clone());
 		let now = Instant::now();
 		let m1_hash = H256::random();
 		let m2_hash = H256::random();
-		let m1 = ConsensusMessage::Bft(message::LocalizedBftMessage {
-			parent_hash: prev_hash,
-			message: message::generic::BftMessage::Auxiliary(Justification {
-				round_number: 0,
-				hash: Default::default(),
-				signatures: Default::default(),
-			}),
-		});
-		let m2 = ConsensusMessage::ChainSpecific(vec![1, 2, 3], best_hash);
+		let m1 = bft_message(prev_hash);
+		let m2 = ConsensusMessage::ChainSpecific(vec![1, 2, 3]);
 
 		macro_rules! push_msg {
-			($hash:expr, $now: expr, $m:expr) => {
+			($hash:expr, $topic:expr, $now: expr, $m:expr) => {
 				consensus.messages.push(MessageEntry {
-					hash: $hash,
+					message_hash: $hash,
+					topic: $topic,
 					instant: $now,
 					message: $m,
+					propagate: true,
 				})
 			}
 		}
 
-		push_msg!(m1_hash, now, m1);
-		push_msg!(m2_hash, now, m2.clone());
-		consensus.message_hashes.insert(m1_hash);
-		consensus.message_hashes.insert(m2_hash);
+		push_msg!(m1_hash, prev_hash, now, m1);
+		push_msg!(m2_hash, best_hash, now, m2.clone());
+		consensus.known_messages.insert(m1_hash);
+		consensus.known_messages.insert(m2_hash);
 
 		// nothing to collect
-		consensus.collect_garbage(None);
-		assert_eq!(consensus.messages.len(), 2);
-		assert_eq!(consensus.message_hashes.len(), 2);
-
-		// random header, nothing should be cleared
-		let mut header = Header {
-			parent_hash: H256::default(),
-			number: 0,
-			state_root: H256::default(),
-			extrinsics_root: H256::default(),
-			digest: Default::default(),
-		};
-
-		consensus.collect_garbage(Some(&H256::default()));
+		consensus.collect_garbage();
 		assert_eq!(consensus.messages.len(), 2);
-		assert_eq!(consensus.message_hashes.len(), 2);
+		assert_eq!(consensus.known_messages.len(), 2);
 
-		// header that matches one of the messages
-		header.parent_hash = prev_hash;
-		consensus.collect_garbage(Some(&prev_hash));
+		// topic of one of the messages expired
+		validator.expired.lock().insert(prev_hash);
+		consensus.collect_garbage();
 		assert_eq!(consensus.messages.len(), 1);
-		assert_eq!(consensus.message_hashes.len(), 1);
-		assert!(consensus.message_hashes.contains(&m2_hash));
+		assert_eq!(consensus.known_messages.len(), 1);
+		assert!(consensus.known_messages.contains(&m2_hash));
 
 		// make timestamp expired
 		consensus.messages.clear();
-		push_msg!(m2_hash, now - MESSAGE_LIFETIME, m2);
-		consensus.collect_garbage(None);
+		push_msg!(m2_hash, best_hash, now - MESSAGE_LIFETIME, m2);
+		consensus.collect_garbage();
 		assert!(consensus.messages.is_empty());
-		assert!(consensus.message_hashes.is_empty());
+		assert!(consensus.known_messages.is_empty());
 	}
 
 	#[test]
 	fn message_stream_include_those_sent_before_asking_for_stream() {
 		use futures::Stream;
 
-		let mut consensus = ConsensusGossip::new();
+		let mut consensus = ConsensusGossip::new(Arc::new(TestValidator::default()));
 
 		let bft_message = generic::BftMessage::Consensus(generic::SignedConsensusMessage::Vote(generic::SignedConsensusVote {
 			vote: generic::ConsensusVote::AdvanceRound(0),
@@ -374,9 +425,78 @@ mod tests {
 		let message_hash = ::protocol::hash_message::<Block>(&message);
 
 		let message = ConsensusMessage::Bft(localized);
-		consensus.register_message(message_hash, message.clone());
+		consensus.register_message(message_hash, parent_hash, message.clone(), true);
 		let stream = consensus.messages_for(parent_hash);
 
 		assert_eq!(stream.wait().next(), Some(Ok(message)));
 	}
+
+	#[test]
+	fn message_stream_only_includes_messages_on_topic() {
+		use futures::Stream;
+
+		let mut consensus = ConsensusGossip::new(Arc::new(TestValidator::default()));
+		let topic_a = H256::random();
+		let topic_b = H256::random();
+		let message_a = bft_message(topic_a);
+		let message_b = bft_message(topic_b);
+
+		consensus.register_message(H256::random(), topic_a, message_a.clone(), true);
+		consensus.register_message(H256::random(), topic_b, message_b, true);
+
+		let mut stream = consensus.messages_for(topic_a).wait();
+		assert_eq!(stream.next(), Some(Ok(message_a)));
+
+		drop(consensus);
+		assert_eq!(stream.next(), None);
+	}
+
+	#[test]
+	fn authority_behind_sentries_only_gossips_with_sentries() {
+		let mut consensus = ConsensusGossip::new(Arc::new(TestValidator::default()));
+		consensus.gossip_only_with_sentries();
+
+		let mut ctx = TestContext::default();
+		consensus.new_peer(&mut ctx, 1, Roles::FULL);
+		consensus.new_peer(&mut ctx, 2, Roles::AUTHORITY);
+		consensus.new_peer(&mut ctx, 3, Roles::FULL | Roles::SENTRY);
+
+		let topic = H256::random();
+		consensus.multicast(&mut ctx, topic, bft_message(topic));
+		assert_eq!(ctx.sent, vec![3]);
+
+		// messages known before a sentry connects are sent to it as well.
+		let mut ctx = TestContext::default();
+		consensus.new_peer(&mut ctx, 4, Roles::FULL);
+		consensus.new_peer(&mut ctx, 5, Roles::FULL | Roles::SENTRY);
+		assert_eq!(ctx.sent, vec![5]);
+	}
+
+	#[test]
+	fn sentries_gossip_with_every_full_peer() {
+		let mut consensus = ConsensusGossip::new(Arc::new(TestValidator::default()));
+
+		let mut ctx = TestContext::default();
+		consensus.new_peer(&mut ctx, 1, Roles::LIGHT);
+		consensus.new_peer(&mut ctx, 2, Roles::AUTHORITY);
+		consensus.new_peer(&mut ctx, 3, Roles::FULL);
+
+		let topic = H256::random();
+		consensus.multicast(&mut ctx, topic, bft_message(topic));
+		ctx.sent.sort();
+		assert_eq!(ctx.sent, vec![2, 3]);
+	}
+
+	#[test]
+	fn messages_are_deduplicated_by_hash() {
+		let mut consensus = ConsensusGossip::new(Arc::new(TestValidator::default()));
+		let topic = H256::random();
+		let message_hash = H256::random();
+
+		consensus.register_message(message_hash, topic, bft_message(topic), true);
+		consensus.register_message(message_hash, topic, bft_message(topic), true);
+
+		assert_eq!(consensus.messages.len(), 1);
+		assert_eq!(consensus.known_messages.len(), 1);
+	}
 }
@@ -21,13 +21,12 @@ use std::sync::{Arc, Weak};
 use std::sync::atomic::{AtomicBool, Ordering};
 use parking_lot::{Condvar, Mutex, RwLock};
 
-use client::{BlockOrigin, ImportResult};
+use client::{BlockImport, BlockOrigin, ImportResult};
 use network_libp2p::{NodeIndex, Severity};
 
 use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, NumberFor, Zero};
 
 use blocks::BlockData;
-use chain::Client;
 use error::{ErrorKind, Error};
 use protocol::Context;
 use service::ExecuteInContext;
@@ -78,12 +77,12 @@ impl<B: BlockT> AsyncImportQueue<B> {
 		}
 	}
 
-	pub fn start<E: 'static + ExecuteInContext<B>>(&self, sync: Weak<RwLock<ChainSync<B>>>, service: Weak<E>, chain: Weak<Client<B>>) -> Result<(), Error> {
+	pub fn start<E: 'static + ExecuteInContext<B>>(&self, sync: Weak<RwLock<ChainSync<B>>>, service: Weak<E>, block_import: Arc<BlockImport<B>>) -> Result<(), Error> {
 		debug_assert!(self.handle.lock().is_none());
 
 		let qdata = self.data.clone();
 		*self.handle.lock() = Some(::std::thread::Builder::new().name("ImportQueue".into()).spawn(move || {
-			import_thread(sync, service, chain, qdata)
+			import_thread(sync, service, block_import, qdata)
 		}).map_err(|err| Error::from(ErrorKind::Io(err)))?);
 		Ok(())
 	}
@@ -159,7 +158,7 @@ impl<B: BlockT> Drop for AsyncImportQueue<B> {
 }
 
 /// Blocks import thread.
-fn import_thread<B: BlockT, E: ExecuteInContext<B>>(sync: Weak<RwLock<ChainSync<B>>>, service: Weak<E>, chain: Weak<Client<B>>, qdata: Arc<AsyncImportQueueData<B>>) {
+fn import_thread<B: BlockT, E: ExecuteInContext<B>>(sync: Weak<RwLock<ChainSync<B>>>, service: Weak<E>, block_import: Arc<BlockImport<B>>, qdata: Arc<AsyncImportQueueData<B>>) {
 	trace!(target: "sync", "Starting import thread");
 	loop {
 		if qdata.is_stopping.load(Ordering::SeqCst) {
@@ -178,10 +177,10 @@ fn import_thread<B: BlockT, E: ExecuteInContext<B>>(sync: Weak<RwLock<ChainSync<
 			}
 		};
 
-		match (sync.upgrade(), service.upgrade(), chain.upgrade()) {
-			(Some(sync), Some(service), Some(chain)) => {
+		match (sync.upgrade(), service.upgrade()) {
+			(Some(sync), Some(service)) => {
 				let blocks_hashes: Vec<B::Hash> = new_blocks.1.iter().map(|b| b.block.hash.clone()).collect();
-				if !import_many_blocks(&mut SyncLink::Indirect(&sync, &*chain, &*service), Some(&*qdata), new_blocks) {
+				if !import_many_blocks(&mut SyncLink::Indirect(&sync, &*block_import, &*service), Some(&*qdata), new_blocks) {
 					break;
 				}
 
@@ -199,8 +198,8 @@ fn import_thread<B: BlockT, E: ExecuteInContext<B>>(sync: Weak<RwLock<ChainSync<
 
 /// ChainSync link trait.
 trait SyncLinkApi<B: BlockT> {
-	/// Get chain reference.
-	fn chain(&self) -> &Client<B>;
+	/// Get block import reference.
+	fn block_import(&self) -> &BlockImport<B>;
 	/// Block imported.
 	fn block_imported(&mut self, hash: &B::Hash, number: NumberFor<B>);
 	/// Maintain sync.
@@ -216,10 +215,10 @@ trait SyncLinkApi<B: BlockT> {
 /// Link with the ChainSync service.
 enum SyncLink<'a, B: 'a + BlockT, E: 'a + ExecuteInContext<B>> {
 	/// Indirect link (through service).
-	Indirect(&'a RwLock<ChainSync<B>>, &'a Client<B>, &'a E),
+	Indirect(&'a RwLock<ChainSync<B>>, &'a BlockImport<B>, &'a E),
 	/// Direct references are given.
 	#[cfg(test)]
-	Direct(&'a mut ChainSync<B>, &'a mut Context<B>),
+	Direct(&'a mut ChainSync<B>, &'a mut Context<B>, &'a BlockImport<B>),
 }
 
 /// Block import successful result.
@@ -265,7 +264,7 @@ fn import_many_blocks<'a, B: BlockT>(
 
 	// Blocks in the response/drain should be in ascending order.
 	for block in blocks {
-		let import_result = import_single_block(link.chain(), blocks_origin.clone(), block);
+		let import_result = import_single_block(link.block_import(), blocks_origin.clone(), block);
 		let is_import_failed = import_result.is_err();
 		imported += process_import_result(link, import_result);
 		if is_import_failed {
@@ -285,23 +284,23 @@ fn import_many_blocks<'a, B: BlockT>(
 
 /// Single block import function.
 fn import_single_block<B: BlockT>(
-	chain: &Client<B>,
+	block_import: &BlockImport<B>,
 	block_origin: BlockOrigin,
 	block: BlockData<B>
 ) -> Result<BlockImportResult<B::Hash, <<B as BlockT>::Header as HeaderT>::Number>, BlockImportError>
 {
 	let origin = block.origin;
 	let block = block.block;
-	match (block.header, block.justification) {
-		(Some(header), Some(justification)) => {
+	match block.header {
+		Some(header) => {
 			let number = header.number().clone();
 			let hash = header.hash();
 			let parent = header.parent_hash().clone();
 
-			let result = chain.import(
+			let result = block_import.import(
 				block_origin,
 				header,
-				justification,
+				block.justification,
 				block.body,
 			);
 			match result {
@@ -325,20 +324,20 @@ fn import_single_block<B: BlockT>(
 					debug!(target: "sync", "Peer gave us a bad block {}: {:?}", number, hash);
 					Err(BlockImportError::DisconnectAndRestart(origin)) //TODO: use persistent ID
 				}
+				Ok(ImportResult::MissingJustification) => {
+					debug!(target: "sync", "Justification set for block {} was not provided by {} ", hash, origin);
+					Err(BlockImportError::Disconnect(origin)) //TODO: use persistent ID
+				}
 				Err(e) => {
 					debug!(target: "sync", "Error importing block {}: {:?}: {:?}", number, hash, e);
 					Err(BlockImportError::Restart)
 				}
 			}
 		},
-		(None, _) => {
+		None => {
 			debug!(target: "sync", "Header {} was not provided by {} ", block.hash, origin);
 			Err(BlockImportError::Disconnect(origin)) //TODO: use persistent ID
 		},
-		(_, None) => {
-			debug!(target: "sync", "Justification set for block {} was not provided by {} ", block.hash, origin);
-			Err(BlockImportError::Disconnect(origin)) //TODO: use persistent ID
-		}
 	}
 }
 
@@ -381,7 +380,7 @@ impl<'a, B: 'static + BlockT, E: 'a + ExecuteInContext<B>> SyncLink<'a, B, E> {
 	fn with_sync<F: Fn(&mut ChainSync<B>, &mut Context<B>)>(&mut self, closure: F) {
 		match *self {
 			#[cfg(test)]
-			SyncLink::Direct(ref mut sync, ref mut protocol) =>
+			SyncLink::Direct(ref mut sync, ref mut protocol, _) =>
 				closure(*sync, *protocol),
 			SyncLink::Indirect(ref sync, _, ref service) =>
 				service.execute_in_context(move |protocol| {
@@ -393,11 +392,11 @@ impl<'a, B: 'static + BlockT, E: 'a + ExecuteInContext<B>> SyncLink<'a, B, E> {
 }
 
 impl<'a, B: 'static + BlockT, E: ExecuteInContext<B>> SyncLinkApi<B> for SyncLink<'a, B, E> {
-	fn chain(&self) -> &Client<B> {
+	fn block_import(&self) -> &BlockImport<B> {
 		match *self {
 			#[cfg(test)]
-			SyncLink::Direct(_, ref protocol) => protocol.client(),
-			SyncLink::Indirect(_, ref chain, _) => *chain,
+			SyncLink::Direct(_, _, ref block_import) => *block_import,
+			SyncLink::Indirect(_, ref block_import, _) => *block_import,
 		}
 	}
 
@@ -436,14 +435,14 @@ pub mod tests {
 	use super::*;
 
 	/// Blocks import queue that is importing blocks in the same thread.
-	pub struct SyncImportQueue;
+	pub struct SyncImportQueue<B: BlockT>(pub Arc<BlockImport<B>>);
 	struct DummyExecuteInContext;
 
 	impl<B: 'static + BlockT> ExecuteInContext<B> for DummyExecuteInContext {
 		fn execute_in_context<F: Fn(&mut Context<B>)>(&self, _closure: F) { }
 	}
 
-	impl<B: 'static + BlockT> ImportQueue<B> for SyncImportQueue {
+	impl<B: 'static + BlockT> ImportQueue<B> for SyncImportQueue<B> {
 		fn clear(&self) { }
 
 		fn stop(&self) { }
@@ -460,12 +459,12 @@ pub mod tests {
 		}
 
 		fn import_blocks(&self, sync: &mut ChainSync<B>, protocol: &mut Context<B>, blocks: (BlockOrigin, Vec<BlockData<B>>)) {
-			import_many_blocks(&mut SyncLink::Direct::<_, DummyExecuteInContext>(sync, protocol), None, blocks);
+			import_many_blocks(&mut SyncLink::Direct::<_, DummyExecuteInContext>(sync, protocol, &*self.0), None, blocks);
 		}
 	}
 
 	struct TestLink {
-		chain: Arc<Client<Block>>,
+		chain: Arc<BlockImport<Block>>,
 		imported: usize,
 		maintains: usize,
 		disconnects: usize,
@@ -489,7 +488,7 @@ pub mod tests {
 	}
 
 	impl SyncLinkApi<Block> for TestLink {
-		fn chain(&self) -> &Client<Block> { &*self.chain }
+		fn block_import(&self) -> &BlockImport<Block> { &*self.chain }
 		fn block_imported(&mut self, _hash: &Hash, _number: NumberFor<Block>) { self.imported += 1; }
 		fn maintain_sync(&mut self) { self.maintains += 1; }
 		fn useless_peer(&mut self, _: NodeIndex, _: &str) { self.disconnects += 1; }
@@ -586,8 +585,7 @@ pub mod tests {
 	fn async_import_queue_drops() {
 		let queue = AsyncImportQueue::new();
 		let service = Arc::new(DummyExecutor);
-		let chain = Arc::new(test_client::new());
-		queue.start(Weak::new(), Arc::downgrade(&service), Arc::downgrade(&chain) as Weak<Client<Block>>).unwrap();
+		queue.start(Weak::new(), Arc::downgrade(&service), Arc::new(test_client::new())).unwrap();
 		drop(queue);
 	}
 }
@@ -495,6 +495,40 @@ pub mod generic {
 		}
 	}
 
+	impl<Block: Encode, Header: Encode, Hash: Encode, Number: Encode, Extrinsic: Encode>
+		Message<Block, Header, Hash, Number, Extrinsic>
+	{
+		/// Encode the message in the format understood by the given protocol version.
+		/// Returns `None` if the message can't be represented in that version.
+		pub fn encode_for_version(&self, version: u32) -> Option<Vec<u8>> {
+			if version < ::protocol::MIN_VERSION || version > ::protocol::CURRENT_VERSION {
+				return None;
+			}
+
+			match *self {
+				// earlier versions don't know about the sentry role and fail to decode it.
+				Message::Status(ref status) if version < ::protocol::SENTRY_ROLE_VERSION => {
+					let mut dest = vec![0];
+					status.encode_as(version, status.roles - Roles::SENTRY, &mut dest);
+					Some(dest)
+				}
+				_ => Some(self.encode()),
+			}
+		}
+	}
+
+	impl<Block: Decode, Header: Decode, Hash: Decode, Number: Decode, Extrinsic: Decode>
+		Message<Block, Header, Hash, Number, Extrinsic>
+	{
+		/// Decode a message sent in the format of the given protocol version.
+		pub fn decode_for_version<I: Input>(input: &mut I, version: u32) -> Option<Self> {
+			match version {
+				v if v >= ::protocol::MIN_VERSION && v <= ::protocol::CURRENT_VERSION => Decode::decode(input),
+				_ => None,
+			}
+		}
+	}
+
 	impl<Block: Decode, Header: Decode, Hash: Decode, Number: Decode, Extrinsic: Decode> Decode
 		for Message<Block, Header, Hash, Number, Extrinsic>
 	{
@@ -529,28 +563,42 @@ pub mod generic {
 		pub genesis_hash: Hash,
 		/// Chain-specific status.
 		pub chain_status: Vec<u8>,
+		/// Lowest protocol version supported.
+		pub min_supported_version: u32,
 	}
 
-	impl<Hash: Encode, Number: Encode> Encode for Status<Hash, Number> {
-		fn encode_to<T: Output>(&self, dest: &mut T) {
-			dest.push(&self.version);
-			dest.push_byte(self.roles.bits());
+	impl<Hash: Encode, Number: Encode> Status<Hash, Number> {
+		// encode with the given version and roles in place of our own.
+		fn encode_as<T: Output>(&self, version: u32, roles: Roles, dest: &mut T) {
+			dest.push(&version);
+			dest.push_byte(roles.bits());
 			dest.push(&self.best_number);
 			dest.push(&self.best_hash);
 			dest.push(&self.genesis_hash);
 			dest.push(&self.chain_status);
+			// appended last so that peers predating version negotiation can still decode the status.
+			dest.push(&self.min_supported_version);
+		}
+	}
+
+	impl<Hash: Encode, Number: Encode> Encode for Status<Hash, Number> {
+		fn encode_to<T: Output>(&self, dest: &mut T) {
+			self.encode_as(self.version, self.roles, dest)
 		}
 	}
 
 	impl<Hash: Decode, Number: Decode> Decode for Status<Hash, Number> {
 		fn decode<I: Input>(input: &mut I) -> Option<Self> {
+			let version = Decode::decode(input)?;
 			Some(Status {
-				version: Decode::decode(input)?,
+				version,
 				roles: Roles::from_bits(input.read_byte()?)?,
 				best_number: Decode::decode(input)?,
 				best_hash: Decode::decode(input)?,
 				genesis_hash: Decode::decode(input)?,
 				chain_status: Decode::decode(input)?,
+				// peers predating version negotiation don't report the lowest version they support.
+				min_supported_version: Decode::decode(input).unwrap_or(version),
 			})
 		}
 	}
@@ -22,11 +22,11 @@ use parking_lot::RwLock;
 use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, Hash, HashFor, As};
 use runtime_primitives::generic::BlockId;
 use network_libp2p::{NodeIndex, Severity};
-use codec::{Encode, Decode};
+use codec::Encode;
 
 use message::{self, Message};
 use message::generic::Message as GenericMessage;
-use specialization::Specialization;
+use specialization::{Specialization, BlockAnnounceValidation};
 use sync::{ChainSync, Status as SyncStatus, SyncState};
 use service::{Roles, TransactionPool};
 use import_queue::ImportQueue;
@@ -39,7 +39,11 @@ use error;
 const REQUEST_TIMEOUT_SEC: u64 = 40;
 
 /// Current protocol version.
-pub (crate) const CURRENT_VERSION: u32 = 1;
+pub (crate) const CURRENT_VERSION: u32 = 2;
+/// Lowest version we support
+pub (crate) const MIN_VERSION: u32 = 1;
+/// First version with the sentry role.
+pub (crate) const SENTRY_ROLE_VERSION: u32 = 2;
 /// Current packet count.
 pub (crate) const CURRENT_PACKET_COUNT: u8 = 1;
 
@@ -71,7 +75,7 @@ pub struct ProtocolStatus<B: BlockT> {
 
 /// Peer information
 struct Peer<B: BlockT> {
-	/// Protocol version
+	/// Protocol version negotiated with the peer
 	protocol_version: u32,
 	/// Roles
 	roles: Roles,
@@ -96,7 +100,7 @@ struct Peer<B: BlockT> {
 pub struct PeerInfo<B: BlockT> {
 	/// Roles
 	pub roles: Roles,
-	/// Protocol version
+	/// Protocol version negotiated with the peer
 	pub protocol_version: u32,
 	/// Peer best block hash
 	pub best_hash: B::Hash,
@@ -229,7 +233,11 @@ impl<B: BlockT, S: Specialization<B>> Protocol<B, S> {
 	}
 
 	pub fn handle_packet(&self, io: &mut SyncIo, who: NodeIndex, mut data: &[u8]) {
-		let message: Message<B> = match Decode::decode(&mut data) {
+		// until the version is negotiated we use the one the transport agreed on.
+		let version = self.context_data.peers.read().get(&who)
+			.map(|p| p.protocol_version)
+			.unwrap_or_else(|| transport_version(io, who));
+		let message: Message<B> = match GenericMessage::decode_for_version(&mut data, version) {
 			Some(m) => m,
 			None => {
 				trace!(target: "sync", "Invalid packet from {}", who);
@@ -425,13 +433,17 @@ impl<B: BlockT, S: Specialization<B>> Protocol<B, S> {
 				io.report_peer(who, Severity::Bad(&format!("Peer is on different chain (our genesis: {} theirs: {})", self.genesis_hash, status.genesis_hash)));
 				return;
 			}
-			if status.version != CURRENT_VERSION {
-				io.report_peer(who, Severity::Bad(&format!("Peer using unsupported protocol version {}", status.version)));
-				return;
-			}
+			let version = match negotiate_version(status.version, status.min_supported_version) {
+				Some(version) => version,
+				None => {
+					io.report_peer(who, Severity::Bad(&format!("Peer using unsupported protocol versions {}..{}",
+						status.min_supported_version, status.version)));
+					return;
+				}
+			};
 
 			let peer = Peer {
-				protocol_version: status.version,
+				protocol_version: version,
 				roles: status.roles,
 				best_hash: status.best_hash,
 				best_number: status.best_number,
@@ -518,6 +530,7 @@ impl<B: BlockT, S: Specialization<B>> Protocol<B, S> {
 				best_number: info.chain.best_number,
 				best_hash: info.chain.best_hash,
 				chain_status: self.specialization.read().status(),
+				min_supported_version: MIN_VERSION,
 			};
 			self.send_message(io, who, GenericMessage::Status(status))
 		}
@@ -546,6 +559,17 @@ impl<B: BlockT, S: Specialization<B>> Protocol<B, S> {
 	pub fn on_block_announce(&self, io: &mut SyncIo, who: NodeIndex, announce: message::BlockAnnounce<B::Header>) {
 		let header = announce.header;
 		let hash = header.hash();
+		let validation = self.specialization.read().validate_block_announce(
+			&ProtocolContext::new(&self.context_data, io),
+			who,
+			&header,
+		);
+		if let BlockAnnounceValidation::Invalid(reason) = validation {
+			trace!(target: "sync", "Invalid announcement of block {:?} from {}: {}", hash, who, reason);
+			io.report_peer(who, Severity::Bad(&format!("Peer announced an invalid block: {}", reason)));
+			return;
+		}
+
 		{
 			let mut peers = self.context_data.peers.write();
 			if let Some(ref mut peer) = peers.get_mut(&who) {
@@ -611,19 +635,46 @@ impl<B: BlockT, S: Specialization<B>> Protocol<B, S> {
 }
 
 fn send_message<B: BlockT>(peers: &RwLock<HashMap<NodeIndex, Peer<B>>>, io: &mut SyncIo, who: NodeIndex, mut message: Message<B>) {
-	match &mut message {
+	let version = match &mut message {
 		&mut GenericMessage::BlockRequest(ref mut r) => {
 			let mut peers = peers.write();
-			if let Some(ref mut peer) = peers.get_mut(&who) {
+			peers.get_mut(&who).map(|peer| {
 				r.id = peer.next_request_id;
 				peer.next_request_id = peer.next_request_id + 1;
 				peer.block_request = Some(r.clone());
 				peer.request_timestamp = Some(time::Instant::now());
-			}
+				peer.protocol_version
+			})
 		},
-		_ => (),
+		_ => peers.read().get(&who).map(|peer| peer.protocol_version),
+	};
+
+	// peers which haven't completed the handshake are sent messages in the version
+	// the transport agreed on.
+	let version = version.unwrap_or_else(|| transport_version(io, who));
+	match message.encode_for_version(version) {
+		Some(data) => io.send(who, data),
+		None => trace!(target: "sync", "Not sending message to {}: unsupported by protocol version {}", who, version),
+	}
+}
+
+/// The protocol version agreed on with a peer when the connection was opened.
+fn transport_version(io: &SyncIo, who: NodeIndex) -> u32 {
+	io.peer_session_info(who)
+		.map(|info| info.protocol_version)
+		.and_then(|version| if version >= MIN_VERSION && version <= CURRENT_VERSION { Some(version) } else { None })
+		.unwrap_or(CURRENT_VERSION)
+}
+
+/// Negotiate the protocol version to use with a peer, given the range of versions it
+/// supports. Returns `None` if there is no version supported by both sides.
+fn negotiate_version(peer_version: u32, peer_min_version: u32) -> Option<u32> {
+	let version = cmp::min(CURRENT_VERSION, peer_version);
+	if version < MIN_VERSION || version < peer_min_version {
+		None
+	} else {
+		Some(version)
 	}
-	io.send(who, message.encode());
 }
 
 /// Hash a message.
@@ -631,3 +682,77 @@ pub(crate) fn hash_message<B: BlockT>(message: &Message<B>) -> B::Hash {
 	let data = message.encode();
 	HashFor::<B>::hash(&data)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{negotiate_version, CURRENT_VERSION, MIN_VERSION};
+
+	#[test]
+	fn negotiates_highest_common_version() {
+		assert_eq!(negotiate_version(CURRENT_VERSION, MIN_VERSION), Some(CURRENT_VERSION));
+		assert_eq!(negotiate_version(CURRENT_VERSION + 5, CURRENT_VERSION), Some(CURRENT_VERSION));
+		assert_eq!(negotiate_version(MIN_VERSION, MIN_VERSION), Some(MIN_VERSION));
+	}
+
+	#[test]
+	fn decodes_status_of_peers_predating_negotiation() {
+		use codec::{Decode, Encode};
+		use message::generic::Status;
+		use service::Roles;
+
+		let status = Status::<u64, u64> {
+			version: 1,
+			roles: Roles::FULL,
+			best_number: 10,
+			best_hash: 5,
+			genesis_hash: 1,
+			chain_status: vec![1, 2, 3],
+			min_supported_version: 1,
+		};
+
+		// such peers send the status without the lowest version they support.
+		let mut encoded = status.encode();
+		encoded.truncate(encoded.len() - 4);
+		assert_eq!(Status::decode(&mut &encoded[..]), Some(status.clone()));
+		assert_eq!(negotiate_version(status.version, status.min_supported_version), Some(1));
+	}
+
+	#[test]
+	fn sentry_role_is_hidden_from_version_1_peers() {
+		use codec::Decode;
+		use message::generic::{Message, Status};
+		use service::Roles;
+		use super::SENTRY_ROLE_VERSION;
+
+		type TestMessage = Message<u64, u64, u64, u64, u64>;
+
+		let status = Status::<u64, u64> {
+			version: CURRENT_VERSION,
+			roles: Roles::FULL | Roles::SENTRY,
+			best_number: 10,
+			best_hash: 5,
+			genesis_hash: 1,
+			chain_status: vec![],
+			min_supported_version: MIN_VERSION,
+		};
+		let message: TestMessage = Message::Status(status.clone());
+
+		let encoded = message.encode_for_version(SENTRY_ROLE_VERSION - 1).unwrap();
+		match TestMessage::decode(&mut &encoded[..]) {
+			Some(Message::Status(ref s)) => {
+				assert_eq!(s.roles, Roles::FULL);
+				assert_eq!(s.version, SENTRY_ROLE_VERSION - 1);
+			},
+			_ => panic!("status must be decodable by version 1 peers"),
+		}
+
+		let encoded = message.encode_for_version(SENTRY_ROLE_VERSION).unwrap();
+		assert_eq!(TestMessage::decode(&mut &encoded[..]), Some(Message::Status(status)));
+	}
+
+	#[test]
+	fn rejects_disjoint_version_ranges() {
+		assert_eq!(negotiate_version(MIN_VERSION - 1, MIN_VERSION - 1), None);
+		assert_eq!(negotiate_version(CURRENT_VERSION + 2, CURRENT_VERSION + 1), None);
+	}
+}
@@ -28,6 +28,7 @@ use protocol::{Protocol, ProtocolContext, Context, ProtocolStatus, PeerInfo as P
 use config::{ProtocolConfig};
 use error::Error;
 use chain::Client;
+use client::BlockImport;
 use message::LocalizedBftMessage;
 use specialization::Specialization;
 use on_demand::OnDemandService;
@@ -56,6 +57,8 @@ bitflags! {
 		const LIGHT = 0b00000010;
 		/// Act as an authority
 		const AUTHORITY = 0b00000100;
+		/// Full node relaying traffic for authorities hidden behind it.
+		const SENTRY = 0b00001000;
 	}
 }
 
@@ -127,6 +130,8 @@ pub struct Params<B: BlockT, S> {
 	pub network_config: NetworkConfiguration,
 	/// Polkadot relay chain access point.
 	pub chain: Arc<Client<B>>,
+	/// Verifies and imports blocks received from the network.
+	pub block_import: Arc<BlockImport<B>>,
 	/// On-demand service reference.
 	pub on_demand: Option<Arc<OnDemandService<B>>>,
 	/// Transaction pool.
@@ -148,7 +153,7 @@ pub struct Service<B: BlockT + 'static, S: Specialization<B>> {
 impl<B: BlockT + 'static, S: Specialization<B>> Service<B, S> {
 	/// Creates and register protocol with the network service
 	pub fn new(params: Params<B, S>, protocol_id: ProtocolId) -> Result<Arc<Service<B, S>>, Error> {
-		let chain = params.chain.clone();
+		let block_import = params.block_import;
 		let import_queue = Arc::new(AsyncImportQueue::new());
 		let handler = Arc::new(ProtocolHandler {
 			protocol: Protocol::new(
@@ -160,7 +165,9 @@ impl<B: BlockT + 'static, S: Specialization<B>> Service<B, S> {
 				params.specialization,
 			)?,
 		});
-		let versions = [(::protocol::CURRENT_VERSION as u8, ::protocol::CURRENT_PACKET_COUNT)];
+		let versions: Vec<_> = (::protocol::MIN_VERSION..::protocol::CURRENT_VERSION + 1)
+			.map(|version| (version as u8, ::protocol::CURRENT_PACKET_COUNT))
+			.collect();
 		let protocols = vec![(handler.clone() as Arc<_>, protocol_id, &versions[..])];
 		let service = match NetworkService::new(params.network_config.clone(), protocols, None) {
 			Ok(service) => service,
@@ -182,7 +189,7 @@ impl<B: BlockT + 'static, S: Specialization<B>> Service<B, S> {
 		import_queue.start(
 			Arc::downgrade(sync.handler.protocol.sync()),
 			Arc::downgrade(&sync),
-			Arc::downgrade(&chain)
+			block_import,
 		)?;
 
 		Ok(sync)
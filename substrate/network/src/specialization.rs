@@ -19,6 +19,24 @@
 use ::NodeIndex;
 use runtime_primitives::traits::Block as BlockT;
 use protocol::Context;
+use chain::Client;
+
+/// Outcome of the validation of a block announcement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockAnnounceValidation {
+	/// The announcement is valid and the announced block may be requested.
+	Valid,
+	/// The announcement is invalid. The announced block is not requested and
+	/// the peer which sent it is penalized.
+	Invalid(String),
+}
+
+/// Chain-specific validation of block announcements, performed before the announced
+/// block is requested from the peer.
+pub trait BlockAnnounceValidator<B: BlockT>: Send + Sync {
+	/// Validate the header of an announced block.
+	fn validate(&self, client: &Client<B>, header: &B::Header) -> BlockAnnounceValidation;
+}
 
 /// A specialization of the substrate network protocol. Handles events and sends messages.
 pub trait Specialization<B: BlockT>: Send + Sync + 'static {
@@ -43,6 +61,12 @@ pub trait Specialization<B: BlockT>: Send + Sync + 'static {
 	/// Called periodically to maintain peers and handle timeouts.
 	fn maintain_peers(&mut self, _ctx: &mut Context<B>) { }
 
+	/// Called when a peer announces a new block, before it is requested through sync.
+	/// Invalid announcements are dropped and the peer is penalized.
+	fn validate_block_announce(&self, _ctx: &Context<B>, _who: NodeIndex, _header: &B::Header) -> BlockAnnounceValidation {
+		BlockAnnounceValidation::Valid
+	}
+
 	/// Called when a block is _imported_ at the head of the chain (not during major sync).
 	fn on_block_imported(&mut self, _ctx: &mut Context<B>, _hash: B::Hash, _header: &B::Header) { }
 }
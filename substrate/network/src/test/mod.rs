@@ -34,13 +34,22 @@ use codec::Encode;
 use import_queue::tests::SyncImportQueue;
 use test_client::{self, TestClient};
 use test_client::runtime::{Block, Hash, Transfer, Extrinsic};
-use specialization::Specialization;
+use specialization::{Specialization, BlockAnnounceValidation, BlockAnnounceValidator};
 
-pub struct DummySpecialization;
+pub struct DummySpecialization {
+	announce_validator: Option<Arc<BlockAnnounceValidator<Block>>>,
+}
 
 impl Specialization<Block> for DummySpecialization {
 	fn status(&self) -> Vec<u8> { vec![] }
 
+	fn validate_block_announce(&self, ctx: &Context<Block>, _peer_id: NodeIndex, header: &<Block as BlockT>::Header) -> BlockAnnounceValidation {
+		match self.announce_validator {
+			Some(ref validator) => validator.validate(ctx.client(), header),
+			None => BlockAnnounceValidation::Valid,
+		}
+	}
+
 	fn on_connect(&mut self, _ctx: &mut Context<Block>, _peer_id: NodeIndex, _status: ::message::Status<Block>) {
 
 	}
@@ -241,10 +250,15 @@ impl TestNet {
 	}
 
 	pub fn add_peer(&mut self, config: &ProtocolConfig) {
+		self.add_peer_with_announce_validator(config, None)
+	}
+
+	pub fn add_peer_with_announce_validator(&mut self, config: &ProtocolConfig, announce_validator: Option<Arc<BlockAnnounceValidator<Block>>>) {
 		let client = Arc::new(test_client::new());
 		let tx_pool = Arc::new(EmptyTransactionPool);
-		let import_queue = Arc::new(SyncImportQueue);
-		let sync = Protocol::new(config.clone(), client.clone(), import_queue, None, tx_pool, DummySpecialization).unwrap();
+		let import_queue = Arc::new(SyncImportQueue(client.clone()));
+		let specialization = DummySpecialization { announce_validator };
+		let sync = Protocol::new(config.clone(), client.clone(), import_queue, None, tx_pool, specialization).unwrap();
 		self.peers.push(Arc::new(Peer {
 			sync: sync,
 			client: client,
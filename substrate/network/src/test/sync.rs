@@ -17,6 +17,8 @@
 use client::backend::Backend;
 use client::blockchain::HeaderBackend as BlockchainHeaderBackend;
 use sync::SyncState;
+use specialization::{BlockAnnounceValidation, BlockAnnounceValidator};
+use chain::Client;
 use Roles;
 use super::*;
 
@@ -119,3 +121,31 @@ fn blocks_are_not_announced_by_light_nodes() {
 	assert_eq!(net.peer(1).client.backend().blockchain().info().unwrap().best_number, 1);
 	assert_eq!(net.peer(2).client.backend().blockchain().info().unwrap().best_number, 0);
 }
+
+#[test]
+fn invalid_block_announcements_are_rejected() {
+	struct RejectAll;
+
+	impl BlockAnnounceValidator<Block> for RejectAll {
+		fn validate(&self, _client: &Client<Block>, _header: &<Block as BlockT>::Header) -> BlockAnnounceValidation {
+			BlockAnnounceValidation::Invalid("rejecting everything".into())
+		}
+	}
+
+	::env_logger::init().ok();
+	let mut net = TestNet::new(0);
+	net.add_peer(&ProtocolConfig::default());
+	net.add_peer_with_announce_validator(&ProtocolConfig::default(), Some(Arc::new(RejectAll)));
+	net.sync();
+
+	// peer 0 imports a block and announces it to peer 1, which rejects the announcement
+	net.peer(0).push_blocks(1, false);
+	net.peer(0).start();
+	while !net.done() {
+		net.sync_step();
+	}
+
+	assert_eq!(net.peer(0).client.backend().blockchain().info().unwrap().best_number, 1);
+	assert_eq!(net.peer(1).client.backend().blockchain().info().unwrap().best_number, 0);
+	assert!(net.disconnect_events.contains(&(0, 0)));
+}
@@ -17,6 +17,7 @@
 //! Hashing functions.
 
 use blake2_rfc;
+use tiny_keccak;
 use twox_hash;
 
 /// Do a Blake2 512-bit hash and place result in `dest`.
@@ -55,6 +56,11 @@ pub fn blake2_128(data: &[u8]) -> [u8; 16] {
 	r
 }
 
+/// Do a Keccak 256-bit hash, as used by Ethereum, and return result.
+pub fn keccak_256(data: &[u8]) -> [u8; 32] {
+	tiny_keccak::keccak256(data)
+}
+
 /// Do a XX 128-bit hash and place result in `dest`.
 pub fn twox_128_into(data: &[u8], dest: &mut [u8; 16]) {
 	use ::core::hash::Hasher;
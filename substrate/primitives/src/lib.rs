@@ -38,6 +38,8 @@ extern crate twox_hash;
 #[cfg(feature = "std")]
 extern crate blake2_rfc;
 #[cfg(feature = "std")]
+extern crate tiny_keccak;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate serde_derive;
 #[cfg(feature = "std")]
@@ -69,7 +71,7 @@ pub mod bytes;
 #[cfg(feature = "std")]
 pub mod hashing;
 #[cfg(feature = "std")]
-pub use hashing::{blake2_256, twox_128, twox_256};
+pub use hashing::{blake2_256, twox_128, twox_256, keccak_256};
 #[cfg(feature = "std")]
 pub mod hexdisplay;
 
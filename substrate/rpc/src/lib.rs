@@ -23,6 +23,7 @@ extern crate jsonrpc_pubsub;
 extern crate parking_lot;
 extern crate substrate_codec as codec;
 extern crate substrate_client as client;
+extern crate substrate_executor as executor;
 extern crate substrate_extrinsic_pool as extrinsic_pool;
 extern crate substrate_primitives as primitives;
 extern crate substrate_runtime_primitives as runtime_primitives;
@@ -93,6 +93,8 @@ pub struct State<B, E, Block: BlockT> {
 	client: Arc<Client<B, E, Block>>,
 	/// Current subscriptions.
 	subscriptions: Subscriptions,
+	/// Fuel limit for wasm execution of runtime calls. `None` if unlimited.
+	fuel_limit: Option<u64>,
 }
 
 impl<B, E, Block: BlockT> State<B, E, Block> {
@@ -101,8 +103,15 @@ impl<B, E, Block: BlockT> State<B, E, Block> {
 		Self {
 			client,
 			subscriptions: Subscriptions::new(executor),
+			fuel_limit: None,
 		}
 	}
+
+	/// Limit the fuel available to runtime calls made through this API.
+	pub fn with_fuel_limit(mut self, fuel_limit: Option<u64>) -> Self {
+		self.fuel_limit = fuel_limit;
+		self
+	}
 }
 
 impl<B, E, Block> StateApi<Block::Hash> for State<B, E, Block> where
@@ -119,7 +128,12 @@ impl<B, E, Block> StateApi<Block::Hash> for State<B, E, Block> where
 
 	fn call_at(&self, method: String, data: Vec<u8>, block: Block::Hash) -> Result<Vec<u8>> {
 		trace!(target: "rpc", "Calling runtime at {:?} for method {} ({})", block, method, HexDisplay::from(&data));
-		Ok(self.client.executor().call(&BlockId::Hash(block), &method, &data)?.return_data)
+		let call = || self.client.executor().call(&BlockId::Hash(block), &method, &data);
+		let result = match self.fuel_limit {
+			Some(limit) => executor::with_restricted_fuel(limit, call),
+			None => call(),
+		};
+		Ok(result?.return_data)
 	}
 
 	fn storage_hash_at(&self, key: StorageKey, block: Block::Hash) -> Result<Block::Hash> {
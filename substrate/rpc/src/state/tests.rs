@@ -59,6 +59,7 @@ fn should_notify_about_storage_changes() {
 		let api = State {
 			client: Arc::new(test_client::new()),
 			subscriptions: Subscriptions::new(remote),
+			fuel_limit: None,
 		};
 
 		api.subscribe_storage(Default::default(), subscriber, None.into());
@@ -93,6 +94,7 @@ fn should_send_initial_storage_changes_and_notifications() {
 		let api = State {
 			client: Arc::new(test_client::new()),
 			subscriptions: Subscriptions::new(remote),
+			fuel_limit: None,
 		};
 
 		api.subscribe_storage(Default::default(), subscriber, Some(vec![
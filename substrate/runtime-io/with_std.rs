@@ -23,18 +23,29 @@ extern crate substrate_primitives as primitives;
 extern crate substrate_state_machine;
 extern crate triehash;
 extern crate ed25519;
+extern crate secp256k1;
+extern crate schnorrkel;
+extern crate rayon;
 
 #[doc(hidden)]
 pub extern crate substrate_codec as codec;
 // re-export hashing functions.
-pub use primitives::{blake2_256, twox_128, twox_256};
+pub use primitives::{blake2_256, twox_128, twox_256, keccak_256};
 
-pub use substrate_state_machine::{Externalities, TestExternalities};
+pub use substrate_state_machine::{Externalities, OffchainExt, TestExternalities};
+use std::cell::RefCell;
 use primitives::hexdisplay::HexDisplay;
+use rayon::prelude::*;
 
 // TODO: use the real error, not NoError.
 
 environmental!(ext: trait Externalities);
+environmental!(offchain: trait OffchainExt);
+
+thread_local! {
+	// signatures queued since `start_batch_verify`, if a batch is in progress.
+	static BATCH_VERIFIER: RefCell<Option<ParallelVerifier>> = RefCell::new(None);
+}
 
 /// Get `key` from storage and return a `Vec`, empty if there's a problem.
 pub fn storage(key: &[u8]) -> Option<Vec<u8>> {
@@ -123,10 +134,128 @@ pub fn ed25519_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) ->
 	ed25519::verify(sig, msg, pubkey)
 }
 
+/// Signatures queued during a batch, to be verified in parallel once it finishes.
+///
+/// This is not batch verification in the cryptographic sense: every signature is still checked
+/// on its own, so the work per signature stays the same and only the wall time is shortened by
+/// spreading the checks over threads.
+#[derive(Default)]
+pub struct ParallelVerifier {
+	ed25519: Vec<([u8; 64], Vec<u8>, Vec<u8>)>,
+}
+
+impl ParallelVerifier {
+	/// Queue an ed25519 signature.
+	pub fn push_ed25519(&mut self, sig: [u8; 64], msg: Vec<u8>, pubkey: Vec<u8>) {
+		self.ed25519.push((sig, msg, pubkey));
+	}
+
+	/// Verify each queued signature, in parallel. Returns `true` if all of them are valid.
+	pub fn verify(self) -> bool {
+		self.ed25519.par_iter().all(|&(ref sig, ref msg, ref pubkey)| ed25519::verify(&sig[..], msg, pubkey))
+	}
+}
+
+/// Start a batch: signatures passed to `ed25519_batch_verify` are queued rather than verified
+/// right away, until `finish_batch_verify` is called. A batch already in progress is kept.
+pub fn start_batch_verify() {
+	BATCH_VERIFIER.with(|b| {
+		let mut batch = b.borrow_mut();
+		if batch.is_none() {
+			*batch = Some(ParallelVerifier::default());
+		}
+	})
+}
+
+/// Verify an ed25519 signature, or queue it if a batch is in progress. A queued signature is
+/// reported as valid; whether it really is only becomes known through `finish_batch_verify`.
+pub fn ed25519_batch_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) -> bool {
+	let queued = BATCH_VERIFIER.with(|b| match *b.borrow_mut() {
+		Some(ref mut batch) => {
+			batch.push_ed25519(*sig, msg.to_vec(), pubkey.as_ref().to_vec());
+			true
+		}
+		None => false,
+	});
+	queued || ed25519_verify(sig, msg, pubkey)
+}
+
+/// Finish the batch in progress, verifying all of its signatures in parallel. Returns `true` if
+/// all of them are valid or there was no batch in progress.
+pub fn finish_batch_verify() -> bool {
+	BATCH_VERIFIER.with(|b| b.borrow_mut().take()).map_or(true, ParallelVerifier::verify)
+}
+
+/// Signing context of sr25519 signatures made by Substrate keys.
+const SR25519_SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// Verify an sr25519 signature.
+pub fn sr25519_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) -> bool {
+	let signature = match schnorrkel::Signature::from_bytes(sig) {
+		Ok(signature) => signature,
+		Err(_) => return false,
+	};
+	match schnorrkel::PublicKey::from_bytes(pubkey.as_ref()) {
+		Ok(public) => public.verify(schnorrkel::signing_context(SR25519_SIGNING_CONTEXT).bytes(msg), &signature),
+		Err(_) => false,
+	}
+}
+
+/// Recover the public key which produced the secp256k1 ECDSA signature `sig` of the message hash
+/// `msg`. The signature is `r`, `s` and the recovery id `v`, which may be given Ethereum style as
+/// 27 or 28. Returns the uncompressed public key without its leading `0x04` byte.
+pub fn secp256k1_ecdsa_recover(sig: &[u8; 65], msg: &[u8; 32]) -> Option<[u8; 64]> {
+	let v = secp256k1::RecoveryId::parse(if sig[64] > 26 { sig[64] - 27 } else { sig[64] }).ok()?;
+	let mut rs = [0u8; 64];
+	rs.copy_from_slice(&sig[0..64]);
+	let public = secp256k1::recover(&secp256k1::Message::parse(msg), &secp256k1::Signature::parse(&rs), &v).ok()?;
+
+	let mut res = [0u8; 64];
+	res.copy_from_slice(&public.serialize()[1..65]);
+	Some(res)
+}
+
+/// Submit an encoded extrinsic to the local extrinsic pool. Only available to offchain workers;
+/// returns `true` if the extrinsic was accepted.
+pub fn submit_extrinsic(extrinsic: &[u8]) -> bool {
+	offchain::with(|offchain| offchain.submit_extrinsic(extrinsic.to_vec()))
+		.unwrap_or(false)
+}
+
+/// Read an entry of the node-local key-value store. Only available to offchain workers.
+pub fn local_storage_get(key: &[u8]) -> Option<Vec<u8>> {
+	offchain::with(|offchain| offchain.local_storage_get(key))
+		.unwrap_or(None)
+}
+
+/// Set an entry of the node-local key-value store. Only available to offchain workers.
+pub fn local_storage_set(key: &[u8], value: &[u8]) {
+	offchain::with(|offchain|
+		offchain.local_storage_set(key.to_vec(), value.to_vec())
+	);
+}
+
+/// Sign a message with the node's local ed25519 key. Only available to offchain workers; returns
+/// the public key and the signature, or `None` if the node has no key.
+pub fn local_sign(msg: &[u8]) -> Option<([u8; 32], [u8; 64])> {
+	offchain::with(|offchain| offchain.sign(msg))
+		.unwrap_or(None)
+}
+
+/// Execute the given closure with the offchain worker functions routing into `offchain`.
+/// Forwards the value that the closure returns.
+pub fn with_offchain<R, F: FnOnce() -> R>(offchain: &mut OffchainExt, f: F) -> R {
+	offchain::using(offchain, f)
+}
+
 /// Execute the given closure with global function available whose functionality routes into the
 /// externalities `ext`. Forwards the value that the closure returns.
 pub fn with_externalities<R, F: FnOnce() -> R>(ext: &mut Externalities, f: F) -> R {
-	ext::using(ext, f)
+	// a batch never outlives the call which started it, even if that call panicked.
+	BATCH_VERIFIER.with(|b| *b.borrow_mut() = None);
+	let r = ext::using(ext, f);
+	BATCH_VERIFIER.with(|b| *b.borrow_mut() = None);
+	r
 }
 
 /// Trait for things which can be printed.
@@ -244,4 +373,61 @@ mod std_tests {
 			assert!(storage(b":abc").is_none());
 		});
 	}
+
+	#[test]
+	fn secp256k1_ecdsa_recover_works() {
+		let secret = secp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+		let public = secp256k1::PublicKey::from_secret_key(&secret);
+		let msg = keccak_256(b"Hello world!");
+		let (rs, v) = secp256k1::sign(&secp256k1::Message::parse(&msg), &secret).unwrap();
+
+		let mut sig = [0u8; 65];
+		sig[0..64].copy_from_slice(&rs.serialize()[..]);
+		sig[64] = v.serialize() + 27;
+		assert_eq!(&secp256k1_ecdsa_recover(&sig, &msg).unwrap()[..], &public.serialize()[1..65]);
+
+		sig[64] = 4;
+		assert!(secp256k1_ecdsa_recover(&sig, &msg).is_none());
+	}
+
+	#[test]
+	fn batch_verify_works() {
+		let pair = ed25519::Pair::from_seed(&[1u8; 32]);
+		let good = pair.sign(b"good").0;
+		let bad = pair.sign(b"bad").0;
+
+		assert!(ed25519_batch_verify(&good, b"good", pair.public()));
+		assert!(!ed25519_batch_verify(&bad, b"good", pair.public()));
+
+		start_batch_verify();
+		assert!(ed25519_batch_verify(&good, b"good", pair.public()));
+		assert!(finish_batch_verify());
+
+		start_batch_verify();
+		assert!(ed25519_batch_verify(&good, b"good", pair.public()));
+		assert!(ed25519_batch_verify(&bad, b"good", pair.public()));
+		assert!(!finish_batch_verify());
+
+		// nothing is left queued after a batch.
+		assert!(finish_batch_verify());
+	}
+
+	#[test]
+	fn sr25519_verify_works() {
+		let pair = schnorrkel::MiniSecretKey::from_bytes(&[1u8; 32]).unwrap().expand_to_keypair();
+		let public = pair.public.to_bytes();
+		let msg = b"Hello world!";
+		let sig = pair.sign(schnorrkel::signing_context(b"substrate").bytes(msg)).to_bytes();
+		assert!(sr25519_verify(&sig, msg, &public));
+		assert!(!sr25519_verify(&sig, b"Hello world?", &public));
+
+		// signatures made in another context are rejected.
+		let other = pair.sign(schnorrkel::signing_context(b"other").bytes(msg)).to_bytes();
+		assert!(!sr25519_verify(&other, msg, &public));
+	}
+
+	#[test]
+	fn sr25519_verify_rejects_garbage() {
+		assert!(!sr25519_verify(&[0u8; 64], b"Hello world!", &[0u8; 32]));
+	}
 }
@@ -66,7 +66,17 @@ extern "C" {
 	fn ext_blake2_256(data: *const u8, len: u32, out: *mut u8);
 	fn ext_twox_128(data: *const u8, len: u32, out: *mut u8);
 	fn ext_twox_256(data: *const u8, len: u32, out: *mut u8);
+	fn ext_keccak_256(data: *const u8, len: u32, out: *mut u8);
 	fn ext_ed25519_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32;
+	fn ext_start_batch_verify();
+	fn ext_ed25519_batch_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32;
+	fn ext_finish_batch_verify() -> u32;
+	fn ext_submit_extrinsic(data: *const u8, len: u32) -> u32;
+	fn ext_local_storage_get(key_data: *const u8, key_len: u32, written_out: *mut u32) -> *mut u8;
+	fn ext_local_storage_set(key_data: *const u8, key_len: u32, value_data: *const u8, value_len: u32);
+	fn ext_local_sign(msg_data: *const u8, msg_len: u32, pubkey_data: *mut u8, sig_data: *mut u8) -> u32;
+	fn ext_sr25519_verify(msg_data: *const u8, msg_len: u32, sig_data: *const u8, pubkey_data: *const u8) -> u32;
+	fn ext_secp256k1_ecdsa_recover(msg_data: *const u8, sig_data: *const u8, pubkey_data: *mut u8) -> u32;
 }
 
 /// Get `key` from storage and return a `Vec`, empty if there's a problem.
@@ -214,6 +224,15 @@ pub fn twox_128(data: &[u8]) -> [u8; 16] {
 	result
 }
 
+/// Conduct a 256-bit Keccak hash, as used by Ethereum.
+pub fn keccak_256(data: &[u8]) -> [u8; 32] {
+	let mut result: [u8; 32] = Default::default();
+	unsafe {
+		ext_keccak_256(data.as_ptr(), data.len() as u32, result.as_mut_ptr());
+	}
+	result
+}
+
 /// Verify a ed25519 signature.
 pub fn ed25519_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) -> bool {
 	unsafe {
@@ -221,6 +240,90 @@ pub fn ed25519_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) ->
 	}
 }
 
+/// Start a batch: signatures passed to `ed25519_batch_verify` are queued rather than verified
+/// right away, until `finish_batch_verify` is called. A batch already in progress is kept.
+pub fn start_batch_verify() {
+	unsafe {
+		ext_start_batch_verify();
+	}
+}
+
+/// Verify an ed25519 signature, or queue it if a batch is in progress. A queued signature is
+/// reported as valid; whether it really is only becomes known through `finish_batch_verify`.
+pub fn ed25519_batch_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) -> bool {
+	unsafe {
+		ext_ed25519_batch_verify(msg.as_ptr(), msg.len() as u32, sig.as_ptr(), pubkey.as_ref().as_ptr()) == 0
+	}
+}
+
+/// Finish the batch in progress, verifying all of its signatures in parallel. Returns `true` if
+/// all of them are valid or there was no batch in progress.
+pub fn finish_batch_verify() -> bool {
+	unsafe {
+		ext_finish_batch_verify() == 0
+	}
+}
+
+/// Verify an sr25519 signature.
+pub fn sr25519_verify<P: AsRef<[u8]>>(sig: &[u8; 64], msg: &[u8], pubkey: P) -> bool {
+	unsafe {
+		ext_sr25519_verify(msg.as_ptr(), msg.len() as u32, sig.as_ptr(), pubkey.as_ref().as_ptr()) == 0
+	}
+}
+
+/// Recover the public key which produced the secp256k1 ECDSA signature `sig` of the message hash
+/// `msg`. The signature is `r`, `s` and the recovery id `v`, which may be given Ethereum style as
+/// 27 or 28. Returns the uncompressed public key without its leading `0x04` byte.
+pub fn secp256k1_ecdsa_recover(sig: &[u8; 65], msg: &[u8; 32]) -> Option<[u8; 64]> {
+	let mut pubkey = [0u8; 64];
+	match unsafe { ext_secp256k1_ecdsa_recover(msg.as_ptr(), sig.as_ptr(), pubkey.as_mut_ptr()) } {
+		0 => Some(pubkey),
+		_ => None,
+	}
+}
+
+/// Submit an encoded extrinsic to the local extrinsic pool. Only available to offchain workers;
+/// returns `true` if the extrinsic was accepted.
+pub fn submit_extrinsic(extrinsic: &[u8]) -> bool {
+	unsafe {
+		ext_submit_extrinsic(extrinsic.as_ptr(), extrinsic.len() as u32) == 0
+	}
+}
+
+/// Read an entry of the node-local key-value store. Only available to offchain workers.
+pub fn local_storage_get(key: &[u8]) -> Option<Vec<u8>> {
+	let mut length: u32 = 0;
+	unsafe {
+		let ptr = ext_local_storage_get(key.as_ptr(), key.len() as u32, &mut length);
+		if length == u32::max_value() {
+			None
+		} else {
+			Some(Vec::from_raw_parts(ptr, length as usize, length as usize))
+		}
+	}
+}
+
+/// Set an entry of the node-local key-value store. Only available to offchain workers.
+pub fn local_storage_set(key: &[u8], value: &[u8]) {
+	unsafe {
+		ext_local_storage_set(
+			key.as_ptr(), key.len() as u32,
+			value.as_ptr(), value.len() as u32
+		);
+	}
+}
+
+/// Sign a message with the node's local ed25519 key. Only available to offchain workers; returns
+/// the public key and the signature, or `None` if the node has no key.
+pub fn local_sign(msg: &[u8]) -> Option<([u8; 32], [u8; 64])> {
+	let mut pubkey = [0u8; 32];
+	let mut sig = [0u8; 64];
+	match unsafe { ext_local_sign(msg.as_ptr(), msg.len() as u32, pubkey.as_mut_ptr(), sig.as_mut_ptr()) } {
+		0 => Some((pubkey, sig)),
+		_ => None,
+	}
+}
+
 /// Trait for things which can be printed.
 pub trait Printable {
 	fn print(self);
@@ -36,17 +36,26 @@ extern crate substrate_runtime_io as runtime_io;
 extern crate substrate_runtime_primitives as primitives;
 extern crate substrate_codec as codec;
 extern crate substrate_runtime_system as system;
+extern crate substrate_misbehavior_check as misbehavior_check;
 extern crate substrate_primitives;
 
+#[cfg(test)]
+extern crate substrate_keyring as keyring;
+
 use rstd::prelude::*;
-use runtime_support::{storage, Parameter};
+use codec::Encode;
+use runtime_support::{storage, Parameter, StorageValue, StorageMap};
 use runtime_support::dispatch::Result;
 use runtime_support::storage::unhashed::StorageVec;
-use primitives::traits::{RefInto, MaybeSerializeDebug, MaybeEmpty};
+use primitives::traits::{RefInto, MaybeSerializeDebug, MaybeEmpty, As};
 use primitives::bft::MisbehaviorReport;
+use substrate_primitives::AuthorityId;
 
 pub const AUTHORITY_AT: &'static [u8] = b":auth:";
 pub const AUTHORITY_COUNT: &'static [u8] = b":auth:len";
+/// The number of the block which last changed the authorities. Finality voters use it to
+/// identify the authority set in charge.
+pub const AUTHORITY_SET_CHANGED: &'static [u8] = b":auth_changed";
 
 struct AuthorityStorageVec<S: codec::Codec + Default>(rstd::marker::PhantomData<S>);
 impl<S: codec::Codec + Default> StorageVec for AuthorityStorageVec<S> {
@@ -58,9 +67,39 @@ pub const CODE: &'static [u8] = b":code";
 
 pub type KeyValue = (Vec<u8>, Vec<u8>);
 
+/// Number of blocks after which misbehavior can no longer be reported.
+pub const REPORT_LATENCY: u64 = 4096;
+
+/// An authority has been proven to misbehave.
+pub trait OnMisbehavior<SessionKey, AccountId> {
+	/// The authority with session key `offender` misbehaved, as proven by `reporter`.
+	fn on_misbehavior(offender: &SessionKey, reporter: &AccountId);
+}
+
+impl<SessionKey, AccountId> OnMisbehavior<SessionKey, AccountId> for () {
+	fn on_misbehavior(_offender: &SessionKey, _reporter: &AccountId) {}
+}
+
+/// An authority has been noted offline by a block author.
+pub trait OnOfflineValidator {
+	/// The authority at `validator_index` in the current set was noted offline.
+	fn on_offline_validator(validator_index: usize);
+}
+
+impl OnOfflineValidator for () {
+	fn on_offline_validator(_validator_index: usize) {}
+}
+
 pub trait Trait: system::Trait {
+	// the position of the optional note-offline extrinsic.
+	const NOTE_OFFLINE_POSITION: u32;
+
 	type PublicAux: RefInto<Self::AccountId> + MaybeEmpty;		// MaybeEmpty is for Timestamp's usage.
 	type SessionKey: Parameter + Default + MaybeSerializeDebug;
+	/// Invoked for each verified misbehavior report; expected to punish the offender.
+	type OnMisbehavior: OnMisbehavior<Self::SessionKey, Self::AccountId>;
+	/// Invoked for each authority noted offline; expected to track its liveness.
+	type OnOfflineValidator: OnOfflineValidator;
 }
 
 decl_module! {
@@ -70,6 +109,7 @@ decl_module! {
 	pub enum Call where aux: T::PublicAux {
 		fn report_misbehavior(aux, report: MisbehaviorReport<T::Hash, T::BlockNumber>) -> Result = 0;
 		fn remark(aux, remark: Vec<u8>) -> Result = 1;
+		fn note_offline(aux, offline_val_indices: Vec<u32>) -> Result = 2;
 	}
 
 	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -79,12 +119,44 @@ decl_module! {
 	}
 }
 
+decl_storage! {
+	trait Store for Module<T: Trait>;
+
+	// Authority sets which were replaced during the last `REPORT_LATENCY` blocks, oldest first.
+	// Each set is paired with the number of the block during which it was replaced, i.e. it
+	// was in charge of building on top of all parents with a lower number.
+	pub AuthorityHistory get(authority_history): b"con:ahi" => default Vec<(T::BlockNumber, Vec<T::SessionKey>)>;
+	// Misbehavior which was already reported, by parent hash and offender.
+	pub Reported get(reported): b"con:rep:" => default map [ (T::Hash, AuthorityId) => bool ];
+	// Keys of `Reported` along with the parent number of the report. Used to prune reports
+	// which are too old to be made again.
+	pub ReportedIndex get(reported_index): b"con:rix" => default Vec<(T::BlockNumber, (T::Hash, AuthorityId))>;
+}
+
 impl<T: Trait> Module<T> {
 	/// Get the current set of authorities. These are the session keys.
 	pub fn authorities() -> Vec<T::SessionKey> {
 		AuthorityStorageVec::<T::SessionKey>::items()
 	}
 
+	/// Get the set of authorities which was in charge of building on top of the block
+	/// with the given number. `None` if this is too far in the past to be known.
+	pub fn authorities_at(parent_number: T::BlockNumber) -> Option<Vec<T::SessionKey>> {
+		let history = Self::authority_history();
+		let now = <system::Module<T>>::block_number();
+		if parent_number >= now {
+			return None;
+		}
+		if now - parent_number > T::BlockNumber::sa(REPORT_LATENCY) {
+			return None;
+		}
+
+		match history.into_iter().find(|&(replaced_at, _)| parent_number < replaced_at) {
+			Some((_, set)) => Some(set),
+			None => Some(Self::authorities()),
+		}
+	}
+
 	/// Set the new code.
 	fn set_code(new: Vec<u8>) -> Result {
 		storage::unhashed::put_raw(CODE, &new);
@@ -100,27 +172,123 @@ impl<T: Trait> Module<T> {
 	}
 
 	/// Report some misbehaviour.
-	fn report_misbehavior(_aux: &T::PublicAux, _report: MisbehaviorReport<T::Hash, T::BlockNumber>) -> Result {
-		// TODO.
+	///
+	/// The report is checked against the authority set of the block it refers to and the
+	/// offender gets punished through `OnMisbehavior`. Each offence may only be reported once.
+	fn report_misbehavior(aux: &T::PublicAux, report: MisbehaviorReport<T::Hash, T::BlockNumber>) -> Result {
+		let reporter = aux.ref_into();
+		ensure!(reporter.encode() != report.target.encode(), "offender may not report itself");
+
+		let reported_key = (report.parent_hash, report.target.clone());
+		ensure!(!Self::reported(&reported_key), "misbehavior already reported");
+
+		ensure!(<system::BlockHash<T>>::exists(&report.parent_number), "unknown parent block");
+		ensure!(
+			<system::Module<T>>::block_hash(&report.parent_number) == report.parent_hash,
+			"parent hash doesn't match parent number"
+		);
+
+		let authorities = Self::authorities_at(report.parent_number).ok_or("report is too old")?;
+		let offender = authorities.into_iter()
+			.find(|key| key.encode() == report.target.encode())
+			.ok_or("target wasn't an authority at that block")?;
+
+		ensure!(
+			misbehavior_check::evaluate_misbehavior::<(), T::Hash>(
+				&report.target,
+				report.parent_hash,
+				&report.misbehavior,
+			),
+			"invalid misbehavior proof"
+		);
+
+		Self::prune_reported();
+		let mut index = Self::reported_index();
+		index.push((report.parent_number, reported_key.clone()));
+		<ReportedIndex<T>>::put(index);
+		<Reported<T>>::insert(&reported_key, true);
+
+		T::OnMisbehavior::on_misbehavior(&offender, reporter);
 		Ok(())
 	}
 
+	/// Forget reports whose parent is more than `REPORT_LATENCY` blocks old; they can no
+	/// longer be made again anyway.
+	fn prune_reported() {
+		let now = <system::Module<T>>::block_number();
+		let latency = T::BlockNumber::sa(REPORT_LATENCY);
+		let (stale, live): (Vec<_>, Vec<_>) = Self::reported_index().into_iter()
+			.partition(|&(parent_number, _)| parent_number + latency < now);
+		if stale.is_empty() {
+			return;
+		}
+
+		for (_, key) in stale {
+			<Reported<T>>::remove(&key);
+		}
+		<ReportedIndex<T>>::put(live);
+	}
+
 	/// Make some on-chain remark.
 	fn remark(_aux: &T::PublicAux, _remark: Vec<u8>) -> Result {
 		Ok(())
 	}
 
+	/// Note the authorities which the block author saw offline, by index in the current set.
+	///
+	/// This is an inherent extrinsic; it may only appear at `NOTE_OFFLINE_POSITION`.
+	fn note_offline(aux: &T::PublicAux, offline_val_indices: Vec<u32>) -> Result {
+		assert!(aux.is_empty());
+		assert!(
+			<system::Module<T>>::extrinsic_index() == T::NOTE_OFFLINE_POSITION,
+			"note_offline extrinsic must be at position {} in the block",
+			T::NOTE_OFFLINE_POSITION
+		);
+
+		let authority_count = AuthorityStorageVec::<T::SessionKey>::count();
+		ensure!(
+			offline_val_indices.iter().all(|&i| i < authority_count),
+			"offline validator index out of range"
+		);
+		for validator_index in offline_val_indices {
+			T::OnOfflineValidator::on_offline_validator(validator_index as usize);
+		}
+		Ok(())
+	}
+
 	/// Set the current set of authorities' session keys.
 	///
 	/// Called by `next_session` only.
 	pub fn set_authorities(authorities: &[T::SessionKey]) {
+		Self::note_authorities_change();
 		AuthorityStorageVec::<T::SessionKey>::set_items(authorities);
 	}
 
 	/// Set a single authority by index.
 	pub fn set_authority(index: u32, key: &T::SessionKey) {
+		Self::note_authorities_change();
 		AuthorityStorageVec::<T::SessionKey>::set_item(index, key);
 	}
+
+	/// Save the current authority set into the history before it gets changed, so that
+	/// misbehavior from before the change can still be checked. Also prunes sets which
+	/// are too old to be reported against.
+	fn note_authorities_change() {
+		let now = <system::Module<T>>::block_number();
+		storage::unhashed::put(AUTHORITY_SET_CHANGED, &now);
+		let mut history = Self::authority_history();
+
+		// only the set which was in place at the beginning of the block is of interest.
+		if history.last().map_or(false, |&(replaced_at, _)| replaced_at == now) {
+			return;
+		}
+
+		let latency = T::BlockNumber::sa(REPORT_LATENCY);
+		history.retain(|&(replaced_at, _)| replaced_at + latency >= now);
+		history.push((now, Self::authorities()));
+		<AuthorityHistory<T>>::put(history);
+		Self::prune_reported();
+	}
 }
 
 #[cfg(any(feature = "std", test))]
@@ -157,3 +325,169 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T>
 		Ok(r)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::RefCell;
+	use runtime_io::with_externalities;
+	use substrate_primitives::H256;
+	use primitives::BuildStorage;
+	use primitives::bft::{Action, Message, MisbehaviorKind};
+	use primitives::traits::{HasPublicAux, BlakeTwo256};
+	use primitives::testing::{Digest, Header};
+	use keyring::Keyring;
+
+	thread_local! {
+		static MISBEHAVIOR: RefCell<Vec<(AuthorityId, H256)>> = RefCell::new(Vec::new());
+	}
+
+	pub struct RecordMisbehavior;
+	impl OnMisbehavior<AuthorityId, H256> for RecordMisbehavior {
+		fn on_misbehavior(offender: &AuthorityId, reporter: &H256) {
+			MISBEHAVIOR.with(|m| m.borrow_mut().push((*offender, *reporter)));
+		}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl HasPublicAux for Test {
+		type PublicAux = H256;
+	}
+	impl system::Trait for Test {
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = H256;
+		type Header = Header;
+	}
+	impl Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
+		type PublicAux = H256;
+		type SessionKey = AuthorityId;
+		type OnMisbehavior = RecordMisbehavior;
+		type OnOfflineValidator = ();
+	}
+	type Consensus = Module<Test>;
+
+	const PARENT_NUMBER: u64 = 1;
+
+	fn parent_hash() -> H256 {
+		[0xff; 32].into()
+	}
+
+	fn new_test_ext() -> runtime_io::TestExternalities {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		t.extend(GenesisConfig::<Test> {
+			authorities: vec![Keyring::One.to_raw_public().into(), Keyring::Two.to_raw_public().into()],
+			code: vec![],
+		}.build_storage().unwrap());
+		t
+	}
+
+	fn sign_prepare(key: Keyring, round: u32, hash: H256) -> (H256, substrate_primitives::Signature) {
+		let message = Message::<(), H256> { parent: parent_hash(), action: Action::Prepare(round, hash) };
+		(hash, key.sign(&message.encode()).into())
+	}
+
+	fn double_prepare_by(key: Keyring) -> MisbehaviorReport<H256, u64> {
+		MisbehaviorReport {
+			parent_hash: parent_hash(),
+			parent_number: PARENT_NUMBER,
+			target: key.to_raw_public().into(),
+			misbehavior: MisbehaviorKind::BftDoublePrepare(
+				1,
+				sign_prepare(key, 1, [1; 32].into()),
+				sign_prepare(key, 1, [2; 32].into()),
+			),
+		}
+	}
+
+	fn setup_parent_block(now: u64) {
+		<system::BlockHash<Test>>::insert(PARENT_NUMBER, parent_hash());
+		<system::Module<Test>>::set_block_number(now);
+		MISBEHAVIOR.with(|m| m.borrow_mut().clear());
+	}
+
+	fn reported_misbehavior() -> Vec<(AuthorityId, H256)> {
+		MISBEHAVIOR.with(|m| m.borrow().clone())
+	}
+
+	#[test]
+	fn valid_report_punishes_offender() {
+		with_externalities(&mut new_test_ext(), || {
+			setup_parent_block(2);
+			let reporter: H256 = Keyring::Two.to_raw_public().into();
+
+			assert_ok!(Consensus::report_misbehavior(&reporter, double_prepare_by(Keyring::One)));
+			assert_eq!(reported_misbehavior(), vec![(Keyring::One.to_raw_public().into(), reporter)]);
+		});
+	}
+
+	#[test]
+	fn report_with_invalid_signature_is_rejected() {
+		with_externalities(&mut new_test_ext(), || {
+			setup_parent_block(2);
+			let mut report = double_prepare_by(Keyring::One);
+			// signed by another authority than the target.
+			report.misbehavior = MisbehaviorKind::BftDoublePrepare(
+				1,
+				sign_prepare(Keyring::Two, 1, [1; 32].into()),
+				sign_prepare(Keyring::Two, 1, [2; 32].into()),
+			);
+
+			assert_eq!(Consensus::report_misbehavior(&[7; 32].into(), report), Err("invalid misbehavior proof"));
+			assert!(reported_misbehavior().is_empty());
+		});
+	}
+
+	#[test]
+	fn misbehavior_can_only_be_reported_once() {
+		with_externalities(&mut new_test_ext(), || {
+			setup_parent_block(2);
+			let reporter: H256 = [7; 32].into();
+
+			assert_ok!(Consensus::report_misbehavior(&reporter, double_prepare_by(Keyring::One)));
+			assert_eq!(
+				Consensus::report_misbehavior(&reporter, double_prepare_by(Keyring::One)),
+				Err("misbehavior already reported")
+			);
+			assert_eq!(reported_misbehavior().len(), 1);
+		});
+	}
+
+	#[test]
+	fn offender_may_not_report_itself() {
+		with_externalities(&mut new_test_ext(), || {
+			setup_parent_block(2);
+			let reporter: H256 = Keyring::One.to_raw_public().into();
+
+			assert_eq!(
+				Consensus::report_misbehavior(&reporter, double_prepare_by(Keyring::One)),
+				Err("offender may not report itself")
+			);
+			assert!(reported_misbehavior().is_empty());
+		});
+	}
+
+	#[test]
+	fn old_reports_are_pruned() {
+		with_externalities(&mut new_test_ext(), || {
+			setup_parent_block(2);
+			let key = (parent_hash(), Keyring::One.to_raw_public().into());
+			assert_ok!(Consensus::report_misbehavior(&[7; 32].into(), double_prepare_by(Keyring::One)));
+			assert!(Consensus::reported(&key));
+
+			<system::Module<Test>>::set_block_number(PARENT_NUMBER + REPORT_LATENCY);
+			Consensus::prune_reported();
+			assert!(Consensus::reported(&key));
+
+			<system::Module<Test>>::set_block_number(PARENT_NUMBER + REPORT_LATENCY + 1);
+			Consensus::prune_reported();
+			assert!(!Consensus::reported(&key));
+			assert!(Consensus::reported_index().is_empty());
+		});
+	}
+}
@@ -23,7 +23,6 @@ use rstd::prelude::*;
 use runtime_primitives::traits::{Zero, CheckedAdd, CheckedSub};
 use runtime_support::StorageMap;
 use staking;
-use system;
 
 pub struct CreateReceipt<T: Trait> {
 	pub address: T::AccountId,
@@ -197,7 +196,7 @@ fn transfer<T: Trait>(
 	if would_create && value < <staking::Module<T>>::existential_deposit() {
 		return Err("value too low to create account");
 	}
-	if <staking::Module<T>>::bondage(transactor) > <system::Module<T>>::block_number() {
+	if new_from_balance < <staking::Module<T>>::locked_balance(transactor) {
 		return Err("bondage too high to send value");
 	}
 
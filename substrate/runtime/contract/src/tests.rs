@@ -32,8 +32,11 @@ impl HasPublicAux for Test {
 	type PublicAux = u64;
 }
 impl consensus::Trait for Test {
+	const NOTE_OFFLINE_POSITION: u32 = 1;
 	type PublicAux = <Self as HasPublicAux>::PublicAux;
 	type SessionKey = u64;
+	type OnMisbehavior = ();
+	type OnOfflineValidator = ();
 }
 impl system::Trait for Test {
 	type Index = u64;
@@ -97,7 +100,9 @@ fn new_test_ext(existential_deposit: u64, gas_price: u64) -> runtime_io::TestExt
 			current_era: 0,
 			balances: vec![],
 			intentions: vec![],
+			bonded: vec![],
 			validator_count: 2,
+			minimum_validator_count: 0,
 			bonding_duration: 0,
 			transaction_base_fee: 0,
 			transaction_byte_fee: 0,
@@ -106,6 +111,10 @@ fn new_test_ext(existential_deposit: u64, gas_price: u64) -> runtime_io::TestExt
 			creation_fee: 0,
 			reclaim_rebate: 0,
 			early_era_slash: 0,
+			misbehavior_slash: 0,
+			offline_slash: 0,
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 			session_reward: 0,
 		}.build_storage()
 			.unwrap(),
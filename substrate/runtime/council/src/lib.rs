@@ -93,6 +93,13 @@ pub mod voting;
 // following B blocks, the top C candidates are elected and have their bond returned. the top C
 // candidates and all other candidates beyond the top C+K are cleared.
 
+// if the number of approvals (voters times candidate slots) is no more than the on-chain tally
+// limit, nobody needs to present: the totals of all candidates are counted at the end of the B
+// blocks instead.
+
+// the runners-up are remembered until the next vote; should a member be removed in the meantime,
+// the best runner-up still standing takes their seat for the rest of its term.
+
 // vote-clearing happens lazily; for an approval to count, the most recent vote at the time of the
 // voter's most recent vote must be no later than the most recent vote at the time that the
 // candidate in the approval position was registered there. as candidates are removed from the
@@ -151,6 +158,9 @@ decl_storage! {
 	pub TermDuration get(term_duration): b"cou:trm" => required T::BlockNumber;
 	// Number of accounts that should be sitting on the council.
 	pub DesiredSeats get(desired_seats): b"cou:sts" => required u32;
+	// The most approvals (voters times candidate slots) to count on-chain when a vote ends; beyond
+	// that, winners must be presented.
+	pub OnChainTallyLimit get(on_chain_tally_limit): b"cou:otl" => default u32;
 
 	// permanent state (always relevant, changes only at the finalisation of voting)
 	// The current council. When there's a vote going on, this should still be used for executive
@@ -172,13 +182,15 @@ decl_storage! {
 	// The present candidate list.
 	pub Candidates get(candidates): b"cou:can" => default Vec<T::AccountId>; // has holes
 	pub CandidateCount get(candidate_count): b"cou:cnc" => default u32;
+	// The runners-up of the last vote, best first.
+	pub RunnersUp get(runners_up): b"cou:run" => default Vec<T::AccountId>;
 
 	// temporary state (only relevant during finalisation/presentation)
 	// The accounts holding the seats that will become free on the next tally.
 	pub NextFinalise get(next_finalise): b"cou:nxt" => (T::BlockNumber, u32, Vec<T::AccountId>);
 	// The stakes as they were at the point that the vote ended.
 	pub SnapshotedStakes get(snapshoted_stakes): b"cou:sss" => required Vec<T::Balance>;
-	// Get the leaderboard if we;re in the presentation phase.
+	// Get the leaderboard if we;re in the presentation phase and winners are to be presented.
 	pub Leaderboard get(leaderboard): b"cou:win" => Vec<(T::Balance, T::AccountId)>; // ORDERED low -> high
 }
 
@@ -364,7 +376,7 @@ impl<T: Trait> Module<T> {
 		let bad_presentation_punishment = Self::present_slash_per_voter() * T::Balance::sa(voters.len() as u64);
 		ensure!(<staking::Module<T>>::can_slash(aux.ref_into(), bad_presentation_punishment), "presenter must have sufficient slashable funds");
 
-		let mut leaderboard = Self::leaderboard().ok_or("leaderboard must exist while present phase active")?;
+		let mut leaderboard = Self::leaderboard().ok_or("winners are tallied on-chain in this vote")?;
 		ensure!(total > leaderboard[0].0, "candidate not worthy of leaderboard");
 
 		if let Some(p) = Self::active_council().iter().position(|&(ref c, _)| c == &candidate) {
@@ -373,16 +385,7 @@ impl<T: Trait> Module<T> {
 
 		let (registered_since, candidate_index): (VoteIndex, u32) =
 			Self::candidate_reg_info(&candidate).ok_or("presented candidate must be current")?;
-		let actual_total = voters.iter()
-			.zip(stakes.iter())
-			.filter_map(|(voter, stake)|
-				match Self::voter_last_active(voter) {
-					Some(b) if b >= registered_since =>
-						Self::approvals_of(voter).get(candidate_index as usize)
-							.and_then(|approved| if *approved { Some(*stake) } else { None }),
-					_ => None,
-				})
-			.fold(Zero::zero(), |acc, n| acc + n);
+		let actual_total = Self::approval_total(&voters, &stakes, registered_since, candidate_index);
 		let dupe = leaderboard.iter().find(|&&(_, ref c)| c == &candidate).is_some();
 		if total == actual_total && !dupe {
 			// insert into leaderboard
@@ -406,15 +409,23 @@ impl<T: Trait> Module<T> {
 		Ok(())
 	}
 
-	/// Remove a particular member. A tally will happen instantly (if not already in a presentation
-	/// period) to fill the seat if removal means that the desired members are not met.
+	/// Remove a particular member. Unless in a presentation period, the best runner-up of the last
+	/// vote still standing takes the seat for the rest of its term. Otherwise a tally will happen
+	/// instantly (if not already in a presentation period) to fill the seat if removal means that
+	/// the desired members are not met.
 	/// This is effective immediately.
 	fn remove_member(who: Address<T::AccountId, T::AccountIndex>) -> Result {
 		let who = <staking::Module<T>>::lookup(who)?;
-		let new_council: Vec<(T::AccountId, T::BlockNumber)> = Self::active_council()
-			.into_iter()
-			.filter(|i| i.0 != who)
-			.collect();
+		let mut new_council = Self::active_council();
+		if let Some(p) = new_council.iter().position(|i| i.0 == who) {
+			let (_, expiry) = new_council.remove(p);
+			if !Self::presentation_active() {
+				if let Some(runner_up) = Self::take_runner_up() {
+					new_council.push((runner_up, expiry));
+					new_council.sort_by_key(|&(_, expiry)| expiry);
+				}
+			}
+		}
 		<ActiveCouncil<T>>::put(new_council);
 		Ok(())
 	}
@@ -452,6 +463,72 @@ impl<T: Trait> Module<T> {
 		Ok(())
 	}
 
+	/// The total stake, as snapshotted, of the voters approving the candidate registered in
+	/// `candidate_index` since `registered_since`.
+	fn approval_total(
+		voters: &[T::AccountId],
+		stakes: &[T::Balance],
+		registered_since: VoteIndex,
+		candidate_index: u32
+	) -> T::Balance {
+		voters.iter()
+			.zip(stakes.iter())
+			.filter_map(|(voter, stake)|
+				match Self::voter_last_active(voter) {
+					Some(b) if b >= registered_since =>
+						Self::approvals_of(voter).get(candidate_index as usize)
+							.and_then(|approved| if *approved { Some(*stake) } else { None }),
+					_ => None,
+				})
+			.fold(Zero::zero(), |acc, n| acc + n)
+	}
+
+	/// Count the approvals of every candidate and build the leaderboard of the given size from
+	/// them, as presentations would have. Candidates already on the council whose seat is not
+	/// `expiring` are left out.
+	fn tally_on_chain(leaderboard_size: usize, expiring: &[T::AccountId]) -> Vec<(T::Balance, T::AccountId)> {
+		let voters = Self::voters();
+		let stakes = Self::snapshoted_stakes();
+		let active_council = Self::active_council();
+		let mut totals: Vec<(T::Balance, T::AccountId)> = Self::candidates().into_iter()
+			.filter(|c| *c != T::AccountId::default())
+			.filter(|c| active_council.iter()
+				.position(|&(ref m, _)| m == c)
+				.map_or(true, |p| p < expiring.len())
+			)
+			.filter_map(|c| Self::candidate_reg_info(&c)
+				.map(|(since, index)| (Self::approval_total(&voters, &stakes, since, index), c))
+			)
+			.filter(|&(ref total, _)| !total.is_zero())
+			.collect();
+		totals.sort_by_key(|&(t, _)| t);
+
+		let excess = totals.len().saturating_sub(leaderboard_size);
+		let mut leaderboard = vec![(T::Balance::zero(), T::AccountId::default()); leaderboard_size - (totals.len() - excess)];
+		leaderboard.extend(totals.into_iter().skip(excess));
+		leaderboard
+	}
+
+	/// Take the best runner-up of the last vote who is still a candidate off the candidate list,
+	/// returning their bond.
+	fn take_runner_up() -> Option<T::AccountId> {
+		let mut runners_up = Self::runners_up().into_iter();
+		let mut taken = None;
+		for r in runners_up.by_ref() {
+			if let Some((_, slot)) = <RegisterInfoOf<T>>::take(&r) {
+				let mut candidates = Self::candidates();
+				candidates[slot as usize] = T::AccountId::default();
+				<Candidates<T>>::put(candidates);
+				<CandidateCount<T>>::put(Self::candidate_count() - 1);
+				<staking::Module<T>>::unreserve(&r, Self::candidacy_bond());
+				taken = Some(r);
+				break;
+			}
+		}
+		<RunnersUp<T>>::put(runners_up.collect::<Vec<_>>());
+		taken
+	}
+
 	/// Remove a voter from the system. Trusts that Self::voters()[index] != voter.
 	fn remove_voter(voter: &T::AccountId, index: usize, mut voters: Vec<T::AccountId>) {
 		<Voters<T>>::put({ voters.swap_remove(index); voters });
@@ -473,9 +550,12 @@ impl<T: Trait> Module<T> {
 			let votes = voters.iter().map(<staking::Module<T>>::voting_balance).collect::<Vec<_>>();
 			<SnapshotedStakes<T>>::put(votes);
 
-			// initialise leaderboard.
-			let leaderboard_size = empty_seats + Self::carry_count() as usize;
-			<Leaderboard<T>>::put(vec![(T::Balance::zero(), T::AccountId::default()); leaderboard_size]);
+			// initialise leaderboard, unless the winners can be tallied on-chain.
+			let approvals = voters.len() as u64 * Self::candidates().len() as u64;
+			if approvals > Self::on_chain_tally_limit() as u64 {
+				let leaderboard_size = empty_seats + Self::carry_count() as usize;
+				<Leaderboard<T>>::put(vec![(T::Balance::zero(), T::AccountId::default()); leaderboard_size]);
+			}
 		}
 	}
 
@@ -484,10 +564,13 @@ impl<T: Trait> Module<T> {
 	/// a new vote is started.
 	/// Clears all presented candidates, returning the bond of the elected ones.
 	fn finalise_tally() -> Result {
-		<SnapshotedStakes<T>>::kill();
 		let (_, coming, expiring): (T::BlockNumber, u32, Vec<T::AccountId>) =
 			<NextFinalise<T>>::take().ok_or("finalise can only be called after a tally is started.")?;
-		let leaderboard: Vec<(T::Balance, T::AccountId)> = <Leaderboard<T>>::take().unwrap_or_default();
+		let leaderboard: Vec<(T::Balance, T::AccountId)> = match <Leaderboard<T>>::take() {
+			Some(leaderboard) => leaderboard,
+			None => Self::tally_on_chain(coming as usize + Self::carry_count() as usize, &expiring),
+		};
+		<SnapshotedStakes<T>>::kill();
 		let new_expiry = <system::Module<T>>::block_number() + Self::term_duration();
 
 		// return bond to winners.
@@ -517,16 +600,18 @@ impl<T: Trait> Module<T> {
 		// clear all except runners-up from candidate list.
 		let candidates = Self::candidates();
 		let mut new_candidates = vec![T::AccountId::default(); candidates.len()];	// shrink later.
-		let runners_up = leaderboard.into_iter()
+		let runners_up: Vec<_> = leaderboard.into_iter()
 			.rev()
 			.take_while(|&(b, _)| !b.is_zero())
 			.skip(coming as usize)
-			.filter_map(|(_, a)| Self::candidate_reg_info(&a).map(|i| (a, i.1)));
+			.filter_map(|(_, a)| Self::candidate_reg_info(&a).map(|i| (a, i.1)))
+			.collect();
 		let mut count = 0u32;
-		for (address, slot) in runners_up {
-			new_candidates[slot as usize] = address;
+		for &(ref address, slot) in &runners_up {
+			new_candidates[slot as usize] = address.clone();
 			count += 1;
 		}
+		<RunnersUp<T>>::put(runners_up.into_iter().map(|(a, _)| a).collect::<Vec<_>>());
 		for (old, new) in candidates.iter().zip(new_candidates.iter()) {
 			if old != new {
 				// removed - kill it
@@ -560,6 +645,7 @@ pub struct GenesisConfig<T: Trait> {
 	pub desired_seats: u32,
 	pub term_duration: T::BlockNumber,
 	pub inactive_grace_period: T::BlockNumber,
+	pub on_chain_tally_limit: u32,
 
 
 	// for the council's votes.
@@ -576,6 +662,7 @@ impl<T: Trait> Default for GenesisConfig<T> {
 			present_slash_per_voter: T::Balance::sa(1),
 			carry_count: 2,
 			inactive_grace_period: T::BlockNumber::sa(1),
+			on_chain_tally_limit: 0,
 			active_council: vec![],
 			approval_voting_period: T::BlockNumber::sa(1000),
 			presentation_duration: T::BlockNumber::sa(1000),
@@ -593,7 +680,7 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T>
 	fn build_storage(self) -> ::std::result::Result<runtime_io::TestExternalities, String> {
 		use codec::Encode;
 
-		Ok(map![
+		let mut r: runtime_io::TestExternalities = map![
 			Self::hash(<CandidacyBond<T>>::key()).to_vec() => self.candidacy_bond.encode(),
 			Self::hash(<VotingBond<T>>::key()).to_vec() => self.voter_bond.encode(),
 			Self::hash(<PresentSlashPerVoter<T>>::key()).to_vec() => self.present_slash_per_voter.encode(),
@@ -608,7 +695,13 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T>
 			Self::hash(<voting::CooloffPeriod<T>>::key()).to_vec() => self.cooloff_period.encode(),
 			Self::hash(<voting::VotingPeriod<T>>::key()).to_vec() => self.voting_period.encode(),
 			Self::hash(<voting::Proposals<T>>::key()).to_vec() => vec![0u8; 0].encode()
-		])
+		];
+
+		// Optional; absence means winners are always presented.
+		if self.on_chain_tally_limit != 0 {
+			r.insert(Self::hash(<OnChainTallyLimit<T>>::key()).to_vec(), self.on_chain_tally_limit.encode());
+		}
+		Ok(r)
 	}
 }
 
@@ -636,8 +729,11 @@ mod tests {
 		type PublicAux = u64;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = <Self as HasPublicAux>::PublicAux;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl system::Trait for Test {
 		type Index = u64;
@@ -682,7 +778,9 @@ mod tests {
 			current_era: 0,
 			balances: vec![(1, 10), (2, 20), (3, 30), (4, 40), (5, 50), (6, 60)],
 			intentions: vec![],
+			bonded: vec![],
 			validator_count: 2,
+			minimum_validator_count: 0,
 			bonding_duration: 0,
 			transaction_base_fee: 0,
 			transaction_byte_fee: 0,
@@ -691,12 +789,19 @@ mod tests {
 			creation_fee: 0,
 			reclaim_rebate: 0,
 			early_era_slash: 0,
+			misbehavior_slash: 0,
+			offline_slash: 0,
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 			session_reward: 0,
 		}.build_storage().unwrap());
 		t.extend(democracy::GenesisConfig::<Test>{
 			launch_period: 1,
 			voting_period: 3,
 			minimum_deposit: 1,
+			preimage_byte_deposit: 0,
+			enactment_delay: 0,
+			cooloff_period: 0,
 		}.build_storage().unwrap());
 		t.extend(GenesisConfig::<Test>{
 			candidacy_bond: 9,
@@ -704,6 +809,7 @@ mod tests {
 			present_slash_per_voter: 1,
 			carry_count: 2,
 			inactive_grace_period: 1,
+			on_chain_tally_limit: 0,
 			active_council: if with_council { vec![
 				(1, 10),
 				(2, 10),
@@ -1456,6 +1562,69 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn on_chain_tally_should_work_without_presentations() {
+		with_externalities(&mut new_test_ext(false), || {
+			System::set_block_number(4);
+			<OnChainTallyLimit<Test>>::put(25);
+
+			assert_ok!(Council::submit_candidacy(&1, 0));
+			assert_ok!(Council::set_approvals(&6, vec![true], 0));
+			assert_ok!(Council::submit_candidacy(&2, 1));
+			assert_ok!(Council::set_approvals(&2, vec![false, true], 0));
+			assert_ok!(Council::submit_candidacy(&3, 2));
+			assert_ok!(Council::set_approvals(&3, vec![false, false, true], 0));
+			assert_ok!(Council::submit_candidacy(&4, 3));
+			assert_ok!(Council::set_approvals(&4, vec![false, false, false, true], 0));
+			assert_ok!(Council::submit_candidacy(&5, 4));
+			assert_ok!(Council::set_approvals(&5, vec![false, false, false, false, true], 0));
+			assert_ok!(Council::end_block(System::block_number()));
+
+			System::set_block_number(6);
+			assert!(Council::presentation_active());
+			assert_eq!(Council::leaderboard(), None);
+			assert_noop!(Council::present_winner(&4, 1.into(), 60, 0), "winners are tallied on-chain in this vote");
+			assert_ok!(Council::end_block(System::block_number()));
+
+			assert!(!Council::presentation_active());
+			assert_eq!(Council::active_council(), vec![(1, 11), (5, 11)]);
+			assert_eq!(Council::runners_up(), vec![4, 3]);
+			assert!(!Council::is_a_candidate(&2));
+			assert!(Council::is_a_candidate(&3));
+			assert!(Council::is_a_candidate(&4));
+		});
+	}
+
+	#[test]
+	fn runner_up_should_replace_removed_member() {
+		with_externalities(&mut new_test_ext(false), || {
+			System::set_block_number(4);
+			<OnChainTallyLimit<Test>>::put(25);
+			assert_ok!(Council::submit_candidacy(&1, 0));
+			assert_ok!(Council::set_approvals(&6, vec![true], 0));
+			assert_ok!(Council::submit_candidacy(&3, 1));
+			assert_ok!(Council::set_approvals(&3, vec![false, true], 0));
+			assert_ok!(Council::submit_candidacy(&4, 2));
+			assert_ok!(Council::set_approvals(&4, vec![false, false, true], 0));
+			assert_ok!(Council::submit_candidacy(&5, 3));
+			assert_ok!(Council::set_approvals(&5, vec![false, false, false, true], 0));
+			assert_ok!(Council::end_block(System::block_number()));
+
+			System::set_block_number(6);
+			assert_ok!(Council::end_block(System::block_number()));
+			assert_eq!(Council::active_council(), vec![(1, 11), (5, 11)]);
+			assert_eq!(Council::runners_up(), vec![4, 3]);
+
+			System::set_block_number(7);
+			assert_ok!(Council::remove_member(5.into()));
+			assert_eq!(Council::active_council(), vec![(1, 11), (4, 11)]);
+			assert_eq!(Council::runners_up(), vec![3]);
+			assert!(!Council::is_a_candidate(&4));
+			assert_eq!(Council::candidates(), vec![0, 3, 0]);
+			assert_eq!(Council::candidate_count(), 1);
+		});
+	}
+
 	#[test]
 	fn second_tally_should_use_runners_up() {
 		with_externalities(&mut new_test_ext(false), || {
@@ -18,7 +18,7 @@
 
 use rstd::prelude::*;
 use rstd::borrow::Borrow;
-use primitives::traits::{Executable, RefInto, Hash};
+use primitives::traits::{Executable, OffchainWorker, RefInto, Hash};
 use runtime_io::print;
 use substrate_runtime_support::dispatch::Result;
 use substrate_runtime_support::{StorageValue, StorageMap, IsSubType};
@@ -86,6 +86,10 @@ impl<T: Trait> Module<T> {
 
 		ensure!(!<ProposalOf<T>>::exists(proposal_hash), "duplicate proposals not allowed");
 		ensure!(!Self::is_vetoed(&proposal_hash), "proposal is vetoed");
+		ensure!(!<democracy::Module<T>>::is_blacklisted(&proposal_hash), "proposal is blacklisted");
+		if let Some(&democracy::PrivCall::fast_track(ref external_hash, voting_period)) = IsSubType::<democracy::Module<T>>::is_sub_type(&*proposal) {
+			<democracy::Module<T>>::check_fast_track(external_hash, voting_period)?;
+		}
 
 		let mut proposals = Self::proposals();
 		proposals.push((expiry, proposal_hash));
@@ -179,20 +183,38 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Conclude the council proposals expiring at `now`. A proposal which can no longer be
+	/// carried out doesn't keep the others from being concluded.
 	fn end_block(now: T::BlockNumber) -> Result {
 		while let Some((proposal, proposal_hash)) = Self::take_proposal_if_expiring_at(now) {
 			let tally = Self::take_tally(&proposal_hash);
-			if let Some(&democracy::PrivCall::cancel_referendum(ref_index)) = IsSubType::<democracy::Module<T>>::is_sub_type(&proposal) {
-				if let (_, 0, 0) = tally {
-					<democracy::Module<T>>::internal_cancel_referendum(ref_index);
+			// emergency measures need a unanimous council.
+			match IsSubType::<democracy::Module<T>>::is_sub_type(&proposal) {
+				Some(&democracy::PrivCall::cancel_referendum(ref_index)) => {
+					if let (_, 0, 0) = tally {
+						<democracy::Module<T>>::internal_cancel_referendum(ref_index);
+					}
+				}
+				Some(&democracy::PrivCall::fast_track(external_hash, voting_period)) => {
+					if let (_, 0, 0) = tally {
+						if let Err(e) = <democracy::Module<T>>::internal_fast_track(external_hash, voting_period) {
+							print("Council proposal failed to fast-track");
+							print(e);
+						}
+					}
 				}
-			} else {
-				if tally.0 > tally.1 + tally.2 {
-					Self::kill_veto_of(&proposal_hash);
-					match tally {
-						(_, 0, 0) => <democracy::Module<T>>::internal_start_referendum(proposal, democracy::VoteThreshold::SuperMajorityAgainst).map(|_| ())?,
-						_ => <democracy::Module<T>>::internal_start_referendum(proposal, democracy::VoteThreshold::SimpleMajority).map(|_| ())?,
-					};
+				_ => {
+					if tally.0 > tally.1 + tally.2 {
+						Self::kill_veto_of(&proposal_hash);
+						let threshold = match tally {
+							(_, 0, 0) => democracy::VoteThreshold::SuperMajorityAgainst,
+							_ => democracy::VoteThreshold::SimpleMajority,
+						};
+						if let Err(e) = <democracy::Module<T>>::internal_start_referendum(proposal, threshold) {
+							print("Council proposal failed to start a referendum");
+							print(e);
+						}
+					}
 				}
 			}
 		}
@@ -214,6 +236,8 @@ impl<T: Trait> Executable for Council<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Council<T> {}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -257,7 +281,7 @@ mod tests {
 			System::set_block_number(1);
 			let proposal = bonding_duration_proposal(42);
 			assert_ok!(Democracy::internal_start_referendum(proposal.clone(), VoteThreshold::SuperMajorityApprove), 0);
-			assert_eq!(Democracy::active_referendums(), vec![(0, 4, proposal, VoteThreshold::SuperMajorityApprove)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 4, H256::from(proposal.blake2_256()), VoteThreshold::SuperMajorityApprove)]);
 
 			let cancellation = cancel_referendum_proposal(0);
 			let hash = cancellation.blake2_256().into();
@@ -290,7 +314,7 @@ mod tests {
 
 			System::set_block_number(2);
 			assert_ok!(CouncilVoting::end_block(System::block_number()));
-			assert_eq!(Democracy::active_referendums(), vec![(0, 4, proposal, VoteThreshold::SuperMajorityApprove)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 4, H256::from(proposal.blake2_256()), VoteThreshold::SuperMajorityApprove)]);
 		});
 	}
 
@@ -309,7 +333,85 @@ mod tests {
 
 			System::set_block_number(2);
 			assert_ok!(CouncilVoting::end_block(System::block_number()));
-			assert_eq!(Democracy::active_referendums(), vec![(0, 4, proposal, VoteThreshold::SuperMajorityApprove)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 4, H256::from(proposal.blake2_256()), VoteThreshold::SuperMajorityApprove)]);
+		});
+	}
+
+	fn fast_track_proposal(hash: H256, voting_period: u64) -> Proposal {
+		Proposal::Democracy(democracy::PrivCall::fast_track(hash, voting_period))
+	}
+
+	#[test]
+	fn fast_track_should_work_when_unanimous() {
+		with_externalities(&mut new_test_ext(true), || {
+			System::set_block_number(1);
+			let external = H256::from(bonding_duration_proposal(42).blake2_256());
+			let fast_track = fast_track_proposal(external, 1);
+			let hash = fast_track.blake2_256().into();
+			assert_ok!(CouncilVoting::propose(&1, Box::new(fast_track)));
+			assert_ok!(CouncilVoting::vote(&2, hash, true));
+			assert_ok!(CouncilVoting::vote(&3, hash, true));
+			assert_ok!(CouncilVoting::end_block(System::block_number()));
+
+			System::set_block_number(2);
+			assert_ok!(CouncilVoting::end_block(System::block_number()));
+			assert_eq!(Democracy::active_referendums(), vec![(0, 3, external, VoteThreshold::SuperMajorityAgainst)]);
+		});
+	}
+
+	#[test]
+	fn fast_track_should_fail_when_not_unanimous() {
+		with_externalities(&mut new_test_ext(true), || {
+			System::set_block_number(1);
+			let external = H256::from(bonding_duration_proposal(42).blake2_256());
+			let fast_track = fast_track_proposal(external, 1);
+			let hash = fast_track.blake2_256().into();
+			assert_ok!(CouncilVoting::propose(&1, Box::new(fast_track)));
+			assert_ok!(CouncilVoting::vote(&2, hash, true));
+			assert_ok!(CouncilVoting::vote(&3, hash, false));
+			assert_ok!(CouncilVoting::end_block(System::block_number()));
+
+			System::set_block_number(2);
+			assert_ok!(CouncilVoting::end_block(System::block_number()));
+			assert_eq!(Democracy::active_referendums(), vec![]);
+		});
+	}
+
+	#[test]
+	fn invalid_fast_track_should_not_be_proposed() {
+		with_externalities(&mut new_test_ext(true), || {
+			System::set_block_number(1);
+			let external = H256::from(bonding_duration_proposal(42).blake2_256());
+			assert_noop!(CouncilVoting::propose(&1, Box::new(fast_track_proposal(external, 0))), "voting period must be non-zero");
+			assert_noop!(CouncilVoting::propose(&1, Box::new(fast_track_proposal(external, 100))), "fast-track may not lengthen the voting period");
+		});
+	}
+
+	#[test]
+	fn failing_proposal_should_not_stop_others() {
+		with_externalities(&mut new_test_ext(true), || {
+			System::set_block_number(1);
+			<democracy::CooloffPeriod<Test>>::put(5);
+			let external = H256::from(bonding_duration_proposal(42).blake2_256());
+			let fast_track = fast_track_proposal(external, 1);
+			let fast_track_hash = fast_track.blake2_256().into();
+			let proposal = bonding_duration_proposal(43);
+			let proposal_hash = proposal.blake2_256().into();
+			assert_ok!(CouncilVoting::propose(&1, Box::new(fast_track)));
+			assert_ok!(CouncilVoting::propose(&1, Box::new(proposal.clone())));
+			for who in 2..4 {
+				assert_ok!(CouncilVoting::vote(&who, fast_track_hash, true));
+				assert_ok!(CouncilVoting::vote(&who, proposal_hash, true));
+			}
+
+			// the external proposal gets blacklisted before the council concludes.
+			let r = Democracy::internal_start_referendum(bonding_duration_proposal(42), VoteThreshold::SuperMajorityApprove).unwrap();
+			Democracy::internal_cancel_referendum(r);
+
+			System::set_block_number(2);
+			assert_ok!(CouncilVoting::end_block(System::block_number()));
+			assert_eq!(CouncilVoting::proposals().len(), 0);
+			assert_eq!(Democracy::active_referendums(), vec![(1, 5, H256::from(proposal.blake2_256()), VoteThreshold::SuperMajorityAgainst)]);
 		});
 	}
 
@@ -373,7 +475,7 @@ mod tests {
 			System::set_block_number(4);
 			assert_ok!(CouncilVoting::end_block(System::block_number()));
 			assert_eq!(CouncilVoting::proposals().len(), 0);
-			assert_eq!(Democracy::active_referendums(), vec![(0, 7, bonding_duration_proposal(42), VoteThreshold::SimpleMajority)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 7, H256::from(bonding_duration_proposal(42).blake2_256()), VoteThreshold::SimpleMajority)]);
 		});
 	}
 
@@ -438,7 +540,7 @@ mod tests {
 			System::set_block_number(2);
 			assert_ok!(CouncilVoting::end_block(System::block_number()));
 			assert_eq!(CouncilVoting::proposals().len(), 0);
-			assert_eq!(Democracy::active_referendums(), vec![(0, 5, proposal, VoteThreshold::SuperMajorityAgainst)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 5, H256::from(proposal.blake2_256()), VoteThreshold::SuperMajorityAgainst)]);
 		});
 	}
 
@@ -456,7 +558,7 @@ mod tests {
 			System::set_block_number(2);
 			assert_ok!(CouncilVoting::end_block(System::block_number()));
 			assert_eq!(CouncilVoting::proposals().len(), 0);
-			assert_eq!(Democracy::active_referendums(), vec![(0, 5, proposal, VoteThreshold::SimpleMajority)]);
+			assert_eq!(Democracy::active_referendums(), vec![(0, 5, H256::from(proposal.blake2_256()), VoteThreshold::SimpleMajority)]);
 		});
 	}
 
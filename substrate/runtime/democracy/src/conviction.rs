@@ -0,0 +1,98 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate Demo.
+
+// Substrate Demo is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate Demo is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate Demo.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Vote convictions.
+
+use codec::{Decode, Encode, Input};
+
+/// How strongly a vote is held: the weight of a vote is multiplied in exchange for keeping the
+/// voting balance locked for a while after the referendum ends.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum Conviction {
+	/// The vote counts once; the balance is only locked until the end of the referendum.
+	None,
+	/// The vote counts twice; the balance is locked for one voting period more.
+	Locked2x,
+	/// The vote counts three times; the balance is locked for two voting periods more.
+	Locked3x,
+	/// The vote counts four times; the balance is locked for four voting periods more.
+	Locked4x,
+	/// The vote counts five times; the balance is locked for eight voting periods more.
+	Locked5x,
+	/// The vote counts six times; the balance is locked for sixteen voting periods more.
+	Locked6x,
+}
+
+impl Default for Conviction {
+	fn default() -> Self {
+		Conviction::None
+	}
+}
+
+impl Conviction {
+	/// The factor by which the voting balance is multiplied.
+	pub fn multiplier(self) -> u64 {
+		match self {
+			Conviction::None => 1,
+			Conviction::Locked2x => 2,
+			Conviction::Locked3x => 3,
+			Conviction::Locked4x => 4,
+			Conviction::Locked5x => 5,
+			Conviction::Locked6x => 6,
+		}
+	}
+
+	/// The number of voting periods the voting balance stays locked for once the referendum
+	/// has ended.
+	pub fn lock_periods(self) -> u64 {
+		match self {
+			Conviction::None => 0,
+			Conviction::Locked2x => 1,
+			Conviction::Locked3x => 2,
+			Conviction::Locked4x => 4,
+			Conviction::Locked5x => 8,
+			Conviction::Locked6x => 16,
+		}
+	}
+}
+
+impl Decode for Conviction {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		input.read_byte().and_then(|v| match v {
+			0 => Some(Conviction::None),
+			1 => Some(Conviction::Locked2x),
+			2 => Some(Conviction::Locked3x),
+			3 => Some(Conviction::Locked4x),
+			4 => Some(Conviction::Locked5x),
+			5 => Some(Conviction::Locked6x),
+			_ => None,
+		})
+	}
+}
+
+impl Encode for Conviction {
+	fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+		f(&[match *self {
+			Conviction::None => 0u8,
+			Conviction::Locked2x => 1u8,
+			Conviction::Locked3x => 2u8,
+			Conviction::Locked4x => 3u8,
+			Conviction::Locked5x => 4u8,
+			Conviction::Locked6x => 5u8,
+		}])
+	}
+}
@@ -45,12 +45,15 @@ extern crate substrate_runtime_system as system;
 extern crate substrate_runtime_timestamp as timestamp;
 
 use rstd::prelude::*;
-use rstd::result;
-use primitives::traits::{Zero, Executable, RefInto, As, MaybeSerializeDebug};
+use rstd::{cmp, result};
+use codec::Decode;
+use primitives::traits::{Zero, One, Bounded, Executable, OffchainWorker, RefInto, As, Hash, MaybeSerializeDebug};
 use substrate_runtime_support::{StorageValue, StorageMap, Parameter, Dispatchable, IsSubType};
 use substrate_runtime_support::dispatch::Result;
 
+mod conviction;
 mod vote_threshold;
+pub use conviction::Conviction;
 pub use vote_threshold::{Approved, VoteThreshold};
 
 /// A proposal index.
@@ -58,6 +61,13 @@ pub type PropIndex = u32;
 /// A referendum index.
 pub type ReferendumIndex = u32;
 
+/// The lock on the balance backing votes.
+const VOTING_ID: staking::LockIdentifier = *b"democrac";
+/// The lock on the balance of delegators.
+const DELEGATION_ID: staking::LockIdentifier = *b"delegate";
+/// The maximum length of a chain of delegations.
+const MAX_DELEGATION_DEPTH: usize = 8;
+
 pub trait Trait: staking::Trait + Sized {
 	type Proposal: Parameter + Dispatchable + IsSubType<Module<Self>> + MaybeSerializeDebug;
 }
@@ -67,15 +77,19 @@ decl_module! {
 
 	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 	pub enum Call where aux: T::PublicAux {
-		fn propose(aux, proposal: Box<T::Proposal>, value: T::Balance) -> Result = 0;
+		fn propose(aux, proposal_hash: T::Hash, value: T::Balance) -> Result = 0;
 		fn second(aux, proposal: PropIndex) -> Result = 1;
-		fn vote(aux, ref_index: ReferendumIndex, approve_proposal: bool) -> Result = 2;
+		fn vote(aux, ref_index: ReferendumIndex, approve_proposal: bool, conviction: Conviction) -> Result = 2;
+		fn delegate(aux, to: T::AccountId, conviction: Conviction) -> Result = 3;
+		fn undelegate(aux) -> Result = 4;
+		fn note_preimage(aux, encoded_proposal: Vec<u8>) -> Result = 5;
 	}
 
 	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 	pub enum PrivCall {
 		fn start_referendum(proposal: Box<T::Proposal>, vote_threshold: VoteThreshold) -> Result = 0;
 		fn cancel_referendum(ref_index: ReferendumIndex) -> Result = 1;
+		fn fast_track(proposal_hash: T::Hash, voting_period: T::BlockNumber) -> Result = 2;
 	}
 }
 
@@ -84,30 +98,51 @@ decl_storage! {
 
 	// The number of (public) proposals that have been made so far.
 	pub PublicPropCount get(public_prop_count): b"dem:ppc" => default PropIndex;
-	// The public proposals. Unsorted.
-	pub PublicProps get(public_props): b"dem:pub" => default Vec<(PropIndex, T::Proposal, T::AccountId)>;
+	// The public proposals, by hash of the proposal. Unsorted.
+	pub PublicProps get(public_props): b"dem:pub" => default Vec<(PropIndex, T::Hash, T::AccountId)>;
 	// Those who have locked a deposit.
 	pub DepositOf get(deposit_of): b"dem:dep:" => map [ PropIndex => (T::Balance, Vec<T::AccountId>) ];
 	// How often (in blocks) new public referenda are launched.
 	pub LaunchPeriod get(launch_period): b"dem:lau" => required T::BlockNumber;
 	// The minimum amount to be used as a deposit for a public referendum proposal.
 	pub MinimumDeposit get(minimum_deposit): b"dem:min" => required T::Balance;
+	// The amount reserved per byte of a noted preimage.
+	pub PreimageByteDeposit get(preimage_byte_deposit): b"dem:pbd" => default T::Balance;
+	// The preimages of proposal hashes, along with who noted them and the deposit they reserved.
+	pub Preimages get(preimages): b"dem:pim:" => map [ T::Hash => (T::Proposal, Option<(T::AccountId, T::Balance)>) ];
+
+	// How long (in blocks) to wait between a referendum passing and its proposal being enacted.
+	pub EnactmentDelay get(enactment_delay): b"dem:ena" => default T::BlockNumber;
+	// The hashes of passed proposals to be enacted at each block.
+	pub DispatchQueue get(dispatch_queue): b"dem:dq:" => default map [ T::BlockNumber => Vec<T::Hash> ];
+
+	// How long (in blocks) the proposal of a cancelled referendum may not be proposed again.
+	pub CooloffPeriod get(cooloff_period): b"dem:coo" => default T::BlockNumber;
+	// The block until which a proposal hash is blacklisted, having had its referendum cancelled.
+	pub Blacklist get(blacklist): b"dem:bla:" => map [ T::Hash => T::BlockNumber ];
+	// The blacklisted proposal hashes whose cooloff ends at each block.
+	pub BlacklistExpiry get(blacklist_expiry): b"dem:blx:" => default map [ T::BlockNumber => Vec<T::Hash> ];
 
 	// How often (in blocks) to check for new votes.
 	pub VotingPeriod get(voting_period): b"dem:per" => required T::BlockNumber;
 
 	// The next free referendum index, aka the number of referendums started so far.
 	pub ReferendumCount get(referendum_count): b"dem:rco" => required ReferendumIndex;
-	// The next referendum index that should be tallied.
+	// The lowest referendum index that may still need to be tallied.
 	pub NextTally get(next_tally): b"dem:nxt" => required ReferendumIndex;
 	// Information concerning any given referendum.
-	pub ReferendumInfoOf get(referendum_info): b"dem:pro:" => map [ ReferendumIndex => (T::BlockNumber, T::Proposal, VoteThreshold) ];
+	pub ReferendumInfoOf get(referendum_info): b"dem:pro:" => map [ ReferendumIndex => (T::BlockNumber, T::Hash, VoteThreshold) ];
 
 	// Get the voters for the current proposal.
 	pub VotersFor get(voters_for): b"dem:vtr:" => default map [ ReferendumIndex => Vec<T::AccountId> ];
 
-	// Get the vote, if Some, of `who`.
-	pub VoteOf get(vote_of): b"dem:vot:" => map [ (ReferendumIndex, T::AccountId) => bool ];
+	// Get the vote, if Some, of `who`, along with its conviction.
+	pub VoteOf get(vote_of): b"dem:vot:" => map [ (ReferendumIndex, T::AccountId) => (bool, Conviction) ];
+
+	// The account each account delegates its vote to, and with which conviction.
+	pub Delegations get(delegations): b"dem:dlg:" => map [ T::AccountId => (T::AccountId, Conviction) ];
+	// The accounts delegating their vote to each account.
+	pub Delegators get(delegators): b"dem:dls:" => default map [ T::AccountId => Vec<T::AccountId> ];
 }
 
 impl<T: Trait> Module<T> {
@@ -125,8 +160,15 @@ impl<T: Trait> Module<T> {
 		<ReferendumInfoOf<T>>::exists(ref_index)
 	}
 
+	/// Return true if `proposal_hash` may not currently be proposed, since a referendum on it was
+	/// cancelled.
+	pub fn is_blacklisted(proposal_hash: &T::Hash) -> bool {
+		Self::blacklist(proposal_hash)
+			.map_or(false, |until| <system::Module<T>>::block_number() < until)
+	}
+
 	/// Get all referendums currently active.
-	pub fn active_referendums() -> Vec<(ReferendumIndex, T::BlockNumber, T::Proposal, VoteThreshold)> {
+	pub fn active_referendums() -> Vec<(ReferendumIndex, T::BlockNumber, T::Hash, VoteThreshold)> {
 		let next = Self::next_tally();
 		let last = Self::referendum_count();
 		(next..last).into_iter()
@@ -135,28 +177,64 @@ impl<T: Trait> Module<T> {
 	}
 
 	/// Get all referendums ready for tally at block `n`.
-	pub fn maturing_referendums_at(n: T::BlockNumber) -> Vec<(ReferendumIndex, T::BlockNumber, T::Proposal, VoteThreshold)> {
+	pub fn maturing_referendums_at(n: T::BlockNumber) -> Vec<(ReferendumIndex, T::BlockNumber, T::Hash, VoteThreshold)> {
 		let next = Self::next_tally();
 		let last = Self::referendum_count();
 		(next..last).into_iter()
 			.filter_map(|i| Self::referendum_info(i).map(|(n, p, t)| (i, n, p, t)))
-			.take_while(|&(_, block_number, _, _)| block_number == n)
+			.filter(|&(_, block_number, _, _)| block_number == n)
 			.collect()
 	}
 
-	/// Get the voters for the current proposal.
+	/// Get the votes for and against the proposal of a referendum, weighted by conviction.
+	///
+	/// Each voter also carries the votes delegated to it by accounts which did not vote
+	/// themselves, following delegations transitively for up to `MAX_DELEGATION_DEPTH` hops.
 	pub fn tally(ref_index: ReferendumIndex) -> (T::Balance, T::Balance) {
 		Self::voters_for(ref_index).iter()
-			.map(|a| (<staking::Module<T>>::voting_balance(a), Self::vote_of((ref_index, a.clone())).unwrap_or(false)/*defensive only: all items come from `voters`; for an item to be in `voters` there must be a vote registered; qed*/))
-			.map(|(bal, vote)| if vote { (bal, Zero::zero()) } else { (Zero::zero(), bal) })
+			.map(|a| (a, Self::vote_of((ref_index, a.clone())).unwrap_or_default()/*defensive only: all items come from `voters`; for an item to be in `voters` there must be a vote registered; qed*/))
+			.map(|(a, (vote, conviction))| (Self::votes_of(a, conviction) + Self::delegated_votes(ref_index, a, MAX_DELEGATION_DEPTH), vote))
+			.map(|(votes, vote)| if vote { (votes, Zero::zero()) } else { (Zero::zero(), votes) })
 			.fold((Zero::zero(), Zero::zero()), |(a, b), (c, d)| (a + c, b + d))
 	}
 
+	/// The votes of `who` when voting with `conviction`.
+	fn votes_of(who: &T::AccountId, conviction: Conviction) -> T::Balance {
+		<staking::Module<T>>::voting_balance(who) * T::Balance::sa(conviction.multiplier())
+	}
+
+	/// The votes delegated to `to` in a referendum, directly or through at most `depth - 1`
+	/// further delegations, by accounts which did not vote in it themselves.
+	///
+	/// Delegators only count with the part of their balance locked by the delegation.
+	fn delegated_votes(ref_index: ReferendumIndex, to: &T::AccountId, depth: usize) -> T::Balance {
+		if depth == 0 {
+			return Zero::zero();
+		}
+		Self::delegators(to).into_iter()
+			.filter(|d| !<VoteOf<T>>::exists(&(ref_index, d.clone())))
+			.fold(Zero::zero(), |acc, d| {
+				let conviction = Self::delegations(&d).map_or_else(Default::default, |(_, c)| c);
+				let votes = Self::delegated_balance(&d) * T::Balance::sa(conviction.multiplier());
+				acc + votes + Self::delegated_votes(ref_index, &d, depth - 1)
+			})
+	}
+
+	/// The part of the voting balance of `who` which is locked by its delegation.
+	fn delegated_balance(who: &T::AccountId) -> T::Balance {
+		let locked = <staking::Module<T>>::locks(who).into_iter()
+			.find(|lock| lock.id == DELEGATION_ID)
+			.map_or_else(Zero::zero, |lock| lock.amount);
+		cmp::min(<staking::Module<T>>::voting_balance(who), locked)
+	}
+
 	// dispatching.
 
-	/// Propose a sensitive action to be taken.
-	fn propose(aux: &T::PublicAux, proposal: Box<T::Proposal>, value: T::Balance) -> Result {
+	/// Propose a sensitive action to be taken. The preimage of `proposal_hash` must be noted
+	/// before the proposal can be enacted.
+	fn propose(aux: &T::PublicAux, proposal_hash: T::Hash, value: T::Balance) -> Result {
 		ensure!(value >= Self::minimum_deposit(), "value too low");
+		ensure!(!Self::is_blacklisted(&proposal_hash), "proposal is blacklisted");
 		<staking::Module<T>>::reserve(aux.ref_into(), value)
 			.map_err(|_| "proposer's balance too low")?;
 
@@ -165,7 +243,7 @@ impl<T: Trait> Module<T> {
 		<DepositOf<T>>::insert(index, (value, vec![aux.ref_into().clone()]));
 
 		let mut props = Self::public_props();
-		props.push((index, (*proposal).clone(), aux.ref_into().clone()));
+		props.push((index, proposal_hash, aux.ref_into().clone()));
 		<PublicProps<T>>::put(props);
 		Ok(())
 	}
@@ -183,61 +261,215 @@ impl<T: Trait> Module<T> {
 
 	/// Vote in a referendum. If `approve_proposal` is true, the vote is to enact the proposal;
 	/// false would be a vote to keep the status quo..
-	fn vote(aux: &T::PublicAux, ref_index: ReferendumIndex, approve_proposal: bool) -> Result {
-		ensure!(Self::is_active_referendum(ref_index), "vote given for invalid referendum.");
-		ensure!(!<staking::Module<T>>::voting_balance(aux.ref_into()).is_zero(),
-			"transactor must have balance to signal approval.");
+	///
+	/// The voting balance is locked until the referendum ends; if the vote turns out to be on
+	/// the winning side, for as long again as the `conviction` demands.
+	fn vote(aux: &T::PublicAux, ref_index: ReferendumIndex, approve_proposal: bool, conviction: Conviction) -> Result {
+		let (end, _, _) = Self::referendum_info(ref_index).ok_or("vote given for invalid referendum.")?;
+		let balance = <staking::Module<T>>::voting_balance(aux.ref_into());
+		ensure!(!balance.is_zero(), "transactor must have balance to signal approval.");
 		if !<VoteOf<T>>::exists(&(ref_index, aux.ref_into().clone())) {
 			let mut voters = Self::voters_for(ref_index);
 			voters.push(aux.ref_into().clone());
 			<VotersFor<T>>::insert(ref_index, voters);
 		}
-		<VoteOf<T>>::insert(&(ref_index, aux.ref_into().clone()), approve_proposal);
+		<VoteOf<T>>::insert(&(ref_index, aux.ref_into().clone()), (approve_proposal, conviction));
+		// the referendum is tallied at the end of its last block.
+		<staking::Module<T>>::extend_lock(VOTING_ID, aux.ref_into(), balance, end + One::one());
+		Ok(())
+	}
+
+	/// Delegate the vote of the transactor to `to` with the given conviction. It applies to
+	/// every referendum the transactor doesn't vote in itself.
+	///
+	/// The voting balance is locked while delegating, and only the locked part counts towards
+	/// the votes of the delegate; delegating again locks the current voting balance.
+	fn delegate(aux: &T::PublicAux, to: T::AccountId, conviction: Conviction) -> Result {
+		let who = aux.ref_into();
+		ensure!(&to != who, "cannot delegate to self");
+
+		let mut next = Some(to.clone());
+		let mut depth = 0;
+		while let Some(delegate) = next {
+			ensure!(&delegate != who, "delegation would create a cycle");
+			depth += 1;
+			ensure!(depth < MAX_DELEGATION_DEPTH, "delegation chain too long");
+			next = Self::delegations(&delegate).map(|(d, _)| d);
+		}
+
+		if let Some((old, _)) = Self::delegations(who) {
+			Self::remove_delegator(&old, who);
+		}
+		let mut delegators = Self::delegators(&to);
+		delegators.push(who.clone());
+		<Delegators<T>>::insert(&to, delegators);
+		<Delegations<T>>::insert(who, (to, conviction));
+
+		let balance = <staking::Module<T>>::voting_balance(who);
+		<staking::Module<T>>::set_lock(DELEGATION_ID, who, balance, T::BlockNumber::max_value());
+		Ok(())
+	}
+
+	/// Stop delegating the vote of the transactor. The voting balance stays locked for as long
+	/// as the conviction of the delegation demands.
+	fn undelegate(aux: &T::PublicAux) -> Result {
+		let who = aux.ref_into();
+		let (to, conviction) = <Delegations<T>>::take(who).ok_or("not delegating")?;
+		Self::remove_delegator(&to, who);
+
+		let balance = <staking::Module<T>>::voting_balance(who);
+		let until = <system::Module<T>>::block_number() + Self::lock_duration(conviction);
+		<staking::Module<T>>::set_lock(DELEGATION_ID, who, balance, until);
+		Ok(())
+	}
+
+	/// Note the preimage of a proposal, reserving a deposit for each of its bytes. The deposit
+	/// is returned once the proposal is enacted.
+	fn note_preimage(aux: &T::PublicAux, encoded_proposal: Vec<u8>) -> Result {
+		let proposal_hash = T::Hashing::hash(&encoded_proposal[..]);
+		ensure!(!<Preimages<T>>::exists(&proposal_hash), "preimage already noted");
+		let proposal = T::Proposal::decode(&mut &encoded_proposal[..]).ok_or("not a valid proposal")?;
+
+		let deposit = Self::preimage_byte_deposit() * T::Balance::sa(encoded_proposal.len() as u64);
+		<staking::Module<T>>::reserve(aux.ref_into(), deposit)
+			.map_err(|_| "not enough balance to note preimage")?;
+		<Preimages<T>>::insert(proposal_hash, (proposal, Some((aux.ref_into().clone(), deposit))));
 		Ok(())
 	}
 
 	/// Start a referendum.
 	fn start_referendum(proposal: Box<T::Proposal>, vote_threshold: VoteThreshold) -> Result {
-		Self::inject_referendum(
-			<system::Module<T>>::block_number() + Self::voting_period(),
-			*proposal,
-			vote_threshold
-		).map(|_| ())
+		Self::internal_start_referendum(*proposal, vote_threshold).map(|_| ())
 	}
 
 	/// Remove a referendum.
 	fn cancel_referendum(ref_index: ReferendumIndex) -> Result {
-		Self::clear_referendum(ref_index);
+		Self::internal_cancel_referendum(ref_index);
 		Ok(())
 	}
 
+	/// Start a referendum on an existing proposal hash straight away, with a voting period shorter
+	/// than usual.
+	fn fast_track(proposal_hash: T::Hash, voting_period: T::BlockNumber) -> Result {
+		Self::internal_fast_track(proposal_hash, voting_period).map(|_| ())
+	}
+
 	// exposed mutables.
 
 	/// Start a referendum. Can be called directly by the council.
 	pub fn internal_start_referendum(proposal: T::Proposal, vote_threshold: VoteThreshold) -> result::Result<ReferendumIndex, &'static str> {
-		<Module<T>>::inject_referendum(<system::Module<T>>::block_number() + <Module<T>>::voting_period(), proposal, vote_threshold)
+		let proposal_hash = T::Hashing::hash_of(&proposal);
+		ensure!(!<Module<T>>::is_blacklisted(&proposal_hash), "proposal is blacklisted");
+		let ref_index = <Module<T>>::inject_referendum(<system::Module<T>>::block_number() + <Module<T>>::voting_period(), proposal_hash, vote_threshold)?;
+		<Module<T>>::note_imminent_preimage(proposal_hash, proposal);
+		Ok(ref_index)
 	}
 
 	/// Remove a referendum. Can be called directly by the council.
+	/// Its proposal is blacklisted for the cooloff period.
 	pub fn internal_cancel_referendum(ref_index: ReferendumIndex) {
+		let cooloff_period = <Module<T>>::cooloff_period();
+		match <Module<T>>::referendum_info(ref_index) {
+			Some((_, proposal_hash, _)) if !cooloff_period.is_zero() => {
+				let until = <system::Module<T>>::block_number() + cooloff_period;
+				<Blacklist<T>>::insert(proposal_hash, until);
+				let mut expiring = <Module<T>>::blacklist_expiry(until);
+				expiring.push(proposal_hash);
+				<BlacklistExpiry<T>>::insert(until, expiring);
+			}
+			_ => {}
+		}
 		<Module<T>>::clear_referendum(ref_index);
 	}
 
+	/// Fast-track a proposal into a referendum ending after `voting_period`. If it is a public
+	/// proposal, it is withdrawn and its deposits returned. Can be called directly by the council.
+	pub fn internal_fast_track(proposal_hash: T::Hash, voting_period: T::BlockNumber) -> result::Result<ReferendumIndex, &'static str> {
+		<Module<T>>::check_fast_track(&proposal_hash, voting_period)?;
+
+		let mut public_props = <Module<T>>::public_props();
+		if let Some(i) = public_props.iter().position(|&(_, ref h, _)| h == &proposal_hash) {
+			let (prop_index, _, _) = public_props.swap_remove(i);
+			if let Some((deposit, depositors)) = <DepositOf<T>>::take(prop_index) {
+				for d in &depositors {
+					<staking::Module<T>>::unreserve(d, deposit);
+				}
+			}
+			<PublicProps<T>>::put(public_props);
+		}
+
+		let end = <system::Module<T>>::block_number() + voting_period;
+		<Module<T>>::inject_referendum(end, proposal_hash, VoteThreshold::SuperMajorityAgainst)
+	}
+
+	/// Check that `proposal_hash` could currently be fast-tracked into a referendum ending
+	/// after `voting_period`.
+	pub fn check_fast_track(proposal_hash: &T::Hash, voting_period: T::BlockNumber) -> Result {
+		ensure!(!voting_period.is_zero(), "voting period must be non-zero");
+		ensure!(voting_period <= <Module<T>>::voting_period(), "fast-track may not lengthen the voting period");
+		ensure!(!<Module<T>>::is_blacklisted(proposal_hash), "proposal is blacklisted");
+		Ok(())
+	}
+
 	// private.
 
+	/// The time for which the balance behind a vote with `conviction` is locked.
+	fn lock_duration(conviction: Conviction) -> T::BlockNumber {
+		Self::voting_period() * T::BlockNumber::sa(conviction.lock_periods())
+	}
+
+	/// Remove `who` from the delegators of `to`.
+	fn remove_delegator(to: &T::AccountId, who: &T::AccountId) {
+		let mut delegators = Self::delegators(to);
+		delegators.retain(|d| d != who);
+		if delegators.is_empty() {
+			<Delegators<T>>::remove(to);
+		} else {
+			<Delegators<T>>::insert(to, delegators);
+		}
+	}
+
+	/// Lock the balance of the voters on the winning side of a referendum for as long as their
+	/// conviction demands after `enactment`.
+	fn lock_winners(ref_index: ReferendumIndex, approved: bool, enactment: T::BlockNumber) {
+		for v in Self::voters_for(ref_index) {
+			if let Some((vote, conviction)) = Self::vote_of((ref_index, v.clone())) {
+				if vote == approved && conviction != Conviction::None {
+					let balance = <staking::Module<T>>::voting_balance(&v);
+					<staking::Module<T>>::extend_lock(VOTING_ID, &v, balance, enactment + Self::lock_duration(conviction));
+				}
+			}
+		}
+	}
+
+	/// Note the preimage of a proposal which doesn't need a deposit, since it didn't come from
+	/// the public.
+	fn note_imminent_preimage(proposal_hash: T::Hash, proposal: T::Proposal) {
+		if !<Preimages<T>>::exists(&proposal_hash) {
+			let no_deposit: Option<(T::AccountId, T::Balance)> = None;
+			<Preimages<T>>::insert(proposal_hash, (proposal, no_deposit));
+		}
+	}
+
+	/// Dispatch a passed proposal, returning the deposit of whoever noted its preimage.
+	fn enact_proposal(proposal_hash: T::Hash) -> Result {
+		let (proposal, deposit) = <Preimages<T>>::take(&proposal_hash)
+			.ok_or("preimage of passed proposal not noted")?;
+		if let Some((who, amount)) = deposit {
+			<staking::Module<T>>::unreserve(&who, amount);
+		}
+		proposal.dispatch()
+	}
+
 	/// Start a referendum
 	fn inject_referendum(
 		end: T::BlockNumber,
-		proposal: T::Proposal,
+		proposal_hash: T::Hash,
 		vote_threshold: VoteThreshold
 	) -> result::Result<ReferendumIndex, &'static str> {
 		let ref_index = Self::referendum_count();
-		if ref_index > 0 && Self::referendum_info(ref_index - 1).map(|i| i.0 > end).unwrap_or(false) {
-			Err("Cannot inject a referendum that ends earlier than preceeding referendum")?
-		}
-
 		<ReferendumCount<T>>::put(ref_index + 1);
-		<ReferendumInfoOf<T>>::insert(ref_index, (end, proposal, vote_threshold));
+		<ReferendumInfoOf<T>>::insert(ref_index, (end, proposal_hash, vote_threshold));
 		Ok(ref_index)
 	}
 
@@ -259,29 +491,55 @@ impl<T: Trait> Module<T> {
 				.enumerate()
 				.max_by_key(|x| Self::locked_for((x.1).0).unwrap_or_else(Zero::zero)/*defensive only: All current public proposals have an amount locked*/)
 			{
-				let (prop_index, proposal, _) = public_props.swap_remove(winner_index);
+				let (prop_index, proposal_hash, _) = public_props.swap_remove(winner_index);
 				if let Some((deposit, depositors)) = <DepositOf<T>>::take(prop_index) {//: (T::Balance, Vec<T::AccountId>) =
 					// refund depositors
 					for d in &depositors {
 						<staking::Module<T>>::unreserve(d, deposit);
 					}
 					<PublicProps<T>>::put(public_props);
-					Self::inject_referendum(now + Self::voting_period(), proposal, VoteThreshold::SuperMajorityApprove)?;
+					Self::inject_referendum(now + Self::voting_period(), proposal_hash, VoteThreshold::SuperMajorityApprove)?;
 				} else {
 					return Err("depositors always exist for current proposals")
 				}
 			}
 		}
 
-		// tally up votes for any expiring referenda.
-		for (index, _, proposal, vote_threshold) in Self::maturing_referendums_at(now) {
+		// tally up votes for any expiring referenda; passed proposals are queued for enactment.
+		for (index, _, proposal_hash, vote_threshold) in Self::maturing_referendums_at(now) {
 			let (approve, against) = Self::tally(index);
 			let total_stake = <staking::Module<T>>::total_stake();
+			let approved = vote_threshold.approved(approve, against, total_stake);
+			let enactment = now + Self::enactment_delay();
+			Self::lock_winners(index, approved, enactment);
 			Self::clear_referendum(index);
-			if vote_threshold.approved(approve, against, total_stake) {
-				proposal.dispatch()?;
+			if approved {
+				let mut queue = Self::dispatch_queue(enactment);
+				queue.push(proposal_hash);
+				<DispatchQueue<T>>::insert(enactment, queue);
+			}
+		}
+
+		// skip past the referenda which are no longer active; fast-tracked ones may end early.
+		let count = Self::referendum_count();
+		let mut next = Self::next_tally();
+		while next < count && !Self::is_active_referendum(next) {
+			next += 1;
+		}
+		<NextTally<T>>::put(next);
+
+		// forget blacklisted proposals whose cooloff is over.
+		for proposal_hash in <BlacklistExpiry<T>>::take(now) {
+			if Self::blacklist(&proposal_hash).map_or(false, |until| until <= now) {
+				<Blacklist<T>>::remove(&proposal_hash);
+			}
+		}
+
+		// enact any proposals whose delay is up.
+		for proposal_hash in <DispatchQueue<T>>::take(now) {
+			if let Err(e) = Self::enact_proposal(proposal_hash) {
+				runtime_io::print(e);
 			}
-			<NextTally<T>>::put(index + 1);
 		}
 		Ok(())
 	}
@@ -295,6 +553,8 @@ impl<T: Trait> Executable for Module<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Module<T> {}
+
 #[cfg(any(feature = "std", test))]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -303,6 +563,9 @@ pub struct GenesisConfig<T: Trait> {
 	pub launch_period: T::BlockNumber,
 	pub voting_period: T::BlockNumber,
 	pub minimum_deposit: T::Balance,
+	pub preimage_byte_deposit: T::Balance,
+	pub enactment_delay: T::BlockNumber,
+	pub cooloff_period: T::BlockNumber,
 }
 
 #[cfg(any(feature = "std", test))]
@@ -312,6 +575,9 @@ impl<T: Trait> GenesisConfig<T> {
 			launch_period: T::BlockNumber::sa(1),
 			voting_period: T::BlockNumber::sa(1),
 			minimum_deposit: T::Balance::sa(1),
+			preimage_byte_deposit: T::Balance::sa(0),
+			enactment_delay: T::BlockNumber::sa(0),
+			cooloff_period: T::BlockNumber::sa(0),
 		}
 	}
 
@@ -320,6 +586,9 @@ impl<T: Trait> GenesisConfig<T> {
 			launch_period: T::BlockNumber::sa(1),
 			voting_period: T::BlockNumber::sa(3),
 			minimum_deposit: T::Balance::sa(1),
+			preimage_byte_deposit: T::Balance::sa(0),
+			enactment_delay: T::BlockNumber::sa(0),
+			cooloff_period: T::BlockNumber::sa(0),
 		}
 	}
 }
@@ -331,6 +600,9 @@ impl<T: Trait> Default for GenesisConfig<T> {
 			launch_period: T::BlockNumber::sa(1000),
 			voting_period: T::BlockNumber::sa(1000),
 			minimum_deposit: T::Balance::sa(0),
+			preimage_byte_deposit: T::Balance::sa(0),
+			enactment_delay: T::BlockNumber::sa(0),
+			cooloff_period: T::BlockNumber::sa(0),
 		}
 	}
 }
@@ -341,14 +613,26 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T>
 	fn build_storage(self) -> ::std::result::Result<runtime_io::TestExternalities, String> {
 		use codec::Encode;
 
-		Ok(map![
+		let mut r: runtime_io::TestExternalities = map![
 			Self::hash(<LaunchPeriod<T>>::key()).to_vec() => self.launch_period.encode(),
 			Self::hash(<VotingPeriod<T>>::key()).to_vec() => self.voting_period.encode(),
 			Self::hash(<MinimumDeposit<T>>::key()).to_vec() => self.minimum_deposit.encode(),
 			Self::hash(<ReferendumCount<T>>::key()).to_vec() => (0 as ReferendumIndex).encode(),
 			Self::hash(<NextTally<T>>::key()).to_vec() => (0 as ReferendumIndex).encode(),
 			Self::hash(<PublicPropCount<T>>::key()).to_vec() => (0 as PropIndex).encode()
-		])
+		];
+
+		// Optional; absence means no deposit, immediate enactment and no blacklisting.
+		if !self.preimage_byte_deposit.is_zero() {
+			r.insert(Self::hash(<PreimageByteDeposit<T>>::key()).to_vec(), self.preimage_byte_deposit.encode());
+		}
+		if !self.enactment_delay.is_zero() {
+			r.insert(Self::hash(<EnactmentDelay<T>>::key()).to_vec(), self.enactment_delay.encode());
+		}
+		if !self.cooloff_period.is_zero() {
+			r.insert(Self::hash(<CooloffPeriod<T>>::key()).to_vec(), self.cooloff_period.encode());
+		}
+		Ok(r)
 	}
 }
 
@@ -358,6 +642,7 @@ mod tests {
 	use runtime_io::with_externalities;
 	use substrate_primitives::H256;
 	use primitives::BuildStorage;
+	use codec::Encode;
 	use primitives::traits::{HasPublicAux, Identity, BlakeTwo256};
 	use primitives::testing::{Digest, Header};
 	use session::OnSessionChange;
@@ -378,8 +663,11 @@ mod tests {
 		type PublicAux = u64;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = <Self as HasPublicAux>::PublicAux;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl system::Trait for Test {
 		type Index = u64;
@@ -423,7 +711,9 @@ mod tests {
 			current_era: 0,
 			balances: vec![(1, 10), (2, 20), (3, 30), (4, 40), (5, 50), (6, 60)],
 			intentions: vec![],
+			bonded: vec![],
 			validator_count: 2,
+			minimum_validator_count: 0,
 			bonding_duration: 3,
 			transaction_base_fee: 0,
 			transaction_byte_fee: 0,
@@ -432,12 +722,19 @@ mod tests {
 			creation_fee: 0,
 			reclaim_rebate: 0,
 			early_era_slash: 0,
+			misbehavior_slash: 0,
+			offline_slash: 0,
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 			session_reward: 0,
 		}.build_storage().unwrap());
 		t.extend(GenesisConfig::<Test>{
 			launch_period: 1,
 			voting_period: 1,
 			minimum_deposit: 1,
+			preimage_byte_deposit: 0,
+			enactment_delay: 0,
+			cooloff_period: 0,
 		}.build_storage().unwrap());
 		t.extend(timestamp::GenesisConfig::<Test>::default().build_storage().unwrap());
 		t
@@ -461,7 +758,7 @@ mod tests {
 	}
 
 	fn propose_sessions_per_era(who: u64, value: u64, locked: u64) -> super::Result {
-		Democracy::propose(&who, Box::new(Proposal::Staking(staking::PrivCall::set_sessions_per_era(value))), locked)
+		Democracy::propose(&who, BlakeTwo256::hash_of(&sessions_per_era_proposal(value)), locked)
 	}
 
 	#[test]
@@ -481,16 +778,17 @@ mod tests {
 	fn single_proposal_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
+			assert_ok!(Democracy::note_preimage(&1, sessions_per_era_proposal(2).encode()));
 			assert_ok!(propose_sessions_per_era(1, 2, 1));
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
 
 			System::set_block_number(2);
 			let r = 0;
-			assert_ok!(Democracy::vote(&1, r, true));
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
 
 			assert_eq!(Democracy::referendum_count(), 1);
 			assert_eq!(Democracy::voters_for(r), vec![1]);
-			assert_eq!(Democracy::vote_of((r, 1)), Some(true));
+			assert_eq!(Democracy::vote_of((r, 1)), Some((true, Conviction::None)));
 			assert_eq!(Democracy::tally(r), (10, 0));
 
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
@@ -556,33 +854,66 @@ mod tests {
 		});
 	}
 
+	fn bonding_duration_proposal(value: u64) -> Proposal {
+		Proposal::Staking(staking::PrivCall::set_bonding_duration(value))
+	}
+
+	#[test]
+	fn preimage_deposit_should_be_returned_on_enactment() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			<PreimageByteDeposit<Test>>::put(1);
+			let encoded = sessions_per_era_proposal(2).encode();
+			let len = encoded.len() as u64;
+			assert_ok!(Democracy::note_preimage(&6, encoded.clone()));
+			assert_noop!(Democracy::note_preimage(&6, encoded), "preimage already noted");
+			assert_noop!(Democracy::note_preimage(&1, vec![42; 11]), "not a valid proposal");
+			assert_eq!(Staking::free_balance(&6), 60 - len);
+
+			assert_ok!(propose_sessions_per_era(1, 2, 1));
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+
+			System::set_block_number(2);
+			assert_ok!(Democracy::vote(&1, 0, true, Conviction::None));
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+
+			assert_eq!(Staking::era_length(), 2);
+			assert_eq!(Staking::free_balance(&6), 60);
+			assert!(!<Preimages<Test>>::exists(BlakeTwo256::hash_of(&sessions_per_era_proposal(2))));
+		});
+	}
+
 	fn propose_bonding_duration(who: u64, value: u64, locked: u64) -> super::Result {
-		Democracy::propose(&who, Box::new(Proposal::Staking(staking::PrivCall::set_bonding_duration(value))), locked)
+		Democracy::propose(&who, BlakeTwo256::hash_of(&bonding_duration_proposal(value)), locked)
 	}
 
 	#[test]
 	fn runners_up_should_come_after() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(0);
+			for value in 2..5 {
+				assert_ok!(Democracy::note_preimage(&1, bonding_duration_proposal(value).encode()));
+			}
 			assert_ok!(propose_bonding_duration(1, 2, 2));
 			assert_ok!(propose_bonding_duration(1, 4, 4));
 			assert_ok!(propose_bonding_duration(1, 3, 3));
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
 
 			System::set_block_number(1);
-			assert_ok!(Democracy::vote(&1, 0, true));
+			assert_ok!(Democracy::vote(&1, 0, true, Conviction::None));
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
 			Staking::on_session_change(true, 0);
 			assert_eq!(Staking::bonding_duration(), 4);
 
 			System::set_block_number(2);
-			assert_ok!(Democracy::vote(&1, 1, true));
+			assert_ok!(Democracy::vote(&1, 1, true, Conviction::None));
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
 			Staking::on_session_change(true, 0);
 			assert_eq!(Staking::bonding_duration(), 3);
 
 			System::set_block_number(3);
-			assert_ok!(Democracy::vote(&1, 2, true));
+			assert_ok!(Democracy::vote(&1, 2, true, Conviction::None));
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
 			Staking::on_session_change(true, 0);
 			assert_eq!(Staking::bonding_duration(), 2);
@@ -593,15 +924,21 @@ mod tests {
 		Proposal::Staking(staking::PrivCall::set_sessions_per_era(value))
 	}
 
+	fn inject_referendum(end: u64, proposal: Proposal, vote_threshold: VoteThreshold) -> result::Result<ReferendumIndex, &'static str> {
+		let proposal_hash = BlakeTwo256::hash_of(&proposal);
+		Democracy::note_imminent_preimage(proposal_hash, proposal);
+		Democracy::inject_referendum(end, proposal_hash, vote_threshold)
+	}
+
 	#[test]
 	fn simple_passing_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&1, r, true));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
 
 			assert_eq!(Democracy::voters_for(r), vec![1]);
-			assert_eq!(Democracy::vote_of((r, 1)), Some(true));
+			assert_eq!(Democracy::vote_of((r, 1)), Some((true, Conviction::None)));
 			assert_eq!(Democracy::tally(r), (10, 0));
 
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
@@ -615,8 +952,8 @@ mod tests {
 	fn cancel_referendum_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&1, r, true));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
 			assert_ok!(Democracy::cancel_referendum(r));
 
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
@@ -626,15 +963,102 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn passed_proposal_should_be_enacted_after_delay() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			<EnactmentDelay<Test>>::put(2);
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::Locked2x));
+
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+			assert_eq!(Staking::era_length(), 1);
+			assert_eq!(Democracy::dispatch_queue(3), vec![BlakeTwo256::hash_of(&sessions_per_era_proposal(2))]);
+
+			System::set_block_number(3);
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+			assert_eq!(Staking::era_length(), 2);
+			assert!(Democracy::dispatch_queue(3).is_empty());
+
+			// the conviction lock runs from the enactment of the proposal.
+			assert_noop!(Staking::transfer(&1, 2.into(), 1), "bondage too high to send value");
+			System::set_block_number(4);
+			assert_ok!(Staking::transfer(&1, 2.into(), 1));
+		});
+	}
+
+	#[test]
+	fn cancelled_proposal_should_be_blacklisted() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			<CooloffPeriod<Test>>::put(2);
+			let r = Democracy::internal_start_referendum(sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::cancel_referendum(r));
+
+			let hash = BlakeTwo256::hash_of(&sessions_per_era_proposal(2));
+			assert!(Democracy::is_blacklisted(&hash));
+			assert_noop!(propose_sessions_per_era(1, 2, 1), "proposal is blacklisted");
+			assert_noop!(Democracy::fast_track(hash, 1), "proposal is blacklisted");
+			assert_eq!(
+				Democracy::internal_start_referendum(sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove),
+				Err("proposal is blacklisted")
+			);
+
+			System::set_block_number(3);
+			assert!(!Democracy::is_blacklisted(&hash));
+			assert_ok!(propose_sessions_per_era(1, 2, 1));
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			assert_eq!(Democracy::blacklist(&hash), None);
+			assert_eq!(Democracy::blacklist_expiry(3), vec![]);
+		});
+	}
+
+	#[test]
+	fn fast_tracked_referendum_may_end_before_earlier_ones() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			<VotingPeriod<Test>>::put(3);
+			assert_ok!(Democracy::note_preimage(&1, sessions_per_era_proposal(2).encode()));
+			assert_ok!(propose_sessions_per_era(1, 2, 2));
+			let r = inject_referendum(4, bonding_duration_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+
+			let hash = BlakeTwo256::hash_of(&sessions_per_era_proposal(2));
+			assert_noop!(Democracy::fast_track(hash, 4), "fast-track may not lengthen the voting period");
+			assert_ok!(Democracy::fast_track(hash, 1));
+			assert_eq!(Democracy::public_props(), vec![]);
+			assert_eq!(Staking::free_balance(&1), 10);
+			assert_eq!(Democracy::active_referendums(), vec![
+				(r, 4, BlakeTwo256::hash_of(&bonding_duration_proposal(2)), VoteThreshold::SuperMajorityApprove),
+				(r + 1, 2, hash, VoteThreshold::SuperMajorityAgainst),
+			]);
+
+			System::set_block_number(2);
+			assert_ok!(Democracy::vote(&1, r + 1, true, Conviction::None));
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+			assert_eq!(Staking::era_length(), 2);
+			assert_eq!(Democracy::next_tally(), r);
+
+			System::set_block_number(4);
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+			assert_eq!(Staking::bonding_duration(), 2);
+			assert_eq!(Democracy::next_tally(), r + 2);
+		});
+	}
+
 	#[test]
 	fn simple_failing_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&1, r, false));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, false, Conviction::None));
 
 			assert_eq!(Democracy::voters_for(r), vec![1]);
-			assert_eq!(Democracy::vote_of((r, 1)), Some(false));
+			assert_eq!(Democracy::vote_of((r, 1)), Some((false, Conviction::None)));
 			assert_eq!(Democracy::tally(r), (0, 10));
 
 			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
@@ -648,13 +1072,13 @@ mod tests {
 	fn controversial_voting_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&1, r, true));
-			assert_ok!(Democracy::vote(&2, r, false));
-			assert_ok!(Democracy::vote(&3, r, false));
-			assert_ok!(Democracy::vote(&4, r, true));
-			assert_ok!(Democracy::vote(&5, r, false));
-			assert_ok!(Democracy::vote(&6, r, true));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
+			assert_ok!(Democracy::vote(&2, r, false, Conviction::None));
+			assert_ok!(Democracy::vote(&3, r, false, Conviction::None));
+			assert_ok!(Democracy::vote(&4, r, true, Conviction::None));
+			assert_ok!(Democracy::vote(&5, r, false, Conviction::None));
+			assert_ok!(Democracy::vote(&6, r, true, Conviction::None));
 
 			assert_eq!(Democracy::tally(r), (110, 100));
 
@@ -669,9 +1093,9 @@ mod tests {
 	fn controversial_low_turnout_voting_should_work() {
 		with_externalities(&mut new_test_ext(), || {
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&5, r, false));
-			assert_ok!(Democracy::vote(&6, r, true));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&5, r, false, Conviction::None));
+			assert_ok!(Democracy::vote(&6, r, true, Conviction::None));
 
 			assert_eq!(Democracy::tally(r), (60, 50));
 
@@ -689,10 +1113,10 @@ mod tests {
 			assert_eq!(Staking::total_stake(), 210);
 
 			System::set_block_number(1);
-			let r = Democracy::inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
-			assert_ok!(Democracy::vote(&4, r, true));
-			assert_ok!(Democracy::vote(&5, r, false));
-			assert_ok!(Democracy::vote(&6, r, true));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&4, r, true, Conviction::None));
+			assert_ok!(Democracy::vote(&5, r, false, Conviction::None));
+			assert_ok!(Democracy::vote(&6, r, true, Conviction::None));
 
 			assert_eq!(Democracy::tally(r), (100, 50));
 
@@ -702,4 +1126,95 @@ mod tests {
 			assert_eq!(Staking::era_length(), 2);
 		});
 	}
+
+	#[test]
+	fn conviction_should_multiply_votes_and_lock_winners() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::Locked6x));
+			assert_ok!(Democracy::vote(&4, r, true, Conviction::None));
+			assert_ok!(Democracy::vote(&5, r, false, Conviction::Locked2x));
+
+			assert_eq!(Democracy::tally(r), (100, 100));
+			assert_ok!(Democracy::vote(&5, r, false, Conviction::None));
+			assert_eq!(Democracy::tally(r), (100, 50));
+
+			// the balance behind a vote can't move before the referendum ends.
+			assert_noop!(Staking::transfer(&4, 6.into(), 1), "bondage too high to send value");
+
+			assert_eq!(Democracy::end_block(System::block_number()), Ok(()));
+			Staking::on_session_change(true, 0);
+			assert_eq!(Staking::era_length(), 2);
+
+			// winners stay locked for as long as their conviction demands.
+			System::set_block_number(2);
+			assert_ok!(Staking::transfer(&4, 6.into(), 1));
+			assert_ok!(Staking::transfer(&5, 6.into(), 1));
+			assert_noop!(Staking::transfer(&1, 6.into(), 1), "bondage too high to send value");
+
+			System::set_block_number(17);
+			assert_ok!(Staking::transfer(&1, 6.into(), 1));
+		});
+	}
+
+	#[test]
+	fn delegated_votes_should_be_tallied() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(Democracy::delegate(&2, 1, Conviction::Locked2x));
+			assert_ok!(Democracy::delegate(&3, 2, Conviction::None));
+			assert_noop!(Democracy::delegate(&1, 3, Conviction::None), "delegation would create a cycle");
+			assert_noop!(Democracy::delegate(&1, 1, Conviction::None), "cannot delegate to self");
+
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
+			assert_eq!(Democracy::tally(r), (80, 0));
+
+			// voting directly takes precedence over the delegation.
+			assert_ok!(Democracy::vote(&2, r, false, Conviction::None));
+			assert_eq!(Democracy::tally(r), (10, 50));
+
+			// delegators stay locked until they undelegate.
+			assert_noop!(Staking::transfer(&3, 4.into(), 1), "bondage too high to send value");
+			assert_ok!(Democracy::undelegate(&3));
+			assert_noop!(Democracy::undelegate(&3), "not delegating");
+			assert_ok!(Staking::transfer(&3, 4.into(), 1));
+			assert_eq!(Democracy::tally(r), (10, 20));
+		});
+	}
+
+	#[test]
+	fn delegated_votes_should_be_backed_by_the_lock() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(Democracy::delegate(&3, 1, Conviction::None));
+			let r = inject_referendum(1, sessions_per_era_proposal(2), VoteThreshold::SuperMajorityApprove).unwrap();
+			assert_ok!(Democracy::vote(&1, r, true, Conviction::None));
+			assert_eq!(Democracy::tally(r), (40, 0));
+
+			// funds received after delegating are neither locked nor counted...
+			assert_ok!(Staking::transfer(&4, 3.into(), 20));
+			assert_eq!(Democracy::tally(r), (40, 0));
+			assert_ok!(Staking::transfer(&3, 4.into(), 20));
+
+			// ...until the delegation is renewed.
+			assert_ok!(Staking::transfer(&4, 3.into(), 20));
+			assert_ok!(Democracy::delegate(&3, 1, Conviction::None));
+			assert_eq!(Democracy::tally(r), (60, 0));
+			assert_noop!(Staking::transfer(&3, 4.into(), 1), "bondage too high to send value");
+		});
+	}
+
+	#[test]
+	fn delegation_chains_should_be_bounded() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			for who in 11..(10 + MAX_DELEGATION_DEPTH as u64) {
+				assert_ok!(Democracy::delegate(&who, who - 1, Conviction::None));
+			}
+			let last = 10 + MAX_DELEGATION_DEPTH as u64;
+			assert_noop!(Democracy::delegate(&last, last - 1, Conviction::None), "delegation chain too long");
+		});
+	}
 }
@@ -54,7 +54,7 @@ use rstd::marker::PhantomData;
 use rstd::result;
 use runtime_support::StorageValue;
 use primitives::traits::{self, Header, Zero, One, Checkable, Applyable, CheckEqual, Executable,
-	MakePayment, Hash, AuxLookup};
+	OffchainWorker, MakePayment, Hash, AuxLookup};
 use codec::{Codec, Encode};
 use system::extrinsics_root;
 use primitives::{ApplyOutcome, ApplyError};
@@ -87,7 +87,7 @@ impl<
 	Block: traits::Block<Header=System::Header, Hash=System::Hash>,
 	Lookup: AuxLookup<Source=Address, Target=System::AccountId>,
 	Payment: MakePayment<System::AccountId>,
-	Finalisation: Executable,
+	Finalisation: Executable + OffchainWorker<System::BlockNumber>,
 > Executive<System, Block, Lookup, Payment, Finalisation> where
 	Block::Extrinsic: Checkable<fn(Address) -> Result<System::AccountId, &'static str>> + Codec,
 	<Block::Extrinsic as Checkable<fn(Address) -> Result<System::AccountId, &'static str>>>::Checked: Applyable<Index=System::Index, AccountId=System::AccountId>
@@ -120,9 +120,11 @@ impl<
 		// any initial checks
 		Self::initial_checks(&block);
 
-		// execute transactions
+		// execute transactions, verifying their signatures as a batch.
 		let (header, extrinsics) = block.deconstruct();
+		runtime_io::start_batch_verify();
 		extrinsics.into_iter().for_each(Self::apply_extrinsic_no_note);
+		assert!(runtime_io::finish_batch_verify(), "All extrinsics should be properly signed");
 
 		// post-transactional book-keeping.
 		Finalisation::execute();
@@ -141,6 +143,12 @@ impl<
 		<system::Module<System>>::finalise()
 	}
 
+	/// Run the off-chain logic of all modules on top of the imported block `n`. Any
+	/// state changes are discarded by the caller.
+	pub fn offchain_worker(n: System::BlockNumber) {
+		Finalisation::generate_extrinsics(n)
+	}
+
 	/// Apply extrinsic outside of the block execution function.
 	/// This doesn't attempt to validate anything regarding the block, but it builds a list of uxt
 	/// hashes.
@@ -239,8 +247,11 @@ mod tests {
 		type PublicAux = u64;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = <Self as HasPublicAux>::PublicAux;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl system::Trait for Test {
 		type Index = u64;
@@ -276,7 +287,9 @@ mod tests {
 			current_era: 0,
 			balances: vec![(1, 111)],
 			intentions: vec![],
+			bonded: vec![],
 			validator_count: 0,
+			minimum_validator_count: 0,
 			bonding_duration: 0,
 			transaction_base_fee: 10,
 			transaction_byte_fee: 0,
@@ -285,6 +298,10 @@ mod tests {
 			creation_fee: 0,
 			reclaim_rebate: 0,
 			early_era_slash: 0,
+			misbehavior_slash: 0,
+			offline_slash: 0,
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 			session_reward: 0,
 		}.build_storage().unwrap());
 		let xt = primitives::testing::TestXt((1, 0, Call::transfer(2.into(), 69)));
@@ -126,7 +126,7 @@ where
 					index: self.extrinsic.index,
 					function: self.extrinsic.function,
 				};
-			if ::verify_encoded_lazy(&self.signature, &extrinsic, &extrinsic.signed) {
+			if ::batch_verify_encoded_lazy(&self.signature, &extrinsic, &extrinsic.signed) {
 				Ok(CheckedExtrinsic(extrinsic))
 			} else {
 				Err("bad signature in extrinsic")
@@ -259,6 +259,12 @@ impl<Item> traits::Digest for Digest<Item> where
 	fn push(&mut self, item: Self::Item) {
 		self.logs.push(item);
 	}
+	fn pop(&mut self) -> Option<Self::Item> {
+		self.logs.pop()
+	}
+	fn logs(&self) -> &[Self::Item] {
+		&self.logs
+	}
 }
 
 
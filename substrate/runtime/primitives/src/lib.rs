@@ -40,12 +40,16 @@ extern crate substrate_primitives;
 
 #[cfg(test)]
 extern crate serde_json;
+#[cfg(test)]
+extern crate schnorrkel;
+#[cfg(test)]
+extern crate secp256k1;
 
 #[cfg(feature = "std")]
 use std::collections::HashMap;
 
 use rstd::prelude::*;
-use substrate_primitives::hash::{H256, H512};
+use substrate_primitives::hash::{H160, H256, H512};
 
 #[cfg(feature = "std")]
 use substrate_primitives::hexdisplay::ascii_format;
@@ -91,6 +95,9 @@ impl Verify for Ed25519Signature {
 	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &Self::Signer) -> bool {
 		runtime_io::ed25519_verify(&(self.0).0, msg.get(), &signer.0[..])
 	}
+	fn batch_verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &Self::Signer) -> bool {
+		runtime_io::ed25519_batch_verify(&(self.0).0, msg.get(), &signer.0[..])
+	}
 }
 
 impl codec::Decode for Ed25519Signature {
@@ -111,6 +118,70 @@ impl From<H512> for Ed25519Signature {
 	}
 }
 
+/// Sr25519 (Schnorrkel) signature verify.
+#[derive(Eq, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct Sr25519Signature(pub H512);
+
+impl Verify for Sr25519Signature {
+	type Signer = H256;
+	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &Self::Signer) -> bool {
+		runtime_io::sr25519_verify(&(self.0).0, msg.get(), &signer.0[..])
+	}
+}
+
+impl codec::Decode for Sr25519Signature {
+	fn decode<I: codec::Input>(input: &mut I) -> Option<Self> {
+		Some(Sr25519Signature(codec::Decode::decode(input)?,))
+	}
+}
+
+impl codec::Encode for Sr25519Signature {
+	fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+		self.0.using_encoded(f)
+	}
+}
+
+impl From<H512> for Sr25519Signature {
+	fn from(h: H512) -> Sr25519Signature {
+		Sr25519Signature(h)
+	}
+}
+
+/// Ethereum-compatible secp256k1 ECDSA signature verify: `r` and `s` followed by the recovery id.
+///
+/// The signer is the address derived from the recovered public key, i.e. the last 20 bytes of its
+/// Keccak 256-bit hash, and the message is hashed with Keccak before recovery.
+#[derive(Eq, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct Secp256k1Signature(pub H512, pub u8);
+
+impl Verify for Secp256k1Signature {
+	type Signer = H160;
+	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &Self::Signer) -> bool {
+		let mut sig = [0u8; 65];
+		sig[0..64].copy_from_slice(&(self.0).0[..]);
+		sig[64] = self.1;
+		match runtime_io::secp256k1_ecdsa_recover(&sig, &runtime_io::keccak_256(msg.get())) {
+			Some(pubkey) => runtime_io::keccak_256(&pubkey[..])[12..] == signer.0[..],
+			None => false,
+		}
+	}
+}
+
+impl codec::Decode for Secp256k1Signature {
+	fn decode<I: codec::Input>(input: &mut I) -> Option<Self> {
+		Some(Secp256k1Signature(codec::Decode::decode(input)?, codec::Decode::decode(input)?))
+	}
+}
+
+impl codec::Encode for Secp256k1Signature {
+	fn encode_to<W: codec::Output>(&self, dest: &mut W) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+	}
+}
+
 #[derive(Eq, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "std", derive(Debug, Serialize))]
 #[repr(u8)]
@@ -202,6 +273,13 @@ impl<T: Verify> Verify for MaybeUnsigned<T> where
 			self.0.verify(msg, signer)
 		}
 	}
+	fn batch_verify<L: Lazy<[u8]>>(&self, msg: L, signer: &Self::Signer) -> bool {
+		if !self.is_signed() {
+			!self.is_addressed(signer)
+		} else {
+			self.0.batch_verify(msg, signer)
+		}
+	}
 }
 
 impl<T: codec::Decode> codec::Decode for MaybeUnsigned<T> {
@@ -222,30 +300,38 @@ impl<T> From<T> for MaybeUnsigned<T> {
 	}
 }
 
-/// Verify a signature on an encoded value in a lazy manner. This can be
-/// an optimization if the signature scheme has an "unsigned" escape hash.
-pub fn verify_encoded_lazy<V: Verify, T: codec::Encode>(sig: &V, item: &T, signer: &V::Signer) -> bool {
-	// The `Lazy<T>` trait expresses something like `X: FnMut<Output = for<'a> &'a T>`.
-	// unfortunately this is a lifetime relationship that can't
-	// be expressed without generic associated types, better unification of HRTBs in type position,
-	// and some kind of integration into the Fn* traits.
-	struct LazyEncode<F> {
-		inner: F,
-		encoded: Option<Vec<u8>>,
-	}
+// The `Lazy<T>` trait expresses something like `X: FnMut<Output = for<'a> &'a T>`.
+// unfortunately this is a lifetime relationship that can't
+// be expressed without generic associated types, better unification of HRTBs in type position,
+// and some kind of integration into the Fn* traits.
+struct LazyEncode<F> {
+	inner: F,
+	encoded: Option<Vec<u8>>,
+}
 
-	impl<F: Fn() -> Vec<u8>> traits::Lazy<[u8]> for LazyEncode<F> {
-		fn get(&mut self) -> &[u8] {
-			self.encoded.get_or_insert_with(&self.inner).as_slice()
-		}
+impl<F: Fn() -> Vec<u8>> traits::Lazy<[u8]> for LazyEncode<F> {
+	fn get(&mut self) -> &[u8] {
+		self.encoded.get_or_insert_with(&self.inner).as_slice()
 	}
+}
 
+/// Verify a signature on an encoded value in a lazy manner. This can be
+/// an optimization if the signature scheme has an "unsigned" escape hash.
+pub fn verify_encoded_lazy<V: Verify, T: codec::Encode>(sig: &V, item: &T, signer: &V::Signer) -> bool {
 	sig.verify(
 		LazyEncode { inner: || item.encode(), encoded: None },
 		signer,
 	)
 }
 
+/// Like `verify_encoded_lazy`, but the signature may be queued if a batch is in progress.
+pub fn batch_verify_encoded_lazy<V: Verify, T: codec::Encode>(sig: &V, item: &T, signer: &V::Signer) -> bool {
+	sig.batch_verify(
+		LazyEncode { inner: || item.encode(), encoded: None },
+		signer,
+	)
+}
+
 #[macro_export]
 macro_rules! __impl_outer_config_types {
 	($concrete:ident $config:ident $snake:ident $($rest:ident)*) => {
@@ -284,3 +370,50 @@ macro_rules! impl_outer_config {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime_io::{blake2_256, keccak_256};
+
+	#[test]
+	fn sr25519_signature_should_verify() {
+		let pair = ::schnorrkel::MiniSecretKey::from_bytes(&blake2_256(b"test")).unwrap().expand_to_keypair();
+		let signer = H256(pair.public.to_bytes());
+		let signature = Sr25519Signature(pair.sign(::schnorrkel::signing_context(b"substrate").bytes(b"all ok!")).to_bytes().into());
+
+		assert!(signature.verify(&b"all ok!"[..], &signer));
+		assert!(!signature.verify(&b"all is not ok!"[..], &signer));
+		assert!(!signature.verify(&b"all ok!"[..], &H256([1; 32])));
+		assert!(!Sr25519Signature::default().verify(&b"all ok!"[..], &signer));
+	}
+
+	#[test]
+	fn secp256k1_signature_should_verify_against_address() {
+		let secret = ::secp256k1::SecretKey::parse(&blake2_256(b"test")).unwrap();
+		let public = ::secp256k1::PublicKey::from_secret_key(&secret);
+		let (rs, v) = ::secp256k1::sign(&::secp256k1::Message::parse(&keccak_256(b"all ok!")), &secret).unwrap();
+		let signature = Secp256k1Signature(rs.serialize().into(), v.serialize() + 27);
+
+		// the signer is the last 20 bytes of the Keccak hash of the uncompressed public key.
+		let mut signer = H160::default();
+		signer.0.copy_from_slice(&keccak_256(&public.serialize()[1..65])[12..]);
+
+		assert!(signature.verify(&b"all ok!"[..], &signer));
+		assert!(Secp256k1Signature(signature.0, signature.1 - 27).verify(&b"all ok!"[..], &signer));
+		assert!(!signature.verify(&b"all is not ok!"[..], &signer));
+		assert!(!signature.verify(&b"all ok!"[..], &H160([1; 20])));
+	}
+
+	#[test]
+	fn secp256k1_signature_with_malformed_recovery_id_should_not_verify() {
+		let secret = ::secp256k1::SecretKey::parse(&blake2_256(b"test")).unwrap();
+		let public = ::secp256k1::PublicKey::from_secret_key(&secret);
+		let (rs, _) = ::secp256k1::sign(&::secp256k1::Message::parse(&keccak_256(b"all ok!")), &secret).unwrap();
+		let mut signer = H160::default();
+		signer.0.copy_from_slice(&keccak_256(&public.serialize()[1..65])[12..]);
+
+		assert!(!Secp256k1Signature(rs.serialize().into(), 42).verify(&b"all ok!"[..], &signer));
+		assert!(!Secp256k1Signature(rs.serialize().into(), 4).verify(&b"all ok!"[..], &signer));
+	}
+}
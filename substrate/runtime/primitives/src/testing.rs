@@ -46,6 +46,12 @@ impl traits::Digest for Digest {
 	fn push(&mut self, item: Self::Item) {
 		self.logs.push(item);
 	}
+	fn pop(&mut self) -> Option<Self::Item> {
+		self.logs.pop()
+	}
+	fn logs(&self) -> &[Self::Item] {
+		&self.logs
+	}
 }
 
 #[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
@@ -43,6 +43,11 @@ pub trait Verify {
 	type Signer;
 	/// Verify a signature. Return `true` if signature is valid for the value.
 	fn verify<L: Lazy<[u8]>>(&self, msg: L, signer: &Self::Signer) -> bool;
+	/// Like `verify`, but the signature may be queued if a batch is in progress, see
+	/// `runtime_io::start_batch_verify`, in which case `true` is returned.
+	fn batch_verify<L: Lazy<[u8]>>(&self, msg: L, signer: &Self::Signer) -> bool {
+		self.verify(msg, signer)
+	}
 }
 
 /// Means of changing one type into another in a manner dependent on the source type.
@@ -193,6 +198,20 @@ impl<A: Executable, B: Executable> Executable for (A, B) {
 	}
 }
 
+/// Something that can run off-chain logic after a block has been imported.
+pub trait OffchainWorker<BlockNumber> {
+	/// Called with the number of the imported block. May submit extrinsics to the local pool.
+	fn generate_extrinsics(_n: BlockNumber) {}
+}
+
+impl<N> OffchainWorker<N> for () {}
+impl<N: Clone, A: OffchainWorker<N>, B: OffchainWorker<N>> OffchainWorker<N> for (A, B) {
+	fn generate_extrinsics(n: N) {
+		A::generate_extrinsics(n.clone());
+		B::generate_extrinsics(n);
+	}
+}
+
 /// Abstraction around hashing
 pub trait Hash: 'static + MaybeSerializeDebug + Clone + Eq + PartialEq {	// Stupid bug in the Rust compiler believes derived
 																	// traits must be fulfilled by all type parameters.
@@ -320,6 +339,8 @@ impl<T: Send + Sync + Sized + MaybeSerializeDebug + Eq + PartialEq + Clone + 'st
 pub trait Digest {
 	type Item: Member;
 	fn push(&mut self, item: Self::Item);
+	fn pop(&mut self) -> Option<Self::Item>;
+	fn logs(&self) -> &[Self::Item];
 }
 
 /// Something which fulfills the abstract idea of a Substrate header. It has types for a `Number`,
@@ -46,7 +46,7 @@ extern crate substrate_runtime_system as system;
 extern crate substrate_runtime_timestamp as timestamp;
 
 use rstd::prelude::*;
-use primitives::traits::{Zero, One, RefInto, Executable, Convert, As};
+use primitives::traits::{Zero, One, RefInto, Executable, OffchainWorker, Convert, As};
 use runtime_support::{StorageValue, StorageMap};
 use runtime_support::dispatch::Result;
 
@@ -231,6 +231,8 @@ impl<T: Trait> Executable for Module<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Module<T> {}
+
 #[cfg(any(feature = "std", test))]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -284,8 +286,11 @@ mod tests {
 		type PublicAux = u64;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = <Self as HasPublicAux>::PublicAux;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl system::Trait for Test {
 		type Index = u64;
@@ -26,7 +26,9 @@ use {runtime_io, primitives};
 use super::{Trait, ENUM_SET_SIZE, EnumSet, NextEnumSet, Intentions, CurrentEra,
 	BondingDuration, CreationFee, TransferFee, ReclaimRebate,
 	ExistentialDeposit, TransactionByteFee, TransactionBaseFee, TotalStake,
-	SessionsPerEra, ValidatorCount, FreeBalance, SessionReward, EarlyEraSlash};
+	SessionsPerEra, ValidatorCount, MinimumValidatorCount, FreeBalance, SessionReward, EarlyEraSlash, MisbehaviorSlash,
+	OfflineSlash, OfflineSlashGrace, ReportAfterMissed,
+	Bonded, Ledger, StakingLedger};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -36,7 +38,11 @@ pub struct GenesisConfig<T: Trait> {
 	pub current_era: T::BlockNumber,
 	pub balances: Vec<(T::AccountId, T::Balance)>,
 	pub intentions: Vec<T::AccountId>,
+	/// Initial bonds, as `(stash, controller, value)`.
+	pub bonded: Vec<(T::AccountId, T::AccountId, T::Balance)>,
 	pub validator_count: u64,
+	/// Minimum number of validators an election must yield for the validator set to change.
+	pub minimum_validator_count: u32,
 	pub bonding_duration: T::BlockNumber,
 	pub transaction_base_fee: T::Balance,
 	pub transaction_byte_fee: T::Balance,
@@ -46,6 +52,12 @@ pub struct GenesisConfig<T: Trait> {
 	pub existential_deposit: T::Balance,
 	pub session_reward: T::Balance,
 	pub early_era_slash: T::Balance,
+	pub misbehavior_slash: T::Balance,
+	pub offline_slash: T::Balance,
+	/// Number of consecutive sessions a validator may be noted offline in before it gets slashed.
+	pub offline_slash_grace: u32,
+	/// Number of consecutive agreements a validator may miss before it is reported offline.
+	pub report_after_missed: u32,
 }
 
 impl<T: Trait> GenesisConfig<T> where T::AccountId: From<u64> {
@@ -55,7 +67,9 @@ impl<T: Trait> GenesisConfig<T> where T::AccountId: From<u64> {
 			current_era: T::BlockNumber::sa(0),
 			balances: vec![(T::AccountId::from(1), T::Balance::sa(111))],
 			intentions: vec![T::AccountId::from(1), T::AccountId::from(2), T::AccountId::from(3)],
+			bonded: vec![],
 			validator_count: 3,
+			minimum_validator_count: 0,
 			bonding_duration: T::BlockNumber::sa(0),
 			transaction_base_fee: T::Balance::sa(0),
 			transaction_byte_fee: T::Balance::sa(0),
@@ -65,6 +79,10 @@ impl<T: Trait> GenesisConfig<T> where T::AccountId: From<u64> {
 			reclaim_rebate: T::Balance::sa(0),
 			session_reward: T::Balance::sa(0),
 			early_era_slash: T::Balance::sa(0),
+			misbehavior_slash: T::Balance::sa(0),
+			offline_slash: T::Balance::sa(0),
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 		}
 	}
 
@@ -82,7 +100,9 @@ impl<T: Trait> GenesisConfig<T> where T::AccountId: From<u64> {
 				(T::AccountId::from(7), T::Balance::sa(1))
 			],
 			intentions: vec![T::AccountId::from(1), T::AccountId::from(2), T::AccountId::from(3)],
+			bonded: vec![],
 			validator_count: 3,
+			minimum_validator_count: 0,
 			bonding_duration: T::BlockNumber::sa(0),
 			transaction_base_fee: T::Balance::sa(1),
 			transaction_byte_fee: T::Balance::sa(0),
@@ -92,6 +112,10 @@ impl<T: Trait> GenesisConfig<T> where T::AccountId: From<u64> {
 			reclaim_rebate: T::Balance::sa(0),
 			session_reward: T::Balance::sa(0),
 			early_era_slash: T::Balance::sa(0),
+			misbehavior_slash: T::Balance::sa(0),
+			offline_slash: T::Balance::sa(0),
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 		}
 	}
 }
@@ -103,7 +127,9 @@ impl<T: Trait> Default for GenesisConfig<T> {
 			current_era: T::BlockNumber::sa(0),
 			balances: vec![],
 			intentions: vec![],
+			bonded: vec![],
 			validator_count: 0,
+			minimum_validator_count: 0,
 			bonding_duration: T::BlockNumber::sa(1000),
 			transaction_base_fee: T::Balance::sa(0),
 			transaction_byte_fee: T::Balance::sa(0),
@@ -113,6 +139,10 @@ impl<T: Trait> Default for GenesisConfig<T> {
 			reclaim_rebate: T::Balance::sa(0),
 			session_reward: T::Balance::sa(0),
 			early_era_slash: T::Balance::sa(0),
+			misbehavior_slash: T::Balance::sa(0),
+			offline_slash: T::Balance::sa(0),
+			offline_slash_grace: 0,
+			report_after_missed: 0,
 		}
 	}
 }
@@ -139,6 +169,23 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T> {
 			Self::hash(<TotalStake<T>>::key()).to_vec() => total_stake.encode()
 		];
 
+		// Optional; absence means no slash.
+		if !self.misbehavior_slash.is_zero() {
+			r.insert(Self::hash(<MisbehaviorSlash<T>>::key()).to_vec(), self.misbehavior_slash.encode());
+		}
+		if !self.offline_slash.is_zero() {
+			r.insert(Self::hash(<OfflineSlash<T>>::key()).to_vec(), self.offline_slash.encode());
+		}
+		if self.offline_slash_grace != 0 {
+			r.insert(Self::hash(<OfflineSlashGrace<T>>::key()).to_vec(), self.offline_slash_grace.encode());
+		}
+		if self.report_after_missed != 0 {
+			r.insert(Self::hash(<ReportAfterMissed<T>>::key()).to_vec(), self.report_after_missed.encode());
+		}
+		if self.minimum_validator_count != 0 {
+			r.insert(Self::hash(<MinimumValidatorCount<T>>::key()).to_vec(), self.minimum_validator_count.encode());
+		}
+
 		let ids: Vec<_> = self.balances.iter().map(|x| x.0.clone()).collect();
 		for i in 0..(ids.len() + ENUM_SET_SIZE - 1) / ENUM_SET_SIZE {
 			r.insert(Self::hash(&<EnumSet<T>>::key_for(T::AccountIndex::sa(i))).to_vec(),
@@ -147,6 +194,15 @@ impl<T: Trait> primitives::BuildStorage for GenesisConfig<T> {
 		for (who, value) in self.balances.into_iter() {
 			r.insert(Self::hash(&<FreeBalance<T>>::key_for(who)).to_vec(), value.encode());
 		}
+		for (stash, controller, value) in self.bonded.into_iter() {
+			r.insert(Self::hash(&<Bonded<T>>::key_for(&stash)).to_vec(), controller.encode());
+			r.insert(Self::hash(&<Ledger<T>>::key_for(controller)).to_vec(), StakingLedger::<_, _, T::BlockNumber> {
+				stash,
+				total: value,
+				active: value,
+				unlocking: vec![],
+			}.encode());
+		}
 		Ok(r)
 	}
 }
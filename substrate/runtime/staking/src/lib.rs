@@ -44,15 +44,15 @@ extern crate substrate_runtime_session as session;
 extern crate substrate_runtime_system as system;
 extern crate substrate_runtime_timestamp as timestamp;
 
-#[cfg(test)] use std::fmt::Debug;
 use rstd::prelude::*;
 use rstd::{cmp, result};
 use codec::{Encode, Decode, Codec, Input, Output};
 use runtime_support::{StorageValue, StorageMap, Parameter};
 use runtime_support::dispatch::Result;
+use consensus::{OnMisbehavior, OnOfflineValidator};
 use session::OnSessionChange;
-use primitives::traits::{Zero, One, Bounded, RefInto, SimpleArithmetic, Executable, MakePayment,
-	As, AuxLookup, Member, CheckedAdd, CheckedSub};
+use primitives::traits::{Zero, One, RefInto, SimpleArithmetic, Executable, OffchainWorker, MakePayment,
+	As, AuxLookup, Member, CheckedAdd, CheckedSub, Convert};
 use address::Address as RawAddress;
 
 mod mock;
@@ -60,6 +60,7 @@ mod mock;
 pub mod address;
 mod tests;
 mod genesis_config;
+mod phragmen;
 
 #[cfg(feature = "std")]
 pub use genesis_config::GenesisConfig;
@@ -70,22 +71,293 @@ const ENUM_SET_SIZE: usize = 64;
 /// The byte to identify intention to reclaim an existing account index.
 const RECLAIM_INDEX_MAGIC: usize = 0x69;
 
+/// The reporter of a misbehavior receives one part in this many of the slashed funds.
+const MISBEHAVIOR_REPORTER_SHARE: u64 = 10;
+
+/// The maximum number of unbonding chunks a ledger may have queued at once.
+const MAX_UNLOCKING_CHUNKS: usize = 32;
+
+/// The maximum number of candidates a nominator may approve of.
+const MAX_NOMINATIONS: usize = 16;
+
+/// Commissions are expressed in parts of this.
+const COMMISSION_DENOMINATOR: u32 = 1_000_000;
+
+/// Default number of times a validator may be reported offline before it is unstaked.
+const DEFAULT_UNSTAKE_THRESHOLD: u32 = 3;
+
+/// The offline slash doubles with each report past the grace, up to this many times.
+const MAX_OFFLINE_SLASH_EXPONENT: u32 = 32;
+
 pub type Address<T> = RawAddress<<T as system::Trait>::AccountId, <T as Trait>::AccountIndex>;
 
-#[cfg(test)]
-#[derive(Debug, PartialEq, Clone)]
-pub enum LockStatus<BlockNumber: Debug + PartialEq + Clone> {
-	Liquid,
-	LockedUntil(BlockNumber),
-	Staked,
+/// Identifies the module which placed a lock on the balance of an account.
+pub type LockIdentifier = [u8; 8];
+
+/// A lock on the free balance of an account, placed by some other module.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BalanceLock<Balance, BlockNumber> {
+	/// The module which placed the lock.
+	pub id: LockIdentifier,
+	/// Amount of the free balance which cannot be moved.
+	pub amount: Balance,
+	/// Block from which the lock no longer applies.
+	pub until: BlockNumber,
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for BalanceLock<Balance, BlockNumber> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.id);
+		dest.push(&self.amount);
+		dest.push(&self.until);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for BalanceLock<Balance, BlockNumber> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(BalanceLock {
+			id: Decode::decode(input)?,
+			amount: Decode::decode(input)?,
+			until: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A chunk of bonded funds on its way to becoming free.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct UnlockChunk<Balance, BlockNumber> {
+	/// Amount of funds to be unlocked.
+	pub value: Balance,
+	/// Era from which the funds can be withdrawn.
+	pub era: BlockNumber,
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for UnlockChunk<Balance, BlockNumber> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.value);
+		dest.push(&self.era);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for UnlockChunk<Balance, BlockNumber> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(UnlockChunk {
+			value: Decode::decode(input)?,
+			era: Decode::decode(input)?,
+		})
+	}
+}
+
+/// The bond of a stash account, kept under the key of its controller.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct StakingLedger<AccountId, Balance, BlockNumber> {
+	/// The stash account whose funds are bonded.
+	pub stash: AccountId,
+	/// The total amount of the stash's free balance that is locked, including funds which
+	/// are being unbonded.
+	pub total: Balance,
+	/// The amount of the stash's free balance that counts as stake.
+	pub active: Balance,
+	/// Funds being unbonded, in order of maturity.
+	pub unlocking: Vec<UnlockChunk<Balance, BlockNumber>>,
 }
 
-#[cfg(not(test))]
-#[derive(PartialEq, Clone)]
-pub enum LockStatus<BlockNumber: PartialEq + Clone> {
-	Liquid,
-	LockedUntil(BlockNumber),
+impl<AccountId: Encode, Balance: Encode, BlockNumber: Encode> Encode for StakingLedger<AccountId, Balance, BlockNumber> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.stash);
+		dest.push(&self.total);
+		dest.push(&self.active);
+		dest.push(&self.unlocking);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode, BlockNumber: Decode> Decode for StakingLedger<AccountId, Balance, BlockNumber> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(StakingLedger {
+			stash: Decode::decode(input)?,
+			total: Decode::decode(input)?,
+			active: Decode::decode(input)?,
+			unlocking: Decode::decode(input)?,
+		})
+	}
+}
+
+/// The part of a nominator's stake that backs a validator.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct IndividualExposure<AccountId, Balance> {
+	/// The controller of the nominator.
+	pub who: AccountId,
+	/// The amount of stake it exposes.
+	pub value: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for IndividualExposure<AccountId, Balance> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.who);
+		dest.push(&self.value);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for IndividualExposure<AccountId, Balance> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(IndividualExposure {
+			who: Decode::decode(input)?,
+			value: Decode::decode(input)?,
+		})
+	}
+}
+
+/// The stake backing a validator for the current era, as determined by the election.
+#[derive(PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Exposure<AccountId, Balance> {
+	/// The total stake backing the validator.
+	pub total: Balance,
+	/// The validator's own stake.
+	pub own: Balance,
+	/// The stake of the nominators backing the validator.
+	pub others: Vec<IndividualExposure<AccountId, Balance>>,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for Exposure<AccountId, Balance> {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.total);
+		dest.push(&self.own);
+		dest.push(&self.others);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Exposure<AccountId, Balance> {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(Exposure {
+			total: Decode::decode(input)?,
+			own: Decode::decode(input)?,
+			others: Decode::decode(input)?,
+		})
+	}
+}
+
+/// Where the rewards of a staker are paid.
+#[derive(PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum RewardDestination {
+	/// Pay into the stash and add the reward to the bond.
 	Staked,
+	/// Pay into the stash, leaving the reward free.
+	Stash,
+	/// Pay into the controller.
+	Controller,
+}
+
+impl Default for RewardDestination {
+	fn default() -> Self {
+		RewardDestination::Staked
+	}
+}
+
+impl Encode for RewardDestination {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push_byte(match *self {
+			RewardDestination::Staked => 0,
+			RewardDestination::Stash => 1,
+			RewardDestination::Controller => 2,
+		});
+	}
+}
+
+impl Decode for RewardDestination {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		match input.read_byte()? {
+			0 => Some(RewardDestination::Staked),
+			1 => Some(RewardDestination::Stash),
+			2 => Some(RewardDestination::Controller),
+			_ => None,
+		}
+	}
+}
+
+/// Preferences of a validator.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct ValidatorPrefs {
+	/// The part of each session reward, in millionths, that the validator takes before the
+	/// rest is shared with its nominators.
+	pub commission: u32,
+	/// Number of times the validator may be reported offline before it is unstaked.
+	pub unstake_threshold: u32,
+}
+
+impl Default for ValidatorPrefs {
+	fn default() -> Self {
+		ValidatorPrefs {
+			commission: 0,
+			unstake_threshold: DEFAULT_UNSTAKE_THRESHOLD,
+		}
+	}
+}
+
+impl Encode for ValidatorPrefs {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		dest.push(&self.commission);
+		dest.push(&self.unstake_threshold);
+	}
+}
+
+impl Decode for ValidatorPrefs {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		Some(ValidatorPrefs {
+			commission: Decode::decode(input)?,
+			unstake_threshold: Decode::decode(input)?,
+		})
+	}
+}
+
+impl<AccountId, Balance: SimpleArithmetic + Copy, BlockNumber: PartialOrd> StakingLedger<AccountId, Balance, BlockNumber> {
+	/// Remove the chunks which can be withdrawn in the era `current_era`, unlocking their funds.
+	fn consolidate_unlocked(&mut self, current_era: BlockNumber) {
+		let mut unlocked = Zero::zero();
+		self.unlocking.retain(|chunk| if chunk.era > current_era {
+			true
+		} else {
+			unlocked = unlocked + chunk.value;
+			false
+		});
+		self.total = self.total - unlocked;
+	}
+
+	/// Shrink the ledger so that no more than `value` is bonded, taking from the active
+	/// funds first and from the most recent unlocking chunks afterwards.
+	fn shrink_to(&mut self, value: Balance) {
+		if self.total <= value {
+			return;
+		}
+		let mut excess = self.total - value;
+		self.total = value;
+
+		let from_active = cmp::min(self.active, excess);
+		self.active = self.active - from_active;
+		excess = excess - from_active;
+
+		while !excess.is_zero() {
+			let emptied = match self.unlocking.last_mut() {
+				Some(chunk) => {
+					let from_chunk = cmp::min(chunk.value, excess);
+					chunk.value = chunk.value - from_chunk;
+					excess = excess - from_chunk;
+					chunk.value.is_zero()
+				}
+				None => break,
+			};
+			if !emptied {
+				break;
+			}
+			self.unlocking.pop();
+		}
+	}
 }
 
 /// The account was the given id was killed.
@@ -118,8 +390,14 @@ decl_module! {
 		fn transfer(aux, dest: RawAddress<T::AccountId, T::AccountIndex>, value: T::Balance) -> Result = 0;
 		fn stake(aux) -> Result = 1;
 		fn unstake(aux, index: u32) -> Result = 2;
-		fn nominate(aux, target: RawAddress<T::AccountId, T::AccountIndex>) -> Result = 3;
-		fn unnominate(aux, target_index: u32) -> Result = 4;
+		fn nominate(aux, targets: Vec<RawAddress<T::AccountId, T::AccountIndex>>) -> Result = 3;
+		fn unnominate(aux) -> Result = 4;
+		fn bond(aux, controller: RawAddress<T::AccountId, T::AccountIndex>, value: T::Balance) -> Result = 5;
+		fn bond_extra(aux, max_additional: T::Balance) -> Result = 6;
+		fn unbond(aux, value: T::Balance) -> Result = 7;
+		fn withdraw_unbonded(aux) -> Result = 8;
+		fn set_preferences(aux, prefs: ValidatorPrefs) -> Result = 9;
+		fn set_payee(aux, payee: RewardDestination) -> Result = 10;
 	}
 
 	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -134,10 +412,12 @@ decl_module! {
 decl_storage! {
 	trait Store for Module<T: Trait>;
 
-	// The length of the bonding duration in eras.
+	// The number of eras that unbonded funds stay locked for.
 	pub BondingDuration get(bonding_duration): b"sta:loc" => required T::BlockNumber;
 	// The length of a staking era in sessions.
 	pub ValidatorCount get(validator_count): b"sta:vac" => required u32;
+	// The minimum number of validators an election must yield for the validator set to change.
+	pub MinimumValidatorCount get(minimum_validator_count): b"sta:minimum_validator_count" => default u32;
 	// The length of a staking era in sessions.
 	pub SessionsPerEra get(sessions_per_era): b"sta:spe" => required T::BlockNumber;
 	// The total amount of stake on the system.
@@ -159,17 +439,35 @@ decl_storage! {
 	pub SessionReward get(session_reward): b"sta:session_reward" => required T::Balance;
 	// Slash, per validator that is taken per abnormal era end.
 	pub EarlyEraSlash get(early_era_slash): b"sta:early_era_slash" => required T::Balance;
+	// Slash, shared between a validator and its nominators, that is taken per proven misbehavior.
+	pub MisbehaviorSlash get(misbehavior_slash): b"sta:misbehavior_slash" => default T::Balance;
+	// Slash, shared between a validator and its nominators, that is taken for the first offline
+	// report past the grace; it doubles for every further one.
+	pub OfflineSlash get(offline_slash): b"sta:offline_slash" => default T::Balance;
+	// Number of consecutive sessions a validator may be reported offline in before it is slashed.
+	pub OfflineSlashGrace get(offline_slash_grace): b"sta:offline_slash_grace" => default u32;
+	// Number of consecutive agreements a validator may miss before nodes report it offline.
+	// Zero leaves it to the node's default.
+	pub ReportAfterMissed get(report_after_missed): b"sta:report_after_missed" => default u32;
 
 	// The current era index.
 	pub CurrentEra get(current_era): b"sta:era" => required T::BlockNumber;
-	// All the accounts with a desire to stake.
+	// All the controllers with a desire to stake.
 	pub Intentions get(intentions): b"sta:wil:" => default Vec<T::AccountId>;
-	// All nominator -> nominee relationships.
-	pub Nominating get(nominating): b"sta:nominating" => map [ T::AccountId => T::AccountId ];
+	// All nominators and the candidates they approve of, both by controller.
+	pub Nominating get(nominating): b"sta:nominating" => map [ T::AccountId => Vec<T::AccountId> ];
 	// Nominators for a particular account.
 	pub NominatorsFor get(nominators_for): b"sta:nominators_for" => default map [ T::AccountId => Vec<T::AccountId> ];
-	// Nominators for a particular account that is in action right now.
-	pub CurrentNominatorsFor get(current_nominators_for): b"sta:current_nominators_for" => default map [ T::AccountId => Vec<T::AccountId> ];
+	// The stake backing each validator that is in action right now.
+	pub Stakers get(stakers): b"sta:stakers" => default map [ T::AccountId => Exposure<T::AccountId, T::Balance> ];
+	// The preferences of each validator, by controller.
+	pub ValidatorPreferences get(validator_preferences): b"sta:validator_preferences" => default map [ T::AccountId => ValidatorPrefs ];
+	// Where the rewards of each staker are paid, by controller.
+	pub Payee get(payee): b"sta:payee" => default map [ T::AccountId => RewardDestination ];
+	// The number of consecutive sessions each validator has been reported offline in.
+	pub SlashCount get(slash_count): b"sta:slash_count" => default map [ T::AccountId => u32 ];
+	// The validators reported offline during the current session.
+	pub OfflineThisSession get(offline_this_session): b"sta:offline_this_session" => default Vec<T::AccountId>;
 	// The next value of sessions per era.
 	pub NextSessionsPerEra get(next_sessions_per_era): b"sta:nse" => T::BlockNumber;
 	// The session index at which the era length last changed.
@@ -190,7 +488,7 @@ decl_storage! {
 	// This is the only balance that matters in terms of most operations on tokens. It is
 	// alone used to determine the balance when in the contract execution environment. When this
 	// balance falls below the value of `ExistentialDeposit`, then the "current account" is
-	// deleted: specifically, `Bondage` and `FreeBalance`. Furthermore, `OnAccountKill` callback
+	// deleted: specifically, `FreeBalance` and any bond it has. Furthermore, `OnAccountKill` callback
 	// is invoked, giving a chance to external modules to cleanup data associated with
 	// the deleted account.
 	//
@@ -203,7 +501,7 @@ decl_storage! {
 	//
 	// This balance is a "reserve" balance that other subsystems use in order to set aside tokens
 	// that are still "owned" by the account holder, but which are unspendable. This is different
-	// and wholly unrelated to the `Bondage` system used for staking.
+	// and wholly unrelated to the `Ledger` system used for staking.
 	//
 	// When this balance falls below the value of `ExistentialDeposit`, then this "reserve account"
 	// is deleted: specifically, `ReservedBalance`.
@@ -212,8 +510,13 @@ decl_storage! {
 	// collapsed to zero if it ever becomes less than `ExistentialDeposit`.
 	pub ReservedBalance get(reserved_balance): b"sta:lbo:" => default map [ T::AccountId => T::Balance ];
 
-	// The block at which the `who`'s funds become entirely liquid.
-	pub Bondage get(bondage): b"sta:bon:" => default map [ T::AccountId => T::BlockNumber ];
+	// Map from all bonded stash accounts to their controller.
+	pub Bonded get(bonded): b"sta:bonded" => map [ T::AccountId => T::AccountId ];
+	// Map from all controllers to the ledger of the stash they control.
+	pub Ledger get(ledger): b"sta:ledger" => map [ T::AccountId => StakingLedger<T::AccountId, T::Balance, T::BlockNumber> ];
+
+	// Locks which other modules placed on the free balance of an account.
+	pub Locks get(locks): b"sta:locks" => default map [ T::AccountId => Vec<BalanceLock<T::Balance, T::BlockNumber>> ];
 }
 
 enum NewAccountOutcome {
@@ -253,20 +556,32 @@ impl<T: Trait> Module<T> {
 	/// Same result as `reserve(who, value)` (but without the side-effects) assuming there
 	/// are no balance changes in the meantime.
 	pub fn can_reserve(who: &T::AccountId, value: T::Balance) -> bool {
-		if let LockStatus::Liquid = Self::unlock_block(who) {
-			Self::free_balance(who) >= value
-		} else {
-			false
-		}
+		Self::free_balance(who).checked_sub(&value).map_or(false, |b| b >= Self::locked_balance(who))
 	}
 
-	/// The block at which the `who`'s funds become entirely liquid.
-	pub fn unlock_block(who: &T::AccountId) -> LockStatus<T::BlockNumber> {
-		match Self::bondage(who) {
-			i if i == T::BlockNumber::max_value() => LockStatus::Staked,
-			i if i <= <system::Module<T>>::block_number() => LockStatus::Liquid,
-			i => LockStatus::LockedUntil(i),
-		}
+	/// The part of `who`'s free balance that is bonded or locked and cannot be moved.
+	///
+	/// Bonds and locks overlap: the largest of them is what is locked.
+	pub fn locked_balance(who: &T::AccountId) -> T::Balance {
+		let bonded = Self::bonded(who)
+			.and_then(|controller| Self::ledger(controller))
+			.map_or_else(Zero::zero, |ledger| ledger.total);
+		let now = <system::Module<T>>::block_number();
+		Self::locks(who).into_iter()
+			.filter(|lock| lock.until > now)
+			.fold(bonded, |acc, lock| cmp::max(acc, lock.amount))
+	}
+
+	/// The stake that the stash controlled by `controller` puts behind its intention or
+	/// nomination.
+	pub fn stake_of(controller: &T::AccountId) -> T::Balance {
+		Self::ledger(controller).map_or_else(Zero::zero, |ledger| ledger.active)
+	}
+
+	/// The account which holds the funds of `controller`. This is `controller` itself if it
+	/// has no bond.
+	pub fn stash_of(controller: &T::AccountId) -> T::AccountId {
+		Self::ledger(controller).map_or_else(|| controller.clone(), |ledger| ledger.stash)
 	}
 
 	// PUBLIC DISPATCH
@@ -288,7 +603,7 @@ impl<T: Trait> Module<T> {
 		if would_create && value < Self::existential_deposit() {
 			return Err("value too low to create account");
 		}
-		if <Bondage<T>>::get(transactor) > <system::Module<T>>::block_number() {
+		if new_from_balance < Self::locked_balance(transactor) {
 			return Err("bondage too high to send value");
 		}
 
@@ -306,22 +621,23 @@ impl<T: Trait> Module<T> {
 		Ok(())
 	}
 
-	/// Declare the desire to stake for the transactor.
+	/// Declare the desire to stake for the transactor, which must be a controller.
 	///
 	/// Effects will be felt at the beginning of the next era.
 	fn stake(aux: &T::PublicAux) -> Result {
 		let aux = aux.ref_into();
+		ensure!(<Ledger<T>>::exists(aux), "Cannot stake if not a controller.");
 		ensure!(Self::nominating(aux).is_none(), "Cannot stake if already nominating.");
 		let mut intentions = <Intentions<T>>::get();
 		// can't be in the list twice.
 		ensure!(intentions.iter().find(|&t| t == aux).is_none(), "Cannot stake if already staked.");
 		intentions.push(aux.clone());
 		<Intentions<T>>::put(intentions);
-		<Bondage<T>>::insert(aux, T::BlockNumber::max_value());
 		Ok(())
 	}
 
-	/// Retract the desire to stake for the transactor.
+	/// Retract the desire to stake for the transactor, which must be a controller. The bonded
+	/// funds stay bonded until they are explicitly unbonded.
 	///
 	/// Effects will be felt at the beginning of the next era.
 	fn unstake(aux: &T::PublicAux, position: u32) -> Result {
@@ -334,55 +650,141 @@ impl<T: Trait> Module<T> {
 		}
 		intentions.swap_remove(position);
 		<Intentions<T>>::put(intentions);
-		<Bondage<T>>::insert(aux.ref_into(), Self::current_era() + Self::bonding_duration());
 		Ok(())
 	}
 
-	fn nominate(aux: &T::PublicAux, target: RawAddress<T::AccountId, T::AccountIndex>) -> Result {
-		let target = Self::lookup(target)?;
+	/// Nominate the staking controllers `targets` with the stake of the transactor, which must
+	/// be a controller. The election spreads the stake over those of the targets which get
+	/// elected.
+	fn nominate(aux: &T::PublicAux, targets: Vec<RawAddress<T::AccountId, T::AccountIndex>>) -> Result {
 		let aux = aux.ref_into();
 
+		ensure!(<Ledger<T>>::exists(aux), "Cannot nominate if not a controller.");
 		ensure!(Self::nominating(aux).is_none(), "Cannot nominate if already nominating.");
 		ensure!(Self::intentions().iter().find(|&t| t == aux.ref_into()).is_none(), "Cannot nominate if already staked.");
+		ensure!(!targets.is_empty(), "Cannot nominate nobody.");
+		ensure!(targets.len() <= MAX_NOMINATIONS, "Cannot nominate that many targets.");
+
+		let mut nominated = Vec::with_capacity(targets.len());
+		for target in targets {
+			let target = Self::lookup(target)?;
+			if !nominated.contains(&target) {
+				nominated.push(target);
+			}
+		}
 
 		// update nominators_for
-		let mut t = Self::nominators_for(&target);
-		t.push(aux.clone());
-		<NominatorsFor<T>>::insert(&target, t);
+		for target in nominated.iter() {
+			let mut t = Self::nominators_for(target);
+			t.push(aux.clone());
+			<NominatorsFor<T>>::insert(target, t);
+		}
 
 		// update nominating
-		<Nominating<T>>::insert(aux, &target);
-
-		// Update bondage
-		<Bondage<T>>::insert(aux.ref_into(), T::BlockNumber::max_value());
+		<Nominating<T>>::insert(aux, nominated);
 
 		Ok(())
 	}
 
-	/// Will panic if called when source isn't currently nominating target.
-	/// Updates Nominating, NominatorsFor and NominationBalance.
-	fn unnominate(aux: &T::PublicAux, target_index: u32) -> Result {
+	/// Retract all nominations of the transactor.
+	///
+	/// Updates Nominating and NominatorsFor.
+	fn unnominate(aux: &T::PublicAux) -> Result {
 		let source = aux.ref_into();
-		let target_index = target_index as usize;
+		ensure!(Self::nominating(source).is_some(), "Account must be nominating");
+		Self::remove_nominations(source);
+		Ok(())
+	}
 
-		let target = <Nominating<T>>::get(source).ok_or("Account must be nominating")?;
+	/// Bond `value` of the transactor's free balance, making the transactor a stash and
+	/// `controller` the account which stakes or nominates with it.
+	fn bond(aux: &T::PublicAux, controller: RawAddress<T::AccountId, T::AccountIndex>, value: T::Balance) -> Result {
+		let controller = Self::lookup(controller)?;
+		let stash = aux.ref_into();
+
+		ensure!(!<Bonded<T>>::exists(stash), "Stash is already bonded.");
+		ensure!(!<Ledger<T>>::exists(&controller), "Controller is already paired.");
+		ensure!(!value.is_zero(), "Cannot bond nothing.");
+		ensure!(Self::free_balance(stash) >= value, "Stash balance too low to bond.");
+
+		<Bonded<T>>::insert(stash, &controller);
+		<Ledger<T>>::insert(&controller, StakingLedger {
+			stash: stash.clone(),
+			total: value,
+			active: value,
+			unlocking: vec![],
+		});
+		Ok(())
+	}
 
-		let mut t = Self::nominators_for(&target);
-		if t.get(target_index) != Some(source) {
-			return Err("Invalid target index")
-		}
+	/// Bond up to `max_additional` more of the transactor's free balance. The transactor must
+	/// be a bonded stash.
+	fn bond_extra(aux: &T::PublicAux, max_additional: T::Balance) -> Result {
+		let stash = aux.ref_into();
+		let controller = Self::bonded(stash).ok_or("Not a stash.")?;
+		let mut ledger = Self::ledger(&controller).ok_or("Not a controller.")?;
+
+		let free = Self::free_balance(stash);
+		ensure!(free > ledger.total, "Nothing left to bond.");
+		let extra = cmp::min(free - ledger.total, max_additional);
+		ledger.total = ledger.total + extra;
+		ledger.active = ledger.active + extra;
+		<Ledger<T>>::insert(&controller, ledger);
+		Ok(())
+	}
 
-		// Ok - all valid.
+	/// Schedule up to `value` of the stake of the transactor, which must be a controller, to
+	/// be unlocked. The funds can be withdrawn after `BondingDuration` eras.
+	///
+	/// The stake counted for intentions and nominations drops immediately.
+	fn unbond(aux: &T::PublicAux, value: T::Balance) -> Result {
+		let controller = aux.ref_into();
+		let mut ledger = Self::ledger(controller).ok_or("Not a controller.")?;
+		ensure!(ledger.unlocking.len() < MAX_UNLOCKING_CHUNKS, "Too many unlocking chunks.");
+
+		let value = cmp::min(value, ledger.active);
+		ensure!(!value.is_zero(), "Nothing to unbond.");
+		ledger.active = ledger.active - value;
+		ledger.unlocking.push(UnlockChunk {
+			value,
+			era: Self::current_era() + Self::bonding_duration(),
+		});
+		<Ledger<T>>::insert(controller, ledger);
+		Ok(())
+	}
 
-		// update nominators_for
-		t.swap_remove(target_index);
-		<NominatorsFor<T>>::insert(&target, t);
+	/// Unlock the matured unbonding chunks of the transactor, which must be a controller.
+	///
+	/// Once nothing is left bonded, the bond between the stash and the controller is removed.
+	fn withdraw_unbonded(aux: &T::PublicAux) -> Result {
+		let controller = aux.ref_into();
+		let mut ledger = Self::ledger(controller).ok_or("Not a controller.")?;
+		ledger.consolidate_unlocked(Self::current_era());
+
+		if ledger.total.is_zero() && !Self::is_staking(controller) {
+			<Bonded<T>>::remove(&ledger.stash);
+			Self::remove_controller(controller);
+		} else {
+			<Ledger<T>>::insert(controller, ledger);
+		}
+		Ok(())
+	}
 
-		// update nominating
-		<Nominating<T>>::remove(source);
+	/// Set the preferences of the transactor, which must be a controller, for when it
+	/// validates.
+	fn set_preferences(aux: &T::PublicAux, prefs: ValidatorPrefs) -> Result {
+		let controller = aux.ref_into();
+		ensure!(<Ledger<T>>::exists(controller), "Not a controller.");
+		ensure!(prefs.commission <= COMMISSION_DENOMINATOR, "Commission cannot exceed the whole reward.");
+		<ValidatorPreferences<T>>::insert(controller, prefs);
+		Ok(())
+	}
 
-		// update bondage
-		<Bondage<T>>::insert(aux.ref_into(), Self::current_era() + Self::bonding_duration());
+	/// Set where the rewards of the transactor, which must be a controller, are paid.
+	fn set_payee(aux: &T::PublicAux, payee: RewardDestination) -> Result {
+		let controller = aux.ref_into();
+		ensure!(<Ledger<T>>::exists(controller), "Not a controller.");
+		<Payee<T>>::insert(controller, payee);
 		Ok(())
 	}
 
@@ -465,8 +867,8 @@ impl<T: Trait> Module<T> {
 		// Free balance can never be less than ED. If that happens, it gets reduced to zero
 		// and the account information relevant to this subsystem is deleted (i.e. the
 		// account is reaped).
-		// NOTE: This is orthogonal to the `Bondage` value that an account has, a high
-		// value of which makes even the `free_balance` unspendable.
+		// NOTE: This is orthogonal to the bonded part of the `free_balance`, which is
+		// unspendable.
 		// TODO: enforce this for the other balance-altering functions.
 		if balance < ed {
 			Self::on_free_too_low(who);
@@ -522,7 +924,7 @@ impl<T: Trait> Module<T> {
 		if b < value {
 			return Err("not enough free funds")
 		}
-		if Self::unlock_block(who) != LockStatus::Liquid {
+		if b - value < Self::locked_balance(who) {
 			return Err("free funds are still bonded")
 		}
 		Self::set_reserved_balance(who, Self::reserved_balance(who) + value);
@@ -592,6 +994,14 @@ impl<T: Trait> Module<T> {
 	fn new_session(normal_rotation: bool, actual_elapsed: T::Moment) {
 		let session_index = <session::Module<T>>::current_index();
 
+		// validators which went through the session without being reported offline start afresh.
+		let offline = <OfflineThisSession<T>>::take();
+		for v in <session::Module<T>>::validators().iter() {
+			if !offline.contains(v) {
+				<SlashCount<T>>::remove(v);
+			}
+		}
+
 		if normal_rotation {
 			// reward
 			let ideal_elapsed = <session::Module<T>>::ideal_session_duration();
@@ -599,27 +1009,33 @@ impl<T: Trait> Module<T> {
 			let reward = Self::session_reward() * T::Balance::sa(per65536) / T::Balance::sa(65536u64);
 			// apply good session reward
 			for v in <session::Module<T>>::validators().iter() {
-				let noms = Self::current_nominators_for(v);
-				let total = noms.iter().map(Self::voting_balance).fold(Self::voting_balance(v), |acc, x| acc + x);
-				if !total.is_zero() {
-					let safe_mul_rational = |b| b * reward / total;// TODO: avoid overflow
-					for n in noms.iter() {
-						let _ = Self::reward(n, safe_mul_rational(Self::voting_balance(n)));
+				let exposure = Self::stakers(v);
+				if exposure.total.is_zero() {
+					// nobody was backing it at the last election.
+					Self::pay_reward(v, reward);
+				} else {
+					// the validator takes its commission off the top.
+					let commission = T::Balance::sa(Self::validator_preferences(v).commission as u64);
+					let commission = reward * commission / T::Balance::sa(COMMISSION_DENOMINATOR as u64);
+					let shared = reward - commission;
+					let safe_mul_rational = |b| b * shared / exposure.total;// TODO: avoid overflow
+					for n in exposure.others.iter() {
+						Self::pay_reward(&n.who, safe_mul_rational(n.value));
 					}
-					let _ = Self::reward(v, safe_mul_rational(Self::voting_balance(v)));
+					Self::pay_reward(v, commission + safe_mul_rational(exposure.own));
 				}
 			}
 		} else {
 			// slash
 			let early_era_slash = Self::early_era_slash();
 			for v in <session::Module<T>>::validators().iter() {
-				if let Some(rem) = Self::slash(v, early_era_slash) {
-					let noms = Self::current_nominators_for(v);
-					let total = noms.iter().map(Self::voting_balance).fold(T::Balance::zero(), |acc, x| acc + x);
+				if let Some(rem) = Self::slash_stake(v, early_era_slash) {
+					let exposure = Self::stakers(v);
+					let total = exposure.total - exposure.own;
 					if !total.is_zero() {
 						let safe_mul_rational = |b| b * rem / total;// TODO: avoid overflow
-						for n in noms.iter() {
-							let _ = Self::slash(n, safe_mul_rational(Self::voting_balance(n)));	// best effort - not much that can be done on fail.
+						for n in exposure.others.iter() {
+							let _ = Self::slash_stake(&n.who, safe_mul_rational(n.value));	// best effort - not much that can be done on fail.
 						}
 					}
 				}
@@ -633,9 +1049,160 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
-	/// Balance of a (potential) validator that includes all nominators.
-	fn nomination_balance(who: &T::AccountId) -> T::Balance {
-		Self::nominators_for(who).iter().map(Self::voting_balance).fold(Zero::zero(), |acc, x| acc + x)
+	/// A validator has been proven to misbehave. Slash it and its current nominators in
+	/// proportion to their stake, reward the reporter and remove the offender from the
+	/// intentions.
+	fn on_misbehavior_reported(offender: &T::SessionKey, reporter: &T::AccountId) {
+		let offender = match Self::account_for_session_key(offender) {
+			Some(offender) => offender,
+			None => return,
+		};
+
+		let slashed = Self::slash_validator(&offender, Self::misbehavior_slash());
+		// the offender doesn't get to recover part of its slash by reporting itself.
+		if reporter != &offender && reporter != &Self::stash_of(&offender) {
+			let _ = Self::reward(reporter, slashed / T::Balance::sa(MISBEHAVIOR_REPORTER_SHARE));
+		}
+		Self::chill(&offender);
+	}
+
+	/// The validator at `validator_index` in the current set was reported offline.
+	///
+	/// Only the first report of each session counts. Once a validator has been reported in more
+	/// consecutive sessions than `OfflineSlashGrace` allows it gets slashed, twice as much for
+	/// each further session, and once it has been reported more often than its
+	/// `unstake_threshold` it is removed from the intentions.
+	fn on_offline_validator_reported(validator_index: usize) {
+		let v = match <session::Module<T>>::validators().get(validator_index) {
+			Some(v) => v.clone(),
+			None => return,
+		};
+
+		let mut offline = Self::offline_this_session();
+		if offline.contains(&v) {
+			return;
+		}
+		offline.push(v.clone());
+		<OfflineThisSession<T>>::put(offline);
+
+		let count = Self::slash_count(&v) + 1;
+		<SlashCount<T>>::insert(&v, count);
+
+		let grace = Self::offline_slash_grace();
+		if count > grace {
+			let exponent = cmp::min(count - grace - 1, MAX_OFFLINE_SLASH_EXPONENT);
+			// never slash more than is at stake, even if the doubling overflows.
+			let exposure = Self::stakers(&v).total;
+			let max_slash = if exposure.is_zero() { Self::voting_balance(&v) } else { exposure };
+			let slash = Self::offline_slash()
+				.checked_mul(&T::Balance::sa(1u64 << exponent))
+				.map_or(max_slash, |slash| cmp::min(slash, max_slash));
+			Self::slash_validator(&v, slash);
+		}
+
+		if count > Self::validator_preferences(&v).unstake_threshold {
+			Self::chill(&v);
+		}
+	}
+
+	/// Slash `value` from the validator controlled by `v` and its current nominators, in
+	/// proportion to their exposure. Returns the amount which could actually be slashed.
+	fn slash_validator(v: &T::AccountId, value: T::Balance) -> T::Balance {
+		let exposure = Self::stakers(v);
+		let slashes = if exposure.total.is_zero() {
+			vec![(v.clone(), value)]
+		} else {
+			let safe_mul_rational = |b| b * value / exposure.total;// TODO: avoid overflow
+			exposure.others.iter()
+				.map(|n| (n.who.clone(), safe_mul_rational(n.value)))
+				.chain(Some((v.clone(), safe_mul_rational(exposure.own))))
+				.collect::<Vec<_>>()
+		};
+		slashes.into_iter().fold(T::Balance::zero(), |acc, (who, part)| {
+			acc + part - Self::slash_stake(&who, part).unwrap_or_else(Zero::zero)
+		})
+	}
+
+	/// Remove `controller` from the intentions, so that it is not elected in the next era.
+	fn chill(controller: &T::AccountId) {
+		let mut intentions = <Intentions<T>>::get();
+		if let Some(position) = intentions.iter().position(|t| t == controller) {
+			intentions.swap_remove(position);
+			<Intentions<T>>::put(intentions);
+		}
+	}
+
+	/// Retract all nominations of `controller`, updating `Nominating` and `NominatorsFor`.
+	fn remove_nominations(controller: &T::AccountId) {
+		let targets = match <Nominating<T>>::take(controller) {
+			Some(targets) => targets,
+			None => return,
+		};
+
+		for target in targets.iter() {
+			let mut t = Self::nominators_for(target);
+			if let Some(position) = t.iter().position(|n| n == controller) {
+				t.swap_remove(position);
+			}
+			if t.is_empty() {
+				<NominatorsFor<T>>::remove(target);
+			} else {
+				<NominatorsFor<T>>::insert(target, t);
+			}
+		}
+	}
+
+	/// Whether `controller` has the intention to stake or is nominating.
+	fn is_staking(controller: &T::AccountId) -> bool {
+		Self::nominating(controller).is_some() || Self::intentions().iter().any(|v| v == controller)
+	}
+
+	/// Pay a reward to the staker controlled by `controller`, as set by its `Payee`.
+	///
+	/// Rewards for the controller fall back to the stash if the controller has no account.
+	fn pay_reward(controller: &T::AccountId, value: T::Balance) {
+		let payee = Self::payee(controller);
+		if payee == RewardDestination::Controller && Self::reward(controller, value).is_ok() {
+			return;
+		}
+		match Self::ledger(controller) {
+			Some(mut ledger) => if Self::reward(&ledger.stash, value).is_ok() && payee == RewardDestination::Staked {
+				ledger.total = ledger.total + value;
+				ledger.active = ledger.active + value;
+				<Ledger<T>>::insert(controller, ledger);
+			},
+			None => { let _ = Self::reward(controller, value); }
+		}
+	}
+
+	/// Slash the stash controlled by `controller`, reducing its bond to what is left of its
+	/// free balance.
+	///
+	/// Returns the part of `value` that could not be slashed, as `slash`.
+	fn slash_stake(controller: &T::AccountId, value: T::Balance) -> Option<T::Balance> {
+		match Self::ledger(controller) {
+			Some(mut ledger) => {
+				let remaining = Self::slash(&ledger.stash, value);
+				if <Ledger<T>>::exists(controller) {
+					ledger.shrink_to(Self::free_balance(&ledger.stash));
+					<Ledger<T>>::insert(controller, ledger);
+				}
+				remaining
+			}
+			None => Self::slash(controller, value),
+		}
+	}
+
+	/// The account controlling the given session key, if it's a current validator or has the
+	/// intention to stake.
+	fn account_for_session_key(key: &T::SessionKey) -> Option<T::AccountId> {
+		let authorities = <consensus::Module<T>>::authorities();
+		<session::Module<T>>::validators().into_iter()
+			.zip(authorities.into_iter())
+			.find(|&(_, ref k)| k == key)
+			.map(|(v, _)| v)
+			.or_else(|| Self::intentions().into_iter()
+				.find(|v| T::ConvertAccountIdToSessionKey::convert(v.clone()) == *key))
 	}
 
 	/// The era has changed - enact new staking set.
@@ -654,34 +1221,58 @@ impl<T: Trait> Module<T> {
 			}
 		}
 
-		// evaluate desired staking amounts and nominations and optimise to find the best
-		// combination of validators, then use session::internal::set_validators().
-		// for now, this just orders would-be stakers by their balances and chooses the top-most
-		// <ValidatorCount<T>>::get() of them.
-		// TODO: this is not sound. this should be moved to an off-chain solution mechanism.
-		let mut intentions = <Intentions<T>>::get()
-			.into_iter()
-			.map(|v| (Self::voting_balance(&v) + Self::nomination_balance(&v), v))
+		// elect <ValidatorCount<T>>::get() of the intentions, spreading the stake of their
+		// nominators so as to maximise the backing of the least backed validator.
+		// TODO: this should be moved to an off-chain solution mechanism.
+		let intentions = <Intentions<T>>::get();
+		// stakes beyond what a vote can carry saturate rather than wrap around.
+		let max_votes = <T::Balance as As<u64>>::sa(u64::max_value());
+		let to_votes = |b: T::Balance| <T::Balance as As<u64>>::as_(cmp::min(b, max_votes));
+		let mut voters = intentions.iter()
+			.map(|v| phragmen::Voter { who: v.clone(), stake: to_votes(Self::stake_of(v)), targets: vec![v.clone()] })
 			.collect::<Vec<_>>();
-		intentions.sort_unstable_by(|&(ref b1, _), &(ref b2, _)| b2.cmp(&b1));
-
-		<StakeThreshold<T>>::put(
-			if intentions.len() > 0 {
-				let i = (<ValidatorCount<T>>::get() as usize).min(intentions.len() - 1);
-				intentions[i].0.clone()
-			} else { Zero::zero() }
-		);
-		let vals = &intentions.into_iter()
-				.map(|(_, v)| v)
-				.take(<ValidatorCount<T>>::get() as usize)
-				.collect::<Vec<_>>();
+		let mut nominators: Vec<T::AccountId> = Vec::new();
+		for n in intentions.iter().flat_map(|v| Self::nominators_for(v)) {
+			if !nominators.contains(&n) {
+				nominators.push(n);
+			}
+		}
+		voters.extend(nominators.into_iter().map(|n| phragmen::Voter {
+			stake: to_votes(Self::stake_of(&n)),
+			targets: Self::nominating(&n).unwrap_or_default(),
+			who: n,
+		}));
+
+		let elected = phragmen::elect(&intentions, voters, <ValidatorCount<T>>::get() as usize);
+
+		// keep the current validators rather than leave the chain with too few of them.
+		if elected.is_empty() || elected.len() < Self::minimum_validator_count() as usize {
+			return;
+		}
+
 		for v in <session::Module<T>>::validators().iter() {
-			<CurrentNominatorsFor<T>>::remove(v);
+			<Stakers<T>>::remove(v);
 		}
-		for v in vals.iter() {
-			<CurrentNominatorsFor<T>>::insert(v, Self::nominators_for(v));
+		let mut threshold = None;
+		let mut vals = Vec::with_capacity(elected.len());
+		for candidate in elected {
+			let mut exposure = Exposure::default();
+			for (who, value) in candidate.backing {
+				let value = T::Balance::sa(value);
+				exposure.total = exposure.total + value;
+				if who == candidate.who {
+					exposure.own = exposure.own + value;
+				} else {
+					exposure.others.push(IndividualExposure { who, value });
+				}
+			}
+			threshold = Some(threshold.map_or(exposure.total, |t| cmp::min(t, exposure.total)));
+			<Stakers<T>>::insert(&candidate.who, exposure);
+			vals.push(candidate.who);
 		}
-		<session::Module<T>>::set_validators(vals);
+
+		<StakeThreshold<T>>::put(threshold.unwrap_or_else(Zero::zero));
+		<session::Module<T>>::set_validators(&vals);
 	}
 
 	fn enum_set_size() -> T::AccountIndex {
@@ -772,10 +1363,59 @@ impl<T: Trait> Module<T> {
 		ret
 	}
 
+	/// Lock `amount` of the free balance of `who` until the block `until`, replacing any lock
+	/// with the same `id`.
+	pub fn set_lock(id: LockIdentifier, who: &T::AccountId, amount: T::Balance, until: T::BlockNumber) {
+		Self::update_locks(id, who, |_| Some(BalanceLock { id, amount, until }));
+	}
+
+	/// Lock at least `amount` of the free balance of `who` until at least the block `until`,
+	/// extending any lock with the same `id`.
+	pub fn extend_lock(id: LockIdentifier, who: &T::AccountId, amount: T::Balance, until: T::BlockNumber) {
+		Self::update_locks(id, who, |existing| Some(match existing {
+			Some(lock) => BalanceLock {
+				id,
+				amount: cmp::max(lock.amount, amount),
+				until: cmp::max(lock.until, until),
+			},
+			None => BalanceLock { id, amount, until },
+		}));
+	}
+
+	/// Remove the lock with the given `id` from the free balance of `who`.
+	pub fn remove_lock(id: LockIdentifier, who: &T::AccountId) {
+		Self::update_locks(id, who, |_| None);
+	}
+
+	/// Replace the lock with the given `id` by what `f` makes of it, pruning expired locks.
+	fn update_locks<F>(id: LockIdentifier, who: &T::AccountId, f: F) where
+		F: FnOnce(Option<BalanceLock<T::Balance, T::BlockNumber>>) -> Option<BalanceLock<T::Balance, T::BlockNumber>>
+	{
+		let now = <system::Module<T>>::block_number();
+		let mut existing = None;
+		let mut locks = Vec::new();
+		for lock in Self::locks(who) {
+			if lock.id == id {
+				existing = Some(lock);
+			} else if lock.until > now {
+				locks.push(lock);
+			}
+		}
+		locks.extend(f(existing));
+		if locks.is_empty() {
+			<Locks<T>>::remove(who);
+		} else {
+			<Locks<T>>::insert(who, locks);
+		}
+	}
+
 	/// Kill an account's free portion.
 	fn on_free_too_low(who: &T::AccountId) {
 		<FreeBalance<T>>::remove(who);
-		<Bondage<T>>::remove(who);
+		<Locks<T>>::remove(who);
+		if let Some(controller) = <Bonded<T>>::take(who) {
+			Self::remove_controller(&controller);
+		}
 		T::OnAccountKill::on_account_kill(who);
 
 		if Self::reserved_balance(who).is_zero() {
@@ -783,6 +1423,16 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Remove the ledger of `controller` along with its staking settings, intention and
+	/// nominations.
+	fn remove_controller(controller: &T::AccountId) {
+		Self::chill(controller);
+		Self::remove_nominations(controller);
+		<Ledger<T>>::remove(controller);
+		<ValidatorPreferences<T>>::remove(controller);
+		<Payee<T>>::remove(controller);
+	}
+
 	/// Kill an account's reserved portion.
 	fn on_reserved_too_low(who: &T::AccountId) {
 		<ReservedBalance<T>>::remove(who);
@@ -797,12 +1447,26 @@ impl<T: Trait> Executable for Module<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Module<T> {}
+
 impl<T: Trait> OnSessionChange<T::Moment> for Module<T> {
 	fn on_session_change(normal_rotation: bool, elapsed: T::Moment) {
 		Self::new_session(normal_rotation, elapsed);
 	}
 }
 
+impl<T: Trait> OnMisbehavior<T::SessionKey, T::AccountId> for Module<T> {
+	fn on_misbehavior(offender: &T::SessionKey, reporter: &T::AccountId) {
+		Self::on_misbehavior_reported(offender, reporter);
+	}
+}
+
+impl<T: Trait> OnOfflineValidator for Module<T> {
+	fn on_offline_validator(validator_index: usize) {
+		Self::on_offline_validator_reported(validator_index);
+	}
+}
+
 impl<T: Trait> AuxLookup for Module<T> {
 	type Source = address::Address<T::AccountId, T::AccountIndex>;
 	type Target = T::AccountId;
@@ -821,6 +1485,9 @@ impl<T: Trait> MakePayment<T::AccountId> for Module<T> {
 		if b < transaction_fee + Self::existential_deposit() {
 			return Err("not enough funds for transaction fee");
 		}
+		if b - transaction_fee < Self::locked_balance(transactor) {
+			return Err("transaction fee would touch bonded or locked funds");
+		}
 		<FreeBalance<T>>::insert(transactor, b - transaction_fee);
 		Ok(())
 	}
@@ -32,8 +32,11 @@ impl HasPublicAux for Test {
 	type PublicAux = u64;
 }
 impl consensus::Trait for Test {
+	const NOTE_OFFLINE_POSITION: u32 = 1;
 	type PublicAux = <Self as HasPublicAux>::PublicAux;
 	type SessionKey = u64;
+	type OnMisbehavior = Staking;
+	type OnOfflineValidator = Staking;
 }
 impl system::Trait for Test {
 	type Index = u64;
@@ -87,7 +90,9 @@ pub fn new_test_ext(ext_deposit: u64, session_length: u64, sessions_per_era: u64
 			vec![(10, balance_factor), (20, balance_factor)]
 		},
 		intentions: vec![],
+		bonded: vec![],
 		validator_count: 2,
+		minimum_validator_count: 0,
 		bonding_duration: 3,
 		transaction_base_fee: 0,
 		transaction_byte_fee: 0,
@@ -97,6 +102,10 @@ pub fn new_test_ext(ext_deposit: u64, session_length: u64, sessions_per_era: u64
 		reclaim_rebate: 0,
 		session_reward: reward,
 		early_era_slash: if monied { 20 } else { 0 },
+		misbehavior_slash: if monied { 20 } else { 0 },
+		offline_slash: if monied { 20 } else { 0 },
+		offline_slash_grace: 0,
+		report_after_missed: 0,
 	}.build_storage().unwrap());
 	t.extend(timestamp::GenesisConfig::<Test>{
 		period: 5
@@ -0,0 +1,157 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Sequential Phragmén election.
+//!
+//! Candidates are elected one at a time. Each round elects the candidate whose backers would
+//! end up with the lowest load, which spreads the stake of the voters so that the least
+//! backed elected candidate is backed as much as possible.
+//!
+//! All arithmetic is done in fixed point: voter stakes are first normalised to fractions of
+//! the total stake.
+
+use rstd::prelude::*;
+
+/// Number of fractional bits of the fixed point numbers.
+const FRACTION_BITS: u32 = 32;
+
+/// A voter in the election.
+pub struct Voter<AccountId> {
+	/// The account of the voter.
+	pub who: AccountId,
+	/// The stake of the voter.
+	pub stake: u64,
+	/// The candidates the voter approves of.
+	pub targets: Vec<AccountId>,
+}
+
+/// An elected candidate along with the stake backing it, per voter.
+pub struct Elected<AccountId> {
+	/// The account of the candidate.
+	pub who: AccountId,
+	/// The voters backing the candidate and how much of their stake backs it.
+	pub backing: Vec<(AccountId, u64)>,
+}
+
+struct VoterState {
+	stake: u64,
+	weight: u128,
+	load: u128,
+	// elected candidates approved by the voter and the load they put on it.
+	edges: Vec<(usize, u128)>,
+}
+
+struct CandidateState {
+	approval: u128,
+	voters: Vec<usize>,
+	elected: bool,
+}
+
+/// Elect up to `count` of the `candidates`, in order of election.
+///
+/// Candidates nobody approves of are never elected; neither are targets of voters which are
+/// not in `candidates`.
+pub fn elect<AccountId: Clone + PartialEq>(
+	candidates: &[AccountId],
+	voters: Vec<Voter<AccountId>>,
+	count: usize,
+) -> Vec<Elected<AccountId>> {
+	let total_stake = voters.iter().fold(0u128, |acc, v| acc + v.stake as u128);
+	if total_stake == 0 {
+		return Vec::new();
+	}
+
+	let mut candidate_states: Vec<_> = candidates.iter().map(|_| CandidateState {
+		approval: 0,
+		voters: Vec::new(),
+		elected: false,
+	}).collect();
+	let mut voter_states = Vec::with_capacity(voters.len());
+	let mut voter_ids = Vec::with_capacity(voters.len());
+
+	for voter in voters {
+		let weight = ((voter.stake as u128) << FRACTION_BITS) / total_stake;
+		let index = voter_states.len();
+		let mut approved = Vec::new();
+		for target in voter.targets.iter() {
+			if let Some(c) = candidates.iter().position(|c| c == target) {
+				if !approved.contains(&c) {
+					approved.push(c);
+					candidate_states[c].approval += weight;
+					candidate_states[c].voters.push(index);
+				}
+			}
+		}
+		voter_states.push(VoterState { stake: voter.stake, weight, load: 0, edges: Vec::new() });
+		voter_ids.push(voter.who);
+	}
+
+	let mut elected = Vec::new();
+	for _ in 0..count {
+		let mut best: Option<(usize, u128)> = None;
+		for (i, candidate) in candidate_states.iter().enumerate() {
+			if candidate.elected || candidate.approval == 0 {
+				continue;
+			}
+			let numerator = candidate.voters.iter()
+				.map(|&v| voter_states[v].weight * voter_states[v].load)
+				.fold(1u128 << (2 * FRACTION_BITS), |acc, x| acc + x);
+			let score = numerator / candidate.approval;
+			if best.map_or(true, |(_, s)| score < s) {
+				best = Some((i, score));
+			}
+		}
+
+		let (winner, score) = match best {
+			Some(best) => best,
+			None => break,
+		};
+		candidate_states[winner].elected = true;
+		for &v in candidate_states[winner].voters.iter() {
+			let voter = &mut voter_states[v];
+			voter.edges.push((winner, score - voter.load));
+			voter.load = score;
+		}
+		elected.push(winner);
+	}
+
+	let mut backing: Vec<Vec<(AccountId, u64)>> = elected.iter().map(|_| Vec::new()).collect();
+	for (voter, who) in voter_states.into_iter().zip(voter_ids.into_iter()) {
+		if voter.load == 0 {
+			continue;
+		}
+		let last = voter.edges.len() - 1;
+		let mut remaining = voter.stake;
+		for (n, (candidate, edge)) in voter.edges.into_iter().enumerate() {
+			// the last edge takes what rounding left over.
+			let value = if n == last {
+				remaining
+			} else {
+				let ratio = (edge << FRACTION_BITS) / voter.load;
+				((voter.stake as u128 * ratio) >> FRACTION_BITS) as u64
+			};
+			remaining -= value;
+			let position = elected.iter().position(|&c| c == candidate)
+				.expect("edges are only created towards elected candidates; qed");
+			backing[position].push((who.clone(), value));
+		}
+	}
+
+	elected.into_iter().zip(backing.into_iter()).map(|(c, backing)| Elected {
+		who: candidates[c].clone(),
+		backing,
+	}).collect()
+}
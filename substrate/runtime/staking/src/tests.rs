@@ -183,6 +183,9 @@ fn staking_should_work() {
 
 		// Block 1: Add three validators. No obvious change.
 		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
 		assert_ok!(Staking::stake(&1));
 		assert_ok!(Staking::stake(&2));
 		assert_ok!(Staking::stake(&4));
@@ -196,10 +199,12 @@ fn staking_should_work() {
 		assert_eq!(Staking::current_era(), 1);
 		assert_eq!(Session::validators(), vec![4, 2]);
 
-		// Block 3: Unstake highest, introduce another staker. No change yet.
+		// Block 3: Unstake and unbond highest, introduce another staker. No change yet.
 		System::set_block_number(3);
+		assert_ok!(Staking::bond(&3, 3.into(), 30));
 		assert_ok!(Staking::stake(&3));
 		assert_ok!(Staking::unstake(&4, Staking::intentions().iter().position(|&x| x == 4).unwrap() as u32));
+		assert_ok!(Staking::unbond(&4, 40));
 		assert_eq!(Staking::current_era(), 1);
 		Session::check_rotate_session();
 
@@ -209,29 +214,96 @@ fn staking_should_work() {
 		assert_eq!(Staking::current_era(), 2);
 		assert_eq!(Session::validators(), vec![3, 2]);
 
-		// Block 5: Transfer stake from highest to lowest. No change yet.
+		// Block 5: Unbonded funds are still locked.
 		System::set_block_number(5);
-		assert_ok!(Staking::transfer(&4, 1.into(), 40));
+		assert_ok!(Staking::withdraw_unbonded(&4));
+		assert_eq!(Staking::locked_balance(&4), 40);
+		assert_noop!(Staking::transfer(&4, 1.into(), 40), "bondage too high to send value");
 		Session::check_rotate_session();
 
-		// Block 6: Lowest now validator.
+		// Block 6-7: Nothing changes.
 		System::set_block_number(6);
 		Session::check_rotate_session();
+		System::set_block_number(7);
+		Session::check_rotate_session();
+		assert_eq!(Staking::current_era(), 3);
+		assert_eq!(Session::validators(), vec![3, 2]);
+
+		// Block 8: Bonding duration is over. Transfer stake from highest to lowest.
+		System::set_block_number(8);
+		Session::check_rotate_session();
+		assert_eq!(Staking::current_era(), 4);
+		assert_ok!(Staking::withdraw_unbonded(&4));
+		assert_eq!(Staking::bonded(&4), None);
+		assert_ok!(Staking::transfer(&4, 1.into(), 40));
+		assert_ok!(Staking::bond_extra(&1, 40));
+		assert_eq!(Staking::stake_of(&1), 50);
+
+		// Block 9-10: Lowest now validator.
+		System::set_block_number(9);
+		Session::check_rotate_session();
+		System::set_block_number(10);
+		Session::check_rotate_session();
 		assert_eq!(Session::validators(), vec![1, 3]);
 
-		// Block 7: Unstake three. No change yet.
-		System::set_block_number(7);
+		// Block 11: Unstake three. No change yet.
+		System::set_block_number(11);
 		assert_ok!(Staking::unstake(&3, Staking::intentions().iter().position(|&x| x == 3).unwrap() as u32));
 		Session::check_rotate_session();
 		assert_eq!(Session::validators(), vec![1, 3]);
 
-		// Block 8: Back to one and two.
-		System::set_block_number(8);
+		// Block 12: Back to one and two.
+		System::set_block_number(12);
 		Session::check_rotate_session();
 		assert_eq!(Session::validators(), vec![1, 2]);
 	});
 }
 
+#[test]
+fn bonding_should_work() {
+	with_externalities(&mut new_test_ext(0, 1, 2, 0, true, 0), || {
+		// 1 bonds some of its funds to the controller 10.
+		assert_ok!(Staking::bond(&1, 10.into(), 5));
+		assert_eq!(Staking::bonded(&1), Some(10));
+		assert_eq!(Staking::ledger(&10), Some(StakingLedger { stash: 1, total: 5, active: 5, unlocking: vec![] }));
+		assert_noop!(Staking::bond(&1, 20.into(), 1), "Stash is already bonded.");
+		assert_noop!(Staking::bond(&2, 10.into(), 1), "Controller is already paired.");
+		assert_noop!(Staking::bond(&2, 20.into(), 21), "Stash balance too low to bond.");
+
+		// extra funds can be bonded by the stash, up to its free balance.
+		assert_ok!(Staking::bond_extra(&1, 3));
+		assert_eq!(Staking::stake_of(&10), 8);
+		assert_ok!(Staking::bond_extra(&1, 100));
+		assert_eq!(Staking::stake_of(&10), 10);
+		assert_noop!(Staking::bond_extra(&1, 1), "Nothing left to bond.");
+		assert_noop!(Staking::transfer(&1, 2.into(), 1), "bondage too high to send value");
+
+		// only the controller can stake.
+		assert_noop!(Staking::stake(&1), "Cannot stake if not a controller.");
+		assert_ok!(Staking::stake(&10));
+
+		// unbonded funds stay locked for the bonding duration.
+		assert_ok!(Staking::unbond(&10, 4));
+		assert_eq!(Staking::stake_of(&10), 6);
+		assert_eq!(Staking::locked_balance(&1), 10);
+		assert_ok!(Staking::withdraw_unbonded(&10));
+		assert_eq!(Staking::locked_balance(&1), 10);
+
+		<CurrentEra<Test>>::put(3);
+		assert_ok!(Staking::withdraw_unbonded(&10));
+		assert_eq!(Staking::locked_balance(&1), 6);
+		assert_ok!(Staking::transfer(&1, 2.into(), 4));
+
+		// once everything is withdrawn, the bond is dissolved.
+		assert_ok!(Staking::unstake(&10, 0));
+		assert_ok!(Staking::unbond(&10, 6));
+		<CurrentEra<Test>>::put(6);
+		assert_ok!(Staking::withdraw_unbonded(&10));
+		assert_eq!(Staking::bonded(&1), None);
+		assert_eq!(Staking::ledger(&10), None);
+	});
+}
+
 #[test]
 fn nominating_and_rewards_should_work() {
 	with_externalities(&mut new_test_ext(0, 1, 1, 0, true, 10), || {
@@ -241,10 +313,14 @@ fn nominating_and_rewards_should_work() {
 		assert_eq!(Session::validators(), vec![10, 20]);
 
 		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::bond(&3, 3.into(), 30));
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
 		assert_ok!(Staking::stake(&1));
 		assert_ok!(Staking::stake(&2));
 		assert_ok!(Staking::stake(&3));
-		assert_ok!(Staking::nominate(&4, 1.into()));
+		assert_ok!(Staking::nominate(&4, vec![1.into()]));
 		Session::check_rotate_session();
 		assert_eq!(Staking::current_era(), 1);
 		assert_eq!(Session::validators(), vec![1, 3]);	// 4 + 1, 3
@@ -254,7 +330,7 @@ fn nominating_and_rewards_should_work() {
 		assert_eq!(Staking::voting_balance(&4), 40);
 
 		System::set_block_number(2);
-		assert_ok!(Staking::unnominate(&4, 0));
+		assert_ok!(Staking::unnominate(&4));
 		Session::check_rotate_session();
 		assert_eq!(Staking::current_era(), 2);
 		assert_eq!(Session::validators(), vec![3, 2]);
@@ -266,7 +342,7 @@ fn nominating_and_rewards_should_work() {
 		System::set_block_number(3);
 		assert_ok!(Staking::stake(&4));
 		assert_ok!(Staking::unstake(&3, Staking::intentions().iter().position(|&x| x == 3).unwrap() as u32));
-		assert_ok!(Staking::nominate(&3, 1.into()));
+		assert_ok!(Staking::nominate(&3, vec![1.into()]));
 		Session::check_rotate_session();
 		assert_eq!(Session::validators(), vec![1, 4]);
 		assert_eq!(Staking::voting_balance(&1), 12);
@@ -283,6 +359,97 @@ fn nominating_and_rewards_should_work() {
 	});
 }
 
+#[test]
+fn too_few_elected_validators_should_keep_current_set() {
+	with_externalities(&mut new_test_ext(0, 1, 1, 0, true, 0), || {
+		<Stakers<Test>>::insert(&10, Exposure { total: 1, own: 1, others: vec![] });
+
+		// Block 1: Nobody intends to validate; the genesis validators stay.
+		System::set_block_number(1);
+		Session::check_rotate_session();
+		assert_eq!(Staking::current_era(), 1);
+		assert_eq!(Session::validators(), vec![10, 20]);
+		assert_eq!(Staking::stakers(&10), Exposure { total: 1, own: 1, others: vec![] });
+
+		// Block 2: One intention, but at least two validators are required.
+		System::set_block_number(2);
+		<MinimumValidatorCount<Test>>::put(2);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::stake(&1));
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![10, 20]);
+
+		// Block 3: A second intention lets the election through.
+		System::set_block_number(3);
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::stake(&2));
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![2, 1]);
+		assert_eq!(Staking::stakers(&10), Exposure::default());
+	});
+}
+
+#[test]
+fn nominating_several_targets_should_spread_stake() {
+	with_externalities(&mut new_test_ext(0, 1, 1, 0, true, 0), || {
+		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::bond(&3, 3.into(), 30));
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
+		assert_ok!(Staking::stake(&1));
+		assert_ok!(Staking::stake(&2));
+		assert_ok!(Staking::stake(&3));
+		assert_noop!(Staking::nominate(&4, vec![]), "Cannot nominate nobody.");
+		assert_ok!(Staking::nominate(&4, vec![1.into(), 3.into(), 1.into()]));
+		assert_eq!(Staking::nominating(&4), Some(vec![1, 3]));
+		Session::check_rotate_session();
+
+		// 3 is elected first; what is left of the stake of 4 then lifts 1 above 2.
+		assert_eq!(Session::validators(), vec![3, 1]);
+		assert_eq!(Staking::stakers(&3), Exposure { total: 48, own: 30, others: vec![IndividualExposure { who: 4, value: 18 }] });
+		assert_eq!(Staking::stakers(&1), Exposure { total: 32, own: 10, others: vec![IndividualExposure { who: 4, value: 22 }] });
+		assert_eq!(Staking::stake_threshold(), 32);
+
+		System::set_block_number(2);
+		assert_ok!(Staking::unnominate(&4));
+		assert_eq!(Staking::nominators_for(&1), Vec::<u64>::new());
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![3, 2]);
+		assert_eq!(Staking::stakers(&1), Exposure::default());
+	});
+}
+
+#[test]
+fn reaped_stash_should_stop_staking_and_nominating() {
+	with_externalities(&mut new_test_ext(1, 1, 1, 0, true, 0), || {
+		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 256 * 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 256 * 20));
+		assert_ok!(Staking::bond(&3, 3.into(), 256 * 30));
+		assert_ok!(Staking::bond(&4, 4.into(), 256 * 40));
+		assert_ok!(Staking::stake(&1));
+		assert_ok!(Staking::stake(&2));
+		assert_ok!(Staking::stake(&3));
+		assert_ok!(Staking::nominate(&4, vec![1.into()]));
+
+		// both 1 and 4 lose all of their funds and are reaped.
+		assert_eq!(Staking::slash(&1, 256 * 10), None);
+		assert_eq!(Staking::slash(&4, 256 * 40), None);
+		assert_eq!(Staking::bonded(&1), None);
+		assert_eq!(Staking::bonded(&4), None);
+
+		assert_eq!(Staking::intentions(), vec![3, 2]);
+		assert_eq!(Staking::nominating(&4), None);
+		assert_eq!(Staking::nominators_for(&1), Vec::<u64>::new());
+
+		Session::check_rotate_session();
+		assert_eq!(Staking::current_era(), 1);
+		assert_eq!(Session::validators(), vec![3, 2]);
+		assert_eq!(Staking::stakers(&1), Exposure::default());
+	});
+}
+
 #[test]
 fn nominating_slashes_should_work() {
 	with_externalities(&mut new_test_ext(0, 2, 2, 0, true, 10), || {
@@ -296,10 +463,14 @@ fn nominating_slashes_should_work() {
 
 		Timestamp::set_timestamp(15);
 		System::set_block_number(4);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::bond(&3, 3.into(), 30));
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
 		assert_ok!(Staking::stake(&1));
 		assert_ok!(Staking::stake(&3));
-		assert_ok!(Staking::nominate(&2, 3.into()));
-		assert_ok!(Staking::nominate(&4, 1.into()));
+		assert_ok!(Staking::nominate(&2, vec![3.into()]));
+		assert_ok!(Staking::nominate(&4, vec![1.into()]));
 		Session::check_rotate_session();
 
 		assert_eq!(Staking::current_era(), 1);
@@ -323,16 +494,74 @@ fn nominating_slashes_should_work() {
 	});
 }
 
+#[test]
+fn validator_commission_should_work() {
+	with_externalities(&mut new_test_ext(0, 1, 1, 0, true, 10), || {
+		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
+		assert_ok!(Staking::stake(&1));
+		assert_ok!(Staking::nominate(&4, vec![1.into()]));
+		let prefs = ValidatorPrefs { commission: 500_000, unstake_threshold: 3 };
+		assert_noop!(Staking::set_preferences(&2, prefs.clone()), "Not a controller.");
+		assert_noop!(
+			Staking::set_preferences(&1, ValidatorPrefs { commission: 1_000_001, unstake_threshold: 3 }),
+			"Commission cannot exceed the whole reward."
+		);
+		assert_ok!(Staking::set_preferences(&1, prefs.clone()));
+		assert_eq!(Staking::validator_preferences(&1), prefs);
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![1]);
+
+		// 1 takes half of the reward; the other half is shared in proportion to the stake.
+		System::set_block_number(2);
+		Session::check_rotate_session();
+		assert_eq!(Staking::voting_balance(&1), 16);
+		assert_eq!(Staking::voting_balance(&4), 44);
+		assert_eq!(Staking::stake_of(&1), 16);
+		assert_eq!(Staking::stake_of(&4), 44);
+	});
+}
+
+#[test]
+fn reward_destination_should_work() {
+	with_externalities(&mut new_test_ext(0, 1, 1, 0, true, 10), || {
+		System::set_block_number(1);
+		assert_ok!(Staking::bond(&3, 4.into(), 30));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
+		assert_ok!(Staking::stake(&4));
+		assert_ok!(Staking::stake(&2));
+		assert_eq!(Staking::payee(&4), RewardDestination::Staked);
+		assert_noop!(Staking::set_payee(&3, RewardDestination::Stash), "Not a controller.");
+		assert_ok!(Staking::set_payee(&4, RewardDestination::Controller));
+		assert_ok!(Staking::set_payee(&2, RewardDestination::Stash));
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![4, 2]);
+
+		System::set_block_number(2);
+		Session::check_rotate_session();
+		// the controller is paid, leaving the stash alone.
+		assert_eq!(Staking::voting_balance(&4), 50);
+		assert_eq!(Staking::voting_balance(&3), 30);
+		assert_eq!(Staking::stake_of(&4), 30);
+		// the stash is paid, but the reward is not bonded.
+		assert_eq!(Staking::voting_balance(&2), 30);
+		assert_eq!(Staking::stake_of(&2), 20);
+	});
+}
+
 #[test]
 fn double_staking_should_fail() {
 	with_externalities(&mut new_test_ext(0, 1, 2, 0, true, 0), || {
 		System::set_block_number(1);
+		assert_ok!(Staking::bond(&1, 1.into(), 10));
+		assert_ok!(Staking::bond(&2, 2.into(), 20));
 		assert_ok!(Staking::stake(&1));
 		assert_noop!(Staking::stake(&1), "Cannot stake if already staked.");
-		assert_noop!(Staking::nominate(&1, 1.into()), "Cannot nominate if already staked.");
-		assert_ok!(Staking::nominate(&2, 1.into()));
+		assert_noop!(Staking::nominate(&1, vec![1.into()]), "Cannot nominate if already staked.");
+		assert_ok!(Staking::nominate(&2, vec![1.into()]));
 		assert_noop!(Staking::stake(&2), "Cannot stake if already nominating.");
-		assert_noop!(Staking::nominate(&2, 1.into()), "Cannot nominate if already nominating.");
+		assert_noop!(Staking::nominate(&2, vec![1.into()]), "Cannot nominate if already nominating.");
 	});
 }
 
@@ -432,11 +661,31 @@ fn staking_balance_transfer_works() {
 fn staking_balance_transfer_when_bonded_should_not_work() {
 	with_externalities(&mut new_test_ext(0, 1, 3, 1, false, 0), || {
 		<FreeBalance<Test>>::insert(1, 111);
-		assert_ok!(Staking::stake(&1));
+		assert_ok!(Staking::bond(&1, 1.into(), 111));
 		assert_noop!(Staking::transfer(&1, 2.into(), 69), "bondage too high to send value");
 	});
 }
 
+#[test]
+fn staking_balance_transfer_when_locked_should_not_work() {
+	with_externalities(&mut new_test_ext(0, 1, 3, 1, false, 0), || {
+		<FreeBalance<Test>>::insert(1, 111);
+		Staking::set_lock(*b"testlock", &1, 100, 5);
+		assert_noop!(Staking::transfer(&1, 2.into(), 12), "bondage too high to send value");
+		assert_ok!(Staking::transfer(&1, 2.into(), 11));
+
+		// locks only ever get extended.
+		Staking::extend_lock(*b"testlock", &1, 50, 10);
+		System::set_block_number(9);
+		assert_noop!(Staking::transfer(&1, 2.into(), 1), "bondage too high to send value");
+
+		System::set_block_number(10);
+		assert_ok!(Staking::transfer(&1, 2.into(), 100));
+		Staking::remove_lock(*b"testlock", &1);
+		assert!(Staking::locks(&1).is_empty());
+	});
+}
+
 #[test]
 fn reserving_balance_should_work() {
 	with_externalities(&mut new_test_ext(0, 1, 3, 1, false, 0), || {
@@ -476,9 +725,9 @@ fn deducting_balance_should_work() {
 fn deducting_balance_when_bonded_should_not_work() {
 	with_externalities(&mut new_test_ext(0, 1, 3, 1, false, 0), || {
 		<FreeBalance<Test>>::insert(1, 111);
-		<Bondage<Test>>::insert(1, 2);
-		System::set_block_number(1);
-		assert_eq!(Staking::unlock_block(&1), LockStatus::LockedUntil(2));
+		assert_ok!(Staking::bond(&1, 1.into(), 69));
+		assert_eq!(Staking::locked_balance(&1), 69);
+		assert!(!Staking::can_reserve(&1, 69));
 		assert_noop!(Staking::reserve(&1, 69), "free funds are still bonded");
 	});
 }
@@ -601,3 +850,115 @@ fn transferring_too_high_value_should_not_panic() {
 		assert_eq!(Staking::free_balance(&2), 1);
 	});
 }
+
+#[test]
+fn misbehavior_should_be_slashed_and_rewarded() {
+	with_externalities(&mut new_test_ext(0, 3, 3, 0, true, 0), || {
+		assert_ok!(Staking::bond(&3, 3.into(), 30));
+		assert_ok!(Staking::stake(&3));
+		<Stakers<Test>>::insert(&3, Exposure { total: 40, own: 30, others: vec![IndividualExposure { who: 1, value: 10 }] });
+
+		<Staking as OnMisbehavior<u64, u64>>::on_misbehavior(&3, &2);
+
+		// 20 is slashed in proportion to the stake of 3 (30) and its nominator 1 (10).
+		assert_eq!(Staking::voting_balance(&3), 15);
+		assert_eq!(Staking::stake_of(&3), 15);
+		assert_eq!(Staking::voting_balance(&1), 5);
+		// the reporter gets a tenth of it.
+		assert_eq!(Staking::voting_balance(&2), 22);
+		assert!(Staking::intentions().is_empty());
+	});
+}
+
+#[test]
+fn transaction_fee_should_not_touch_bonded_funds() {
+	with_externalities(&mut new_test_ext(0, 3, 3, 0, true, 0), || {
+		<TransactionBaseFee<Test>>::put(5);
+		assert_ok!(Staking::bond(&3, 4.into(), 25));
+
+		assert_ok!(Staking::make_payment(&3, 0));
+		assert_eq!(Staking::free_balance(&3), 25);
+		assert_noop!(Staking::make_payment(&3, 0), "transaction fee would touch bonded or locked funds");
+	});
+}
+
+#[test]
+fn self_reported_misbehavior_should_not_be_rewarded() {
+	with_externalities(&mut new_test_ext(0, 3, 3, 0, true, 0), || {
+		assert_ok!(Staking::bond(&3, 4.into(), 30));
+		assert_ok!(Staking::stake(&4));
+		<Stakers<Test>>::insert(&4, Exposure { total: 30, own: 30, others: vec![] });
+
+		// reported through its stash.
+		<Staking as OnMisbehavior<u64, u64>>::on_misbehavior(&4, &3);
+
+		assert_eq!(Staking::voting_balance(&3), 10);
+		assert_eq!(Staking::stake_of(&4), 10);
+		assert!(Staking::intentions().is_empty());
+	});
+}
+
+#[test]
+fn offline_should_be_slashed_increasingly_and_chilled() {
+	with_externalities(&mut new_test_ext(0, 3, 3, 0, true, 0), || {
+		<OfflineSlash<Test>>::put(1);
+		<OfflineSlashGrace<Test>>::put(1);
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
+		assert_ok!(Staking::stake(&4));
+		Session::set_validators(&[4]);
+
+		// the first session is within the grace; reports only count once per session.
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		assert_eq!(Staking::slash_count(&4), 1);
+		assert_eq!(Staking::voting_balance(&4), 40);
+
+		System::set_block_number(3);
+		Timestamp::set_timestamp(15);
+		Session::check_rotate_session();
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		assert_eq!(Staking::slash_count(&4), 2);
+		assert_eq!(Staking::voting_balance(&4), 39);
+		assert_eq!(Staking::stake_of(&4), 39);
+
+		System::set_block_number(6);
+		Timestamp::set_timestamp(30);
+		Session::check_rotate_session();
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		assert_eq!(Staking::voting_balance(&4), 37);
+		assert_eq!(Staking::intentions(), vec![4]);
+
+		// past the unstake threshold of 3 the validator is chilled.
+		System::set_block_number(9);
+		Timestamp::set_timestamp(45);
+		Session::check_rotate_session();
+		assert_eq!(Session::validators(), vec![4]);
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		assert_eq!(Staking::slash_count(&4), 4);
+		assert_eq!(Staking::voting_balance(&4), 33);
+		assert!(Staking::intentions().is_empty());
+
+		// a session without reports starts it afresh.
+		System::set_block_number(12);
+		Timestamp::set_timestamp(60);
+		Session::check_rotate_session();
+		assert_eq!(Staking::slash_count(&4), 0);
+	});
+}
+
+#[test]
+fn offline_slash_should_be_capped_at_the_exposure() {
+	with_externalities(&mut new_test_ext(0, 3, 3, 0, true, 0), || {
+		<OfflineSlash<Test>>::put(u64::max_value());
+		assert_ok!(Staking::bond(&4, 4.into(), 40));
+		assert_ok!(Staking::stake(&4));
+		Session::set_validators(&[4]);
+		<Stakers<Test>>::insert(&4, Exposure { total: 40, own: 40, others: vec![] });
+
+		// the doubled slash overflows; the whole exposure is taken instead.
+		<SlashCount<Test>>::insert(&4, 1);
+		<Staking as OnOfflineValidator>::on_offline_validator(0);
+		assert_eq!(Staking::slash_count(&4), 2);
+		assert_eq!(Staking::voting_balance(&4), 0);
+	});
+}
@@ -40,7 +40,7 @@ extern crate substrate_codec as codec;
 
 use runtime_support::{StorageValue, Parameter};
 use runtime_support::dispatch::Result;
-use runtime_primitives::traits::{Executable, MaybeEmpty, SimpleArithmetic, As, Zero};
+use runtime_primitives::traits::{Executable, OffchainWorker, MaybeEmpty, SimpleArithmetic, As, Zero};
 
 pub trait Trait: consensus::Trait where
 	<Self as consensus::Trait>::PublicAux: MaybeEmpty
@@ -106,6 +106,8 @@ impl<T: Trait> Executable for Module<T> {
 	}
 }
 
+impl<T: Trait> OffchainWorker<T::BlockNumber> for Module<T> {}
+
 #[cfg(any(feature = "std", test))]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -160,8 +162,11 @@ mod tests {
 		type Header = Header;
 	}
 	impl consensus::Trait for Test {
+		const NOTE_OFFLINE_POSITION: u32 = 1;
 		type PublicAux = u64;
 		type SessionKey = u64;
+		type OnMisbehavior = ();
+		type OnOfflineValidator = ();
 	}
 	impl Trait for Test {
 		const TIMESTAMP_SET_POSITION: u32 = 0;
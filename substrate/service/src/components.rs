@@ -134,6 +134,27 @@ pub trait ServiceFactory {
 	/// Build network protocol.
 	fn build_network_protocol(config: &FactoryFullConfiguration<Self>)
 		-> Result<Self::NetworkProtocol, error::Error>;
+
+	/// Block import for the full client. Requires BFT justifications by default.
+	fn build_full_block_import(_config: &FactoryFullConfiguration<Self>, client: Arc<FullClient<Self>>)
+		-> Result<Arc<client::BlockImport<Self::Block>>, error::Error>
+	{
+		Ok(client)
+	}
+	/// Block import for the light client. Requires BFT justifications by default.
+	fn build_light_block_import(_config: &FactoryFullConfiguration<Self>, client: Arc<LightClient<Self>>)
+		-> Result<Arc<client::BlockImport<Self::Block>>, error::Error>
+	{
+		Ok(client)
+	}
+
+	/// Inherent extrinsics to include in a block authored on top of `parent` in dev-seal mode,
+	/// or `None` if no block may be authored on `parent` yet. No inherents by default.
+	fn build_dev_seal_inherents(_client: &FullClient<Self>, _parent: &<Self::Block as BlockT>::Header)
+		-> Result<Option<Vec<<Self::Block as BlockT>::Extrinsic>>, error::Error>
+	{
+		Ok(Some(Vec::new()))
+	}
 }
 
 // TODO: move this to substrate-extrinsic-pool
@@ -154,7 +175,7 @@ pub trait Components {
 	/// Client backend.
 	type Backend: 'static + client::backend::Backend<FactoryBlock<Self::Factory>>;
 	/// Client executor.
-	type Executor: 'static + client::CallExecutor<FactoryBlock<Self::Factory>> + Send + Sync;
+	type Executor: 'static + client::CallExecutor<FactoryBlock<Self::Factory>> + Send + Sync + Clone;
 	/// Extrinsic pool type.
 	type ExtrinsicPool: ExtrinsicPool<FactoryBlock<Self::Factory>>;
 
@@ -171,6 +192,15 @@ pub trait Components {
 	/// Create extrinsic pool.
 	fn build_extrinsic_pool(config: ExtrinsicPoolOptions, client: Arc<ComponentClient<Self>>)
 		-> Result<Self::ExtrinsicPool, error::Error>;
+
+	/// Create block import.
+	fn build_block_import(config: &FactoryFullConfiguration<Self::Factory>, client: Arc<ComponentClient<Self>>)
+		-> Result<Arc<client::BlockImport<FactoryBlock<Self::Factory>>>, error::Error>;
+
+	/// Inherent extrinsics for a block authored on top of `parent` in dev-seal mode, or `None`
+	/// if no block may be authored on `parent` yet.
+	fn build_dev_seal_inherents(client: &ComponentClient<Self>, parent: &<FactoryBlock<Self::Factory> as BlockT>::Header)
+		-> Result<Option<Vec<<FactoryBlock<Self::Factory> as BlockT>::Extrinsic>>, error::Error>;
 }
 
 /// A struct that implement `Components` for the full client.
@@ -206,6 +236,18 @@ impl<Factory: ServiceFactory> Components for FullComponents<Factory> {
 	{
 		Factory::build_full_extrinsic_pool(config, client)
 	}
+
+	fn build_block_import(config: &FactoryFullConfiguration<Self::Factory>, client: Arc<ComponentClient<Self>>)
+		-> Result<Arc<client::BlockImport<FactoryBlock<Self::Factory>>>, error::Error>
+	{
+		Factory::build_full_block_import(config, client)
+	}
+
+	fn build_dev_seal_inherents(client: &ComponentClient<Self>, parent: &<FactoryBlock<Self::Factory> as BlockT>::Header)
+		-> Result<Option<Vec<<FactoryBlock<Self::Factory> as BlockT>::Extrinsic>>, error::Error>
+	{
+		Factory::build_dev_seal_inherents(client, parent)
+	}
 }
 
 /// A struct that implement `Components` for the light client.
@@ -248,4 +290,16 @@ impl<Factory: ServiceFactory> Components for LightComponents<Factory> {
 	{
 		Factory::build_light_extrinsic_pool(config, client)
 	}
+
+	fn build_block_import(config: &FactoryFullConfiguration<Self::Factory>, client: Arc<ComponentClient<Self>>)
+		-> Result<Arc<client::BlockImport<FactoryBlock<Self::Factory>>>, error::Error>
+	{
+		Factory::build_light_block_import(config, client)
+	}
+
+	fn build_dev_seal_inherents(_client: &ComponentClient<Self>, _parent: &<FactoryBlock<Self::Factory> as BlockT>::Header)
+		-> Result<Option<Vec<<FactoryBlock<Self::Factory> as BlockT>::Extrinsic>>, error::Error>
+	{
+		Err(error::ErrorKind::Client(client::error::ErrorKind::NotAvailableOnLightClient).into())
+	}
 }
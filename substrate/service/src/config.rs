@@ -17,6 +17,7 @@
 //! Service configuration.
 
 use std::net::SocketAddr;
+use std::str::FromStr;
 use extrinsic_pool;
 use chain_spec::ChainSpec;
 pub use client::ExecutionStrategy;
@@ -27,6 +28,9 @@ use runtime_primitives::BuildStorage;
 use serde::{Serialize, de::DeserializeOwned};
 use target_info::Target;
 
+/// Default fuel limit for Wasm calls made on behalf of RPC requests.
+pub const DEFAULT_RPC_FUEL_LIMIT: u64 = 200_000_000;
+
 /// Service configuration.
 pub struct Configuration<C, G: Serialize + DeserializeOwned + BuildStorage> {
 	/// Implementation name
@@ -61,12 +65,51 @@ pub struct Configuration<C, G: Serialize + DeserializeOwned + BuildStorage> {
 	pub execution_strategy: ExecutionStrategy,
 	/// Maximum number of heap pages to allocate for Wasm execution.
 	pub max_heap_pages: usize,
+	/// Maximum fuel a single Wasm call may use, e.g. during block import. `None` if unlimited.
+	pub wasm_fuel_limit: Option<u64>,
+	/// Maximum fuel a single Wasm call made on behalf of an RPC request may use. `None` if unlimited.
+	pub rpc_fuel_limit: Option<u64>,
+	/// Whether to profile Wasm runtime execution and report it for every imported block.
+	pub profile_runtime: bool,
 	/// RPC over HTTP binding address. `None` if disabled.
 	pub rpc_http: Option<SocketAddr>,
 	/// RPC over Websockets binding address. `None` if disabled.
 	pub rpc_ws: Option<SocketAddr>,
 	/// Telemetry service URL. `None` if disabled.
 	pub telemetry_url: Option<String>,
+	/// Development block authoring mode. `None` if disabled.
+	pub dev_seal: Option<DevSeal>,
+	/// Slot duration in seconds, if blocks are authored in slots instead of through BFT. `None` if disabled.
+	pub aura_slot_duration: Option<u64>,
+	/// Duration in seconds of the rounds in which authorities vote on finality. `None` if disabled.
+	pub finality_round_duration: Option<u64>,
+	/// Whether to run the runtime's offchain worker on every new best block.
+	pub offchain_worker: bool,
+}
+
+/// Development block authoring mode. Blocks are authored locally and imported without
+/// a justification, bypassing consensus entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevSeal {
+	/// Author a block as soon as transactions are pending, once the chain allows a new block.
+	Instant,
+	/// Author a block every given number of milliseconds, even when there are no transactions.
+	Interval(u64),
+}
+
+impl FromStr for DevSeal {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"instant" => Ok(DevSeal::Instant),
+			_ if s.starts_with("interval=") => match s["interval=".len()..].parse() {
+				Ok(0) | Err(_) => Err(format!("Invalid dev-seal interval in '{}'", s)),
+				Ok(ms) => Ok(DevSeal::Interval(ms)),
+			},
+			_ => Err(format!("Unknown dev-seal mode '{}', expected 'instant' or 'interval=N'", s)),
+		}
+	}
 }
 
 impl<C: Default, G: Serialize + DeserializeOwned + BuildStorage> Configuration<C, G> {
@@ -89,9 +132,16 @@ impl<C: Default, G: Serialize + DeserializeOwned + BuildStorage> Configuration<C
 			pruning: PruningMode::default(),
 			execution_strategy: ExecutionStrategy::Both,
 			max_heap_pages: 1024,
+			wasm_fuel_limit: None,
+			rpc_fuel_limit: Some(DEFAULT_RPC_FUEL_LIMIT),
+			profile_runtime: false,
 			rpc_http: None,
 			rpc_ws: None,
 			telemetry_url: None,
+			dev_seal: None,
+			aura_slot_duration: None,
+			finality_round_duration: None,
+			offchain_worker: false,
 		};
 		configuration.network.boot_nodes = configuration.chain_spec.boot_nodes().to_vec();
 		configuration.telemetry_url = configuration.chain_spec.telemetry_url().map(str::to_owned);
@@ -122,3 +172,20 @@ pub fn full_version_from_strs(impl_version: &str, impl_commit: &str) -> String {
 	format!("{}{}{}-{}", impl_version, commit_dash, impl_commit, platform())
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dev_seal_mode_is_parsed() {
+		assert_eq!("instant".parse(), Ok(DevSeal::Instant));
+		assert_eq!("interval=500".parse(), Ok(DevSeal::Interval(500)));
+
+		assert!("interval=0".parse::<DevSeal>().is_err());
+		assert!("interval=".parse::<DevSeal>().is_err());
+		assert!("interval=abc".parse::<DevSeal>().is_err());
+		assert!("Instant".parse::<DevSeal>().is_err());
+		assert!("".parse::<DevSeal>().is_err());
+	}
+}
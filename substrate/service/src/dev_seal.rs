@@ -0,0 +1,161 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Development block authoring. Blocks are built from the extrinsic pool with the
+//! `BlockBuilder` and imported without a justification, so a single node makes progress
+//! without running consensus.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures::prelude::*;
+use tokio::timer::Interval;
+use client::{self, BlockOrigin, CallExecutor, Client, ImportResult};
+use extrinsic_pool::api::ExtrinsicPool as ExtrinsicPoolApi;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT};
+
+use components::{Components, ComponentClient, ExtrinsicPool};
+use config::DevSeal;
+use error;
+
+/// Interval in milliseconds at which authoring is retried in instant mode, for transactions
+/// which arrived before a block could be authored.
+const INSTANT_RETRY_INTERVAL: u64 = 1000;
+
+/// Returns a future that authors blocks according to `mode` until `exit` resolves.
+pub fn start<C, X>(
+	mode: DevSeal,
+	client: Arc<ComponentClient<C>>,
+	extrinsic_pool: Arc<C::ExtrinsicPool>,
+	exit: X,
+) -> Box<Future<Item=(), Error=()> + Send> where
+	C: Components,
+	X: Future<Item=(), Error=()> + Send + 'static,
+{
+	info!(target: "dev-seal", "Authoring blocks in dev-seal mode: {:?}", mode);
+	let triggers: Box<Stream<Item=(), Error=()> + Send> = match mode {
+		DevSeal::Instant => Box::new(extrinsic_pool.api().import_notification_stream()
+			.select(Interval::new(Instant::now(), Duration::from_millis(INSTANT_RETRY_INTERVAL))
+				.map(|_| ())
+				.map_err(|e| warn!(target: "dev-seal", "Faulty timer: {}", e)))),
+		DevSeal::Interval(ms) => Box::new(Interval::new(Instant::now(), Duration::from_millis(ms))
+			.map(|_| ())
+			.map_err(|e| warn!(target: "dev-seal", "Faulty timer: {}", e))),
+	};
+
+	// empty blocks are only authored when sealing on an interval.
+	let allow_empty = mode != DevSeal::Instant;
+	Box::new(triggers
+		.for_each(move |_| {
+			if let Err(e) = seal_block::<C>(&client, &*extrinsic_pool, allow_empty) {
+				warn!(target: "dev-seal", "Unable to author block: {}", e);
+			}
+			Ok(())
+		})
+		.select(exit)
+		.then(|_| Ok(())))
+}
+
+// Build a block with all pending transactions on top of the best block and import it.
+fn seal_block<C: Components>(
+	client: &ComponentClient<C>,
+	extrinsic_pool: &C::ExtrinsicPool,
+	allow_empty: bool,
+) -> Result<(), error::Error> {
+	let pending = extrinsic_pool.transactions();
+	if pending.is_empty() && !allow_empty {
+		return Ok(());
+	}
+
+	let parent = client.best_block_header()?;
+	let inherents = match C::build_dev_seal_inherents(client, &parent)? {
+		Some(inherents) => inherents,
+		None => {
+			trace!(target: "dev-seal", "Too early to author a block on {}", parent.hash());
+			return Ok(());
+		},
+	};
+	author_block(client, &parent, inherents, pending)?;
+	Ok(())
+}
+
+// Build a block with the given inherents and transactions on top of `parent` and import it,
+// skipping transactions which fail to apply.
+fn author_block<B, E, Block, H>(
+	client: &Client<B, E, Block>,
+	parent: &Block::Header,
+	inherents: Vec<Block::Extrinsic>,
+	pending: Vec<(H, Block::Extrinsic)>,
+) -> Result<Block::Hash, error::Error> where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block> + Clone,
+	Block: BlockT,
+	H: Debug,
+{
+	let mut builder = client.new_block_at(&BlockId::Hash(parent.hash()))?;
+	for inherent in inherents {
+		builder.push(inherent)?;
+	}
+	for (hash, xt) in pending {
+		if let Err(e) = builder.push(xt) {
+			debug!(target: "dev-seal", "Skipping transaction {:?}: {}", hash, e);
+		}
+	}
+
+	let (header, body) = builder.bake()?.deconstruct();
+	let hash = header.hash();
+	match client.import_sealed_block(BlockOrigin::Own, header.clone(), header.clone(), Some(body.clone()))? {
+		ImportResult::Queued => {
+			info!(target: "dev-seal", "Authored block #{} ({}) with {} extrinsics", header.number(), hash, body.len());
+			Ok(hash)
+		},
+		result => Err(format!("Authored block {} was not imported: {:?}", hash, result).into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use keyring::Keyring;
+	use test_client::{self, TestClient};
+	use test_client::runtime::{Extrinsic, Transfer};
+
+	fn transfer(nonce: u64) -> Extrinsic {
+		let transfer = Transfer {
+			from: Keyring::Alice.to_raw_public().into(),
+			to: Keyring::Bob.to_raw_public().into(),
+			amount: 42,
+			nonce,
+		};
+		let signature = Keyring::Alice.sign(&transfer.encode()).into();
+		Extrinsic { transfer, signature }
+	}
+
+	#[test]
+	fn authored_block_is_imported() {
+		let client = test_client::new();
+		let genesis = client.header(&BlockId::Hash(client.genesis_hash())).unwrap().unwrap();
+
+		// the second transaction has a future nonce and is skipped.
+		let hash = author_block(&client, &genesis, Vec::new(), vec![(0, transfer(0)), (1, transfer(5))]).unwrap();
+
+		assert_eq!(client.info().unwrap().chain.best_hash, hash);
+		let body = client.body(&BlockId::Hash(hash)).unwrap().unwrap();
+		assert_eq!(body, vec![transfer(0)]);
+	}
+}
@@ -23,9 +23,11 @@ extern crate futures;
 extern crate exit_future;
 extern crate serde;
 extern crate serde_json;
+extern crate ed25519;
 extern crate substrate_keystore as keystore;
 extern crate substrate_primitives as primitives;
 extern crate substrate_runtime_primitives as runtime_primitives;
+extern crate substrate_runtime_io as runtime_io;
 extern crate substrate_network as network;
 extern crate substrate_executor;
 extern crate substrate_client as client;
@@ -37,6 +39,11 @@ extern crate substrate_rpc_servers as rpc;
 extern crate target_info;
 extern crate tokio;
 
+#[cfg(test)]
+extern crate substrate_keyring as keyring;
+#[cfg(test)]
+extern crate substrate_test_client as test_client;
+
 #[macro_use]
 extern crate substrate_telemetry as tel;
 #[macro_use]
@@ -51,6 +58,8 @@ extern crate serde_derive;
 mod components;
 mod error;
 mod chain_spec;
+mod dev_seal;
+mod offchain;
 pub mod config;
 pub mod chain_ops;
 
@@ -63,10 +72,10 @@ use client::BlockchainEvents;
 use runtime_primitives::traits::{Header, As};
 use exit_future::Signal;
 use tokio::runtime::TaskExecutor;
-use substrate_executor::NativeExecutor;
+use substrate_executor::{NativeExecutor, profiling};
 
 pub use self::error::{ErrorKind, Error};
-pub use config::{Configuration, Roles, PruningMode};
+pub use config::{Configuration, Roles, PruningMode, DevSeal};
 pub use chain_spec::ChainSpec;
 pub use extrinsic_pool::txpool::{Options as ExtrinsicPoolOptions};
 pub use extrinsic_pool::api::{ExtrinsicPool as ExtrinsicPoolApi};
@@ -96,7 +105,11 @@ pub struct Service<Components: components::Components> {
 pub fn new_client<Factory: components::ServiceFactory>(config: FactoryFullConfiguration<Factory>)
 	-> Result<Arc<ComponentClient<components::FullComponents<Factory>>>, error::Error>
 {
-	let executor = NativeExecutor::with_heap_pages(config.max_heap_pages);
+	if config.profile_runtime {
+		profiling::set_enabled(true);
+	}
+	let executor = NativeExecutor::with_heap_pages(config.max_heap_pages)
+		.with_fuel_limit(config.wasm_fuel_limit);
 	let (client, _) = components::FullComponents::<Factory>::build_client(
 		&config,
 		executor,
@@ -118,7 +131,11 @@ impl<Components> Service<Components>
 		let (signal, exit) = ::exit_future::signal();
 
 		// Create client
-		let executor = NativeExecutor::with_heap_pages(config.max_heap_pages);
+		if config.profile_runtime {
+			profiling::set_enabled(true);
+		}
+		let executor = NativeExecutor::with_heap_pages(config.max_heap_pages)
+			.with_fuel_limit(config.wasm_fuel_limit);
 
 		let mut keystore = Keystore::open(config.keystore_path.as_str().into())?;
 		for seed in &config.keys {
@@ -138,6 +155,7 @@ impl<Components> Service<Components>
 		telemetry!("node.start"; "height" => best_header.number().as_(), "best" => ?best_header.hash());
 
 		let network_protocol = <Components::Factory>::build_network_protocol(&config)?;
+		let block_import = Components::build_block_import(&config, client.clone())?;
 		let extrinsic_pool = Arc::new(
 			Components::build_extrinsic_pool(config.extrinsic_pool, client.clone())?
 		);
@@ -149,6 +167,7 @@ impl<Components> Service<Components>
 			},
 			network_config: config.network,
 			chain: client.clone(),
+			block_import,
 			on_demand: on_demand.clone()
 				.map(|d| d as Arc<network::OnDemandService<ComponentBlock<Components>>>),
 			transaction_pool: extrinsic_pool_adapter,
@@ -189,6 +208,33 @@ impl<Components> Service<Components>
 			task_executor.spawn(events);
 		}
 
+		if let Some(mode) = config.dev_seal {
+			if config.roles == Roles::LIGHT {
+				warn!("Dev-seal is not available on light clients");
+			} else {
+				task_executor.spawn(dev_seal::start::<Components, _>(
+					mode,
+					client.clone(),
+					extrinsic_pool.clone(),
+					exit.clone(),
+				));
+			}
+		}
+
+		if config.offchain_worker {
+			if config.roles == Roles::LIGHT {
+				warn!("Offchain workers are not available on light clients");
+			} else {
+				let key = Arc::new(keystore.load(&keystore.contents()?[0], "")?);
+				task_executor.spawn(offchain::start::<Components, _>(
+					client.clone(),
+					extrinsic_pool.clone(),
+					Some(key),
+					exit.clone(),
+				));
+			}
+		}
+
 		// RPC
 		let rpc_config = RpcConfig {
 			chain_name: config.chain_spec.name().to_string(),
@@ -197,10 +243,12 @@ impl<Components> Service<Components>
 		};
 
 		let (rpc_http, rpc_ws) = {
+			let rpc_fuel_limit = config.rpc_fuel_limit;
 			let handler = || {
 				let client = client.clone();
 				let chain = rpc::apis::chain::Chain::new(client.clone(), task_executor.clone());
-				let state = rpc::apis::state::State::new(client.clone(), task_executor.clone());
+				let state = rpc::apis::state::State::new(client.clone(), task_executor.clone())
+					.with_fuel_limit(rpc_fuel_limit);
 				let author = rpc::apis::author::Author::new(client.clone(), extrinsic_pool.api(), task_executor.clone());
 				rpc::rpc_handler::<ComponentBlock<Components>, _, _, _, _>(
 					state,
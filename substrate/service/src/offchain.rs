@@ -0,0 +1,252 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Offchain workers. After a block becomes the new best block the runtime's `offchain_worker`
+//! entry point is called on top of its state, on a dedicated thread. Storage changes it makes
+//! are discarded; instead it may sign with the node's key, submit extrinsics to the local
+//! extrinsic pool and keep data in a node-local key-value store persisted by the client backend.
+//! Blocks imported during the initial sync are skipped.
+
+use std::sync::Arc;
+use std::sync::mpsc::{self, TrySendError};
+use std::thread;
+use futures::prelude::*;
+use client::{self, BlockchainEvents, BlockImportNotification, BlockOrigin, CallExecutor, Client};
+use codec::{Decode, Encode};
+use ed25519;
+use extrinsic_pool::api::ExtrinsicPool as ExtrinsicPoolApi;
+use runtime_io::OffchainExt;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{Block as BlockT, Header as HeaderT, NumberFor};
+
+use components::{Components, ComponentClient, ExtrinsicPool};
+
+/// Maximum number of blocks waiting for the offchain worker. Further blocks are skipped until
+/// the worker catches up.
+const MAX_PENDING_BLOCKS: usize = 16;
+
+/// Returns a future that runs the offchain worker for every new best block until `exit` resolves.
+/// The worker signs with `key`, if given.
+pub fn start<C, X>(
+	client: Arc<ComponentClient<C>>,
+	extrinsic_pool: Arc<C::ExtrinsicPool>,
+	key: Option<Arc<ed25519::Pair>>,
+	exit: X,
+) -> Box<Future<Item=(), Error=()> + Send> where
+	C: Components,
+	X: Future<Item=(), Error=()> + Send + 'static,
+{
+	info!(target: "offchain-worker", "Running offchain workers on new best blocks");
+	let (sender, receiver) = mpsc::sync_channel(MAX_PENDING_BLOCKS);
+	let worker_client = client.clone();
+	let pool = extrinsic_pool.api();
+	let spawned = thread::Builder::new().name("offchain-worker".into()).spawn(move || {
+		// ends once the notification stream below is dropped.
+		for (hash, number) in receiver {
+			run_offchain_worker(&*worker_client, &*pool, key.as_ref().map(|key| &**key), hash, number);
+		}
+	});
+	if let Err(e) = spawned {
+		warn!(target: "offchain-worker", "Unable to spawn the offchain worker thread: {}", e);
+		return Box::new(::futures::future::ok::<(), ()>(()));
+	}
+
+	let events = client.import_notification_stream()
+		.for_each(move |notification| {
+			if !should_run(&notification) {
+				return Ok(());
+			}
+
+			let number = *notification.header.number();
+			if let Err(TrySendError::Full(_)) = sender.try_send((notification.hash, number)) {
+				debug!(target: "offchain-worker", "Offchain worker is busy, skipping block #{}", number);
+			}
+			Ok(())
+		});
+
+	Box::new(events
+		.select(exit)
+		.then(|_| Ok(())))
+}
+
+// Whether the offchain worker runs for an imported block.
+fn should_run<Block: BlockT>(notification: &BlockImportNotification<Block>) -> bool {
+	notification.is_new_best && notification.origin != BlockOrigin::NetworkInitialSync
+}
+
+// Run the offchain worker on top of the state of block `hash`.
+fn run_offchain_worker<B, E, Block, P>(
+	client: &Client<B, E, Block>,
+	pool: &P,
+	key: Option<&ed25519::Pair>,
+	hash: Block::Hash,
+	number: NumberFor<Block>,
+) where
+	B: client::backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+	P: ExtrinsicPoolApi<Block::Extrinsic, BlockId<Block>, Block::Hash>,
+{
+	let mut context = OffchainContext {
+		backend: &**client.backend(),
+		pool,
+		key,
+		at: BlockId::Hash(hash),
+	};
+
+	let result = runtime_io::with_offchain(&mut context, || client.executor().call(
+		&BlockId::Hash(hash),
+		"offchain_worker",
+		&number.encode(),
+	));
+	if let Err(e) = result {
+		debug!(target: "offchain-worker", "Offchain worker failed at block #{}: {}", number, e);
+	}
+}
+
+// Capabilities of a single offchain worker run.
+struct OffchainContext<'a, B: 'a, P: 'a, Block: BlockT> {
+	backend: &'a B,
+	pool: &'a P,
+	key: Option<&'a ed25519::Pair>,
+	at: BlockId<Block>,
+}
+
+impl<'a, B, P, Block> OffchainExt for OffchainContext<'a, B, P, Block> where
+	B: client::backend::Backend<Block>,
+	P: ExtrinsicPoolApi<Block::Extrinsic, BlockId<Block>, Block::Hash>,
+	Block: BlockT,
+{
+	fn submit_extrinsic(&mut self, extrinsic: Vec<u8>) -> bool {
+		let xt = match Decode::decode(&mut &extrinsic[..]) {
+			Some(xt) => xt,
+			None => {
+				debug!(target: "offchain-worker", "Unable to decode submitted extrinsic");
+				return false;
+			}
+		};
+
+		match self.pool.submit(self.at.clone(), vec![xt]) {
+			Ok(_) => true,
+			Err(e) => {
+				debug!(target: "offchain-worker", "Submitted extrinsic rejected: {}", e);
+				false
+			}
+		}
+	}
+
+	fn local_storage_get(&self, key: &[u8]) -> Option<Vec<u8>> {
+		match self.backend.offchain_storage(key) {
+			Ok(value) => value,
+			Err(e) => {
+				warn!(target: "offchain-worker", "Unable to read offchain storage: {}", e);
+				None
+			}
+		}
+	}
+
+	fn local_storage_set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+		if let Err(e) = self.backend.set_offchain_storage(&key, &value) {
+			warn!(target: "offchain-worker", "Unable to write offchain storage: {}", e);
+		}
+	}
+
+	fn sign(&self, msg: &[u8]) -> Option<([u8; 32], [u8; 64])> {
+		self.key.map(|key| (key.public().0, key.sign(msg).0))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Mutex;
+	use client::backend::Backend;
+	use extrinsic_pool::{api, txpool};
+	use extrinsic_pool::watcher::Watcher;
+	use keyring::Keyring;
+	use runtime_primitives::traits::BlindCheckable;
+	use test_client::{self, TestClient};
+	use test_client::runtime::{Block, Extrinsic, Hash};
+	use test_client::runtime::system::OFFCHAIN_LAST_BLOCK;
+
+	#[derive(Default)]
+	struct TestPool {
+		submitted: Mutex<Vec<Extrinsic>>,
+	}
+
+	impl api::ExtrinsicPool<Extrinsic, BlockId<Block>, Hash> for TestPool {
+		type Error = txpool::Error;
+
+		fn submit(&self, _block: BlockId<Block>, xt: Vec<Extrinsic>) -> Result<Vec<Hash>, Self::Error> {
+			let hashes = xt.iter().map(|_| Default::default()).collect();
+			self.submitted.lock().unwrap().extend(xt);
+			Ok(hashes)
+		}
+
+		fn submit_and_watch(&self, _block: BlockId<Block>, _xt: Extrinsic) -> Result<Watcher<Hash>, Self::Error> {
+			unreachable!()
+		}
+
+		fn light_status(&self) -> txpool::LightStatus {
+			unreachable!()
+		}
+
+		fn import_notification_stream(&self) -> api::EventStream {
+			unreachable!()
+		}
+	}
+
+	#[test]
+	fn offchain_worker_stores_and_submits() {
+		let client = test_client::new();
+		let pool = TestPool::default();
+
+		// without a key nothing is signed nor submitted.
+		run_offchain_worker(&client, &pool, None, client.genesis_hash(), 0);
+		assert_eq!(client.backend().offchain_storage(OFFCHAIN_LAST_BLOCK).unwrap(), Some(0u64.encode()));
+		assert!(pool.submitted.lock().unwrap().is_empty());
+
+		let builder = client.new_block().unwrap();
+		client.justify_and_import(BlockOrigin::Own, builder.bake().unwrap()).unwrap();
+		let best = client.info().unwrap().chain.best_hash;
+
+		let key = Keyring::Alice.pair();
+		run_offchain_worker(&client, &pool, Some(&key), best, 1);
+		assert_eq!(client.backend().offchain_storage(OFFCHAIN_LAST_BLOCK).unwrap(), Some(1u64.encode()));
+
+		let submitted = pool.submitted.lock().unwrap();
+		assert_eq!(submitted.len(), 1);
+		assert_eq!(submitted[0].transfer.from, Keyring::Alice.to_raw_public().into());
+		assert!(submitted[0].clone().check().is_ok());
+	}
+
+	#[test]
+	fn only_new_best_blocks_after_initial_sync_are_worked_on() {
+		let client = test_client::new();
+		let header = client.header(&BlockId::Hash(client.genesis_hash())).unwrap().unwrap();
+		let notification = |origin, is_new_best| BlockImportNotification::<Block> {
+			hash: header.hash(),
+			origin,
+			header: header.clone(),
+			is_new_best,
+		};
+
+		assert!(should_run(&notification(BlockOrigin::NetworkBroadcast, true)));
+		assert!(should_run(&notification(BlockOrigin::Own, true)));
+		assert!(!should_run(&notification(BlockOrigin::NetworkBroadcast, false)));
+		assert!(!should_run(&notification(BlockOrigin::NetworkInitialSync, true)));
+	}
+}
@@ -190,6 +190,23 @@ pub trait Externalities {
 	fn storage_root(&mut self) -> [u8; 32];
 }
 
+/// Node-local capabilities of offchain workers. Nothing done through them affects consensus.
+pub trait OffchainExt {
+	/// Submit an encoded extrinsic, signed or unsigned, to the local extrinsic pool. Returns
+	/// `true` if it was accepted.
+	fn submit_extrinsic(&mut self, extrinsic: Vec<u8>) -> bool;
+
+	/// Read an entry of the node-local key-value store.
+	fn local_storage_get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+	/// Set an entry of the node-local key-value store.
+	fn local_storage_set(&mut self, key: Vec<u8>, value: Vec<u8>);
+
+	/// Sign a message with the node's local ed25519 key. Returns the public key and the
+	/// signature, or `None` if the node has no key.
+	fn sign(&self, msg: &[u8]) -> Option<([u8; 32], [u8; 64])>;
+}
+
 /// Code execution engine.
 pub trait CodeExecutor: Sized + Send + Sync {
 	/// Externalities error type.
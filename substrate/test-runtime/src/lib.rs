@@ -164,6 +164,7 @@ pub mod api {
 		initialise_block => |header| system::initialise_block(header),
 		execute_block => |block| system::execute_block(block),
 		apply_extrinsic => |utx| system::execute_transaction(utx),
-		finalise_block => |()| system::finalise_block()
+		finalise_block => |()| system::finalise_block(),
+		offchain_worker => |number| system::offchain_worker(number)
 	);
 }
@@ -18,17 +18,20 @@
 //! and depositing logs.
 
 use rstd::prelude::*;
-use runtime_io::{storage_root, enumerated_trie_root};
+use runtime_io::{storage_root, enumerated_trie_root, local_storage_set, local_sign, submit_extrinsic};
 use runtime_support::storage::{self, StorageValue, StorageMap};
 use runtime_primitives::traits::{Hash as HashT, BlakeTwo256};
 use runtime_primitives::{ApplyError, ApplyOutcome, ApplyResult};
 use codec::{KeyedVec, Encode};
-use super::{AccountId, BlockNumber, Extrinsic, H256 as Hash, Block, Header};
+use primitives::H512;
+use super::{AccountId, BlockNumber, Extrinsic, Transfer, H256 as Hash, Block, Header};
 
 const NONCE_OF: &[u8] = b"nonce:";
 const BALANCE_OF: &[u8] = b"balance:";
 const AUTHORITY_AT: &'static[u8] = b":auth:";
 const AUTHORITY_COUNT: &'static[u8] = b":auth:len";
+/// Key of the node-local storage entry in which the offchain worker records its last block.
+pub const OFFCHAIN_LAST_BLOCK: &[u8] = b"offchain:last";
 
 storage_items! {
 	ExtrinsicIndex: b"sys:xti" => required u32;
@@ -110,6 +113,22 @@ pub fn finalise_block() -> Header {
 	}
 }
 
+/// Record the number of the imported block in the node-local storage and, if the node has a key,
+/// submit an empty transfer to itself signed with it.
+pub fn offchain_worker(number: BlockNumber) {
+	local_storage_set(OFFCHAIN_LAST_BLOCK, &number.encode());
+
+	let from: AccountId = match local_sign(&[]) {
+		Some((public, _)) => public.into(),
+		None => return,
+	};
+	let transfer = Transfer { from, to: from, amount: 0, nonce: nonce_of(from) };
+	if let Some((_, signature)) = local_sign(&transfer.encode()) {
+		let signature = H512::from(signature).into();
+		submit_extrinsic(&Extrinsic { transfer, signature }.encode());
+	}
+}
+
 fn execute_transaction_backend(utx: &Extrinsic) -> ApplyResult {
 	use runtime_primitives::traits::BlindCheckable;
 